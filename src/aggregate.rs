@@ -0,0 +1,205 @@
+use ringbuf::HeapConsumer;
+
+use crate::drift::DriftMeter;
+
+/// Resamples interleaved frames a frame at a time by interpolating between them, for the
+/// callback of a device running at another rate than the engine. Allocates nothing after
+/// it's made.
+pub struct FrameResampler {
+    // how far the input moves for every frame put out, in frames
+    step: f64,
+    // where the next frame put out lies past the previous input frame
+    position: f64,
+    previous: Vec<f32>,
+}
+
+impl FrameResampler {
+    pub fn new(channels: u16, from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate.max(1) as f64 / to_rate.max(1) as f64,
+            position: 0.0,
+            previous: vec![0.0; channels.max(1) as usize],
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.previous.len()
+    }
+
+    /// Takes the next input frame, putting out the samples of every frame that lies before
+    /// it, which is none or more depending on the rates.
+    pub fn push(&mut self, frame: &[f32], mut out: impl FnMut(f32)) {
+        while self.position < 1.0 {
+            for (channel, &previous) in self.previous.iter().enumerate() {
+                let next = frame.get(channel).copied().unwrap_or(0.0);
+                out(previous + (next - previous) * self.position as f32);
+            }
+
+            self.position += self.step;
+        }
+
+        self.position -= 1.0;
+
+        for (previous, &next) in self.previous.iter_mut().zip(frame) {
+            *previous = next;
+        }
+    }
+}
+
+/// The buffer of the second input device, read a frame at a time by the output callback.
+pub struct SecondaryInput {
+    input: HeapConsumer<f32>,
+    channels: u16,
+    // the last frame popped, played again while the buffer runs dry or is held back
+    last_frame: Vec<f32>,
+    drift: DriftMeter,
+    // how many frames the buffer is ahead of the monitoring delay, behind when negative
+    delay_error: i64,
+    holding: bool,
+}
+
+impl SecondaryInput {
+    pub fn new(input: HeapConsumer<f32>, channels: u16, sample_rate: u32) -> Self {
+        Self {
+            input,
+            channels: channels.max(1),
+            last_frame: vec![0.0; channels.max(1) as usize],
+            drift: DriftMeter::new(sample_rate),
+            delay_error: 0,
+            holding: false,
+        }
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Returns the drift of the device's clock from the output's in parts per million, or
+    /// `None` until it was measured for long enough.
+    pub fn ppm(&self) -> Option<f64> {
+        self.drift.ppm()
+    }
+
+    /// Notes how far the buffer is from `delay_frames` at the start of a callback playing
+    /// `buffer_frames` frames, ignoring the jitter of one buffer.
+    pub fn measure(&mut self, delay_frames: u64, buffer_frames: usize) {
+        let channels = self.channels as i64;
+        let fill = self.input.len() as i64;
+        let error = fill - delay_frames as i64 * channels;
+
+        self.drift
+            .observe(fill as f64 / channels as f64, buffer_frames);
+
+        self.delay_error = if error.abs() > buffer_frames as i64 * channels {
+            error / channels
+        } else {
+            0
+        };
+    }
+
+    /// Moves the buffer a frame toward the monitoring delay when `phase` is set, and makes up
+    /// the drift otherwise, called once a frame like the correction of the first device.
+    pub fn correct(&mut self, phase: bool) {
+        self.holding = false;
+
+        let mut correction = self.drift.step();
+        if phase && self.delay_error != 0 {
+            correction = self.delay_error.signum();
+            self.delay_error -= correction;
+        }
+
+        match correction {
+            1 => {
+                self.input.skip(self.channels as usize);
+            }
+            -1 => self.holding = true,
+            _ => {}
+        }
+
+        self.drift.corrected(correction);
+    }
+
+    /// Pops the next sample of a channel of the device, repeating the last one while the
+    /// device is behind or held back.
+    pub fn pop(&mut self, channel: usize) -> f32 {
+        let last = self.last_frame.get(channel).copied().unwrap_or(0.0);
+
+        let sample = if self.holding {
+            last
+        } else {
+            self.input.pop().unwrap_or(last)
+        };
+
+        if let Some(last) = self.last_frame.get_mut(channel) {
+            *last = sample;
+        }
+
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::FrameResampler;
+
+    fn resample(samples: &[f32], channels: u16, from_rate: u32, to_rate: u32) -> Vec<f32> {
+        let mut resampler = FrameResampler::new(channels, from_rate, to_rate);
+        let mut out = Vec::new();
+
+        for frame in samples.chunks(channels as usize) {
+            resampler.push(frame, |sample| out.push(sample));
+        }
+
+        out
+    }
+
+    #[test]
+    fn same_rate_delays_by_a_frame() {
+        let samples = [1.0, -1.0, 2.0, -2.0, 3.0, -3.0];
+        let out = resample(&samples, 2, 48000, 48000);
+
+        assert_eq!(out, [0.0, 0.0, 1.0, -1.0, 2.0, -2.0]);
+    }
+
+    #[test]
+    fn frame_count_follows_the_rates() {
+        let samples = vec![0.0; 44100];
+        let out = resample(&samples, 1, 44100, 48000);
+
+        assert!(
+            (out.len() as i64 - 48000).abs() <= 1,
+            "{} frames",
+            out.len()
+        );
+
+        let out = resample(&samples, 1, 44100, 22050);
+        assert!(
+            (out.len() as i64 - 22050).abs() <= 1,
+            "{} frames",
+            out.len()
+        );
+    }
+
+    #[test]
+    fn sine_keeps_its_pitch() {
+        // a second of a 440 Hz sine at 44.1 kHz, compared with the same sine at 48 kHz
+        let samples = (0..44100)
+            .map(|i| (TAU * 440.0 * i as f32 / 44100.0).sin())
+            .collect::<Vec<_>>();
+        let out = resample(&samples, 1, 44100, 48000);
+
+        // the output trails the input by a frame of the input
+        let delay = 48000.0 / 44100.0;
+        for (i, &sample) in out.iter().enumerate().skip(2) {
+            let expected = (TAU * 440.0 * (i as f32 - delay) / 48000.0).sin();
+            assert!(
+                (sample - expected).abs() < 0.01,
+                "{} at frame {}",
+                sample,
+                i
+            );
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    f32::consts::TAU,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+/// How many of the latest input frames are kept for analysis, a power of two for the fft.
+pub const SNAPSHOT_LEN: usize = 2048;
+
+// the range of the spectrum in hz, spaced logarithmically
+const SPECTRUM_MIN: f32 = 40.0;
+const SPECTRUM_MAX: f32 = 16000.0;
+
+// the quietest level shown in the spectrum, in decibels
+const SPECTRUM_FLOOR: f32 = -60.0;
+
+// the range of pitches the tuner detects, in hz
+const PITCH_MIN: f32 = 60.0;
+const PITCH_MAX: f32 = 1200.0;
+
+// input quieter than this doesn't have a pitch
+const PITCH_MIN_RMS: f32 = 0.01;
+
+// how close to the strongest period another one has to be to count, avoids octave errors
+const PITCH_PEAK_THRESHOLD: f32 = 0.9;
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// The latest input frames, written by the input callback and read by the ui thread without
+/// locking. A read racing a write can see a few torn samples, which analysis doesn't mind.
+pub struct InputSnapshot {
+    samples: Box<[AtomicU32]>,
+    position: AtomicUsize,
+}
+
+impl Default for InputSnapshot {
+    fn default() -> Self {
+        Self {
+            samples: (0..SNAPSHOT_LEN).map(|_| AtomicU32::new(0)).collect(),
+            position: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl InputSnapshot {
+    pub fn push(&self, sample: f32) {
+        let position = self.position.load(Ordering::Relaxed);
+
+        self.samples[position].store(sample.to_bits(), Ordering::Relaxed);
+        self.position
+            .store((position + 1) % SNAPSHOT_LEN, Ordering::Release);
+    }
+
+    /// Returns the latest frames, oldest first.
+    pub fn read(&self) -> Vec<f32> {
+        let position = self.position.load(Ordering::Acquire);
+
+        (0..SNAPSHOT_LEN)
+            .map(|i| {
+                let sample = &self.samples[(position + i) % SNAPSHOT_LEN];
+                f32::from_bits(sample.load(Ordering::Relaxed))
+            })
+            .collect()
+    }
+}
+
+// an in place radix 2 fft, the length must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    // reorder the input by bit reversed index
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f32;
+
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+
+        len <<= 1;
+    }
+}
+
+/// Returns the level of logarithmically spaced bands, from 0 for silence to 1 for full scale.
+pub fn spectrum(samples: &[f32], sample_rate: f32, bands: usize) -> Vec<f32> {
+    let n = samples.len();
+
+    // a hann window keeps the bands from leaking into each other
+    let mut re = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| sample * (0.5 - 0.5 * (TAU * i as f32 / n as f32).cos()))
+        .collect::<Vec<_>>();
+    let mut im = vec![0.0; n];
+
+    fft(&mut re, &mut im);
+
+    let max = SPECTRUM_MAX.min(sample_rate / 2.0);
+    let bin = |frequency: f32| ((frequency * n as f32 / sample_rate) as usize).min(n / 2);
+
+    (0..bands)
+        .map(|band| {
+            let low = SPECTRUM_MIN * (max / SPECTRUM_MIN).powf(band as f32 / bands as f32);
+            let high = SPECTRUM_MIN * (max / SPECTRUM_MIN).powf((band + 1) as f32 / bands as f32);
+
+            let first = bin(low);
+            let last = bin(high).max(first + 1).min(n / 2);
+
+            // the window halves the amplitude, so scale by 4 / n instead of 2 / n
+            let magnitude = (first..last)
+                .map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt() * 4.0 / n as f32)
+                .fold(0.0, f32::max);
+
+            let decibels = 20.0 * magnitude.max(1e-6).log10();
+            (1.0 - decibels / SPECTRUM_FLOOR).clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+/// Estimates the fundamental frequency of the samples in hz, using the normalized square
+/// difference of the signal with delayed copies of itself.
+pub fn detect_pitch(samples: &[f32], sample_rate: f32) -> Option<f32> {
+    let n = samples.len();
+
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / n as f32).sqrt();
+    if rms < PITCH_MIN_RMS {
+        return None;
+    }
+
+    let min_lag = (sample_rate / PITCH_MAX) as usize;
+    let max_lag = ((sample_rate / PITCH_MIN) as usize).min(n / 2);
+
+    if min_lag < 1 || min_lag + 2 >= max_lag {
+        return None;
+    }
+
+    // 1 when the signal repeats perfectly after `lag` samples, -1 when it inverts
+    let nsdf = (0..=max_lag + 1)
+        .map(|lag| {
+            let mut correlation = 0.0;
+            let mut energy = 0.0;
+
+            for i in 0..n - lag {
+                correlation += samples[i] * samples[i + lag];
+                energy += samples[i] * samples[i] + samples[i + lag] * samples[i + lag];
+            }
+
+            if energy > 0.0 {
+                2.0 * correlation / energy
+            } else {
+                0.0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let is_peak = |lag: usize| nsdf[lag] > nsdf[lag - 1] && nsdf[lag] >= nsdf[lag + 1];
+
+    let best = (min_lag..=max_lag)
+        .filter(|&lag| is_peak(lag))
+        .map(|lag| nsdf[lag])
+        .fold(0.0, f32::max);
+
+    if best <= 0.0 {
+        return None;
+    }
+
+    // the shortest period that's nearly as strong as the best one is the fundamental
+    let lag = (min_lag..=max_lag)
+        .find(|&lag| is_peak(lag) && nsdf[lag] >= best * PITCH_PEAK_THRESHOLD)?;
+
+    // fit a parabola through the peak for a period between samples
+    let (left, center, right) = (nsdf[lag - 1], nsdf[lag], nsdf[lag + 1]);
+    let denominator = left - 2.0 * center + right;
+    let offset = if denominator.abs() > f32::EPSILON {
+        0.5 * (left - right) / denominator
+    } else {
+        0.0
+    };
+
+    Some(sample_rate / (lag as f32 + offset))
+}
+
+/// Returns the name of the closest note, like `A4`, and how far off it is in cents.
+pub fn note(frequency: f32) -> (String, f32) {
+    let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+    let nearest = midi.round();
+
+    let index = (nearest as i32).rem_euclid(12) as usize;
+    let octave = (nearest as i32).div_euclid(12) - 1;
+
+    (
+        format!("{}{}", NOTE_NAMES[index], octave),
+        (midi - nearest) * 100.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = 48000.0;
+
+    fn sine(frequency: f32, level: f32) -> Vec<f32> {
+        (0..SNAPSHOT_LEN)
+            .map(|i| (i as f32 * frequency / RATE * TAU).sin() * level)
+            .collect()
+    }
+
+    // how far `detected` is from `expected`, in cents
+    fn cents(detected: f32, expected: f32) -> f32 {
+        1200.0 * (detected / expected).log2()
+    }
+
+    #[test]
+    fn pitch_of_sines() {
+        for frequency in [82.41, 110.0, 196.0, 261.63, 440.0, 659.26, 1046.5] {
+            let detected = detect_pitch(&sine(frequency, 0.5), RATE).unwrap();
+            let off = cents(detected, frequency);
+            assert!(off.abs() < 5.0, "{} hz read as {}", frequency, detected);
+        }
+    }
+
+    #[test]
+    fn pitch_of_a_tone_with_overtones_is_its_fundamental() {
+        let tone = sine(110.0, 0.3)
+            .iter()
+            .zip(sine(220.0, 0.3))
+            .zip(sine(330.0, 0.2))
+            .map(|((a, b), c)| a + b + c)
+            .collect::<Vec<_>>();
+
+        let detected = detect_pitch(&tone, RATE).unwrap();
+        assert!(cents(detected, 110.0).abs() < 5.0, "read as {}", detected);
+    }
+
+    #[test]
+    fn no_pitch_without_a_signal() {
+        assert_eq!(detect_pitch(&[0.0; SNAPSHOT_LEN], RATE), None);
+        assert_eq!(detect_pitch(&sine(440.0, 0.005), RATE), None);
+    }
+
+    #[test]
+    fn note_names_and_cents() {
+        assert_eq!(note(440.0), (String::from("A4"), 0.0));
+
+        let (name, cents) = note(261.63);
+        assert_eq!(name, "C4");
+        assert!(cents.abs() < 1.0);
+
+        // 40 cents sharp of E2 is still closer to it than to F2
+        let (name, cents) = note(82.41 * 2f32.powf(0.4 / 12.0));
+        assert_eq!(name, "E2");
+        assert!((cents - 40.0).abs() < 1.0);
+    }
+}
@@ -1,20 +1,79 @@
 use std::{
     io,
+    path::PathBuf,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
-use crossterm::event::{Event, KeyCode, KeyEvent};
+use cpal::{BufferSize, SampleRate};
+use crossterm::event::{Event, KeyEvent};
 use tui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::Style,
     symbols::DOT,
     text::Spans,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap},
     Frame, Terminal,
 };
 
-use crate::audio::Audio;
+#[cfg(feature = "osc")]
+use crate::osc::Osc;
+use crate::{
+    archive::ArchivedClip,
+    audio::{
+        Audio, AudioSettings, DeviceCapabilities, SettingsError, SupportSide, MAX_CUE_CHANNEL,
+        MAX_DELAY,
+    },
+    clip::Clip,
+    compare::Compare,
+    count::CountOut,
+    debug::FrameCounter,
+    delay::MAX_DELAY_BEATS,
+    duration::DurationPrompt,
+    engine::{CallbackStats, EngineSnapshot},
+    event::Events,
+    history::Layer,
+    keymap::{Action, Context, Keymap},
+    layout::PlayLayout,
+    log::Level,
+    loudness::{ShortTermLoudness, MEASURED_CHANNELS},
+    macros::{load_macros, Macro, MACRO_COUNT},
+    midi::{LearnTarget, Midi},
+    notes::NotesDialog,
+    pedal::LooperPedalState,
+    preview::PreviewSource,
+    queue::QueuedAction,
+    quit,
+    replace::ReplaceBuffer,
+    resample::ResampleJob,
+    scene::Scene,
+    session::SessionNotes,
+    setup::Setup,
+    stack::StackMode,
+    stutter::StutterLength,
+    suspend,
+    template::TemplateDialog,
+    theme::Theme,
+    track::{InputFollow, Tracks, TRACK_COUNT},
+    wav::ExportOptions,
+};
+
+use self::{
+    analysis::AnalysisView,
+    meter::{MeterMode, TrackLevel, DEFAULT_LOUDNESS_TARGET},
+};
+
+// the app's side of the engine features, which live in the library
+pub mod analysis;
+pub mod beat;
+pub mod drift;
+pub mod gate;
+pub mod meter;
+pub mod replace;
+pub mod stutter;
+#[cfg(test)]
+mod tests;
 
 #[repr(i32)]
 #[allow(dead_code)]
@@ -47,44 +106,437 @@ pub enum EditMode {
     None,
     Host,
     InputDevice,
+    SecondaryInputDevice,
     OutputDevice,
     SampleRate,
     BufferSize,
     Delay,
+    TailFold,
+    CueChannel,
     Bpm,
-    Beats,
+    Bars,
+    Meter,
     RecordTrack,
-    RemoveTrack,
+    RemoveTrack(Option<usize>),
+    /// Waiting for the track to move into the archive.
+    ArchiveTrack,
     TrackVolume(Option<usize>),
+    TrackSend(Option<usize>),
+    TrackInput(Option<usize>),
+    TrackOffset(Option<usize>),
+    TrackColor(Option<usize>),
+    TrackGroup(Option<usize>),
+    /// Adjusting how much older layers of a track are damped with every overdub.
+    TrackDamping(Option<usize>),
+    /// Adjusting how hard a track is driven into saturation.
+    TrackDrive(Option<usize>),
+    /// Editing the steps of a track's pattern.
+    TrackPattern(Option<usize>),
+    /// Moving the edges of the region of a track's clip that's heard.
+    TrackRegion(Option<usize>),
+    DelayBeats,
+    DelayFeedback,
+    DelayMix,
+    LoudnessTarget,
+    /// Adjusting how many beats a scene switch crossfades over.
+    SceneFade,
+    GateThreshold,
+    Downbeat,
+    Yank,
+    Paste(Option<usize>),
+    MidiLearn(LearnTarget),
+    MidiPort,
+    /// Waiting for the number of bars to fade out over, and whether to clear the tracks after.
+    Fade(bool),
+    /// Waiting for the scene to save the tracks into, or to switch to.
+    Scene(bool),
+    /// Waiting for the slot to store a recorded macro in.
+    SaveMacro,
+    /// Adjusting how many beats a free loop spans.
+    FreeBeats,
+    /// Waiting for how many times to repeat the loop in an exported mix.
+    ExportMix,
+    /// Waiting for how many loops to record in a row, then for the track to record them into.
+    ArmLoops(Option<u32>),
+}
+
+// the most generation damping goes up to, in steps of 5%
+const MAX_DAMPING_STEPS: i32 = 18;
+
+// full drive, in steps of 5%
+const MAX_DRIVE_STEPS: i32 = 20;
+
+// quieter than this an overdub doesn't extend the region of its track, -60 dB
+const REGION_SILENCE: f32 = 0.001;
+
+// the range the loudness target can be set in, in LUFS
+const MIN_LOUDNESS_TARGET: i32 = -40;
+const MAX_LOUDNESS_TARGET: i32 = -5;
+
+// the longest crossfade between scenes, in beats
+pub const MAX_SCENE_FADE_BEATS: u64 = 16;
+
+// the longest loop that can be reached by doubling
+pub const MAX_BEATS: u64 = 256;
+
+// the most beats a bar can have
+pub const MAX_BEATS_PER_BAR: u64 = 12;
+
+// how many exclusive groups tracks can be put in
+pub const EXCLUSIVE_GROUPS: u8 = 4;
+
+// how far a track is shifted with the fine keys, in seconds
+const FINE_OFFSET: f32 = 0.01;
+
+// how much the tail fold changes per step and how long it can get, in milliseconds
+const TAIL_FOLD_STEP: i32 = 10;
+const MAX_TAIL_FOLD: i32 = 500;
+
+// takes bigger than this are stored without their silences, about a minute of 48 kHz stereo
+const COMPACT_BYTES: usize = 16 << 20;
+
+// anything quieter than this counts as silence when compacting a take, -100 dBFS
+const COMPACT_THRESHOLD: f32 = 0.00001;
+
+// drops the silences of a take big enough for them to matter
+fn compact_large(clip: Clip) -> Clip {
+    if clip.memory_bytes() > COMPACT_BYTES {
+        clip.compacted(COMPACT_THRESHOLD)
+    } else {
+        clip
+    }
+}
+
+// how long a pending track removal or paste waits for confirmation
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// the frame rates that can be chosen in the settings tab
+pub const FRAME_RATES: [u64; 3] = [15, 30, 60];
+
+// how often the ui is redrawn while no stream is running
+const IDLE_REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+// how often the event channel is checked while midi or osc is active
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// the smallest terminal the ui can be laid out in
+pub const MIN_WIDTH: u16 = 80;
+pub const MIN_HEIGHT: u16 = 24;
+
+// a rect of the given size centered in `area`
+pub fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+// the width of a column in the settings tab, given the area it's laid out in
+pub fn settings_column_width(area: Rect) -> u16 {
+    (area.width / 4).min(30)
 }
 
 #[derive(Default)]
 pub struct Settings {
     pub host_state: ListState,
     pub input_device_state: ListState,
+    pub secondary_input_device_state: ListState,
     pub output_device_state: ListState,
     pub sample_rate_state: ListState,
     pub buffer_size_state: ListState,
+    pub midi_port_state: ListState,
 }
 
 pub struct App {
     pub running: bool,
     pub frame_rate: Duration,
+    pub frame_rate_index: usize,
+    pub frame_counter: FrameCounter,
+    pub show_debug: bool,
+    pub callback_stats: Option<CallbackStats>,
     pub audio: Audio,
+    /// The engine state the current frame is drawn from.
+    pub snapshot: EngineSnapshot,
     pub tab: Tab,
     pub edit_mode: EditMode,
     pub settings: Settings,
+    pub last_key: Instant,
+    pub keymap: Keymap,
+    pub warnings: Vec<String>,
+    pub selected_track: usize,
+    pub redraw: bool,
+    pub theme_index: usize,
+    pub theme: Theme,
+    pub start: Instant,
+    pub log: Vec<(Instant, Level, String)>,
+    pub show_log: bool,
+    pub log_state: ListState,
+    /// What the selected devices support, while the list of it is open.
+    pub probe: Option<Vec<(&'static str, DeviceCapabilities)>>,
+    pub probe_state: ListState,
+    pub staged: Option<AudioSettings>,
+    pub zoomed: bool,
+    pub clipboard: Option<Clip>,
+    pub confirm_quit: bool,
+    pub midi: Midi,
+    pub events: Events,
+    pub session_path: Option<PathBuf>,
+    pub pedal: LooperPedalState,
+    pub pedal_loop: u64,
+    /// The changes of clips that can be undone, the latest last.
+    pub layers: Vec<Layer>,
+    pub show_history: bool,
+    /// Whether only the actions safe to use mid-performance work.
+    pub locked: bool,
+    /// When an action was last blocked by the lock.
+    pub locked_flash: Option<Instant>,
+    pub history_state: ListState,
+    /// The change of the history asked to go back to before, waiting for confirmation.
+    pub history_confirm: Option<usize>,
+    /// The clips moved off tracks to keep them around, the latest last.
+    pub archive: Vec<ArchivedClip>,
+    pub show_archive: bool,
+    pub archive_state: ListState,
+    /// The clip of the archive asked to be deleted, waiting for confirmation.
+    pub archive_confirm: Option<usize>,
+    pub stutter_length: StutterLength,
+    pub analysis: AnalysisView,
+    pub last_beat: Option<u64>,
+    pub beat_flash: Option<(Instant, bool)>,
+    pub forward_recording: bool,
+    pub fade_clear: bool,
+    /// Whether takes that look broken are refused instead of only warned about.
+    pub guard_recordings: bool,
+    /// Whether committing a take matches the volume of its track to the other layers.
+    pub auto_gain: bool,
+    /// Whether the output is killed while the process is suspended.
+    pub mute_on_suspend: bool,
+    pub mismatched_tracks: Vec<usize>,
+    pub resample_job: Option<ResampleJob>,
+    pub resample_jobs: u64,
+    pub setup: Option<Setup>,
+    pub replace: Option<Arc<ReplaceBuffer>>,
+    pub template: Option<TemplateDialog>,
+    pub notes: SessionNotes,
+    pub notes_dialog: Option<NotesDialog>,
+    /// The folder typed so far while importing a kit.
+    pub kit_prompt: Option<String>,
+    pub duration_prompt: Option<DurationPrompt>,
+    /// How exported files are written, kept between exports.
+    pub export_options: ExportOptions,
+    pub export_stems: bool,
+    pub scenes: Vec<Option<Scene>>,
+    pub active_scene: Option<usize>,
+    /// The scene switched away from last, heard under the active one while they crossfade.
+    pub faded_scene: Option<usize>,
+    pub queued_scene: Option<(Option<usize>, Tracks)>,
+    pub scene_history: Vec<(Option<usize>, Scene)>,
+    pub preview: Option<(Clip, PreviewSource)>,
+    /// A preview the output callback is still playing until it picks up the next tracks,
+    /// kept so the callback never frees it.
+    pub handed_off_preview: Option<Clip>,
+    pub meter_mode: MeterMode,
+    pub track_levels: Vec<TrackLevel>,
+    pub loudness: ShortTermLoudness,
+    // how far into the output snapshot the loudness has been measured
+    pub loudness_position: usize,
+    /// The loudness the mix is aimed at, in LUFS.
+    pub loudness_target: i32,
+    pub macros: Vec<Option<Macro>>,
+    /// The actions played since macro recording started.
+    pub macro_recording: Option<Macro>,
+    /// A recorded macro waiting for a slot.
+    pub recorded_macro: Option<Macro>,
+    pub stack_mode: StackMode,
+    /// The track recording a number of loops in a row, if any.
+    pub count_out: Option<CountOut>,
+    /// Whether the armed track was picked as the first empty one, and is picked again when
+    /// the loop lands if it was filled meanwhile.
+    pub arm_empty: bool,
+    /// The track flipping between its last layer and the clip before it, if any.
+    pub compare: Option<Compare>,
+    pub layout: PlayLayout,
+    /// The last free loop as it was recorded, kept to refit it while its beats are adjusted.
+    pub free_take: Option<Clip>,
+    /// The step of a pattern being edited.
+    pub pattern_step: usize,
+    #[cfg(feature = "osc")]
+    pub osc: Option<Osc>,
 }
 
 impl App {
     pub fn new() -> Self {
+        let mut app = Self::offline();
+        app.start();
+        app
+    }
+
+    /// Creates an app with the default keymap and settings, without reading the config or
+    /// touching any device, the streams waiting to be launched.
+    pub fn offline() -> Self {
+        let events = Events::new();
+
         Self {
             running: true,
             frame_rate: Duration::from_millis(1000 / 60),
-            audio: Audio::new(),
+            frame_rate_index: FRAME_RATES.len() - 1,
+            frame_counter: FrameCounter::new(),
+            show_debug: false,
+            callback_stats: None,
+            audio: Audio::offline(events.audio_sender.clone()),
+            snapshot: EngineSnapshot::default(),
             tab: Tab::Play,
             edit_mode: EditMode::default(),
             settings: Settings::default(),
+            last_key: Instant::now(),
+            keymap: Keymap::default(),
+            warnings: Vec::new(),
+            selected_track: 0,
+            redraw: true,
+            theme_index: 0,
+            theme: Theme::default(),
+            start: Instant::now(),
+            log: Vec::new(),
+            show_log: false,
+            log_state: ListState::default(),
+            probe: None,
+            probe_state: ListState::default(),
+            staged: None,
+            zoomed: false,
+            clipboard: None,
+            confirm_quit: false,
+            midi: Midi::new(events.sender.clone()),
+            events,
+            session_path: None,
+            pedal: LooperPedalState::default(),
+            pedal_loop: 0,
+            layers: Vec::new(),
+            show_history: false,
+            locked: false,
+            locked_flash: None,
+            history_state: ListState::default(),
+            history_confirm: None,
+            archive: Vec::new(),
+            show_archive: false,
+            archive_state: ListState::default(),
+            archive_confirm: None,
+            stutter_length: StutterLength::default(),
+            analysis: AnalysisView::default(),
+            last_beat: None,
+            beat_flash: None,
+            forward_recording: true,
+            fade_clear: false,
+            guard_recordings: true,
+            auto_gain: false,
+            mute_on_suspend: false,
+            mismatched_tracks: Vec::new(),
+            resample_job: None,
+            resample_jobs: 0,
+            setup: None,
+            replace: None,
+            template: None,
+            notes: SessionNotes::default(),
+            notes_dialog: None,
+            kit_prompt: None,
+            duration_prompt: None,
+            export_options: ExportOptions::default(),
+            export_stems: false,
+            scenes: vec![None; TRACK_COUNT],
+            active_scene: None,
+            faded_scene: None,
+            queued_scene: None,
+            scene_history: Vec::new(),
+            preview: None,
+            handed_off_preview: None,
+            meter_mode: MeterMode::default(),
+            track_levels: vec![TrackLevel::default(); TRACK_COUNT],
+            loudness: ShortTermLoudness::new(0, MEASURED_CHANNELS),
+            loudness_position: 0,
+            loudness_target: DEFAULT_LOUDNESS_TARGET,
+            macros: vec![None; MACRO_COUNT],
+            macro_recording: None,
+            recorded_macro: None,
+            stack_mode: StackMode::Off,
+            count_out: None,
+            arm_empty: false,
+            compare: None,
+            layout: PlayLayout::default(),
+            free_take: None,
+            pattern_step: 0,
+            #[cfg(feature = "osc")]
+            osc: None,
+        }
+    }
+
+    // loads the config, connects midi and launches the streams of an offline app
+    fn start(&mut self) {
+        self.load_config();
+
+        self.audio.start();
+        self.log_stream_result();
+        self.load_click();
+
+        if AudioSettings::is_first_launch() {
+            self.start_setup();
+        }
+    }
+
+    // loads the keymap, midi and macro configs and connects midi and osc
+    pub fn load_config(&mut self) {
+        let (keymap, warnings) = Keymap::load();
+        let (midi, midi_warnings) = Midi::load(self.events.sender.clone());
+        let (macros, macro_warnings) = load_macros();
+
+        self.keymap = keymap;
+        self.warnings = warnings;
+        self.midi = midi;
+        self.macros = macros;
+
+        for warning in self.warnings.clone() {
+            self.warn(format!("keymap: {}", warning));
+        }
+
+        for warning in midi_warnings {
+            self.warn(format!("midi: {}", warning));
+        }
+
+        for warning in macro_warnings {
+            self.warn(format!("macros: {}", warning));
+        }
+
+        self.connect_midi();
+
+        #[cfg(feature = "osc")]
+        self.start_osc();
+    }
+
+    // always launches new streams, to bring back ones a device stopped serving
+    pub fn launch_streams(&mut self) {
+        self.audio.relaunch_streams();
+        self.log_stream_result();
+        self.load_click();
+    }
+
+    fn log_stream_result(&mut self) {
+        match self.audio.error {
+            Some(ref error) => {
+                let message = format!("failed to launch streams: {}", error);
+                self.error(message);
+            }
+            None => self.info("launched streams"),
+        }
+
+        if let Some(ref error) = self.audio.secondary_error {
+            let message = format!(
+                "failed to open the second input, recording from the first only: {}",
+                error
+            );
+            self.error(message);
         }
     }
 
@@ -93,303 +545,1813 @@ impl App {
         let mut last_frame = Instant::now();
 
         loop {
-            let timeout = self.frame_rate.saturating_sub(last_frame.elapsed());
-            if crossterm::event::poll(timeout)? {
-                self.event(crossterm::event::read()?);
+            // wait for input until the next frame is due
+            let mut timeout = self.redraw_interval().saturating_sub(last_frame.elapsed());
+
+            // events from other threads arrive on a channel, so check it often when expecting any
+            if self.expects_events() {
+                timeout = timeout.min(EVENT_POLL_INTERVAL);
             }
 
-            if last_frame.elapsed() >= self.frame_rate {
+            self.terminal_events(timeout)?;
+
+            self.app_events();
+
+            self.update();
+
+            if self.redraw || last_frame.elapsed() >= self.redraw_interval() {
                 terminal.draw(|frame| self.render(frame))?;
+                self.count_frame();
                 last_frame = Instant::now();
+                self.redraw = false;
+            }
+
+            if suspend::take_suspend() {
+                self.suspend(terminal)?;
+            }
+
+            if quit::take_interrupt() {
+                self.quit();
+                self.redraw = true;
             }
 
             if !self.running {
+                self.audio.shutdown();
                 break Ok(());
             }
         }
     }
 
+    // whether another thread might send events to the main loop
+    pub fn expects_events(&self) -> bool {
+        if self.audio.engine.armed_track().is_some() || self.resample_job.is_some() {
+            return true;
+        }
+
+        #[cfg(feature = "osc")]
+        if self.osc.is_some() {
+            return true;
+        }
+
+        self.midi.is_connected()
+    }
+
+    // the beat dependent widgets only animate while the streams are running
+    pub fn redraw_interval(&self) -> Duration {
+        if self.audio.engine.is_recording() {
+            self.frame_rate
+        } else {
+            IDLE_REDRAW_INTERVAL
+        }
+    }
+
+    pub fn cycle_frame_rate(&mut self) {
+        self.frame_rate_index = (self.frame_rate_index + 1) % FRAME_RATES.len();
+        self.frame_rate = Duration::from_millis(1000 / FRAME_RATES[self.frame_rate_index]);
+    }
+
+    // called every iteration of the main loop
+    pub fn update(&mut self) {
+        let track_count = self.audio.tracks.len();
+        self.selected_track = self.selected_track.min(track_count.saturating_sub(1));
+
+        let confirm_pending = matches!(
+            self.edit_mode,
+            EditMode::RemoveTrack(Some(_)) | EditMode::Paste(Some(_))
+        );
+
+        if confirm_pending && self.last_key.elapsed() >= REMOVE_TIMEOUT {
+            self.edit_mode = EditMode::None;
+        }
+
+        if self.archive_confirm.is_some() && self.last_key.elapsed() >= REMOVE_TIMEOUT {
+            self.archive_confirm = None;
+        }
+
+        self.audio.update_queued();
+        self.update_setup();
+        self.update_replace();
+        self.update_fade();
+        self.update_scenes();
+        self.update_meters();
+        self.update_pending_beats();
+        self.update_pedal();
+        self.update_preview();
+        self.finish_fades();
+        self.finish_ramps();
+        self.update_beat_flash();
+        self.check_sample_rates();
+    }
+
     pub fn event(&mut self, event: Event) {
         match event {
-            Event::Key(event) => self.key(event),
+            Event::Key(event) => {
+                self.key(event);
+                self.redraw = true;
+            }
+            Event::Mouse(_) | Event::Resize(_, _) => self.redraw = true,
             _ => {}
         }
     }
 
     pub fn key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char('q') => self.running = false,
-            KeyCode::F(5) => {
-                let _ = self.audio.launch_streams();
-            }
-            KeyCode::Esc => self.edit_mode = EditMode::None,
-            KeyCode::Tab => {
-                self.tab.rotate(1);
-                self.edit_mode = EditMode::None;
-            }
-            _ => {}
+        self.last_key = Instant::now();
+
+        // any key dismisses the startup warnings
+        if !self.warnings.is_empty() {
+            self.warnings.clear();
+            return;
         }
 
-        match self.tab {
-            Tab::Play => self.play_key(key),
-            Tab::Settings => self.settings_key(key),
+        if self.confirm_quit {
+            self.quit_key(key);
+            return;
         }
 
-        match key.code {
-            KeyCode::Up | KeyCode::Char('j') => self.rotate(1),
-            KeyCode::Down | KeyCode::Char('k') => self.rotate(-1),
-            _ => {}
+        if self.setup.is_some() {
+            self.setup_key(key);
+            return;
         }
-    }
 
-    // called when a key is pressed in the play tab
-    pub fn play_key(&mut self, key: KeyEvent) {
-        for i in 0..self.audio.tracks.len() {
-            let Some(digit) = char::from_digit((i as u32 + 1) % 10, 10) else {
-                continue;
-            };
+        if self.template.is_some() {
+            self.template_key(key);
+            return;
+        }
+
+        if self.notes_dialog.is_some() {
+            self.notes_key(key);
+            return;
+        }
+
+        if self.kit_prompt.is_some() {
+            self.kit_key(key);
+            return;
+        }
+
+        if self.duration_prompt.is_some() {
+            self.duration_key(key);
+            return;
+        }
+
+        if self.show_history {
+            self.history_key(key);
+            return;
+        }
 
-            if key.code == KeyCode::Char(digit) {
-                self.track_key(i);
+        if self.show_archive {
+            self.archive_key(key);
+            return;
+        }
+
+        // the export window takes its keys before the play tab, the digits among them left to it
+        if self.edit_mode == EditMode::ExportMix {
+            if let Some(action) = self.keymap.context_action(Context::Export, key) {
+                self.action(action);
+                return;
             }
         }
 
-        match key.code {
-            KeyCode::Char('b') => self.edit_mode = EditMode::Bpm,
-            KeyCode::Char('B') => self.edit_mode = EditMode::Beats,
-            KeyCode::Char('r') => self.edit_mode = EditMode::RecordTrack,
-            KeyCode::Char('R') => self.edit_mode = EditMode::RemoveTrack,
-            KeyCode::Char('v') => self.edit_mode = EditMode::TrackVolume(None),
-            KeyCode::Char('M') => {
-                let metronome = self.audio.engine.metronome();
-                self.audio.engine.set_metronome(!metronome);
-                self.audio.update_tracks();
+        if let EditMode::TrackPattern(Some(index)) = self.edit_mode {
+            if self.pattern_key(index, key) {
+                return;
             }
-            _ => {}
+        }
+
+        if let Some(action) = self.keymap.action(self.tab, key) {
+            self.action(action);
         }
     }
 
-    // called when a track key is pressed
-    pub fn track_key(&mut self, index: usize) {
-        match self.edit_mode {
-            EditMode::TrackVolume(_) => self.edit_mode = EditMode::TrackVolume(Some(index)),
-            EditMode::RemoveTrack => {
-                self.audio.tracks[index].clip = None;
-                self.audio.update_tracks();
+    pub fn action(&mut self, action: Action) {
+        if self.is_locked_out(action) {
+            return;
+        }
+
+        self.record_macro_action(action);
+
+        match action {
+            Action::Quit => self.quit(),
+            Action::LaunchStreams => self.launch_streams(),
+            Action::ToggleLog => self.toggle_log(),
+            Action::Kill => self.toggle_kill(),
+            Action::Suspend => suspend::request_suspend(),
+            Action::ToggleDebug => self.toggle_debug(),
+            Action::SaveSession => {
+                self.save_session();
             }
-            EditMode::RecordTrack => {
-                if let Some(clip) = self.audio.get_clip() {
-                    if let Some(ref mut current_clip) = self.audio.tracks[index].clip {
-                        let new_clip = current_clip.add(&clip, 1.0);
-                        self.audio.tracks[index].clip = Some(new_clip);
-                    } else {
-                        self.audio.tracks[index].clip = Some(clip);
-                    }
+            Action::SaveTemplate => self.open_template_prompt(),
+            Action::LoadTemplate => self.open_template_picker(),
+            Action::Macro(slot) => self.macro_key(slot),
+            Action::Cancel => {
+                let editing = self.edit_mode != EditMode::None;
+                self.edit_mode = EditMode::None;
 
-                    self.audio.update_tracks();
+                // the recording played while choosing a track isn't wanted anymore
+                if self.preview.as_ref().map(|(_, source)| *source) == Some(PreviewSource::Pending)
+                {
+                    self.stop_preview();
                 }
 
-                self.edit_mode = EditMode::None;
+                // with nothing else to cancel, a preview is discarded
+                if self.tab == Tab::Play && !editing {
+                    self.discard_preview();
+                }
+
+                if self.tab == Tab::Settings {
+                    self.revert_settings();
+                }
             }
-            _ => {
-                self.audio.tracks[index].toggle_mute();
-                self.audio.update_tracks();
+            Action::NextTab => {
+                self.tab.rotate(1);
+                self.edit_mode = EditMode::None;
+                self.probe = None;
             }
+            Action::RotateUp => self.rotate_key(1),
+            Action::RotateDown => self.rotate_key(-1),
+            Action::FineUp => self.rotate_fine(1),
+            Action::FineDown => self.rotate_fine(-1),
+            _ => match self.tab {
+                Tab::Play => self.play_action(action),
+                Tab::Settings => self.settings_action(action),
+            },
         }
     }
 
-    // called when a key is pressed in the settings tab
-    pub fn settings_key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char('h') => self.edit_mode = EditMode::Host,
-            KeyCode::Char('i') => self.edit_mode = EditMode::InputDevice,
-            KeyCode::Char('o') => self.edit_mode = EditMode::OutputDevice,
-            KeyCode::Char('r') => self.edit_mode = EditMode::SampleRate,
-            KeyCode::Char('b') => self.edit_mode = EditMode::BufferSize,
-            KeyCode::Char('d') => self.edit_mode = EditMode::Delay,
-            KeyCode::Char('m') => {
-                self.audio.settings.force_mono = !self.audio.settings.force_mono;
-                self.audio.launch_streams();
+    // called when an action is triggered in the play tab
+    pub fn play_action(&mut self, action: Action) {
+        match action {
+            // a digit after the fade key is a number of bars, 0 meaning ten
+            Action::Track(index) if matches!(self.edit_mode, EditMode::Fade(_)) => {
+                self.start_fade(index as u64 + 1);
             }
-            _ => {}
-        }
-    }
-
-    // called when a key is pressed to rotate the value of the current edit mode
-    pub fn rotate(&mut self, offset: i32) {
-        match self.edit_mode {
-            EditMode::Host => self.audio.settings.rotate_host(offset),
-            EditMode::InputDevice => self.audio.settings.rotate_input_device(offset),
-            EditMode::OutputDevice => self.audio.settings.rotate_output_device(offset),
-            EditMode::SampleRate => self.audio.settings.rotate_sample_rate(offset),
-            EditMode::BufferSize => self.audio.settings.rotate_buffer_size(offset),
-            EditMode::Delay => {
-                self.audio.settings.delay =
-                    (self.audio.settings.delay as i32 - offset).max(0) as u32;
+            Action::Track(index) if self.edit_mode == EditMode::SaveMacro => {
+                self.store_macro(index)
             }
-            EditMode::Bpm => {
-                let bpm = self.audio.engine.bpm();
-                let new = (bpm as i32 - offset).max(0) as u64;
-                self.audio.engine.set_bpm(new);
+            Action::Track(index) if matches!(self.edit_mode, EditMode::Scene(_)) => {
+                self.scene_digit(index);
             }
-            EditMode::Beats => {
-                let beats = self.audio.engine.beats();
-                let new = (beats as i32 - offset).max(0) as u64;
-                self.audio.engine.set_beats(new);
+            // a digit after the export key is a number of repeats, 0 meaning ten
+            Action::Track(index) if self.edit_mode == EditMode::ExportMix => {
+                self.export_mix(index as u32 + 1);
             }
-            EditMode::TrackVolume(Some(index)) => {
-                let track = &mut self.audio.tracks[index];
-                track.volume = (track.volume as i32 - offset * 5).clamp(0, 200) as u32;
+            // the first digit after the count key is a number of loops, the second a track
+            Action::Track(index) if self.edit_mode == EditMode::ArmLoops(None) => {
+                self.edit_mode = EditMode::ArmLoops(Some(index as u32 + 1));
+            }
+            Action::Track(index) if index < self.audio.tracks.len() => self.track_key(index),
+            Action::EditBpm => self.edit_mode = EditMode::Bpm,
+            Action::EditBars => self.edit_mode = EditMode::Bars,
+            Action::EditMeter => self.edit_mode = EditMode::Meter,
+            Action::RecordTrack => {
+                self.edit_mode = EditMode::RecordTrack;
+
+                // hear what's about to be committed while choosing the track
+                if !self.forward_recording {
+                    self.preview_pending();
+                }
+            }
+            Action::RemoveTrack => self.edit_mode = EditMode::RemoveTrack(None),
+            Action::ArchiveTrack => self.edit_mode = EditMode::ArchiveTrack,
+            Action::TrackVolume => self.edit_mode = EditMode::TrackVolume(None),
+            Action::TrackSend => self.edit_mode = EditMode::TrackSend(None),
+            Action::TrackInput => self.edit_mode = EditMode::TrackInput(None),
+            Action::TrackOffset => self.edit_mode = EditMode::TrackOffset(None),
+            Action::EditDelayBeats => self.edit_mode = EditMode::DelayBeats,
+            Action::EditDelayFeedback => self.edit_mode = EditMode::DelayFeedback,
+            Action::EditDelayMix => self.edit_mode = EditMode::DelayMix,
+            Action::EditLoudnessTarget => self.edit_mode = EditMode::LoudnessTarget,
+            Action::EditSceneFade => self.edit_mode = EditMode::SceneFade,
+            Action::SelectPrevious => self.select_track(-1),
+            Action::SelectNext => self.select_track(1),
+            Action::MuteSelected => self.toggle_mute(self.selected_track),
+            Action::SyncSelected => self.toggle_sync(self.selected_track),
+            Action::ReverseSelected => self.toggle_reverse(self.selected_track),
+            Action::CueSelected => self.toggle_cue(self.selected_track),
+            Action::VolumeSelected => {
+                self.edit_mode = EditMode::TrackVolume(Some(self.selected_track));
+            }
+            Action::SendSelected => {
+                self.edit_mode = EditMode::TrackSend(Some(self.selected_track));
+            }
+            Action::InputSelected => {
+                self.edit_mode = EditMode::TrackInput(Some(self.selected_track));
+            }
+            Action::OffsetSelected => {
+                self.edit_mode = EditMode::TrackOffset(Some(self.selected_track));
+            }
+            Action::ColorSelected => {
+                self.edit_mode = EditMode::TrackColor(Some(self.selected_track));
+            }
+            Action::GroupSelected => {
+                self.edit_mode = EditMode::TrackGroup(Some(self.selected_track));
+            }
+            Action::DampingSelected => {
+                self.edit_mode = EditMode::TrackDamping(Some(self.selected_track));
+            }
+            Action::DriveSelected => {
+                self.edit_mode = EditMode::TrackDrive(Some(self.selected_track));
+            }
+            Action::PatternSelected => {
+                self.edit_mode = EditMode::TrackPattern(Some(self.selected_track));
+            }
+            Action::RegionSelected => {
+                self.edit_mode = EditMode::TrackRegion(Some(self.selected_track));
+            }
+            Action::RecordSelected => self.record_track(self.selected_track),
+            Action::RemoveSelected => self.remove_track(self.selected_track),
+            Action::ToggleZoom => self.toggle_zoom(),
+            Action::CycleLayout => self.cycle_layout(),
+            Action::Yank => self.edit_mode = EditMode::Yank,
+            Action::Paste => self.edit_mode = EditMode::Paste(None),
+            Action::ExportClipboard => self.export_clipboard(),
+            Action::ExportMix => self.edit_mode = EditMode::ExportMix,
+            Action::CycleExportFormat
+            | Action::ToggleExportNormalize
+            | Action::ToggleExportDither
+            | Action::ToggleExportStems => self.export_action(action),
+            Action::ArmLoops => self.edit_mode = EditMode::ArmLoops(None),
+            Action::ArmEmpty => self.arm_empty_track(),
+            Action::CaptureTake => self.capture_take(),
+            Action::ImportKit => self.open_kit_prompt(),
+            Action::EditLoopDuration => self.open_duration_prompt(),
+            Action::EditNotes => self.open_notes(),
+            Action::MidiLearn => self.midi_learn(),
+            Action::Pedal => self.pedal(),
+            Action::UndoLayer => self.undo_layer(),
+            Action::ToggleHistory => self.toggle_history(),
+            Action::ToggleLock => self.toggle_lock(),
+            Action::ToggleArchive => self.toggle_archive(),
+            Action::ToggleCompare => self.toggle_compare(),
+            Action::KeepCompared => self.keep_compared(),
+            Action::ToggleStutter => self.toggle_stutter(),
+            Action::ToggleReplace => self.toggle_replace(),
+            Action::FadeOut => self.fade_key(),
+            Action::ToggleMeterMode => self.toggle_meter_mode(),
+            Action::RecordMacro => self.toggle_macro_recording(),
+            Action::CycleGate => self.cycle_gate_mode(),
+            Action::CycleInputFollow => self.cycle_input_follow(),
+            Action::CycleStack => self.cycle_stack_mode(),
+            Action::EditGateThreshold => self.edit_mode = EditMode::GateThreshold,
+            Action::ShiftDownbeat => self.edit_mode = EditMode::Downbeat,
+            Action::FreeRecord => self.toggle_free_recording(),
+            Action::SaveScene => self.scene_key(true),
+            Action::SwitchScene => self.scene_key(false),
+            Action::UndoScene => self.undo_scene(),
+            Action::TogglePreview => self.toggle_preview(),
+            Action::TogglePreviewDim => self.toggle_preview_dim(),
+            Action::CycleStutterLength => self.cycle_stutter_length(),
+            Action::CycleAnalysis => self.cycle_analysis(),
+            Action::ResampleTracks => self.resample_tracks(),
+            Action::HalveLoop => self.halve_loop(),
+            Action::DoubleLoop => self.double_loop(),
+            Action::ToggleMetronome => {
+                let pattern = self.audio.engine.metronome().cycled();
+                self.audio.engine.set_metronome(pattern);
                 self.audio.update_tracks();
             }
             _ => {}
         }
+    }
 
-        if self.tab == Tab::Settings && self.edit_mode != EditMode::None {
-            if let Some(sample_rate) = self.audio.settings.get_sample_rate() {
-                self.audio.tracks.resample(sample_rate);
-            }
+    // called when a track key is pressed
+    pub fn track_key(&mut self, index: usize) {
+        self.selected_track = index;
 
-            self.audio.launch_streams();
+        match self.edit_mode {
+            EditMode::TrackVolume(_) => self.edit_mode = EditMode::TrackVolume(Some(index)),
+            EditMode::TrackSend(_) => self.edit_mode = EditMode::TrackSend(Some(index)),
+            EditMode::TrackInput(_) => self.edit_mode = EditMode::TrackInput(Some(index)),
+            EditMode::TrackOffset(_) => self.edit_mode = EditMode::TrackOffset(Some(index)),
+            EditMode::TrackColor(_) => self.edit_mode = EditMode::TrackColor(Some(index)),
+            EditMode::TrackGroup(_) => self.edit_mode = EditMode::TrackGroup(Some(index)),
+            EditMode::TrackDamping(_) => self.edit_mode = EditMode::TrackDamping(Some(index)),
+            EditMode::TrackDrive(_) => self.edit_mode = EditMode::TrackDrive(Some(index)),
+            EditMode::TrackPattern(_) => self.edit_mode = EditMode::TrackPattern(Some(index)),
+            EditMode::TrackRegion(_) => self.edit_mode = EditMode::TrackRegion(Some(index)),
+            EditMode::RemoveTrack(_) => self.remove_track(index),
+            EditMode::ArchiveTrack => self.archive_track(index),
+            EditMode::RecordTrack => self.record_track(index),
+            EditMode::Yank => self.yank_track(index),
+            EditMode::Paste(_) => self.paste_track(index),
+            EditMode::ArmLoops(Some(loops)) => self.arm_loops(index, loops),
+            _ => self.toggle_mute(index),
         }
     }
 
-    pub fn render<B: Backend>(&mut self, frame: &mut Frame<B>) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(1), Constraint::Percentage(100)])
-            .split(frame.size());
+    pub fn toggle_mute(&mut self, index: usize) {
+        // the rest of an exclusive group goes quiet in the same update
+        self.audio.tracks.toggle_mute(index);
+        self.audio.update_tracks();
 
-        self.render_tab_select(frame, chunks[0]);
-        self.render_main_tab(frame, chunks[1]);
+        // touching a track brings the sound back
+        if self.audio.engine.is_killed() {
+            self.audio.engine.set_killed(false);
+            self.info("output restored");
+        }
     }
 
-    pub fn render_tab_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let tabs = Tabs::new(vec![Spans::from("Play"), Spans::from("Settings")])
-            .select(self.tab as usize)
-            .highlight_style(Style::default().fg(Color::Yellow))
-            .divider(DOT);
-
-        frame.render_widget(tabs, area);
+    // runs the monitored input through the armed track's send, and its volume too
+    pub fn cycle_input_follow(&mut self) {
+        let engine = &self.audio.engine;
+        let follow = engine.input_follow().cycled();
+        engine.set_input_follow(follow);
+
+        match follow {
+            InputFollow::Off => self.info("the input is monitored as it is"),
+            InputFollow::Send => self.info("the input follows the send of the armed track"),
+            InputFollow::SendAndVolume => {
+                self.info("the input follows the send and volume of the armed track")
+            }
+        }
     }
 
-    pub fn render_main_tab<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .title(self.tab.name());
-
-        frame.render_widget(block, area);
-
-        match self.tab {
-            Tab::Play => self.render_play(frame, area),
-            Tab::Settings => self.render_settings(frame, area),
+    // pre-listens a track on the cue channels, or in place of the mix without any
+    pub fn toggle_cue(&mut self, index: usize) {
+        let track = &mut self.audio.tracks[index];
+        track.cue = !track.cue;
+        let cue = track.cue;
+
+        self.audio.update_tracks();
+
+        let number = (index + 1) % 10;
+        if !cue {
+            self.info(format!("stopped cueing track {}", number));
+        } else if self.audio.engine.output_channels() > self.audio.engine.cue_channel() {
+            self.info(format!(
+                "cueing track {} on output channels {} and up",
+                number,
+                self.audio.engine.cue_channel() + 1
+            ));
+        } else {
+            self.warn(format!(
+                "the output has no cue channels, soloing track {} in place",
+                number
+            ));
         }
     }
 
-    pub fn render_settings<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let chunks = Layout::default()
-            .margin(1)
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Max(16), Constraint::Max(16)])
-            .split(area);
-
-        self.render_device_select(frame, chunks[0]);
-        self.render_device_config(frame, chunks[1]);
-    }
+    // lets a track loop on the length of its own clip, or restart with the loop again
+    pub fn toggle_sync(&mut self, index: usize) {
+        let track = &mut self.audio.tracks[index];
+        track.synced = !track.synced;
 
-    pub fn render_device_config<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length(20),
-                Constraint::Length(20),
-                Constraint::Length(20),
-                Constraint::Min(1),
-            ])
-            .split(area);
+        let message = if track.synced {
+            format!("track {} restarts with the loop", (index + 1) % 10)
+        } else {
+            format!("track {} runs free", (index + 1) % 10)
+        };
 
-        self.render_sample_rate_select(frame, chunks[0]);
-        self.render_buffer_size_select(frame, chunks[1]);
-        self.render_delay_mono_bar(frame, chunks[2]);
-        self.render_error(frame, chunks[3]);
+        self.audio.update_tracks();
+        self.info(message);
     }
 
-    pub fn render_delay_mono_bar<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Min(1),
-            ])
-            .split(area);
+    // plays a track backwards, or forwards again
+    pub fn toggle_reverse(&mut self, index: usize) {
+        let track = &mut self.audio.tracks[index];
+        track.reversed = !track.reversed;
 
-        self.render_delay_select(frame, chunks[0]);
-        self.render_mono_select(frame, chunks[1]);
-    }
+        let message = if track.reversed {
+            format!("track {} plays backwards", (index + 1) % 10)
+        } else {
+            format!("track {} plays forwards", (index + 1) % 10)
+        };
 
-    pub fn render_sample_rate_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let sample_rates = self
-            .audio
-            .settings
-            .sample_rates
-            .iter()
-            .map(|rate| ListItem::new(format!("{}", rate.0)));
+        self.audio.update_tracks();
+        self.info(message);
+    }
 
-        self.settings
-            .sample_rate_state
-            .select(self.audio.settings.sample_rate);
+    // silences all output, or restores it
+    pub fn toggle_kill(&mut self) {
+        let killed = !self.audio.engine.is_killed();
+        self.audio.engine.set_killed(killed);
 
-        let mut block = Block::default()
-            .title("Sample Rate 'r'")
-            .borders(Borders::ALL);
+        if killed {
+            self.warn("output killed");
+        } else {
+            self.info("output restored");
+        }
+    }
 
-        if self.edit_mode == EditMode::SampleRate {
-            block = block.border_style(Style::default().fg(Color::Red));
+    // records the next loop into a track, or commits the last one with forward recording off
+    pub fn record_track(&mut self, index: usize) {
+        if self.forward_recording {
+            self.arm_track(index);
+        } else {
+            self.commit_recording(index);
         }
 
-        let list = List::new(sample_rates.collect::<Vec<_>>())
-            .block(block)
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+        self.edit_mode = EditMode::None;
+    }
 
-        frame.render_stateful_widget(list, area, &mut self.settings.sample_rate_state);
+    // arms a track to record the next whole loop, or disarms it if it already is
+    //
+    // a track with a clip is overdubbed from right where it was armed instead, since the loop
+    // it layers onto is already playing
+    pub fn arm_track(&mut self, index: usize) {
+        let engine = &self.audio.engine;
+        let armed = engine.armed_track() == Some(index);
+        // a stacked loop lands on an empty track, so there's nothing to punch into
+        let overdub = self.audio.tracks[index].clip.is_some() && !self.stack_mode.is_on();
+
+        // the output callback starts the recording
+        engine.set_armed_recording(false);
+
+        // arming or disarming by hand ends a count out
+        self.cancel_count_out();
+        self.arm_empty = false;
+        let engine = &self.audio.engine;
+
+        if armed {
+            engine.set_armed_track(None);
+            engine.set_punch_in(false);
+            self.info(format!("disarmed track {}", (index + 1) % 10));
+        } else if overdub {
+            engine.set_armed_track(Some(index));
+            engine.set_punch_in(true);
+            self.info(format!(
+                "overdubbing one loop onto track {} from here",
+                (index + 1) % 10
+            ));
+        } else {
+            engine.set_armed_track(Some(index));
+            engine.set_punch_in(false);
+            self.info(format!(
+                "armed track {}, recording starts with the next loop",
+                (index + 1) % 10
+            ));
+        }
     }
 
-    pub fn render_buffer_size_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let buffer_sizes = self
-            .audio
-            .settings
-            .buffer_sizes
-            .iter()
-            .map(|size| ListItem::new(format!("{}", size)));
+    // arms the lowest track without a clip
+    pub fn arm_empty_track(&mut self) {
+        let Some(index) = self.audio.tracks.first_empty() else {
+            self.error("every track has a clip, nothing to arm");
+            return;
+        };
 
-        self.settings
-            .buffer_size_state
-            .select(self.audio.settings.buffer_size);
+        let armed = self.audio.engine.armed_track() == Some(index);
 
-        let mut block = Block::default()
-            .title("Buffer Size 'b'")
-            .borders(Borders::ALL);
+        self.selected_track = index;
+        self.arm_track(index);
+        self.arm_empty = !armed;
+    }
 
-        if self.edit_mode == EditMode::BufferSize {
-            block = block.border_style(Style::default().fg(Color::Red));
+    // picks the track a loop armed into the first empty track lands on, the next empty one if
+    // it was filled while recording, None if it's dropped
+    fn empty_target(&mut self, index: usize) -> Option<usize> {
+        if !self.arm_empty {
+            return Some(index);
         }
 
-        let list = List::new(buffer_sizes.collect::<Vec<_>>())
-            .block(block)
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
-
-        frame.render_stateful_widget(list, area, &mut self.settings.buffer_size_state);
-    }
+        // the pick lasts as long as the track stays armed
+        if self.audio.engine.armed_track().is_none() {
+            self.arm_empty = false;
+        }
 
-    pub fn render_delay_select<B: Backend>(&mut self, frame: &mut Frame<B>, mut area: Rect) {
-        let mut block = Block::default().borders(Borders::ALL).title("Delay 'd'");
+        if self.audio.tracks[index].clip.is_none() {
+            return Some(index);
+        }
+
+        match self.audio.tracks.first_empty() {
+            Some(target) => {
+                self.warn(format!(
+                    "track {} was filled meanwhile, the loop goes to track {}",
+                    (index + 1) % 10,
+                    (target + 1) % 10
+                ));
+                Some(target)
+            }
+            None => {
+                self.error("every track was filled meanwhile, dropped the loop");
+                None
+            }
+        }
+    }
+
+    // called when the loop an armed track was recording has ended
+    pub fn armed_recorded(&mut self, index: usize, clip: Clip) {
+        // a punch in starts partway through the loop, so turn it to line up with the layers
+        let offset = self.audio.engine.arm_offset();
+        let clip = if offset > 0 {
+            clip.rotated(offset)
+        } else {
+            clip.analyze()
+        };
+
+        let Some(index) = self.empty_target(index) else {
+            return;
+        };
+
+        let Some(index) = self.stack_target(index) else {
+            return;
+        };
+
+        self.commit_clip(index, clip);
+        self.finish_full_stack();
+        self.count_recorded_loop();
+    }
+
+    // commits the last recorded loop to a track
+    pub fn commit_recording(&mut self, index: usize) {
+        match self.audio.get_clip() {
+            Some(clip) => self.commit_clip(index, clip),
+            None => self.warn("nothing has been recorded yet"),
+        }
+    }
+
+    // picks the channels a track records out of a recording of the whole input
+    fn track_input(&mut self, index: usize, clip: Clip) -> Clip {
+        match self.audio.tracks[index].input_channel {
+            Some(channel) if channel < clip.channels => return clip.channel(channel),
+            Some(channel) => self.warn(format!(
+                "the input has no channel {}, recorded all of it into track {}",
+                channel + 1,
+                (index + 1) % 10
+            )),
+            None => {}
+        }
+
+        if self.audio.engine.is_mono_input() {
+            clip.with_channels(1)
+        } else {
+            clip
+        }
+    }
+
+    // puts a recorded clip into a track, layering it onto an existing clip
+    pub fn commit_clip(&mut self, index: usize, clip: Clip) {
+        // a take played against two scenes at once would layer onto only one of them
+        if self.audio.tracks[index].clip.is_some() && self.audio.engine.scene_fade().is_some() {
+            self.warn(format!(
+                "can't overdub track {} while scenes crossfade, dropped the take",
+                (index + 1) % 10
+            ));
+            self.stop_preview();
+            return;
+        }
+
+        let clip = self.track_input(index, clip);
+
+        // a broken take would otherwise end up in every later overdub too
+        let (clip, report) = clip.sanitized();
+        let problems = report.problems();
+
+        if !problems.is_empty() {
+            let message = format!(
+                "the take for track {} has {}",
+                (index + 1) % 10,
+                problems.join(", ")
+            );
+
+            if self.guard_recordings && report.is_unsafe() {
+                self.error(format!("{}, refused it", message));
+                self.stop_preview();
+                return;
+            }
+
+            self.warn(message);
+        }
+
+        let number = (index + 1) % 10;
+        if self.audio.tracks[index].clip.is_some() {
+            self.push_layer(index, format!("overdub track {}", number));
+        } else {
+            self.push_layer(index, format!("record track {}", number));
+        }
+
+        let track = &mut self.audio.tracks[index];
+        if let Some(ref mut current_clip) = track.clip {
+            // the layers already there recede a little with every one added
+            if let Some(coefficient) = track.generation_damping {
+                *current_clip = current_clip.damped(coefficient);
+            }
+
+            // the input channel may have changed since the track was first recorded
+            let mut clip = clip.with_channels(current_clip.channels);
+
+            // line the take up with the shifted playback it was played against
+            let frame_count = current_clip.frame_count() as i64;
+            if track.offset_frames != 0 && frame_count > 0 {
+                clip = clip.rotated((-track.offset_frames).rem_euclid(frame_count) as u64);
+            }
+
+            // and with the clip played backwards, so the take sounds where it was played
+            if track.reversed && frame_count > 0 {
+                clip = clip.fit(frame_count as u64).reversed();
+            }
+
+            let new_clip = current_clip.add(&clip, 1.0);
+            track.clip = Some(compact_large(new_clip));
+
+            // the region grows over the take instead of cutting it off
+            if let Some(sounding) = clip.sounding_frames(REGION_SILENCE) {
+                track.extend_region(sounding);
+            }
+            self.info(format!("overdubbed track {}", (index + 1) % 10));
+        } else {
+            // a fresh take is already in time, and keeps its color from here on
+            track.offset_frames = 0;
+            track.reversed = false;
+            track.region = None;
+            track.color.get_or_insert(index);
+            track.clip = Some(compact_large(clip));
+            self.info(format!("recorded into track {}", (index + 1) % 10));
+        }
+
+        self.auto_gain(index);
+
+        // set before the tracks are sent, so the callback can't pick them up first
+        self.hand_off_preview();
+        self.audio.update_tracks();
+    }
+
+    // removes the clip of a track, the first call only asks for confirmation
+    pub fn remove_track(&mut self, index: usize) {
+        let pending = match self.edit_mode {
+            EditMode::RemoveTrack(pending) => pending,
+            _ => None,
+        };
+
+        if self.audio.tracks[index].clip.is_none() {
+            self.edit_mode = EditMode::None;
+        } else if pending == Some(index) {
+            self.push_layer(index, format!("remove track {}", (index + 1) % 10));
+            self.clear_track(index);
+            self.info(format!("removed track {}", (index + 1) % 10));
+
+            self.edit_mode = EditMode::None;
+        } else {
+            // require a second press of the same track to confirm
+            self.edit_mode = EditMode::RemoveTrack(Some(index));
+        }
+    }
+
+    // takes the clip off a track along with what only made sense for it
+    pub fn clear_track(&mut self, index: usize) {
+        let track = &mut self.audio.tracks[index];
+        track.clip = None;
+        track.color = None;
+        track.region = None;
+        self.audio.update_tracks();
+    }
+
+    pub fn halve_loop(&mut self) {
+        let beats = self.target_beats();
+
+        if beats < 2 || beats % 2 == 1 {
+            self.warn(format!("can't halve a loop of {} beats", beats));
+            return;
+        }
+
+        self.resize_loop(beats / 2);
+    }
+
+    pub fn double_loop(&mut self) {
+        let beats = self.target_beats();
+
+        if beats == 0 || beats * 2 > MAX_BEATS {
+            self.warn(format!("can't double a loop of {} beats", beats));
+            return;
+        }
+
+        self.resize_loop(beats * 2);
+    }
+
+    // the loop length after any queued change, so repeated presses compose
+    fn target_beats(&self) -> u64 {
+        let engine = &self.audio.engine;
+        self.audio.pending_beats.unwrap_or_else(|| engine.beats())
+    }
+
+    // the bars the loop spans once a pending resize lands
+    fn target_bars(&self) -> u64 {
+        let beats_per_bar = self.audio.engine.beats_per_bar();
+        self.target_beats().div_ceil(beats_per_bar)
+    }
+
+    // the most bars of the current meter that fit in the longest loop
+    fn max_bars(&self) -> u64 {
+        (MAX_BEATS / self.audio.engine.beats_per_bar()).max(1)
+    }
+
+    // resizes the loop to a number of bars at the next loop boundary
+    fn rotate_bars(&mut self, offset: i32) {
+        let bars = (self.target_bars() as i64 - offset as i64).clamp(1, self.max_bars() as i64);
+        let beats = self.audio.engine.bars_beats(bars as u64);
+
+        self.resize_loop(beats);
+    }
+
+    // changes how many beats make up a bar, resizing the loop at the next loop boundary so it
+    // spans as many bars as before
+    fn rotate_meter(&mut self, offset: i32) {
+        let bars = self.target_bars();
+        let beats_per_bar = self.audio.engine.beats_per_bar() as i64 - offset as i64;
+
+        self.audio
+            .engine
+            .set_beats_per_bar(beats_per_bar.clamp(1, MAX_BEATS_PER_BAR as i64) as u64);
+
+        let beats = self.audio.engine.bars_beats(bars.min(self.max_bars()));
+        self.resize_loop(beats);
+    }
+
+    // queues a new loop length for the next loop boundary, or applies it right away when the
+    // streams aren't running
+    fn resize_loop(&mut self, beats: u64) {
+        let engine = &self.audio.engine;
+
+        if !engine.is_recording() {
+            let frames = engine.beats_frames(beats);
+            engine.set_beats(beats);
+
+            // without a sample rate there's nothing to fit the clips to
+            if frames > 0 {
+                self.audio.tracks = self.audio.tracks.fitted(frames);
+                self.audio.update_tracks();
+            }
+
+            return;
+        }
+
+        if beats == engine.beats() {
+            self.audio.cancel_pending_beats();
+        } else {
+            self.audio.queue_beats(beats);
+        }
+    }
+
+    // changes the tempo at the next loop or beat, or right away with `now` or when there's no
+    // loop playing to wait for
+    pub fn change_bpm(&mut self, bpm: u64, now: bool) {
+        let engine = &self.audio.engine;
+
+        // without a tempo the loop never reaches its boundary
+        if now || !engine.is_recording() || engine.bpm() == 0 {
+            engine.set_bpm(bpm);
+        } else {
+            engine.queue_bpm(bpm);
+        }
+
+        // the queued tracks were fitted at the old tempo
+        if let Some(beats) = self.audio.pending_beats {
+            self.audio.queue_beats(beats);
+        }
+    }
+
+    // takes over the fitted tracks once the output callback has applied a queued loop length
+    fn update_pending_beats(&mut self) {
+        let Some(beats) = self.audio.pending_beats else {
+            return;
+        };
+
+        if self
+            .audio
+            .is_queued(|action| matches!(action, QueuedAction::Resize { .. }))
+        {
+            return;
+        }
+
+        let frames = self.audio.engine.beats_frames(beats);
+        self.audio.tracks = self.audio.tracks.fitted(frames);
+        self.audio.pending_beats = None;
+
+        self.info(format!("the loop is now {} beats", beats));
+    }
+
+    // warns once whenever the set of tracks at the wrong sample rate changes
+    pub fn check_sample_rates(&mut self) {
+        // the tracks are about to be replaced
+        if self.resample_job.is_some() {
+            return;
+        }
+
+        let sample_rate = self.audio.engine.sample_rate();
+
+        let mismatched = (0..self.audio.tracks.len())
+            .filter(|&index| self.audio.tracks[index].rate_mismatch(sample_rate))
+            .collect::<Vec<_>>();
+
+        if mismatched == self.mismatched_tracks {
+            return;
+        }
+
+        if !mismatched.is_empty() {
+            let tracks = mismatched
+                .iter()
+                .map(|index| ((index + 1) % 10).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let (noun, verb) = if mismatched.len() == 1 {
+                ("track", "doesn't")
+            } else {
+                ("tracks", "don't")
+            };
+
+            self.warn(format!(
+                "{} {} {} match the {} Hz stream, press {} to resample",
+                noun,
+                tracks,
+                verb,
+                sample_rate,
+                self.keymap.key_name(Action::ResampleTracks)
+            ));
+        }
+
+        self.mismatched_tracks = mismatched;
+    }
+
+    // resamples every clip to the rate the streams run at
+    pub fn resample_tracks(&mut self) {
+        let sample_rate = self.audio.engine.sample_rate();
+
+        if sample_rate == 0 {
+            self.warn("launch the streams to resample");
+            return;
+        }
+
+        self.start_resample(SampleRate(sample_rate as u32));
+    }
+
+    pub fn select_track(&mut self, offset: i32) {
+        let count = self.audio.tracks.len() as i32;
+
+        if count > 0 {
+            self.selected_track = (self.selected_track as i32 + offset).rem_euclid(count) as usize;
+        }
+    }
+
+    // called when an action is triggered in the settings tab
+    pub fn settings_action(&mut self, action: Action) {
+        match action {
+            Action::EditHost => self.edit_mode = EditMode::Host,
+            Action::EditInputDevice => self.edit_mode = EditMode::InputDevice,
+            Action::EditSecondaryInputDevice => self.edit_mode = EditMode::SecondaryInputDevice,
+            Action::EditOutputDevice => self.edit_mode = EditMode::OutputDevice,
+            Action::EditSampleRate => self.edit_mode = EditMode::SampleRate,
+            Action::EditBufferSize => self.edit_mode = EditMode::BufferSize,
+            Action::EditDelay => self.edit_mode = EditMode::Delay,
+            Action::EditTailFold => self.edit_mode = EditMode::TailFold,
+            Action::EditCueChannel => self.edit_mode = EditMode::CueChannel,
+            Action::EditMidiPort => self.edit_mode = EditMode::MidiPort,
+            Action::CycleTheme => {
+                self.theme_index = (self.theme_index + 1) % Theme::COUNT;
+                self.theme = Theme::from_index(self.theme_index);
+            }
+            Action::CycleFrameRate => self.cycle_frame_rate(),
+            Action::ToggleForwardRecording => {
+                self.forward_recording = !self.forward_recording;
+
+                // an armed track would otherwise still record with the setting off
+                if !self.forward_recording {
+                    self.audio.engine.set_armed_track(None);
+                    self.audio.engine.set_punch_in(false);
+                }
+            }
+            Action::ToggleGuard => self.guard_recordings = !self.guard_recordings,
+            Action::ToggleAutoGain => self.auto_gain = !self.auto_gain,
+            Action::ToggleSuspendMute => self.mute_on_suspend = !self.mute_on_suspend,
+            Action::ToggleDryRecording => {
+                let engine = &self.audio.engine;
+                engine.set_recording_dry(!engine.is_recording_dry());
+            }
+            Action::ToggleTempoTiming => {
+                let engine = &self.audio.engine;
+                engine.set_tempo_timing(engine.tempo_timing().toggled());
+            }
+            Action::ToggleMono => {
+                let settings = self.staged_settings();
+                settings.force_mono = !settings.force_mono;
+            }
+            Action::ApplySettings => self.apply_settings(),
+            Action::ToggleProbe => self.toggle_probe(),
+            _ => {}
+        }
+    }
+
+    // the settings shown in the settings tab, including staged changes
+    pub fn shown_settings(&self) -> &AudioSettings {
+        self.staged.as_ref().unwrap_or(&self.audio.settings)
+    }
+
+    // the staged copy of the settings, created from the live settings on first use
+    pub fn staged_settings(&mut self) -> &mut AudioSettings {
+        self.staged
+            .get_or_insert_with(|| self.audio.settings.clone())
+    }
+
+    // returns true if a staged value differs from the live one
+    pub fn is_staged<T: PartialEq>(&self, value: impl Fn(&AudioSettings) -> T) -> bool {
+        match self.staged {
+            Some(ref staged) => value(staged) != value(&self.audio.settings),
+            None => false,
+        }
+    }
+
+    // formats a settings title, marking it with a `*` when it has staged changes
+    pub fn staged_title(&self, label: &str, action: Action, staged: bool) -> String {
+        let title = self.key_title(label, action);
+
+        if staged {
+            format!("{}*", title)
+        } else {
+            title
+        }
+    }
+
+    // makes the staged settings live, resampling tracks and relaunching the streams once
+    pub fn apply_settings(&mut self) {
+        if let Some(Err(err)) = self.staged.as_ref().map(AudioSettings::validate) {
+            self.error(format!("can't apply: {}", err));
+            return;
+        }
+
+        let Some(settings) = self.staged.take() else {
+            return;
+        };
+
+        let relaunch = self.audio.settings.needs_relaunch(&settings);
+
+        self.audio.settings = settings;
+        self.edit_mode = EditMode::None;
+
+        // the delay and tail fold change on the running streams, without a dropout
+        if relaunch || !self.audio.engine.is_recording() {
+            if let Some(sample_rate) = self.audio.settings.get_sample_rate() {
+                self.start_resample(sample_rate);
+            }
+
+            // streams built the same way keep running
+            if self.audio.launch_streams() {
+                self.log_stream_result();
+            } else {
+                self.info("applied settings, the streams were built the same way");
+            }
+
+            self.load_click();
+        } else {
+            self.audio.apply_live_settings();
+            self.info("applied settings");
+        }
+
+        self.save_audio_settings();
+    }
+
+    // remembers the live settings for the next launch
+    pub fn save_audio_settings(&mut self) {
+        if let Err(err) = self.audio.settings.save() {
+            self.error(format!("failed to save audio settings: {}", err));
+        }
+    }
+
+    pub fn revert_settings(&mut self) {
+        if self.staged.take().is_some() {
+            self.info("reverted staged settings");
+        }
+    }
+
+    // scrolls whatever list is open, or rotates the value of the current edit mode
+    pub fn rotate_key(&mut self, offset: i32) {
+        if self.show_log {
+            self.scroll_log(offset);
+        } else if self.probe.is_some() {
+            self.scroll_probe(offset);
+        } else {
+            self.rotate(offset);
+        }
+    }
+
+    // called when a key is pressed to rotate the value of the current edit mode
+    pub fn rotate(&mut self, offset: i32) {
+        match self.edit_mode {
+            EditMode::Host => {
+                let settings = self.staged_settings();
+                if let Err(err) = settings.rotate_host(offset) {
+                    self.error(err.to_string());
+                    return;
+                }
+
+                let settings = self.shown_settings();
+                let host = settings.host_id().name();
+                let inputs = settings.input_device_count();
+                let outputs = settings.output_device_count();
+                self.info(format!(
+                    "{}: found {} input and {} output devices",
+                    host, inputs, outputs
+                ));
+            }
+            EditMode::InputDevice => self.staged_settings().rotate_input_device(offset),
+            EditMode::SecondaryInputDevice => {
+                self.staged_settings().rotate_secondary_input_device(offset)
+            }
+            EditMode::OutputDevice => self.staged_settings().rotate_output_device(offset),
+            EditMode::SampleRate => self.staged_settings().rotate_sample_rate(offset),
+            EditMode::BufferSize => self.staged_settings().rotate_buffer_size(offset),
+            EditMode::Delay => {
+                let settings = self.staged_settings();
+                settings.delay = (settings.delay as i32 - offset).clamp(0, MAX_DELAY as i32) as u32;
+            }
+            EditMode::TailFold => {
+                let settings = self.staged_settings();
+                let ms = settings.tail_fold as i32 - offset * TAIL_FOLD_STEP;
+                settings.tail_fold = ms.clamp(0, MAX_TAIL_FOLD) as u32;
+            }
+            EditMode::CueChannel => {
+                // the first channel always plays the main mix
+                let settings = self.staged_settings();
+                let channel = settings.cue_channel as i32 - offset;
+                settings.cue_channel = channel.clamp(1, MAX_CUE_CHANNEL as i32) as u16;
+            }
+            EditMode::Bpm => {
+                let bpm = self.audio.engine.target_bpm();
+                self.change_bpm((bpm as i32 - offset).max(0) as u64, false);
+            }
+            EditMode::Bars => self.rotate_bars(offset),
+            EditMode::Meter => self.rotate_meter(offset),
+            EditMode::MidiPort => self.rotate_midi_port(offset),
+            EditMode::TrackVolume(Some(index)) => {
+                self.settle_ramp(index);
+
+                let track = &mut self.audio.tracks[index];
+                track.volume = (track.volume as i32 - offset * 5).clamp(0, 200) as u32;
+                self.audio.update_tracks();
+            }
+            EditMode::TrackSend(Some(index)) => {
+                let track = &mut self.audio.tracks[index];
+                track.send = (track.send - offset as f32 * 0.05).clamp(0.0, 1.0);
+                self.audio.update_tracks();
+            }
+            EditMode::TrackInput(Some(index)) => self.rotate_track_input(index, offset),
+            EditMode::TrackOffset(Some(index)) => {
+                let beat = 60.0 / self.audio.engine.bpm().max(1) as f32;
+                self.shift_track(index, -offset as f32 * beat);
+            }
+            EditMode::TrackColor(Some(index)) => {
+                let count = self.theme.palette.len() as i64;
+                let track = &mut self.audio.tracks[index];
+                let color = track.color.unwrap_or(index) as i64 - offset as i64;
+                track.color = Some(color.rem_euclid(count) as usize);
+            }
+            EditMode::TrackGroup(Some(index)) => {
+                // 0 is no group, the groups follow from 1
+                let track = &mut self.audio.tracks[index];
+                let group = track.exclusive_group.map_or(0, |group| group as i32 + 1) - offset;
+                let group = group.rem_euclid(EXCLUSIVE_GROUPS as i32 + 1);
+                track.exclusive_group = (group > 0).then(|| group as u8 - 1);
+            }
+            EditMode::TrackDamping(Some(index)) => {
+                // in steps of 5%, off at 0
+                let track = &mut self.audio.tracks[index];
+                let steps = track
+                    .generation_damping
+                    .map_or(0, |d| (d * 20.0).round() as i32);
+                let steps = (steps - offset).clamp(0, MAX_DAMPING_STEPS);
+                track.generation_damping = (steps > 0).then(|| steps as f32 / 20.0);
+            }
+            EditMode::TrackDrive(Some(index)) => {
+                // in steps of 5%, so it lands exactly on 0 to bypass it
+                let track = &mut self.audio.tracks[index];
+                let steps = (track.drive * 20.0).round() as i32;
+                let steps = (steps - offset).clamp(0, MAX_DRIVE_STEPS);
+                track.drive = steps as f32 / 20.0;
+                self.audio.update_tracks();
+            }
+            EditMode::TrackPattern(Some(index)) => self.rotate_pattern(index, offset),
+            EditMode::TrackRegion(Some(index)) => self.move_region_end(index, -offset),
+            EditMode::DelayBeats => {
+                let beats = self.audio.engine.delay_beats() as i32 - offset;
+                let beats = beats.clamp(1, MAX_DELAY_BEATS as i32) as u64;
+                self.audio.engine.set_delay_beats(beats);
+            }
+            EditMode::DelayFeedback => {
+                // stay below 100% so the echo always dies out
+                let feedback = self.audio.engine.delay_feedback() as i32 - offset * 5;
+                self.audio
+                    .engine
+                    .set_delay_feedback(feedback.clamp(0, 95) as u64);
+            }
+            EditMode::DelayMix => {
+                let mix = self.audio.engine.delay_mix() as i32 - offset * 5;
+                self.audio.engine.set_delay_mix(mix.clamp(0, 100) as u64);
+            }
+            EditMode::LoudnessTarget => {
+                let target = self.loudness_target - offset;
+                self.loudness_target = target.clamp(MIN_LOUDNESS_TARGET, MAX_LOUDNESS_TARGET);
+            }
+            EditMode::SceneFade => {
+                let beats = self.audio.engine.scene_fade_beats() as i32 - offset;
+                let beats = beats.clamp(0, MAX_SCENE_FADE_BEATS as i32) as u64;
+                self.audio.engine.set_scene_fade_beats(beats);
+            }
+            EditMode::GateThreshold => self.rotate_gate_threshold(offset),
+            EditMode::Downbeat => self.rotate_downbeat(offset),
+            EditMode::FreeBeats => self.rotate_free_beats(offset),
+            _ => {}
+        }
+    }
+
+    // cycles the input channel of a track through the whole input and each of its channels
+    pub fn rotate_track_input(&mut self, index: usize, offset: i32) {
+        let channels = self.audio.engine.input_channels() as i32;
+
+        if channels == 0 {
+            self.warn("launch the streams to pick an input channel");
+            return;
+        }
+
+        // 0 is the whole input, the channels follow from 1
+        let track = &mut self.audio.tracks[index];
+        let current = track.input_channel.map_or(0, |channel| channel as i32 + 1);
+        let next = (current - offset).rem_euclid(channels + 1);
+
+        track.input_channel = match next {
+            0 => None,
+            channel => Some(channel as u16 - 1),
+        };
+
+        self.audio.update_tracks();
+    }
+
+    // names an input channel, along with the channel of the second device it comes from if
+    // it's one of those
+    pub fn input_channel_text(&self, channel: u16) -> String {
+        let engine = &self.audio.engine;
+        let first = engine
+            .input_channels()
+            .saturating_sub(engine.secondary_channels());
+
+        if engine.secondary_channels() > 0 && channel >= first {
+            format!(
+                "channel {} (second input {})",
+                channel + 1,
+                channel - first + 1
+            )
+        } else {
+            format!("channel {}", channel + 1)
+        }
+    }
+
+    // like rotate, in smaller steps where the edit mode has them
+    pub fn rotate_fine(&mut self, offset: i32) {
+        match self.edit_mode {
+            EditMode::TrackOffset(Some(index)) => {
+                self.shift_track(index, -offset as f32 * FINE_OFFSET);
+            }
+            EditMode::Downbeat => self.rotate_downbeat_fine(offset),
+            EditMode::TrackRegion(Some(index)) => self.move_region_start(index, -offset),
+            // the tempo changes right away instead of waiting for the loop or beat
+            EditMode::Bpm => {
+                let bpm = self.audio.engine.target_bpm();
+                self.change_bpm((bpm as i32 - offset).max(0) as u64, true);
+            }
+            // fades the way the plain keys step, over a loop
+            EditMode::TrackVolume(Some(index)) => {
+                let target = if offset > 0 { 0 } else { 100 };
+                self.ramp_volume(index, target);
+            }
+            _ => self.rotate(offset),
+        }
+    }
+
+    // moves the clip of a track later by `seconds`, or earlier when negative, keeping the offset
+    // within half the clip either way
+    pub fn shift_track(&mut self, index: usize, seconds: f32) {
+        let track = &mut self.audio.tracks[index];
+        let Some(ref clip) = track.clip else {
+            self.warn(format!("track {} has nothing to shift", (index + 1) % 10));
+            return;
+        };
+
+        let frame_count = clip.frame_count() as i64;
+        if frame_count == 0 {
+            return;
+        }
+
+        let frames = (seconds * clip.sample_rate.0 as f32).round() as i64;
+        let offset = (track.offset_frames + frames).rem_euclid(frame_count);
+        track.offset_frames = if offset > frame_count / 2 {
+            offset - frame_count
+        } else {
+            offset
+        };
+
+        self.audio.update_tracks();
+    }
+
+    // formats a label with the key bound to an action, e.g. "Delay 'd'"
+    pub fn key_title(&self, label: &str, action: Action) -> String {
+        format!("{} '{}'", label, self.keymap.key_name(action))
+    }
+
+    pub fn render<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let size = frame.size();
+        self.snapshot = self.audio.engine.snapshot();
+
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            self.render_too_small(frame, size);
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+
+        if self.setup.is_some() {
+            self.render_setup(frame);
+            self.render_log(frame);
+            self.render_warnings(frame);
+            self.render_quit_confirm(frame);
+            return;
+        }
+
+        self.render_tab_select(frame, chunks[0]);
+        self.render_hint(frame, chunks[1]);
+        self.render_main_tab(frame, chunks[2]);
+        self.render_status(frame, chunks[3]);
+        self.render_template(frame);
+        self.render_notes(frame);
+        self.render_kit(frame);
+        self.render_duration(frame);
+        self.render_export(frame);
+        self.render_history(frame);
+        self.render_archive(frame);
+        self.render_probe(frame);
+        self.render_log(frame);
+        self.render_warnings(frame);
+        self.render_quit_confirm(frame);
+        self.render_debug(frame);
+    }
+
+    pub fn render_too_small<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let text = vec![
+            Spans::from(format!(
+                "terminal too small (need {}x{})",
+                MIN_WIDTH, MIN_HEIGHT
+            )),
+            Spans::from(format!("currently {}x{}", area.width, area.height)),
+        ];
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+
+        let mut area = area;
+        area.y += area.height / 2;
+        area.height -= area.height / 2;
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_warnings<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if self.warnings.is_empty() {
+            return;
+        }
+
+        let height = self.warnings.len() as u16 + 4;
+        let area = centered_rect(frame.size(), 72, height);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Keymap warnings (press any key)")
+            .border_style(self.theme.warning);
+
+        let text = self.warnings.join("\n");
+        let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_tab_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(10)])
+            .split(area);
+
+        let tabs = Tabs::new(vec![Spans::from("Play"), Spans::from("Settings")])
+            .select(self.tab as usize)
+            .highlight_style(self.theme.accent)
+            .divider(DOT);
+
+        frame.render_widget(tabs, columns[0]);
+
+        if self.locked {
+            let lock = Paragraph::new("🔒 locked")
+                .style(self.theme.warning)
+                .alignment(Alignment::Right);
+            frame.render_widget(lock, columns[1]);
+        }
+    }
+
+    pub fn render_main_tab<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let title = if self.tab == Tab::Settings && self.staged.is_some() {
+            format!("{} (Enter to apply, Esc to revert)", self.tab.name())
+        } else {
+            self.tab.name().to_string()
+        };
+
+        let mut block = Block::default().borders(Borders::ALL).title(title);
+
+        if self.tab == Tab::Play && self.snapshot.killed {
+            block = block.border_style(self.theme.error);
+        } else if let Some(style) = self.beat_flash_style().filter(|_| self.tab == Tab::Play) {
+            block = block.border_style(style);
+        }
+
+        frame.render_widget(block, area);
+
+        match self.tab {
+            Tab::Play => self.render_play(frame, area),
+            Tab::Settings => self.render_settings(frame, area),
+        }
+    }
+
+    pub fn render_settings<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let height = (area.height.saturating_sub(2) / 2).min(16);
+
+        let chunks = Layout::default()
+            .margin(1)
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(height), Constraint::Length(height)])
+            .split(area);
+
+        self.render_device_select(frame, chunks[0]);
+        self.render_device_config(frame, chunks[1]);
+    }
+
+    pub fn render_device_config<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let width = settings_column_width(area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(width),
+                Constraint::Length(width),
+                Constraint::Length(width),
+                Constraint::Min(1),
+            ])
+            .split(area);
+
+        self.render_sample_rate_select(frame, chunks[0]);
+        self.render_buffer_size_select(frame, chunks[1]);
+        self.render_delay_mono_bar(frame, chunks[2]);
+
+        let column = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(chunks[3]);
+
+        self.render_drift(frame, column[0]);
+
+        #[cfg(feature = "osc")]
+        let error_area = {
+            let column = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(4), Constraint::Min(0)])
+                .split(column[1]);
+
+            self.render_osc_status(frame, column[0]);
+            column[1]
+        };
+
+        #[cfg(not(feature = "osc"))]
+        let error_area = column[1];
+
+        self.render_error(frame, error_area);
+    }
+
+    pub fn render_delay_mono_bar<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        self.render_delay_select(frame, chunks[0]);
+        self.render_tail_fold_select(frame, chunks[1]);
+        self.render_cue_channel_select(frame, chunks[2]);
+        self.render_mono_select(frame, chunks[3]);
+        self.render_theme_select(frame, chunks[4]);
+        self.render_frame_rate_select(frame, chunks[5]);
+        self.render_forward_recording_select(frame, chunks[6]);
+        self.render_guard_select(frame, chunks[7]);
+        self.render_auto_gain_select(frame, chunks[8]);
+        self.render_suspend_select(frame, chunks[9]);
+        self.render_tempo_timing_select(frame, chunks[10]);
+        self.render_dry_recording_select(frame, chunks[11]);
+    }
+
+    pub fn render_dry_recording_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Record input", Action::ToggleDryRecording));
+
+        // wet is the input after the gate, dry before it
+        let text = if self.audio.engine.is_recording_dry() {
+            "Dry"
+        } else {
+            "Wet"
+        };
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_tempo_timing_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Tempo change", Action::ToggleTempoTiming));
+
+        let text = self.audio.engine.tempo_timing().name();
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_suspend_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Suspended", Action::ToggleSuspendMute));
+
+        let text = if self.mute_on_suspend {
+            "Mute"
+        } else {
+            "Keep playing"
+        };
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_auto_gain_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Take volume", Action::ToggleAutoGain));
+
+        let text = if self.auto_gain { "Match" } else { "Suggest" };
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_guard_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Broken takes", Action::ToggleGuard));
+
+        let text = if self.guard_recordings {
+            "Refuse"
+        } else {
+            "Warn"
+        };
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_forward_recording_select<B: Backend>(
+        &mut self,
+        frame: &mut Frame<B>,
+        area: Rect,
+    ) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Record", Action::ToggleForwardRecording));
+
+        let text = if self.forward_recording {
+            "Next loop"
+        } else {
+            "Last loop"
+        };
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_frame_rate_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Frame rate", Action::CycleFrameRate));
+
+        let text = format!("{} fps", FRAME_RATES[self.frame_rate_index]);
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_theme_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Theme", Action::CycleTheme));
+
+        let paragraph = Paragraph::new(self.theme.name)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_sample_rate_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let settings = self.shown_settings();
+        let sample_rates = settings
+            .sample_rates()
+            .iter()
+            .map(|&(rate, side)| self.support_item(rate.0, side))
+            .collect::<Vec<_>>();
+
+        let selected = settings.sample_rate_index();
+        let error = settings.sample_rate_error();
+        self.settings.sample_rate_state.select(selected);
+
+        let staged = self.is_staged(|s| s.get_sample_rate());
+        let mut block = Block::default()
+            .title(self.staged_title("Sample Rate", Action::EditSampleRate, staged))
+            .borders(Borders::ALL);
+
+        if self.edit_mode == EditMode::SampleRate {
+            block = block.border_style(self.theme.active);
+        }
+
+        let area = self.render_support_block(frame, area, block, error);
+
+        let list = List::new(sample_rates).highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(list, area, &mut self.settings.sample_rate_state);
+    }
+
+    pub fn render_buffer_size_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let settings = self.shown_settings();
+        let buffer_sizes = settings
+            .buffer_sizes()
+            .iter()
+            .map(|&(size, side)| self.support_item(size, side))
+            .collect::<Vec<_>>();
+
+        let selected = settings.buffer_size_index();
+        let error = settings.buffer_size_error();
+        self.settings.buffer_size_state.select(selected);
+
+        let staged = self.is_staged(|s| s.get_buffer_size());
+        let mut block = Block::default()
+            .title(self.staged_title("Buffer Size", Action::EditBufferSize, staged))
+            .borders(Borders::ALL);
+
+        if self.edit_mode == EditMode::BufferSize {
+            block = block.border_style(self.theme.active);
+        }
+
+        // a refused size can't be fixed by choosing it again, a wrong one being delivered
+        // only matters while nothing else is wrong
+        let area = match (error, self.buffer_size_warning()) {
+            (None, Some(warning)) => {
+                self.render_note_block(frame, area, block, Some((warning, self.theme.warning)))
+            }
+            (error, _) => self.render_support_block(frame, area, block, error),
+        };
+
+        let list = List::new(buffer_sizes).highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(list, area, &mut self.settings.buffer_size_state);
+    }
+
+    // notes when the running streams don't get the buffer size that was asked for
+    fn buffer_size_warning(&self) -> Option<String> {
+        if !self.audio.engine.is_recording() {
+            return None;
+        }
+
+        let Some(BufferSize::Fixed(requested)) = self.audio.settings.get_buffer_size() else {
+            return None;
+        };
+
+        if self.audio.engine.is_buffer_fallback() {
+            return Some(format!(
+                "requested {}, device only runs its default",
+                requested
+            ));
+        }
+
+        let (min, max) = self.audio.engine.buffer_sizes()?;
+        if min == requested as u64 && max == requested as u64 {
+            return None;
+        }
+
+        let delivered = if min == max {
+            min.to_string()
+        } else {
+            format!("{} to {}", min, max)
+        };
+
+        Some(format!(
+            "requested {}, device delivers {}",
+            requested, delivered
+        ))
+    }
+
+    // a value of a settings list, noting which of the devices support it
+    pub fn support_item(&self, value: u32, side: SupportSide) -> ListItem<'static> {
+        let style = match side {
+            SupportSide::Both => Style::default(),
+            SupportSide::Neither => self.theme.empty,
+            _ => self.theme.warning,
+        };
+
+        ListItem::new(format!("{:<7}{}", value, side.label())).style(style)
+    }
+
+    // draws the block of a settings list, explaining on its bottom row why the chosen value
+    // can't be used, and returns the area left for the list
+    pub fn render_support_block<B: Backend>(
+        &self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        block: Block,
+        error: Option<SettingsError>,
+    ) -> Rect {
+        let note = error.map(|error| (error.to_string(), self.theme.error));
+        self.render_note_block(frame, area, block, note)
+    }
+
+    // draws a block with a note on its bottom row, returning the area left above it
+    pub fn render_note_block<B: Backend>(
+        &self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        block: Block,
+        note: Option<(String, Style)>,
+    ) -> Rect {
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let Some((note, style)) = note else {
+            return inner;
+        };
+
+        if inner.height < 2 {
+            return inner;
+        }
+
+        let row = Rect::new(inner.x, inner.y + inner.height - 1, inner.width, 1);
+        let paragraph = Paragraph::new(note).style(style);
+        frame.render_widget(paragraph, row);
+
+        Rect {
+            height: inner.height - 1,
+            ..inner
+        }
+    }
+
+    pub fn render_delay_select<B: Backend>(&mut self, frame: &mut Frame<B>, mut area: Rect) {
+        let staged = self.is_staged(|s| s.delay);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.staged_title("Delay", Action::EditDelay, staged));
 
         if self.edit_mode == EditMode::Delay {
-            block = block.border_style(Style::default().fg(Color::Red));
+            block = block.border_style(self.theme.active);
         }
 
-        let paragraph = Paragraph::new(format!("{}ms", self.audio.settings.delay))
+        let paragraph = Paragraph::new(format!("{}ms", self.shown_settings().delay))
             .alignment(Alignment::Right)
             .block(block);
 
@@ -397,10 +2359,54 @@ impl App {
         frame.render_widget(paragraph, area);
     }
 
+    pub fn render_tail_fold_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let staged = self.is_staged(|s| s.tail_fold);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.staged_title("Tail fold", Action::EditTailFold, staged));
+
+        if self.edit_mode == EditMode::TailFold {
+            block = block.border_style(self.theme.active);
+        }
+
+        let text = match self.shown_settings().tail_fold {
+            0 => String::from("Off"),
+            ms => format!("{}ms", ms),
+        };
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_cue_channel_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let staged = self.is_staged(|s| s.cue_channel);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.staged_title("Cue", Action::EditCueChannel, staged));
+
+        if self.edit_mode == EditMode::CueChannel {
+            block = block.border_style(self.theme.active);
+        }
+
+        let text = format!("Ch {} and up", self.shown_settings().cue_channel + 1);
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
     pub fn render_mono_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let block = Block::default().borders(Borders::ALL).title("Mono 'm'");
+        let staged = self.is_staged(|s| s.force_mono);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.staged_title("Mono", Action::ToggleMono, staged));
 
-        let text = if self.audio.settings.force_mono {
+        let text = if self.shown_settings().force_mono {
             "On"
         } else {
             "Off"
@@ -414,17 +2420,18 @@ impl App {
     }
 
     pub fn render_error<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let Some(ref error) = self.audio.error else {
+        let Some(error) = self.latest_error() else {
             return;
         };
 
         let block = Block::default()
             .borders(Borders::ALL)
             .title("Error")
-            .border_style(Style::default().fg(Color::Red));
+            .border_style(self.theme.error);
 
         let paragraph = Paragraph::new(error.to_string())
             .block(block)
+            .wrap(Wrap { trim: true })
             .alignment(Alignment::Left);
 
         frame.render_widget(paragraph, area);
@@ -0,0 +1,168 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{BarChart, Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::{
+    analysis::{detect_pitch, note, spectrum},
+    app::App,
+    keymap::Action,
+};
+
+// the number of bars in the spectrum
+const SPECTRUM_BANDS: usize = 16;
+
+/// What the analysis panel in the play tab shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnalysisView {
+    #[default]
+    Off,
+    Spectrum,
+    Tuner,
+}
+
+impl AnalysisView {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Spectrum,
+            Self::Spectrum => Self::Tuner,
+            Self::Tuner => Self::Off,
+        }
+    }
+}
+
+impl App {
+    pub fn cycle_analysis(&mut self) {
+        self.analysis = self.analysis.next();
+    }
+
+    pub fn render_analysis<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let title = match self.analysis {
+            AnalysisView::Off => return,
+            AnalysisView::Spectrum => self.key_title("Spectrum", Action::CycleAnalysis),
+            AnalysisView::Tuner => self.key_title("Tuner", Action::CycleAnalysis),
+        };
+
+        let block = Block::default().borders(Borders::ALL).title(title);
+
+        let sample_rate = self.snapshot.sample_rate as f32;
+        if !self.snapshot.is_running() || sample_rate == 0.0 {
+            let paragraph = Paragraph::new("no input")
+                .alignment(Alignment::Center)
+                .style(self.theme.empty)
+                .block(block);
+
+            frame.render_widget(paragraph, area);
+            return;
+        }
+
+        let samples = self.audio.engine.input_snapshot.read();
+
+        match self.analysis {
+            AnalysisView::Spectrum => {
+                self.render_spectrum(frame, area, block, &samples, sample_rate)
+            }
+            AnalysisView::Tuner => self.render_tuner(frame, area, block, &samples, sample_rate),
+            AnalysisView::Off => {}
+        }
+    }
+
+    fn render_spectrum<B: Backend>(
+        &self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        block: Block,
+        samples: &[f32],
+        sample_rate: f32,
+    ) {
+        let levels = spectrum(samples, sample_rate, SPECTRUM_BANDS);
+        let data = levels
+            .iter()
+            .map(|level| ("", (level * 100.0) as u64))
+            .collect::<Vec<_>>();
+
+        let bar_width = (area.width.saturating_sub(2) / SPECTRUM_BANDS as u16).max(1);
+        let color = self.theme.track_color(0);
+
+        let chart = BarChart::default()
+            .block(block)
+            .data(&data)
+            .bar_width(bar_width)
+            .bar_gap(0)
+            .max(100)
+            .bar_style(self.theme.meter(color))
+            .value_style(self.theme.meter(color));
+
+        frame.render_widget(chart, area);
+    }
+
+    fn render_tuner<B: Backend>(
+        &self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        block: Block,
+        samples: &[f32],
+        sample_rate: f32,
+    ) {
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let Some(frequency) = detect_pitch(samples, sample_rate) else {
+            let paragraph = Paragraph::new("-")
+                .alignment(Alignment::Center)
+                .style(self.theme.empty);
+
+            frame.render_widget(paragraph, inner);
+            return;
+        };
+
+        let (name, cents) = note(frequency);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        let in_tune = cents.abs() < 5.0;
+        let style = if in_tune {
+            self.theme.accent
+        } else {
+            self.theme.warning
+        };
+
+        let note = Paragraph::new(Spans::from(vec![
+            Span::styled(name, style),
+            Span::raw(format!(" {:+.0} cents", cents)),
+        ]))
+        .alignment(Alignment::Center);
+
+        let hertz = Paragraph::new(format!("{:.1} Hz", frequency))
+            .alignment(Alignment::Center)
+            .style(self.theme.empty);
+
+        // a needle between -50 and +50 cents
+        let width = rows[2].width as usize;
+        let needle = ((cents + 50.0) / 100.0 * width.saturating_sub(1) as f32).round() as usize;
+        let meter = (0..width)
+            .map(|x| match x {
+                x if x == needle => '┃',
+                x if x == width / 2 => '┆',
+                _ => '─',
+            })
+            .collect::<String>();
+
+        let meter = Paragraph::new(Span::styled(meter, style));
+
+        frame.render_widget(note, rows[0]);
+        frame.render_widget(hertz, rows[1]);
+        frame.render_widget(meter, rows[2]);
+    }
+}
@@ -0,0 +1,135 @@
+use tui::{buffer::Buffer, layout::Rect, style::Style, widgets::Widget};
+
+use crate::engine::DEFAULT_BEATS_PER_BAR;
+
+// the partial blocks filling a cell an eighth at a time, upwards
+const VERTICAL_BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// the beat bar keeps its caption below the gauge when it's at least this tall
+const MIN_CAPTION_HEIGHT: u16 = 6;
+
+/// Returns how far into a loop of `beats` beats `beat` is, from 0 up to but never reaching 1,
+/// so the bar snaps back to empty at the wrap instead of showing a full loop.
+pub fn loop_fraction(beat: f32, beats: u64) -> f32 {
+    if beats == 0 || !beat.is_finite() {
+        return 0.0;
+    }
+
+    let fraction = beat.rem_euclid(beats as f32) / beats as f32;
+
+    // rounding can land right on the end of the loop
+    if fraction < 1.0 {
+        fraction
+    } else {
+        0.0
+    }
+}
+
+/// Returns the beat shown in the caption, rounded down to a quarter beat so it never reads
+/// the end of the loop before the wrap.
+pub fn caption_beat(beat: f32, beats: u64) -> f32 {
+    let beat = loop_fraction(beat, beats) * beats as f32;
+    (beat * 4.0).floor() / 4.0
+}
+
+/// A vertical gauge of the progress through the loop, with a tick every beat, a heavier one
+/// every bar and the beat written under it.
+pub struct BeatBar {
+    beat: f32,
+    beats: u64,
+    beats_per_bar: u64,
+    style: Style,
+    tick: Style,
+    caption: Style,
+}
+
+impl BeatBar {
+    pub fn new(beat: f32, beats: u64) -> Self {
+        Self {
+            beat,
+            beats,
+            beats_per_bar: DEFAULT_BEATS_PER_BAR,
+            style: Style::default(),
+            tick: Style::default(),
+            caption: Style::default(),
+        }
+    }
+
+    /// Sets how many beats a bar has, each bar getting a heavier tick.
+    pub fn beats_per_bar(mut self, beats_per_bar: u64) -> Self {
+        self.beats_per_bar = beats_per_bar.max(1);
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn tick_style(mut self, style: Style) -> Self {
+        self.tick = style;
+        self
+    }
+
+    pub fn caption_style(mut self, style: Style) -> Self {
+        self.caption = style;
+        self
+    }
+
+    // the marks in the empty part of the gauge, by cell from the bottom, bars winning over beats
+    fn tick(&self, cell: u16, cells: u16) -> Option<char> {
+        let mut mark = None;
+
+        for beat in 1..self.beats {
+            let tick = (beat as f32 / self.beats as f32 * cells as f32) as u16;
+            if tick != cell {
+                continue;
+            }
+
+            if beat % self.beats_per_bar == 0 {
+                return Some('━');
+            }
+
+            mark = Some('─');
+        }
+
+        mark
+    }
+}
+
+impl Widget for BeatBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        let mut gauge = area;
+        if area.height >= MIN_CAPTION_HEIGHT {
+            gauge.height -= 2;
+
+            let beat = format!("{:.2}", caption_beat(self.beat, self.beats));
+            let beats = format!("/ {}", self.beats);
+
+            let width = area.width as usize;
+            buf.set_stringn(area.left(), gauge.bottom(), beat, width, self.caption);
+            buf.set_stringn(area.left(), gauge.bottom() + 1, beats, width, self.caption);
+        }
+
+        let cells = gauge.height;
+        let filled = (loop_fraction(self.beat, self.beats) * cells as f32 * 8.0) as u16;
+
+        for cell in 0..cells {
+            let eighths = filled.saturating_sub(cell * 8).min(8) as usize;
+
+            let (symbol, style) = match (eighths, self.tick(cell, cells)) {
+                (0, Some(tick)) => (tick, self.tick),
+                _ => (VERTICAL_BLOCKS[eighths], self.style),
+            };
+
+            let y = gauge.bottom() - 1 - cell;
+            for x in gauge.left()..gauge.right() {
+                buf.get_mut(x, y).set_char(symbol).set_style(style);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::app::App;
+
+// drifts up to this many parts per million are normal for separate clocks
+const USUAL_DRIFT_PPM: f32 = 100.0;
+
+impl App {
+    // shows how far the input clocks drift from the output clock while the streams run, the
+    // second input's after the first's
+    pub fn render_drift<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title("Clock drift");
+
+        let engine = &self.audio.engine;
+        let mut drifts = vec![engine.input_drift()];
+        if engine.secondary_channels() > 0 {
+            drifts.push(engine.secondary_drift());
+        }
+
+        let (text, style) = match drifts.iter().copied().collect::<Option<Vec<_>>>() {
+            _ if !engine.is_recording() => (String::from("-"), self.theme.empty),
+            None => (String::from("measuring"), self.theme.empty),
+            Some(ppms) => {
+                let style = if ppms.iter().any(|ppm| ppm.abs() > USUAL_DRIFT_PPM) {
+                    self.theme.warning
+                } else {
+                    Default::default()
+                };
+
+                let ppms = ppms
+                    .iter()
+                    .map(|ppm| format!("{:+.1}", ppm))
+                    .collect::<Vec<_>>();
+
+                (format!("{} ppm", ppms.join(" / ")), style)
+            }
+        };
+
+        let paragraph = Paragraph::new(text)
+            .style(style)
+            .alignment(Alignment::Right)
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+}
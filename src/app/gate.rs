@@ -0,0 +1,24 @@
+use crate::{
+    app::App,
+    gate::{GateMode, MAX_GATE_THRESHOLD, MIN_GATE_THRESHOLD},
+};
+
+impl App {
+    pub fn cycle_gate_mode(&mut self) {
+        let engine = &self.audio.engine;
+        let mode = engine.gate_mode().cycled();
+        engine.set_gate_mode(mode);
+
+        match mode {
+            GateMode::Off => self.info("noise gate off"),
+            GateMode::Record => self.info("gating what's recorded"),
+            GateMode::Monitor => self.info("gating what's recorded and heard"),
+        }
+    }
+
+    pub fn rotate_gate_threshold(&mut self, offset: i32) {
+        let engine = &self.audio.engine;
+        let threshold = engine.gate_threshold() - offset as i64;
+        engine.set_gate_threshold(threshold.clamp(MIN_GATE_THRESHOLD, MAX_GATE_THRESHOLD));
+    }
+}
@@ -0,0 +1,246 @@
+use tui::{buffer::Buffer, layout::Rect, style::Style, widgets::Widget};
+
+use crate::{
+    app::App,
+    loudness::ShortTermLoudness,
+    meter::{level_db, METER_CEILING, METER_FLOOR},
+};
+
+// how much of a track's level is kept every update, so the meters fall off smoothly
+const LEVEL_DECAY: f32 = 0.85;
+
+/// The levels marked on the meters, in dB.
+pub const METER_TICKS: [f32; 3] = [-18.0, -6.0, 0.0];
+
+/// The loudness the mix is aimed at until another is set, in LUFS, what streaming services
+/// usually turn louder mixes down to.
+pub const DEFAULT_LOUDNESS_TARGET: i32 = -14;
+
+/// The range of the loudness meter, in LUFS.
+pub const LOUDNESS_SCALE: (f64, f64) = (-40.0, 0.0);
+
+// where the meters turn from safe to hot, and from hot to over, in dB
+const HOT_ZONE: f32 = -18.0;
+const OVER_ZONE: f32 = 0.0;
+
+// the partial blocks filling a cell an eighth at a time, upwards and rightwards
+const VERTICAL_BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const HORIZONTAL_BLOCKS: [char; 9] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// Returns how far up the dB scale of a meter a level reaches, from 0 to 1.
+pub fn db_meter_ratio(level: f32) -> f32 {
+    let ratio = (level_db(level) - METER_FLOOR) / (METER_CEILING - METER_FLOOR);
+    ratio.clamp(0.0, 1.0)
+}
+
+/// Returns how much of a meter `cells` long a level fills on the dB scale, in eighths of a
+/// cell.
+pub fn db_meter_height(peak: f32, cells: u16) -> u16 {
+    (db_meter_ratio(peak) * cells as f32 * 8.0).round() as u16
+}
+
+/// A level meter on the dB scale, filled with partial blocks and colored by zone.
+pub struct DbMeter {
+    level: f32,
+    vertical: bool,
+    safe: Style,
+    hot: Style,
+    over: Style,
+    tick: Style,
+}
+
+impl DbMeter {
+    pub fn new(level: f32, vertical: bool) -> Self {
+        Self {
+            level,
+            vertical,
+            safe: Style::default(),
+            hot: Style::default(),
+            over: Style::default(),
+            tick: Style::default(),
+        }
+    }
+
+    /// Sets the styles below -18 dB, up to full scale, and over it.
+    pub fn zones(mut self, safe: Style, hot: Style, over: Style) -> Self {
+        self.safe = safe;
+        self.hot = hot;
+        self.over = over;
+        self
+    }
+
+    pub fn tick_style(mut self, style: Style) -> Self {
+        self.tick = style;
+        self
+    }
+
+    // the style of the cell at `cell` out of `cells`, by the level at its start
+    fn zone(&self, cell: u16, cells: u16) -> Style {
+        let db = METER_FLOOR + (METER_CEILING - METER_FLOOR) * cell as f32 / cells as f32;
+
+        if db >= OVER_ZONE {
+            self.over
+        } else if db >= HOT_ZONE {
+            self.hot
+        } else {
+            self.safe
+        }
+    }
+}
+
+impl Widget for DbMeter {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let cells = if self.vertical {
+            area.height
+        } else {
+            area.width
+        };
+        if cells == 0 {
+            return;
+        }
+
+        let filled = db_meter_height(self.level, cells);
+        let ticks = METER_TICKS.map(|db| {
+            let ratio = (db - METER_FLOOR) / (METER_CEILING - METER_FLOOR);
+            (ratio * cells as f32) as u16
+        });
+
+        for cell in 0..cells {
+            let eighths = filled.saturating_sub(cell * 8).min(8) as usize;
+
+            let (symbol, style) = match eighths {
+                0 if ticks.contains(&cell) && self.vertical => ('─', self.tick),
+                0 if ticks.contains(&cell) => ('│', self.tick),
+                _ if self.vertical => (VERTICAL_BLOCKS[eighths], self.zone(cell, cells)),
+                _ => (HORIZONTAL_BLOCKS[eighths], self.zone(cell, cells)),
+            };
+
+            // vertical meters fill from the bottom up across their whole width
+            if self.vertical {
+                let y = area.bottom() - 1 - cell;
+                for x in area.left()..area.right() {
+                    buf.get_mut(x, y).set_char(symbol).set_style(style);
+                }
+            } else {
+                let x = area.left() + cell;
+                for y in area.top()..area.bottom() {
+                    buf.get_mut(x, y).set_char(symbol).set_style(style);
+                }
+            }
+        }
+    }
+}
+
+/// What the track meters show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeterMode {
+    #[default]
+    Peak,
+    Rms,
+}
+
+impl MeterMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Peak => "Peak",
+            Self::Rms => "RMS",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Peak => Self::Rms,
+            Self::Rms => Self::Peak,
+        }
+    }
+}
+
+/// The level of a track as shown on its meter.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TrackLevel {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+impl TrackLevel {
+    pub fn get(self, mode: MeterMode) -> f32 {
+        match mode {
+            MeterMode::Peak => self.peak,
+            MeterMode::Rms => self.rms,
+        }
+    }
+}
+
+impl App {
+    pub fn toggle_meter_mode(&mut self) {
+        self.meter_mode = self.meter_mode.toggled();
+    }
+
+    // follows the levels the callback published, called every update
+    pub fn update_meters(&mut self) {
+        for (meter, level) in self
+            .audio
+            .engine
+            .track_meters
+            .iter()
+            .zip(&mut self.track_levels)
+        {
+            let taken = meter.take();
+
+            level.peak = taken.peak.max(level.peak * LEVEL_DECAY);
+
+            // nothing was published when the callback hasn't run since
+            if taken.count > 0 {
+                level.rms = taken.rms().max(level.rms * LEVEL_DECAY);
+            } else {
+                level.rms *= LEVEL_DECAY;
+            }
+        }
+
+        self.update_loudness();
+    }
+
+    // measures what the output played since the last update, starting over whenever the
+    // stream changes rate or channels
+    fn update_loudness(&mut self) {
+        let snapshot = &self.audio.engine.output_snapshot;
+        let sample_rate = self.snapshot.sample_rate as u32;
+        let channels = snapshot.channels();
+
+        if !self.loudness.measures(sample_rate, channels) {
+            self.loudness = ShortTermLoudness::new(sample_rate, channels);
+        }
+
+        let mut samples = Vec::new();
+        snapshot.read_since(&mut self.loudness_position, &mut samples);
+        self.loudness.add(&samples);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gain(db: f32) -> f32 {
+        10.0f32.powf(db / 20.0)
+    }
+
+    #[test]
+    fn meter_height_at_db_points() {
+        // 33 cells over the 66 dB of the scale, four eighths a dB
+        let height = |db: f32| db_meter_height(gain(db), 33);
+
+        assert_eq!(db_meter_height(0.0, 33), 0);
+        assert_eq!(height(-80.0), 0);
+        assert_eq!(height(METER_FLOOR), 0);
+        assert_eq!(height(-54.0), 24);
+        assert_eq!(height(-18.0), 168);
+        assert_eq!(height(-6.0), 216);
+        assert_eq!(height(0.0), 240);
+        assert_eq!(height(METER_CEILING), 264);
+        assert_eq!(height(12.0), 264);
+
+        assert_eq!(db_meter_height(f32::NAN, 33), 0);
+        assert_eq!(db_meter_height(1.0, 0), 0);
+    }
+}
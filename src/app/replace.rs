@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use crate::{app::App, replace::ReplaceBuffer};
+
+impl App {
+    // starts replacing the selected track with the input at the playhead, or releases it
+    pub fn toggle_replace(&mut self) {
+        let engine = &self.audio.engine;
+
+        if self.replace.is_some() {
+            if engine.is_replace_held() {
+                engine.set_replace_held(false);
+                self.info("released replace");
+            }
+
+            return;
+        }
+
+        if !engine.is_recording() {
+            self.warn("launch the streams to replace a track");
+            return;
+        }
+
+        let index = self.selected_track;
+        let Some(ref clip) = self.audio.tracks[index].clip else {
+            self.warn(format!("track {} has nothing to replace", (index + 1) % 10));
+            return;
+        };
+
+        let buffer = Arc::new(ReplaceBuffer::new(index, clip));
+
+        // the ui keeps a handle, so the buffer is never freed on the audio thread
+        self.replace = Some(buffer.clone());
+        engine.set_replace_committed(false);
+        engine.set_replace_finished(false);
+        engine.replace_buffer.store(Some(buffer));
+        engine.set_replace_held(true);
+
+        self.info(format!("replacing track {}", (index + 1) % 10));
+    }
+
+    // commits the buffer once the callback has faded out of it, and lets go of it once the
+    // callback plays the committed clip, called every update
+    pub fn update_replace(&mut self) {
+        let Some(ref buffer) = self.replace else {
+            return;
+        };
+
+        let engine = &self.audio.engine;
+
+        if engine.is_replace_finished() && !engine.is_replace_committed() {
+            let index = buffer.track;
+            let clip = buffer.to_clip();
+
+            self.push_layer(index, format!("replace track {}", (index + 1) % 10));
+            self.audio.tracks[index].clip = Some(clip);
+            self.audio.update_tracks();
+
+            // the callback keeps playing the buffer until these tracks arrive
+            self.audio.engine.set_replace_committed(true);
+
+            self.info(format!("replaced track {}", (index + 1) % 10));
+            return;
+        }
+
+        if engine.replace_returned.take().is_some() {
+            self.replace = None;
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{app::App, stutter::Stutter};
+
+impl App {
+    // starts repeating a slice of the selected track, or stops the current stutter
+    pub fn toggle_stutter(&mut self) {
+        let engine = &self.audio.engine;
+
+        match engine.stutter() {
+            Some(stutter) => {
+                engine.set_stutter(None);
+                self.info(format!(
+                    "stopped stutter on track {}",
+                    (stutter.track + 1) % 10
+                ));
+            }
+            None => {
+                let stutter = Stutter {
+                    track: self.selected_track,
+                    length: self.stutter_length,
+                };
+
+                engine.set_stutter(Some(stutter));
+                self.info(format!(
+                    "stuttering track {} every {} beat",
+                    (stutter.track + 1) % 10,
+                    stutter.length.name()
+                ));
+            }
+        }
+    }
+
+    // picks the next slice length, restarting a running stutter with it
+    pub fn cycle_stutter_length(&mut self) {
+        self.stutter_length = self.stutter_length.next();
+
+        let engine = &self.audio.engine;
+        if let Some(stutter) = engine.stutter() {
+            engine.set_stutter(Some(Stutter {
+                length: self.stutter_length,
+                ..stutter
+            }));
+        }
+    }
+}
@@ -0,0 +1,337 @@
+// the app driven by keys with no devices behind it, rendered into a test backend
+
+use cpal::SampleRate;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    backend::TestBackend,
+    buffer::{Buffer, Cell},
+    Terminal,
+};
+
+use crate::{
+    app::{beat::BeatBar, App, EditMode, Tab},
+    clip::Clip,
+    keymap::Keymap,
+    layout::PlayLayout,
+};
+
+fn press(app: &mut App, code: KeyCode) {
+    app.key(KeyEvent::new(code, KeyModifiers::NONE));
+}
+
+fn render(app: &mut App, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|frame| app.render(frame)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+// the rows of a buffer as text, for looking things up in it
+fn lines(buffer: &Buffer) -> Vec<String> {
+    let width = buffer.area.width as usize;
+
+    buffer
+        .content
+        .chunks(width)
+        .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect())
+        .collect()
+}
+
+// the cells of the volume bar of a track, top to bottom
+fn volume_bar(buffer: &Buffer, index: usize) -> Vec<&Cell> {
+    let label = format!("┌{}─", (index + 1) % 10);
+    let (x, y) = find(buffer, &label).expect("no track column");
+
+    // the bar is the last column inside the border, the empty part of it left out
+    (y + 1..buffer.area.height)
+        .map(|row| buffer.get(x + 4, row))
+        .take_while(|cell| cell.symbol != "─")
+        .filter(|cell| cell.symbol != " ")
+        .collect()
+}
+
+fn find(buffer: &Buffer, text: &str) -> Option<(u16, u16)> {
+    lines(buffer).iter().enumerate().find_map(|(y, line)| {
+        let x = line.find(text)?;
+        Some((line[..x].chars().count() as u16, y as u16))
+    })
+}
+
+#[test]
+fn volume_edit_of_a_track() {
+    let mut app = App::offline();
+    let volume = app.audio.tracks[2].volume;
+
+    // volume mode, track 3, up twice, which moves toward the top of the range like it does
+    // in every list, so the volume goes down a step each time
+    press(&mut app, KeyCode::Char('V'));
+    assert_eq!(app.edit_mode, EditMode::TrackVolume(None));
+
+    press(&mut app, KeyCode::Char('3'));
+    assert_eq!(app.edit_mode, EditMode::TrackVolume(Some(2)));
+
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.audio.tracks[2].volume, volume - 10);
+
+    // the hint names the track, and its bar is drawn active and lower than the others
+    let buffer = render(&mut app, 100, 30);
+    assert_eq!(find(&buffer, "volume of track 3"), Some((0, 1)));
+
+    let edited = volume_bar(&buffer, 2);
+    let other = volume_bar(&buffer, 3);
+    let symbols = |bar: &[&Cell]| {
+        bar.iter()
+            .map(|cell| cell.symbol.clone())
+            .collect::<String>()
+    };
+    assert_ne!(symbols(&edited), symbols(&other));
+    assert!(edited
+        .iter()
+        .all(|cell| cell.fg == app.theme.active.fg.unwrap()));
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.edit_mode, EditMode::None);
+    assert_eq!(app.audio.tracks[2].volume, volume - 10);
+
+    // the other tracks are left alone
+    for (index, track) in app.audio.tracks.iter().enumerate() {
+        if index != 2 {
+            assert_eq!(track.volume, volume, "track {}", index + 1);
+        }
+    }
+
+    // the hint and the highlight go away with the edit, the lower bar stays
+    let buffer = render(&mut app, 100, 30);
+    assert_eq!(find(&buffer, "volume of track 3"), None);
+
+    let edited = volume_bar(&buffer, 2);
+    assert_ne!(symbols(&edited), symbols(&volume_bar(&buffer, 3)));
+    assert!(edited
+        .iter()
+        .all(|cell| cell.fg != app.theme.active.fg.unwrap()));
+}
+
+// the frame of a clip furthest from silence
+fn loudest_frame(clip: &Clip) -> u64 {
+    (0..clip.frame_count())
+        .max_by(|&a, &b| clip.sample(a, 0).abs().total_cmp(&clip.sample(b, 0).abs()))
+        .unwrap()
+}
+
+#[test]
+fn overdub_of_a_reversed_track() {
+    let mut app = App::offline();
+
+    // a quiet bed four beats long, played backwards
+    let beat = 1000;
+    let frames = beat * 4;
+    let bed = Clip::new(1, SampleRate(48000), vec![0.01; frames as usize].into());
+    app.audio.tracks[0].clip = Some(bed);
+    app.audio.tracks[0].reversed = true;
+
+    // a click played two beats into the loop
+    let mut take = vec![0.0; frames as usize];
+    take[2 * beat as usize] = 1.0;
+    app.commit_clip(0, Clip::new(1, SampleRate(48000), take.into()));
+
+    let track = &app.audio.tracks[0];
+    assert!(track.reversed);
+
+    // it's stored mirrored, and heard where it was played
+    let clip = track.clip.as_ref().unwrap();
+    assert_eq!(loudest_frame(clip), frames - 1 - 2 * beat);
+    assert_eq!(
+        loudest_frame(&track.render(frames, 1, SampleRate(48000))),
+        2 * beat
+    );
+}
+
+// a beat bar of a loop of 16 beats in bars of four, drawn five wide and ten tall, the bottom two
+// rows of it the caption
+fn beat_bar(beat: f32) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(5, 10)).unwrap();
+    terminal
+        .draw(|frame| frame.render_widget(BeatBar::new(beat, 16), frame.size()))
+        .unwrap();
+    terminal.backend().buffer().clone()
+}
+
+// the gauge of a beat bar from the bottom up, and its caption
+fn gauge(buffer: &Buffer) -> (String, Vec<String>) {
+    let lines = lines(buffer);
+    let gauge = lines[..8]
+        .iter()
+        .rev()
+        .map(|line| line.chars().next().unwrap());
+    let caption = lines[8..].iter().map(|line| line.trim().to_string());
+    (gauge.collect(), caption.collect())
+}
+
+#[test]
+fn beat_bar_positions() {
+    // empty at the start, a tick for every two beats and a heavier one for every bar
+    let (bar, caption) = gauge(&beat_bar(0.0));
+    assert_eq!(bar, "──━─━─━─");
+    assert_eq!(caption, ["0.00", "/ 16"]);
+
+    // partway into a cell, filled in eighths
+    let (bar, caption) = gauge(&beat_bar(7.25));
+    assert_eq!(bar, "███▅━─━─");
+    assert_eq!(caption, ["7.25", "/ 16"]);
+
+    // halfway
+    let (bar, caption) = gauge(&beat_bar(8.0));
+    assert_eq!(bar, "████━─━─");
+    assert_eq!(caption, ["8.00", "/ 16"]);
+
+    // right before the wrap it's not yet full, and reads the last quarter beat
+    let (bar, caption) = gauge(&beat_bar(15.999));
+    assert_eq!(bar, "███████▇");
+    assert_eq!(caption, ["15.75", "/ 16"]);
+
+    // at the wrap it snaps back to empty rather than showing a whole loop
+    assert_eq!(beat_bar(16.0), beat_bar(0.0));
+    assert_eq!(beat_bar(f32::NAN), beat_bar(0.0));
+}
+
+#[test]
+fn f2_cycles_the_layout() {
+    let mut app = App::offline();
+    assert_eq!(app.layout, PlayLayout::Full);
+
+    for layout in [
+        PlayLayout::TracksFocus,
+        PlayLayout::Minimal,
+        PlayLayout::Full,
+    ] {
+        press(&mut app, KeyCode::F(2));
+        assert_eq!(app.layout, layout);
+
+        let message = format!("{} layout", layout.name());
+        assert!(find(&render(&mut app, 100, 30), &message).is_some());
+    }
+}
+
+#[test]
+fn layout_presets_at_a_few_sizes() {
+    // the bottom of the first track column
+    let column_bottom = |buffer: &Buffer| find(buffer, "└────┘").map(|(_, y)| y);
+
+    for (width, height) in [(80, 24), (120, 40), (200, 60)] {
+        let mut app = App::offline();
+
+        // everything, the settings under the tracks
+        let buffer = render(&mut app, width, height);
+        let (_, tracks) = find(&buffer, "┌Tracks").expect("no tracks");
+        let (_, settings) = find(&buffer, "┌Settings").expect("no settings");
+        assert!(settings > tracks, "{}x{}", width, height);
+        let bottom = column_bottom(&buffer).unwrap();
+
+        // the tracks alone, taking the whole height
+        app.layout = PlayLayout::TracksFocus;
+        let buffer = render(&mut app, width, height);
+        assert_eq!(find(&buffer, "┌Tracks").map(|(_, y)| y), Some(tracks));
+        assert_eq!(find(&buffer, "┌Settings"), None);
+        assert!(column_bottom(&buffer).unwrap() > settings.max(bottom));
+
+        // a row of track states and nothing else
+        app.layout = PlayLayout::Minimal;
+        let buffer = render(&mut app, width, height);
+        assert_eq!(find(&buffer, "┌Tracks"), None);
+        assert_eq!(find(&buffer, "┌Settings"), None);
+        assert!(find(&buffer, "· · · ·").is_some());
+        assert!(find(&buffer, "1 2 3 4").is_some());
+    }
+}
+
+#[test]
+fn too_small_a_terminal_shows_only_a_note() {
+    for (width, height) in [(79, 24), (80, 23), (40, 12)] {
+        let mut app = App::offline();
+        let buffer = render(&mut app, width, height);
+
+        assert!(find(&buffer, "terminal too small (need 80x24)").is_some());
+        let current = format!("currently {}x{}", width, height);
+        assert!(find(&buffer, &current).is_some(), "{}x{}", width, height);
+        assert_eq!(find(&buffer, "┌Tracks"), None);
+    }
+
+    // just big enough
+    let buffer = render(&mut App::offline(), 80, 24);
+    assert_eq!(find(&buffer, "terminal too small"), None);
+    assert!(find(&buffer, "┌Tracks").is_some());
+}
+
+#[test]
+fn esc_reverts_the_staged_settings() {
+    let mut app = App::offline();
+    app.tab = Tab::Settings;
+    let delay = app.audio.settings.delay;
+    let mono = app.audio.settings.force_mono;
+
+    // the changes are only staged, the live settings stay as they were
+    press(&mut app, KeyCode::Char('d'));
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Char('m'));
+    assert_eq!(app.shown_settings().delay, delay + 2);
+    assert_eq!(app.shown_settings().force_mono, !mono);
+    assert_eq!(app.audio.settings.delay, delay);
+    assert_eq!(app.audio.settings.force_mono, mono);
+
+    let buffer = render(&mut app, 100, 30);
+    assert!(find(&buffer, "Esc to revert").is_some());
+
+    press(&mut app, KeyCode::Esc);
+    assert!(app.staged.is_none());
+    assert_eq!(app.edit_mode, EditMode::None);
+    assert_eq!(app.shown_settings().delay, delay);
+    assert_eq!(app.shown_settings().force_mono, mono);
+    assert_eq!(app.audio.settings.delay, delay);
+
+    let buffer = render(&mut app, 100, 30);
+    assert_eq!(find(&buffer, "Esc to revert"), None);
+}
+
+#[test]
+fn export_window_keys_go_through_the_keymap() {
+    let mut app = App::offline();
+    let options = app.export_options;
+
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.edit_mode, EditMode::ExportMix);
+
+    for key in ['f', 'n', 'd', 's'] {
+        press(&mut app, KeyCode::Char(key));
+    }
+
+    assert_ne!(app.export_options.format, options.format);
+    assert_eq!(app.export_options.normalize, !options.normalize);
+    assert_eq!(app.export_options.dither, !options.dither);
+    assert!(app.export_stems);
+    assert_eq!(app.edit_mode, EditMode::ExportMix);
+
+    let buffer = render(&mut app, 100, 30);
+    assert!(find(&buffer, "[x] stems 's'").is_some());
+
+    // outside the window the same keys do what they always did
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('d'));
+    assert_eq!(app.edit_mode, EditMode::DelayBeats);
+    assert!(app.export_stems);
+
+    // and a rebound toggle is shown and used under its new key
+    app.keymap = Keymap::parse("toggle_export_stems = \"t\"").0;
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('x'));
+    press(&mut app, KeyCode::Char('t'));
+    assert!(!app.export_stems);
+
+    let buffer = render(&mut app, 100, 30);
+    assert!(find(&buffer, "[ ] stems 't'").is_some());
+
+    // leaving the old key to the play tab
+    press(&mut app, KeyCode::Char('s'));
+    assert_eq!(app.edit_mode, EditMode::TrackSend(Some(0)));
+    assert!(!app.export_stems);
+}
@@ -0,0 +1,301 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App, EditMode},
+    clip::Clip,
+    keymap::Action,
+    preview::PreviewSource,
+};
+
+/// How many clips the archive holds, the oldest making room for a new one.
+pub const ARCHIVE_LEN: usize = 16;
+
+/// A clip moved off a track, kept around without taking up one.
+#[derive(Clone)]
+pub struct ArchivedClip {
+    /// The note of the track the clip came from, or the track itself without one.
+    pub name: String,
+    /// The clip, sharing its samples with any copy of it.
+    pub clip: Clip,
+    pub time: SystemTime,
+}
+
+// how long ago something was, in the largest unit that fits
+fn age(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+
+    match seconds {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+impl App {
+    // moves the clip of a track into the archive, which undo brings back like a removal
+    pub fn archive_track(&mut self, index: usize) {
+        self.edit_mode = EditMode::None;
+
+        let track = &self.audio.tracks[index];
+        let Some(ref clip) = track.clip else {
+            self.warn(format!("track {} is empty", (index + 1) % 10));
+            return;
+        };
+
+        let name = match track.note.as_str() {
+            "" => format!("track {}", (index + 1) % 10),
+            note => note.to_string(),
+        };
+
+        let archived = ArchivedClip {
+            name: name.clone(),
+            clip: clip.clone(),
+            time: SystemTime::now(),
+        };
+
+        if self.archive.len() == ARCHIVE_LEN {
+            let dropped = self.archive.remove(0);
+            self.warn(format!("the archive is full, dropped {}", dropped.name));
+        }
+
+        self.archive.push(archived);
+
+        self.push_layer(index, format!("archive track {}", (index + 1) % 10));
+        self.clear_track(index);
+        self.info(format!("archived track {} as {}", (index + 1) % 10, name));
+    }
+
+    pub fn toggle_archive(&mut self) {
+        self.show_archive = !self.show_archive;
+        self.archive_confirm = None;
+
+        // start at the latest clip
+        let latest = self.archive.len().checked_sub(1);
+        self.archive_state.select(latest);
+
+        if !self.show_archive {
+            self.stop_audition();
+        }
+    }
+
+    // handles a key while the archive is open
+    pub fn archive_key(&mut self, key: KeyEvent) {
+        // anything but a second delete lets go of a pending one
+        if key.code != KeyCode::Delete {
+            self.archive_confirm = None;
+        }
+
+        match key.code {
+            KeyCode::Esc => self.toggle_archive(),
+            KeyCode::Enter | KeyCode::Char(' ') => self.audition_selected(),
+            KeyCode::Delete => self.delete_selected(),
+            // the digits pick the track to restore onto, 0 being the tenth
+            KeyCode::Char(digit) if digit.is_ascii_digit() => {
+                let digit = digit.to_digit(10).unwrap_or(0) as usize;
+                self.restore_selected_archive((digit + 9) % 10);
+            }
+            _ => match self.keymap.action(self.tab, key) {
+                Some(Action::RotateUp) => self.scroll_archive(1),
+                Some(Action::RotateDown) => self.scroll_archive(-1),
+                Some(Action::ToggleArchive) => self.toggle_archive(),
+                _ => {}
+            },
+        }
+    }
+
+    fn scroll_archive(&mut self, offset: i32) {
+        if self.archive.is_empty() {
+            return;
+        }
+
+        let selected = self.archive_state.selected().unwrap_or(0) as i32;
+        let index = (selected - offset).clamp(0, self.archive.len() as i32 - 1);
+        self.archive_state.select(Some(index as usize));
+    }
+
+    fn selected_archive(&self) -> Option<usize> {
+        self.archive_state
+            .selected()
+            .filter(|&selected| selected < self.archive.len())
+    }
+
+    fn is_auditioning(&self) -> bool {
+        matches!(self.preview, Some((_, PreviewSource::Archive)))
+    }
+
+    // whether an archived clip is the one being auditioned, which shares its samples
+    fn is_auditioned(&self, archived: &ArchivedClip) -> bool {
+        match self.preview {
+            Some((ref clip, PreviewSource::Archive)) => {
+                Arc::ptr_eq(&clip.samples, &archived.clip.samples)
+            }
+            _ => false,
+        }
+    }
+
+    fn stop_audition(&mut self) {
+        if self.is_auditioning() {
+            self.stop_preview();
+        }
+    }
+
+    // plays the selected clip on its own through the preview, or stops it
+    fn audition_selected(&mut self) {
+        if self.is_auditioning() {
+            self.stop_preview();
+            return;
+        }
+
+        let Some(selected) = self.selected_archive() else {
+            return;
+        };
+
+        if !self.audio.engine.is_recording() {
+            self.warn("launch the streams to audition");
+            return;
+        }
+
+        self.stop_preview();
+        self.start_preview(self.archive[selected].clip.clone(), PreviewSource::Archive);
+    }
+
+    // puts a copy of the selected clip on a track, the archive keeping it
+    fn restore_selected_archive(&mut self, index: usize) {
+        let Some(selected) = self.selected_archive() else {
+            return;
+        };
+
+        if index >= self.audio.tracks.len() {
+            return;
+        }
+
+        let archived = self.archive[selected].clone();
+        self.stop_audition();
+        self.end_compare(index);
+        self.push_layer(
+            index,
+            format!("restore {} to track {}", archived.name, (index + 1) % 10),
+        );
+
+        let track = &mut self.audio.tracks[index];
+        track.offset_frames = 0;
+        track.region = None;
+        track.color.get_or_insert(index);
+        track.clip = Some(archived.clip);
+        self.audio.update_tracks();
+
+        self.info(format!(
+            "restored {} to track {}",
+            archived.name,
+            (index + 1) % 10
+        ));
+    }
+
+    // deletes the selected clip for good, the first press only asks for confirmation
+    fn delete_selected(&mut self) {
+        let Some(selected) = self.selected_archive() else {
+            return;
+        };
+
+        if self.archive_confirm != Some(selected) {
+            // the callback lets go of an auditioned clip before it's deleted
+            self.stop_audition();
+            self.archive_confirm = Some(selected);
+            return;
+        }
+
+        let archived = self.archive.remove(selected);
+        self.archive_confirm = None;
+
+        let latest = self.archive.len().checked_sub(1);
+        self.archive_state
+            .select(latest.map(|latest| selected.min(latest)));
+        self.info(format!("deleted {} from the archive", archived.name));
+    }
+
+    pub fn render_archive<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if !self.show_archive {
+            return;
+        }
+
+        let size = frame.size();
+        let area = centered_rect(size, size.width / 2, size.height * 3 / 4);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Archive", Action::ToggleArchive))
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let selected = self.selected_archive();
+        let items = self
+            .archive
+            .iter()
+            .map(|archived| {
+                let playing = self.is_auditioned(archived);
+
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{:>8} ", age(archived.time)), self.theme.empty),
+                    Span::raw(archived.name.clone()),
+                    Span::styled(
+                        format!(
+                            " {:.1}s, {}ch{}",
+                            archived.clip.duration(),
+                            archived.clip.channels,
+                            if playing { ", playing" } else { "" }
+                        ),
+                        self.theme.empty,
+                    ),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            let empty = Paragraph::new("nothing archived").style(self.theme.empty);
+            frame.render_widget(empty, rows[0]);
+        } else {
+            let list = List::new(items).highlight_style(self.theme.highlight);
+            frame.render_stateful_widget(list, rows[0], &mut self.archive_state);
+        }
+
+        let footer = match (self.archive_confirm, selected) {
+            (Some(confirm), Some(selected)) if confirm == selected => Span::styled(
+                format!(
+                    "delete again to remove {}, for good",
+                    self.archive[selected].name
+                ),
+                self.theme.warning,
+            ),
+            _ => Span::styled(
+                "enter to audition, a track number to restore, delete to remove, esc to close",
+                self.theme.empty,
+            ),
+        };
+
+        frame.render_widget(Paragraph::new(Spans::from(footer)), rows[1]);
+    }
+}
@@ -1,30 +1,209 @@
 use std::{
-    mem,
-    ops::Range,
-    sync::{atomic::Ordering, Arc},
+    error::Error, fmt, fs, io, ops::Range, path::PathBuf, sync::Arc, thread, time::Duration,
 };
 
 use anyhow::anyhow;
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    BufferSize, Device, Host, HostId, InputCallbackInfo, OutputCallbackInfo, SampleRate, Stream,
-    StreamConfig, SupportedBufferSize,
+    BufferSize, BuildStreamError, ChannelCount, Device, Host, HostId, InputCallbackInfo,
+    OutputCallbackInfo, SampleFormat, SampleRate, Stream, StreamConfig, SupportedBufferSize,
+    SupportedStreamConfig, SupportedStreamConfigRange,
 };
+use crossbeam::{atomic::AtomicCell, channel::Sender};
 use ringbuf::HeapRb;
 
-use crate::{clip::Clip, engine::AudioEngine, gag, track::Tracks};
+use crate::{
+    aggregate::{FrameResampler, SecondaryInput},
+    clip::{Clip, RecordedLoop},
+    config::{self, parse_value, strip_comment},
+    engine::AudioEngine,
+    gag,
+    pre_roll::PreRollCapture,
+    processor::{OutputProcessor, ProcessorInput, CUE_CHANNEL, SHUTDOWN_RAMP},
+    queue::QueuedAction,
+    track::{Tracks, TRACK_COUNT},
+};
 
-fn device_eq(a: &Device, b: &Device) -> bool {
-    if let (Ok(a_name), Ok(b_name)) = (a.name(), b.name()) {
-        a_name == b_name
-    } else {
-        false
+fn device_name(device: &Device) -> String {
+    device
+        .name()
+        .unwrap_or_else(|_| String::from("unknown device"))
+}
+
+/// A stream configuration a device supports, as its host reports it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigDescription {
+    pub format: SampleFormat,
+    pub channels: ChannelCount,
+    pub min_sample_rate: SampleRate,
+    pub max_sample_rate: SampleRate,
+    pub buffer_size: SupportedBufferSize,
+}
+
+impl ConfigDescription {
+    /// Describes the configuration on one line, like `f32, 2 ch, 44100-48000 Hz, 64-4096`.
+    pub fn describe(&self) -> String {
+        let rates = if self.min_sample_rate == self.max_sample_rate {
+            format!("{} Hz", self.min_sample_rate.0)
+        } else {
+            format!("{}-{} Hz", self.min_sample_rate.0, self.max_sample_rate.0)
+        };
+
+        let buffer = match self.buffer_size {
+            SupportedBufferSize::Range { min, max } => format!("buffer {}-{}", min, max),
+            SupportedBufferSize::Unknown => String::from("buffer unknown"),
+        };
+
+        format!(
+            "{}, {} ch, {}, {}",
+            self.format, self.channels, rates, buffer
+        )
+    }
+}
+
+impl From<SupportedStreamConfigRange> for ConfigDescription {
+    fn from(range: SupportedStreamConfigRange) -> Self {
+        Self {
+            format: range.sample_format(),
+            channels: range.channels(),
+            min_sample_rate: range.min_sample_rate(),
+            max_sample_rate: range.max_sample_rate(),
+            buffer_size: *range.buffer_size(),
+        }
+    }
+}
+
+impl From<SupportedStreamConfig> for ConfigDescription {
+    fn from(config: SupportedStreamConfig) -> Self {
+        Self {
+            format: config.sample_format(),
+            channels: config.channels(),
+            min_sample_rate: config.sample_rate(),
+            max_sample_rate: config.sample_rate(),
+            buffer_size: *config.buffer_size(),
+        }
+    }
+}
+
+/// What a device reports it supports in one direction, each query failing on its own.
+#[derive(Clone, Debug)]
+pub struct DeviceCapabilities {
+    pub device: String,
+    pub default: Result<ConfigDescription, String>,
+    pub supported: Result<Vec<ConfigDescription>, String>,
+}
+
+impl DeviceCapabilities {
+    fn input(device: &Device) -> Self {
+        gag!();
+
+        Self {
+            device: device_name(device),
+            default: device
+                .default_input_config()
+                .map(ConfigDescription::from)
+                .map_err(|err| err.to_string()),
+            supported: device
+                .supported_input_configs()
+                .map(|configs| configs.map(ConfigDescription::from).collect())
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    fn output(device: &Device) -> Self {
+        gag!();
+
+        Self {
+            device: device_name(device),
+            default: device
+                .default_output_config()
+                .map(ConfigDescription::from)
+                .map_err(|err| err.to_string()),
+            supported: device
+                .supported_output_configs()
+                .map(|configs| configs.map(ConfigDescription::from).collect())
+                .map_err(|err| err.to_string()),
+        }
     }
 }
 
-fn device_index(devices: &[Device], device: &Option<Device>) -> Option<usize> {
-    let device = device.as_ref()?;
-    devices.iter().position(|d| device_eq(d, device))
+// numbers the second and later of the names that are shared, "(2)", "(3)" and so on, so two
+// devices of the same model can be told apart
+fn disambiguate_names(names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let earlier = names[..index].iter().filter(|other| *other == name).count();
+
+            match earlier {
+                0 => name.clone(),
+                _ => format!("{} ({})", name, earlier + 1),
+            }
+        })
+        .collect()
+}
+
+// the names shown for the devices of a host, one for every device in the same order
+fn device_names(devices: &[Device]) -> Vec<String> {
+    disambiguate_names(&devices.iter().map(device_name).collect::<Vec<_>>())
+}
+
+// the name a numbered name was numbered after
+fn strip_number(name: &str) -> Option<&str> {
+    let (base, number) = name.strip_suffix(')')?.rsplit_once(" (")?;
+    number.parse::<usize>().ok()?;
+    Some(base)
+}
+
+// finds a device by one of the names from `device_names`, falling back to the first device
+// sharing the name when the numbered one is gone
+fn name_position(names: &[String], name: &str) -> Option<usize> {
+    let exact = names.iter().position(|other| other == name);
+    exact.or_else(|| {
+        let base = strip_number(name)?;
+        names.iter().position(|other| other == base)
+    })
+}
+
+// finds the default device, which only has a name to go by. A device is known by its index
+// within the host along with its name, so the current one is kept when it shares the name
+fn default_device_index(
+    devices: &[Device],
+    default: Option<Device>,
+    current: Option<usize>,
+) -> Option<usize> {
+    let name = default?.name().ok()?;
+
+    let current = current.filter(|&index| {
+        let device = devices.get(index);
+        device.is_some_and(|device| device.name().ok().as_deref() == Some(name.as_str()))
+    });
+
+    current.or_else(|| {
+        devices
+            .iter()
+            .position(|device| device.name().ok().as_deref() == Some(name.as_str()))
+    })
+}
+
+// builds a stream, trying again with the default buffer size if the device refuses the one
+// asked for. Returns true along with the stream when it fell back
+fn build_with_fallback(
+    config: &StreamConfig,
+    build: impl Fn(&StreamConfig) -> Result<Stream, BuildStreamError>,
+) -> Result<(Stream, bool), BuildStreamError> {
+    match build(config) {
+        Err(_) if config.buffer_size != BufferSize::Default => {
+            let config = StreamConfig {
+                buffer_size: BufferSize::Default,
+                ..config.clone()
+            };
+
+            Ok((build(&config)?, true))
+        }
+        result => Ok((result?, false)),
+    }
 }
 
 fn input_sample_rates(device: &Device) -> Vec<Range<u32>> {
@@ -54,7 +233,7 @@ fn output_sample_rates(device: &Device) -> Vec<Range<u32>> {
 fn input_buffer_sizes(device: &Device) -> Vec<SupportedBufferSize> {
     if let Ok(configs) = device.supported_input_configs() {
         configs
-            .map(|c| c.buffer_size().clone())
+            .map(|c| *c.buffer_size())
             .collect::<Vec<_>>()
             .into_iter()
             .collect()
@@ -66,7 +245,7 @@ fn input_buffer_sizes(device: &Device) -> Vec<SupportedBufferSize> {
 fn output_buffer_sizes(device: &Device) -> Vec<SupportedBufferSize> {
     if let Ok(configs) = device.supported_output_configs() {
         configs
-            .map(|c| c.buffer_size().clone())
+            .map(|c| *c.buffer_size())
             .collect::<Vec<_>>()
             .into_iter()
             .collect()
@@ -75,9 +254,60 @@ fn output_buffer_sizes(device: &Device) -> Vec<SupportedBufferSize> {
     }
 }
 
+/// Why a setting couldn't be selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SettingsError {
+    UnknownHost(String),
+    UnavailableHost(String),
+    UnknownInputDevice(String),
+    UnknownOutputDevice(String),
+    /// A sample rate along with the device that doesn't support it, if it's down to one.
+    UnsupportedSampleRate(u32, Option<String>),
+    /// A buffer size along with the device that doesn't support it, if it's down to one.
+    UnsupportedBufferSize(u32, Option<String>),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownHost(name) => write!(f, "no host named {}", name),
+            Self::UnavailableHost(name) => write!(f, "host {} is unavailable", name),
+            Self::UnknownInputDevice(name) => write!(f, "no input device named {}", name),
+            Self::UnknownOutputDevice(name) => write!(f, "no output device named {}", name),
+            Self::UnsupportedSampleRate(rate, Some(device)) => {
+                write!(f, "rate {} not supported by {}", rate, device)
+            }
+            Self::UnsupportedSampleRate(rate, None) => {
+                write!(f, "rate {} is not one of the offered sample rates", rate)
+            }
+            Self::UnsupportedBufferSize(size, Some(device)) => {
+                write!(f, "buffer size {} not supported by {}", size, device)
+            }
+            Self::UnsupportedBufferSize(size, None) => {
+                write!(
+                    f,
+                    "buffer size {} is not one of the offered buffer sizes",
+                    size
+                )
+            }
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+// the file the audio settings are saved to once they've been chosen
+const AUDIO_FILE: &str = "audio.toml";
+
 const SAMPLE_RATES: &[u32] = &[44100, 48000, 88200, 96000, 176400, 192000];
 const BUFFER_SIZES: &[u32] = &[32, 64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384];
 
+/// The longest monitoring delay, in milliseconds, the input buffer makes room for.
+pub const MAX_DELAY: u32 = 1000;
+
+/// The furthest output channel the cue bus can start on, counted from 0.
+pub const MAX_CUE_CHANNEL: u16 = 63;
+
 fn sample_rate_supported(sample_rates: &[Range<u32>], sample_rate: u32) -> bool {
     sample_rates
         .iter()
@@ -86,112 +316,133 @@ fn sample_rate_supported(sample_rates: &[Range<u32>], sample_rate: u32) -> bool
 
 fn buffer_size_supported(buffer_sizes: &[SupportedBufferSize], buffer_size: u32) -> bool {
     buffer_sizes.iter().any(|size| match size {
-        SupportedBufferSize::Range { min, max } => {
-            buffer_size >= *min as u32 && buffer_size <= *max as u32
-        }
+        SupportedBufferSize::Range { min, max } => buffer_size >= *min && buffer_size <= *max,
         SupportedBufferSize::Unknown => true,
     })
 }
 
-fn sample_rates(input_device: Option<&Device>, output_device: Option<&Device>) -> Vec<SampleRate> {
-    let mut sample_rates = Vec::new();
-
-    match (input_device, output_device) {
-        (Some(input_device), Some(output_device)) => {
-            let input = input_sample_rates(input_device);
-            let output = output_sample_rates(output_device);
-
-            for &sample_rate in SAMPLE_RATES {
-                let input_supported = sample_rate_supported(&input, sample_rate);
-                let output_supported = sample_rate_supported(&output, sample_rate);
+/// Which of the chosen devices support a sample rate or buffer size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupportSide {
+    Both,
+    Input,
+    Output,
+    Neither,
+}
 
-                if input_supported && output_supported {
-                    sample_rates.push(SampleRate(sample_rate));
-                }
-            }
+impl SupportSide {
+    // a device that isn't chosen doesn't rule anything out
+    fn new(input: Option<bool>, output: Option<bool>) -> Self {
+        match (input.unwrap_or(true), output.unwrap_or(true)) {
+            (true, true) => Self::Both,
+            (true, false) => Self::Input,
+            (false, true) => Self::Output,
+            (false, false) => Self::Neither,
         }
-        (Some(input_device), None) => {
-            let input = input_sample_rates(input_device);
+    }
 
-            for &sample_rate in SAMPLE_RATES {
-                if sample_rate_supported(&input, sample_rate) {
-                    sample_rates.push(SampleRate(sample_rate));
-                }
-            }
-        }
-        (None, Some(output_device)) => {
-            let output = output_sample_rates(output_device);
+    pub fn is_supported(self) -> bool {
+        self == Self::Both
+    }
 
-            for &sample_rate in SAMPLE_RATES {
-                if sample_rate_supported(&output, sample_rate) {
-                    sample_rates.push(SampleRate(sample_rate));
-                }
-            }
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Both => "in+out",
+            Self::Input => "in only",
+            Self::Output => "out only",
+            Self::Neither => "neither",
         }
-        (None, None) => (),
     }
-
-    sample_rates.sort();
-    sample_rates.dedup();
-    sample_rates
 }
 
-fn buffer_sizes(input_device: Option<&Device>, output_device: Option<&Device>) -> Vec<u32> {
-    let mut buffer_sizes = Vec::new();
-
-    match (input_device, output_device) {
-        (Some(input_device), Some(output_device)) => {
-            let input = input_buffer_sizes(input_device);
-            let output = output_buffer_sizes(output_device);
+// every candidate sample rate, along with which devices support it
+fn sample_rates(
+    input_device: Option<&Device>,
+    output_device: Option<&Device>,
+) -> Vec<(SampleRate, SupportSide)> {
+    if input_device.is_none() && output_device.is_none() {
+        return Vec::new();
+    }
 
-            for &buffer_size in BUFFER_SIZES {
-                let input_supported = buffer_size_supported(&input, buffer_size);
-                let output_supported = buffer_size_supported(&output, buffer_size);
+    let input = input_device.map(input_sample_rates);
+    let output = output_device.map(output_sample_rates);
 
-                if input_supported && output_supported {
-                    buffer_sizes.push(buffer_size);
-                }
-            }
-        }
-        (Some(input_device), None) => {
-            let input = input_buffer_sizes(input_device);
-
-            for &buffer_size in BUFFER_SIZES {
-                if buffer_size_supported(&input, buffer_size) {
-                    buffer_sizes.push(buffer_size);
-                }
-            }
-        }
-        (None, Some(output_device)) => {
-            let output = output_buffer_sizes(output_device);
+    SAMPLE_RATES
+        .iter()
+        .map(|&sample_rate| {
+            let side = SupportSide::new(
+                input
+                    .as_ref()
+                    .map(|r| sample_rate_supported(r, sample_rate)),
+                output
+                    .as_ref()
+                    .map(|r| sample_rate_supported(r, sample_rate)),
+            );
+
+            (SampleRate(sample_rate), side)
+        })
+        .collect()
+}
 
-            for &buffer_size in BUFFER_SIZES {
-                if buffer_size_supported(&output, buffer_size) {
-                    buffer_sizes.push(buffer_size);
-                }
-            }
-        }
-        (None, None) => (),
+// every candidate buffer size, along with which devices support it
+fn buffer_sizes(
+    input_device: Option<&Device>,
+    output_device: Option<&Device>,
+) -> Vec<(u32, SupportSide)> {
+    if input_device.is_none() && output_device.is_none() {
+        return Vec::new();
     }
 
-    buffer_sizes.sort();
-    buffer_sizes.dedup();
-    buffer_sizes
+    let input = input_device.map(input_buffer_sizes);
+    let output = output_device.map(output_buffer_sizes);
+
+    BUFFER_SIZES
+        .iter()
+        .map(|&buffer_size| {
+            let side = SupportSide::new(
+                input
+                    .as_ref()
+                    .map(|s| buffer_size_supported(s, buffer_size)),
+                output
+                    .as_ref()
+                    .map(|s| buffer_size_supported(s, buffer_size)),
+            );
+
+            (buffer_size, side)
+        })
+        .collect()
 }
 
+#[derive(Clone)]
 pub struct AudioSettings {
-    pub available_hosts: Vec<HostId>,
-    pub host: Host,
-    pub input_devices: Vec<Device>,
-    pub output_devices: Vec<Device>,
-    pub input_device: Option<usize>,
-    pub output_device: Option<usize>,
-    pub sample_rates: Vec<SampleRate>,
-    pub sample_rate: Option<usize>,
-    pub buffer_sizes: Vec<u32>,
-    pub buffer_size: Option<usize>,
+    // the indices point into the lists of the current host, so they're only changed along
+    // with them
+    available_hosts: Vec<HostId>,
+    host: Arc<Host>,
+    input_devices: Arc<[Device]>,
+    output_devices: Arc<[Device]>,
+    input_device: Option<usize>,
+    output_device: Option<usize>,
+    // a second input device recorded along with the first, if any
+    secondary_input_device: Option<usize>,
+    sample_rates: Vec<(SampleRate, SupportSide)>,
+    sample_rate: Option<usize>,
+    buffer_sizes: Vec<(u32, SupportSide)>,
+    buffer_size: Option<usize>,
     pub delay: u32,
+    /// How many milliseconds captured after the wrap are folded into the head of a loop.
+    pub tail_fold: u32,
+    /// The first output channel of the cue bus, the ones before it playing the main mix.
+    pub cue_channel: u16,
     pub force_mono: bool,
+    /// A WAV file the metronome plays instead of its own click.
+    pub click: Option<PathBuf>,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AudioSettings {
@@ -199,21 +450,25 @@ impl AudioSettings {
         gag!();
 
         let available_hosts = cpal::available_hosts();
-        let host = cpal::default_host();
+        let host = Arc::new(cpal::default_host());
 
         Self {
             available_hosts,
             host,
-            input_devices: Vec::new(),
-            output_devices: Vec::new(),
+            input_devices: Arc::from([]),
+            output_devices: Arc::from([]),
             input_device: None,
             output_device: None,
+            secondary_input_device: None,
             sample_rates: Vec::new(),
             sample_rate: None,
             buffer_sizes: Vec::new(),
             buffer_size: None,
             delay: 15,
+            tail_fold: 0,
+            cue_channel: CUE_CHANNEL,
             force_mono: false,
+            click: None,
         }
     }
 
@@ -223,14 +478,25 @@ impl AudioSettings {
         self.input_devices = self.host.input_devices().unwrap().collect();
         self.output_devices = self.host.output_devices().unwrap().collect();
 
+        // the second input is only ever chosen on purpose
+        self.secondary_input_device = None;
+
         self.query_default_devices();
     }
 
     pub fn query_default_devices(&mut self) {
         gag!();
 
-        self.input_device = device_index(&self.input_devices, &self.host.default_input_device());
-        self.output_device = device_index(&self.output_devices, &self.host.default_output_device());
+        self.input_device = default_device_index(
+            &self.input_devices,
+            self.host.default_input_device(),
+            self.input_device,
+        );
+        self.output_device = default_device_index(
+            &self.output_devices,
+            self.host.default_output_device(),
+            self.output_device,
+        );
 
         self.query_sample_rates();
         self.query_buffer_sizes();
@@ -246,13 +512,13 @@ impl AudioSettings {
     }
 
     pub fn query_default_sample_rate(&mut self) {
-        if let Some(index) = self.sample_rates.iter().position(|s| s.0 == 48000) {
-            self.sample_rate = Some(index);
-        } else if !self.sample_rates.is_empty() {
-            self.sample_rate = Some(0);
-        } else {
-            self.sample_rate = None;
-        }
+        let rates = &self.sample_rates;
+        let preferred = rates
+            .iter()
+            .position(|&(rate, side)| rate.0 == 48000 && side.is_supported());
+        let first = rates.iter().position(|(_, side)| side.is_supported());
+
+        self.sample_rate = preferred.or(first);
     }
 
     pub fn query_buffer_sizes(&mut self) {
@@ -265,13 +531,155 @@ impl AudioSettings {
     }
 
     pub fn query_default_buffer_size(&mut self) {
-        if let Some(index) = self.buffer_sizes.iter().position(|s| *s == 128) {
-            self.buffer_size = Some(index);
-        } else if !self.buffer_sizes.is_empty() {
-            self.buffer_size = Some(0);
-        } else {
-            self.buffer_size = None;
+        let sizes = &self.buffer_sizes;
+        let preferred = sizes
+            .iter()
+            .position(|&(size, side)| size == 128 && side.is_supported());
+        let first = sizes.iter().position(|(_, side)| side.is_supported());
+
+        self.buffer_size = preferred.or(first);
+    }
+
+    /// Returns true when no audio settings have been saved yet, meaning wroom runs for the
+    /// first time.
+    pub fn is_first_launch() -> bool {
+        config::config_file(AUDIO_FILE).is_some_and(|path| !path.exists())
+    }
+
+    /// Restores the settings from the config file, keeping the defaults for anything that's
+    /// missing or no longer available.
+    pub fn load(&mut self) {
+        gag!();
+
+        let Some(source) =
+            config::config_file(AUDIO_FILE).and_then(|path| fs::read_to_string(path).ok())
+        else {
+            return;
+        };
+
+        // a flat TOML table of `name = "value"` lines
+        let values = source
+            .lines()
+            .filter_map(|line| {
+                let (name, value) = strip_comment(line).split_once('=')?;
+                let value = parse_value(value.trim())?.into_iter().next()?;
+                Some((name.trim().to_string(), value))
+            })
+            .collect::<Vec<_>>();
+
+        let value = |name: &str| {
+            values
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        // anything that's no longer available keeps its default, so the errors don't matter
+        if let Some(name) = value("host") {
+            let _ = self.select_host_by_name(name);
+        }
+
+        if let Some(name) = value("input_device") {
+            let _ = self.select_input_device_by_name(name);
+        }
+
+        if let Some(name) = value("output_device") {
+            let _ = self.select_output_device_by_name(name);
+        }
+
+        if let Some(name) = value("secondary_input_device") {
+            let _ = self.select_secondary_input_device_by_name(name);
+        }
+
+        let number = |name: &str| value(name)?.parse::<u32>().ok();
+
+        if let Some(sample_rate) = number("sample_rate") {
+            let _ = self.select_sample_rate(sample_rate);
+        }
+
+        if let Some(buffer_size) = number("buffer_size") {
+            let _ = self.select_buffer_size(buffer_size);
+        }
+
+        if let Some(delay) = number("delay") {
+            self.delay = delay.min(MAX_DELAY);
+        }
+
+        if let Some(tail_fold) = number("tail_fold") {
+            self.tail_fold = tail_fold;
+        }
+
+        // counted from 1 like in the ui
+        if let Some(cue_channel) = number("cue_channel") {
+            self.cue_channel = cue_channel.clamp(2, MAX_CUE_CHANNEL as u32 + 1) as u16 - 1;
+        }
+
+        if let Some(force_mono) = value("force_mono") {
+            self.force_mono = force_mono == "true";
+        }
+
+        self.click = value("click").map(PathBuf::from);
+    }
+
+    /// Returns the monitoring delay in samples of the whole input.
+    pub fn delay_samples(&self, input_channels: u16, sample_rate: SampleRate) -> u32 {
+        input_channels as u32 * sample_rate.0 * self.delay.min(MAX_DELAY) / 1000
+    }
+
+    /// Returns true if going from these settings to `other` takes new streams, which anything
+    /// but the monitoring delay and the tail fold does.
+    pub fn needs_relaunch(&self, other: &Self) -> bool {
+        self.host.id() != other.host.id()
+            || self.input_device_name() != other.input_device_name()
+            || self.output_device_name() != other.output_device_name()
+            || self.secondary_input_device_name() != other.secondary_input_device_name()
+            || self.get_sample_rate() != other.get_sample_rate()
+            || self.get_buffer_size() != other.get_buffer_size()
+            || self.force_mono != other.force_mono
+    }
+
+    /// Writes the settings to the config file, so the next launch starts with them.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(dir) = config::config_dir() else {
+            return Ok(());
+        };
+
+        let mut source = format!("host = \"{}\"\n", self.host.id().name());
+
+        if let Some(name) = self.input_device_name() {
+            source.push_str(&format!("input_device = \"{}\"\n", name));
+        }
+
+        if let Some(name) = self.output_device_name() {
+            source.push_str(&format!("output_device = \"{}\"\n", name));
+        }
+
+        if let Some(name) = self.secondary_input_device_name() {
+            source.push_str(&format!("secondary_input_device = \"{}\"\n", name));
+        }
+
+        if let Some(sample_rate) = self.get_sample_rate() {
+            source.push_str(&format!("sample_rate = \"{}\"\n", sample_rate.0));
+        }
+
+        if let Some(index) = self.buffer_size {
+            source.push_str(&format!(
+                "buffer_size = \"{}\"\n",
+                self.buffer_sizes[index].0
+            ));
+        }
+
+        source.push_str(&format!("delay = \"{}\"\n", self.delay));
+        source.push_str(&format!("tail_fold = \"{}\"\n", self.tail_fold));
+        source.push_str(&format!("cue_channel = \"{}\"\n", self.cue_channel + 1));
+        source.push_str(&format!("force_mono = \"{}\"\n", self.force_mono));
+
+        if let Some(ref click) = self.click {
+            source.push_str(&format!("click = \"{}\"\n", click.display()));
         }
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(AUDIO_FILE), source)
     }
 
     pub fn host_names(&self) -> Vec<&'static str> {
@@ -285,50 +693,177 @@ impl AudioSettings {
             .unwrap()
     }
 
-    pub fn rotate_host(&mut self, offset: i32) {
-        gag!();
+    pub fn host_id(&self) -> HostId {
+        self.host.id()
+    }
+
+    pub fn input_device_count(&self) -> usize {
+        self.input_devices.len()
+    }
+
+    pub fn output_device_count(&self) -> usize {
+        self.output_devices.len()
+    }
+
+    /// Returns the position of the input device in [`Self::input_device_names`].
+    pub fn input_device_index(&self) -> Option<usize> {
+        self.input_device
+    }
+
+    /// Returns the position of the output device in [`Self::output_device_names`].
+    pub fn output_device_index(&self) -> Option<usize> {
+        self.output_device
+    }
+
+    /// Returns the sample rates of the devices and whether each side supports them.
+    pub fn sample_rates(&self) -> &[(SampleRate, SupportSide)] {
+        &self.sample_rates
+    }
+
+    /// Returns the position of the sample rate in [`Self::sample_rates`].
+    pub fn sample_rate_index(&self) -> Option<usize> {
+        self.sample_rate
+    }
+
+    /// Returns the buffer sizes of the devices and whether each side supports them.
+    pub fn buffer_sizes(&self) -> &[(u32, SupportSide)] {
+        &self.buffer_sizes
+    }
 
+    /// Returns the position of the buffer size in [`Self::buffer_sizes`].
+    pub fn buffer_size_index(&self) -> Option<usize> {
+        self.buffer_size
+    }
+
+    pub fn rotate_host(&mut self, offset: i32) -> Result<(), SettingsError> {
         let mut index = self.host_index() as i32 + offset;
         index = index.rem_euclid(self.available_hosts.len() as i32);
-        self.host = cpal::host_from_id(self.available_hosts[index as usize]).unwrap();
+        self.select_host(self.available_hosts[index as usize])
+    }
+
+    /// Switches to the host with the given name, querying its devices.
+    pub fn select_host_by_name(&mut self, name: &str) -> Result<(), SettingsError> {
+        let id = self
+            .available_hosts
+            .iter()
+            .find(|id| id.name() == name)
+            .ok_or_else(|| SettingsError::UnknownHost(name.to_string()))?;
+
+        self.select_host(*id)
+    }
 
+    fn select_host(&mut self, id: HostId) -> Result<(), SettingsError> {
+        gag!();
+
+        let host = cpal::host_from_id(id)
+            .map_err(|_| SettingsError::UnavailableHost(id.name().to_string()))?;
+
+        self.host = Arc::new(host);
         self.query_devices();
+
+        Ok(())
     }
 
+    /// Returns the names of the input devices, numbered where several share a name.
     pub fn input_device_names(&self) -> Vec<String> {
-        self.input_devices
-            .iter()
-            .filter_map(|device| device.name().ok())
-            .collect()
+        device_names(&self.input_devices)
     }
 
     pub fn rotate_input_device(&mut self, offset: i32) {
         if let Some(index) = self.input_device {
             let index = (index as i32 + offset).rem_euclid(self.input_devices.len() as i32);
-            self.input_device = Some(index as usize);
+            self.select_input_device(index as usize);
         } else if !self.input_devices.is_empty() {
-            self.input_device = Some(0);
+            self.select_input_device(0);
         }
+    }
+
+    /// Switches to the input device with the given name, querying what it supports along
+    /// with the output device.
+    pub fn select_input_device_by_name(&mut self, name: &str) -> Result<(), SettingsError> {
+        let index = name_position(&self.input_device_names(), name)
+            .ok_or_else(|| SettingsError::UnknownInputDevice(name.to_string()))?;
 
+        self.select_input_device(index);
+        Ok(())
+    }
+
+    fn select_input_device(&mut self, index: usize) {
+        self.input_device = Some(index);
         self.query_sample_rates();
+        self.query_buffer_sizes();
+
+        // one device can't be both inputs
+        if self.secondary_input_device == Some(index) {
+            self.secondary_input_device = None;
+        }
+    }
+
+    /// Returns the position of the second input device in [`Self::input_device_names`].
+    pub fn secondary_input_device_index(&self) -> Option<usize> {
+        self.secondary_input_device
+    }
+
+    /// Cycles the second input device through none and every input device but the first.
+    pub fn rotate_secondary_input_device(&mut self, offset: i32) {
+        // none comes first, the devices follow from 1
+        let choices = self.input_devices.len() as i32 + 1;
+        let mut choice = self
+            .secondary_input_device
+            .map_or(0, |index| index as i32 + 1);
+
+        for _ in 0..choices {
+            choice = (choice + offset.signum()).rem_euclid(choices);
+
+            if choice == 0 || self.input_device != Some(choice as usize - 1) {
+                break;
+            }
+        }
+
+        self.secondary_input_device = (choice > 0).then(|| choice as usize - 1);
     }
 
+    /// Chooses the input device with the given name as the second input.
+    pub fn select_secondary_input_device_by_name(
+        &mut self,
+        name: &str,
+    ) -> Result<(), SettingsError> {
+        let index = name_position(&self.input_device_names(), name)
+            .filter(|&index| self.input_device != Some(index))
+            .ok_or_else(|| SettingsError::UnknownInputDevice(name.to_string()))?;
+
+        self.secondary_input_device = Some(index);
+        Ok(())
+    }
+
+    /// Returns the names of the output devices, numbered where several share a name.
     pub fn output_device_names(&self) -> Vec<String> {
-        self.output_devices
-            .iter()
-            .filter_map(|device| device.name().ok())
-            .collect()
+        device_names(&self.output_devices)
     }
 
     pub fn rotate_output_device(&mut self, offset: i32) {
         if let Some(index) = self.output_device {
             let index = (index as i32 + offset).rem_euclid(self.output_devices.len() as i32);
-            self.output_device = Some(index as usize);
+            self.select_output_device(index as usize);
         } else if !self.output_devices.is_empty() {
-            self.output_device = Some(0);
+            self.select_output_device(0);
         }
+    }
 
+    /// Switches to the output device with the given name, querying what it supports along
+    /// with the input device.
+    pub fn select_output_device_by_name(&mut self, name: &str) -> Result<(), SettingsError> {
+        let index = name_position(&self.output_device_names(), name)
+            .ok_or_else(|| SettingsError::UnknownOutputDevice(name.to_string()))?;
+
+        self.select_output_device(index);
+        Ok(())
+    }
+
+    fn select_output_device(&mut self, index: usize) {
+        self.output_device = Some(index);
         self.query_sample_rates();
+        self.query_buffer_sizes();
     }
 
     pub fn rotate_sample_rate(&mut self, offset: i32) {
@@ -340,6 +875,23 @@ impl AudioSettings {
         }
     }
 
+    /// Chooses a sample rate both devices support.
+    pub fn select_sample_rate(&mut self, sample_rate: u32) -> Result<(), SettingsError> {
+        let index = self.sample_rates.iter().position(|s| s.0 .0 == sample_rate);
+
+        match index.map(|index| (index, self.sample_rates[index].1)) {
+            Some((index, side)) if side.is_supported() => {
+                self.sample_rate = Some(index);
+                Ok(())
+            }
+            Some((_, side)) => Err(SettingsError::UnsupportedSampleRate(
+                sample_rate,
+                self.unsupported_by(side),
+            )),
+            None => Err(SettingsError::UnsupportedSampleRate(sample_rate, None)),
+        }
+    }
+
     pub fn rotate_buffer_size(&mut self, offset: i32) {
         if let Some(index) = self.buffer_size {
             let index = (index as i32 + offset).rem_euclid(self.buffer_sizes.len() as i32);
@@ -349,6 +901,60 @@ impl AudioSettings {
         }
     }
 
+    /// Chooses a buffer size both devices support.
+    pub fn select_buffer_size(&mut self, buffer_size: u32) -> Result<(), SettingsError> {
+        let index = self.buffer_sizes.iter().position(|s| s.0 == buffer_size);
+
+        match index.map(|index| (index, self.buffer_sizes[index].1)) {
+            Some((index, side)) if side.is_supported() => {
+                self.buffer_size = Some(index);
+                Ok(())
+            }
+            Some((_, side)) => Err(SettingsError::UnsupportedBufferSize(
+                buffer_size,
+                self.unsupported_by(side),
+            )),
+            None => Err(SettingsError::UnsupportedBufferSize(buffer_size, None)),
+        }
+    }
+
+    // names the device that rules out a value supported by `side`
+    fn unsupported_by(&self, side: SupportSide) -> Option<String> {
+        let (kind, device) = match side {
+            SupportSide::Both => return None,
+            SupportSide::Input => ("output", self.get_output_device()),
+            SupportSide::Output | SupportSide::Neither => ("input", self.get_input_device()),
+        };
+
+        let name = device?.name().unwrap_or_else(|_| String::from("unknown"));
+        Some(format!("{} device {}", kind, name))
+    }
+
+    /// Returns why the chosen sample rate can't be used, if it can't.
+    pub fn sample_rate_error(&self) -> Option<SettingsError> {
+        let (rate, side) = self.sample_rates[self.sample_rate?];
+        let device = self.unsupported_by(side)?;
+        Some(SettingsError::UnsupportedSampleRate(rate.0, Some(device)))
+    }
+
+    /// Returns why the chosen buffer size can't be used, if it can't.
+    pub fn buffer_size_error(&self) -> Option<SettingsError> {
+        let (size, side) = self.buffer_sizes[self.buffer_size?];
+        let device = self.unsupported_by(side)?;
+        Some(SettingsError::UnsupportedBufferSize(size, Some(device)))
+    }
+
+    /// Checks that both devices support the chosen sample rate and buffer size.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        match self
+            .sample_rate_error()
+            .or_else(|| self.buffer_size_error())
+        {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
     pub fn get_input_device(&self) -> Option<&Device> {
         self.input_device.map(|i| &self.input_devices[i])
     }
@@ -357,19 +963,49 @@ impl AudioSettings {
         self.output_device.map(|i| &self.output_devices[i])
     }
 
+    pub fn get_secondary_input_device(&self) -> Option<&Device> {
+        self.secondary_input_device.map(|i| &self.input_devices[i])
+    }
+
+    /// Asks the input device what it supports, which can take a moment on some hosts.
+    pub fn input_capabilities(&self) -> Option<DeviceCapabilities> {
+        self.get_input_device().map(DeviceCapabilities::input)
+    }
+
+    /// Asks the output device what it supports, which can take a moment on some hosts.
+    pub fn output_capabilities(&self) -> Option<DeviceCapabilities> {
+        self.get_output_device().map(DeviceCapabilities::output)
+    }
+
+    /// Returns the name of the input device as listed by [`AudioSettings::input_device_names`].
+    pub fn input_device_name(&self) -> Option<String> {
+        self.input_device_names().get(self.input_device?).cloned()
+    }
+
+    /// Returns the name of the output device as listed by
+    /// [`AudioSettings::output_device_names`].
+    pub fn output_device_name(&self) -> Option<String> {
+        self.output_device_names().get(self.output_device?).cloned()
+    }
+
+    /// Returns the name of the second input device as listed by
+    /// [`AudioSettings::input_device_names`].
+    pub fn secondary_input_device_name(&self) -> Option<String> {
+        self.input_device_names()
+            .get(self.secondary_input_device?)
+            .cloned()
+    }
+
     pub fn get_sample_rate(&self) -> Option<SampleRate> {
-        self.sample_rate.map(|i| self.sample_rates[i])
+        self.sample_rate.map(|i| self.sample_rates[i].0)
     }
 
     pub fn get_buffer_size(&self) -> Option<BufferSize> {
-        Some(BufferSize::Fixed(self.buffer_sizes[self.buffer_size?]))
+        Some(BufferSize::Fixed(self.buffer_sizes[self.buffer_size?].0))
     }
 
-    pub fn launch_stream(
-        &self,
-        engine: Arc<AudioEngine>,
-        tracks: &Tracks,
-    ) -> anyhow::Result<(Stream, Stream)> {
+    /// Returns what streams launched with these settings would be built with.
+    pub fn stream_spec(&self) -> anyhow::Result<StreamSpec> {
         let input_device = self.get_input_device().ok_or(anyhow!("no input device"))?;
         let output_device = self
             .get_output_device()
@@ -380,27 +1016,51 @@ impl AudioSettings {
         let default_input_config = input_device.default_input_config().unwrap();
         let default_output_config = output_device.default_output_config().unwrap();
 
-        let input_channels = default_input_config.channels();
-        let output_channels = default_output_config.channels();
+        Ok(StreamSpec {
+            host: self.host.id(),
+            input_device: device_name(input_device),
+            output_device: device_name(output_device),
+            secondary_input_device: self.secondary_input_device_name(),
+            input_config: StreamConfig {
+                channels: default_input_config.channels(),
+                sample_rate,
+                buffer_size,
+            },
+            output_config: StreamConfig {
+                channels: default_output_config.channels(),
+                sample_rate,
+                buffer_size,
+            },
+            force_mono: self.force_mono,
+        })
+    }
 
-        let input_config = StreamConfig {
-            channels: input_channels,
-            sample_rate,
-            buffer_size,
-        };
-        let output_config = StreamConfig {
-            channels: output_channels,
-            sample_rate,
-            buffer_size,
-        };
+    pub fn launch_stream(
+        &self,
+        spec: &StreamSpec,
+        engine: Arc<AudioEngine>,
+        tracks: &Tracks,
+        events: Sender<AudioEvent>,
+    ) -> anyhow::Result<Streams> {
+        let input_device = self.get_input_device().ok_or(anyhow!("no input device"))?;
+        let output_device = self
+            .get_output_device()
+            .ok_or(anyhow!("no output device"))?;
+
+        let input_config = &spec.input_config;
+        let output_config = &spec.output_config;
+        let sample_rate = input_config.sample_rate;
 
-        let is_mono = input_channels != output_channels || self.force_mono;
-        let feedback_channels = if is_mono { 1 } else { input_channels };
+        let input_channels = input_config.channels;
+        let output_channels = output_config.channels;
 
-        let buffer_size = feedback_channels as u32 * sample_rate.0 * self.delay / 1000;
-        let (mut prod, mut cons) = HeapRb::new(buffer_size as usize * 2).split();
+        // the whole input goes through the buffer, so tracks can record any of its channels.
+        // It has room for the longest delay, which the output callback moves toward live
+        let delay_samples = self.delay_samples(input_channels, sample_rate);
+        let capacity = input_channels as u32 * sample_rate.0 * MAX_DELAY / 1000;
+        let (mut prod, cons) = HeapRb::new(capacity as usize * 2).split();
 
-        for _ in 0..buffer_size {
+        for _ in 0..delay_samples {
             prod.push(0.0).unwrap();
         }
 
@@ -410,170 +1070,292 @@ impl AudioSettings {
 
         let mut channel = 0;
         let mut average = 0.0;
+        let input_engine = engine.clone();
 
         let input_stream = input_device.build_input_stream(
-            &input_config,
+            input_config,
             move |data: &[f32], _: &InputCallbackInfo| {
+                let mut peak = 0.0f32;
+                input_engine.record_buffer_size(data.len() as u64 / input_channels as u64);
+
                 for &sample in data {
-                    if !is_mono {
-                        let _ = prod.push(sample);
-                        continue;
-                    }
+                    let _ = prod.push(sample);
 
                     average += sample;
                     channel += 1;
 
                     if channel == input_channels {
-                        prod.push(average / input_channels as f32).unwrap();
+                        let frame = average / input_channels as f32;
+
+                        // the analysis panel looks at the mono mix of the input
+                        input_engine.input_snapshot.push(frame);
+                        peak = peak.max(frame.abs());
+
                         average = 0.0;
                         channel = 0;
                     }
                 }
+
+                input_engine.raise_input_peak(peak);
             },
             error,
             None,
         )?;
 
-        engine.set_sample_rate(sample_rate.0 as u64);
-        let mut tracks = tracks.clone();
-        let mut recording = Vec::new();
-        let mut channel = 0;
-        let mut feedback = 0.0;
-        let mut last_feedback = 0.0;
-
-        let data = move |data: &mut [f32], _: &OutputCallbackInfo| {
-            for target in data {
-                if engine.is_on_beat() {
-                    // if tracks have been updated, use them
-                    if let Some(new_tracks) = engine.take_tracks() {
-                        tracks = new_tracks;
-                    }
-                }
-
-                channel += 1;
+        // the others run without the second input when it can't be opened
+        let (secondary_stream, secondary) = match spec.secondary_input_device {
+            Some(_) => match self.launch_secondary_input(input_config, engine.clone()) {
+                Ok((stream, secondary)) => (Some(Ok(stream)), Some(secondary)),
+                Err(err) => (Some(Err(err)), None),
+            },
+            None => (None, None),
+        };
 
-                if !is_mono {
-                    feedback = cons.pop().unwrap_or(last_feedback);
-                    last_feedback = feedback;
-                    recording.push(feedback);
-                }
+        let secondary_channels = secondary.as_ref().map_or(0, SecondaryInput::channels);
+        let channels = input_channels + secondary_channels;
 
-                if channel == output_channels {
-                    if is_mono {
-                        feedback = cons.pop().unwrap_or(last_feedback);
-                        last_feedback = feedback;
-                        recording.push(feedback);
-                    }
+        let is_mono = channels != output_channels || self.force_mono;
+        engine.set_input_layout(channels, is_mono);
+        engine.set_secondary_channels(secondary_channels);
+        engine.set_monitor_delay(self.delay_samples(channels, sample_rate) as u64);
+        engine.set_output_channels(output_channels);
+        engine.set_tail_fold(self.tail_fold as u64);
+        engine.set_cue_channel(self.cue_channel);
 
-                    engine.sample.fetch_add(1, Ordering::AcqRel);
-                    channel = 0;
-                }
+        let input = ProcessorInput {
+            consumer: cons,
+            channels: input_channels,
+            secondary,
+        };
+        let processor = OutputProcessor::new(
+            engine.clone(),
+            tracks.clone(),
+            input,
+            sample_rate,
+            output_channels,
+            events,
+        );
 
-                *target = get_sample(&engine, &tracks, channel, feedback_channels, feedback);
+        // the processor waits here for the first callback, so a failed attempt doesn't take
+        // it along
+        let slot = Arc::new(AtomicCell::new(Some(processor)));
 
-                if engine.should_loop() {
-                    engine.set_sample(0);
+        let build = |config: &StreamConfig| {
+            let slot = slot.clone();
+            let mut processor = None;
 
-                    let clip = Clip {
-                        channels: feedback_channels,
-                        sample_rate,
-                        samples: Arc::from(mem::take(&mut recording)),
-                    };
+            let data = move |data: &mut [f32], info: &OutputCallbackInfo| {
+                if processor.is_none() {
+                    processor = slot.take();
+                }
 
-                    engine.set_recorded_clip(Some(clip));
+                if let Some(ref mut processor) = processor {
+                    processor.process(data, Some(info.timestamp()));
                 }
-            }
+            };
+
+            output_device.build_output_stream(config, data, error, None)
         };
 
-        let output_stream = output_device.build_output_stream(&output_config, data, error, None)?;
+        let (output_stream, fell_back) = build_with_fallback(output_config, build)?;
+        engine.set_buffer_fallback(fell_back);
 
         input_stream.play()?;
         output_stream.play()?;
 
-        Ok((input_stream, output_stream))
+        // a second input that doesn't start leaves its channels silent
+        let secondary_stream = secondary_stream.map(|stream| -> anyhow::Result<Stream> {
+            let stream = stream?;
+            stream.play()?;
+            Ok(stream)
+        });
+
+        Ok(Streams {
+            input: input_stream,
+            output: output_stream,
+            secondary_input: secondary_stream,
+        })
     }
-}
 
-fn metronome(time: f32) -> f32 {
-    const A6: f32 = 1760.0;
+    // opens the second input device, at the sample rate and buffer size of the first if it
+    // can, its callback filling a buffer of its own that starts out as long as the monitoring
+    // delay
+    fn launch_secondary_input(
+        &self,
+        config: &StreamConfig,
+        engine: Arc<AudioEngine>,
+    ) -> anyhow::Result<(Stream, SecondaryInput)> {
+        let device = self
+            .get_secondary_input_device()
+            .ok_or(anyhow!("no second input device"))?;
+
+        let default = device.default_input_config()?;
+        let channels = default.channels();
+        let sample_rate = config.sample_rate;
+
+        // a device that can't run at the engine's rate runs at its own, resampled
+        let supports_rate = device.supported_input_configs()?.any(|range| {
+            range.channels() == channels
+                && range.min_sample_rate() <= sample_rate
+                && sample_rate <= range.max_sample_rate()
+        });
+
+        let device_config = if supports_rate {
+            StreamConfig {
+                channels,
+                sample_rate,
+                buffer_size: config.buffer_size,
+            }
+        } else {
+            StreamConfig {
+                channels,
+                sample_rate: default.sample_rate(),
+                buffer_size: BufferSize::Default,
+            }
+        };
 
-    (time * A6).sin() * (1.0 - time * 2.0).clamp(0.0, 1.0) * 0.5
-}
+        let mut resampler =
+            FrameResampler::new(channels, device_config.sample_rate.0, sample_rate.0);
 
-fn get_sample(
-    engine: &AudioEngine,
-    tracks: &Tracks,
-    channel: u16,
-    channels: u16,
-    feedback: f32,
-) -> f32 {
-    let mut sample = 0.0;
-    let beat_offset = engine.beat().fract() / engine.bps();
+        let delay_samples = self.delay_samples(channels, sample_rate);
+        let capacity = channels as u32 * sample_rate.0 * MAX_DELAY / 1000;
+        let (mut prod, cons) = HeapRb::new(capacity as usize * 2).split();
 
-    // add in the feedback
-    sample += feedback;
+        for _ in 0..delay_samples {
+            prod.push(0.0).unwrap();
+        }
 
-    // add in the metronome
+        let error = |err| {
+            eprintln!("an error occurred on the second input stream: {}", err);
+        };
 
-    if engine.metronome() {
-        sample += metronome(beat_offset);
-    }
+        let stream = device.build_input_stream(
+            &device_config,
+            move |data: &[f32], _: &InputCallbackInfo| {
+                // the analysis panel follows the first device, the peak is of both
+                let peak = data
+                    .iter()
+                    .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
 
-    let sample_index = engine.sample();
+                for frame in data.chunks(resampler.channels()) {
+                    resampler.push(frame, |sample| {
+                        let _ = prod.push(sample);
+                    });
+                }
 
-    // add in the tracks
-    for track in tracks.iter() {
-        let Some(ref clip) = track.clip else {
-            continue;
-        };
+                engine.raise_input_peak(peak);
+            },
+            error,
+            None,
+        )?;
 
-        let mut track_sample = if clip.channels == channels {
-            clip.sample(sample_index, channel)
-        } else {
-            clip.average_sample(sample_index)
-        };
+        let secondary = SecondaryInput::new(cons, channels, sample_rate.0);
+        Ok((stream, secondary))
+    }
+}
 
-        track_sample *= track.volume_factor();
+/// The streams launched by [`AudioSettings::launch_stream`].
+pub struct Streams {
+    pub input: Stream,
+    pub output: Stream,
+    /// The stream of the second input device if one is chosen, or why it couldn't be opened.
+    pub secondary_input: Option<anyhow::Result<Stream>>,
+}
 
-        sample += track_sample;
-    }
+/// What a pair of streams was built with, to tell when new settings can keep them running.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamSpec {
+    pub host: HostId,
+    pub input_device: String,
+    pub output_device: String,
+    /// The second input device, whose config is only asked for once it's opened.
+    pub secondary_input_device: Option<String>,
+    pub input_config: StreamConfig,
+    pub output_config: StreamConfig,
+    /// The output callback decides how to monitor the input when it's built.
+    pub force_mono: bool,
+}
 
-    sample
+/// Something the output callback finished, sent on the channel given to [`Audio::offline`].
+#[derive(Clone, Debug)]
+pub enum AudioEvent {
+    /// An armed track finished recording a whole loop.
+    Recorded(usize, RecordedLoop),
+    /// A free recording was stopped.
+    FreeRecorded(Clip),
+    /// The last loop length of input was captured for a track, ending at the given frame of
+    /// the loop.
+    Captured(usize, PreRollCapture, u64),
 }
 
 pub struct Audio {
     pub settings: AudioSettings,
     pub input_stream: Option<Stream>,
     pub output_stream: Option<Stream>,
+    pub secondary_input_stream: Option<Stream>,
+    /// Why the second input device couldn't be opened, the other streams running without it.
+    pub secondary_error: Option<anyhow::Error>,
+    /// What the running streams were built with.
+    stream_spec: Option<StreamSpec>,
     pub engine: Arc<AudioEngine>,
     pub tracks: Tracks,
     pub clip: Option<Clip>,
     pub error: Option<anyhow::Error>,
+    events: Sender<AudioEvent>,
+    /// The loop length queued for the next loop boundary.
+    pub pending_beats: Option<u64>,
+    /// What the engine will do at the next loop boundary, along with the id it was queued
+    /// with.
+    pub queued: Vec<(u64, QueuedAction)>,
+    next_action: u64,
+    /// Holds back the tracks from the engine while a macro plays, so its changes can be
+    /// queued together.
+    pub defer_tracks: bool,
+    /// Set when the tracks changed while they were held back.
+    pub tracks_deferred: bool,
+    /// The generation of the tracks sent to the engine last, along with a copy of them.
+    tracks_generation: u64,
+    sent_tracks: Tracks,
+    /// The generation every track last changed in.
+    track_changes: [u64; TRACK_COUNT],
 }
 
 impl Audio {
-    pub fn new() -> Audio {
-        let mut settings = AudioSettings::new();
-        settings.rotate_host(0);
-        settings.query_devices();
-
-        let mut audio = Audio {
-            settings,
+    /// Creates the audio without looking for devices or launching the streams.
+    pub fn offline(events: Sender<AudioEvent>) -> Audio {
+        Audio {
+            settings: AudioSettings::new(),
             input_stream: None,
             output_stream: None,
+            secondary_input_stream: None,
+            secondary_error: None,
+            stream_spec: None,
             engine: Arc::new(AudioEngine::default()),
             tracks: Tracks::default(),
             clip: None,
             error: None,
-        };
-
-        audio.launch_streams();
+            events,
+            pending_beats: None,
+            queued: Vec::new(),
+            next_action: 1,
+            defer_tracks: false,
+            tracks_deferred: false,
+            tracks_generation: 0,
+            sent_tracks: Tracks::default(),
+            track_changes: [0; TRACK_COUNT],
+        }
+    }
 
-        audio
+    /// Finds the devices, loads the saved settings and launches the streams.
+    pub fn start(&mut self) {
+        self.settings.query_devices();
+        self.settings.load();
+        self.relaunch_streams();
     }
 
     pub fn get_clip(&mut self) -> Option<Clip> {
-        if let Some(clip) = self.engine.recorded_clip.take() {
+        if let Some(recorded) = self.engine.recorded_clip.take() {
+            let clip = recorded.folded().analyze();
             self.clip = Some(clip.clone());
             Some(clip)
         } else {
@@ -581,21 +1363,371 @@ impl Audio {
         }
     }
 
+    /// Hands the settings the running streams pick up on the fly to the engine.
+    pub fn apply_live_settings(&mut self) {
+        let engine = &self.engine;
+        let sample_rate = SampleRate(engine.sample_rate() as u32);
+        let delay = self
+            .settings
+            .delay_samples(engine.input_channels(), sample_rate);
+
+        engine.set_monitor_delay(delay as u64);
+        engine.set_tail_fold(self.settings.tail_fold as u64);
+        engine.set_cue_channel(self.settings.cue_channel);
+    }
+
     pub fn update_tracks(&mut self) {
-        self.engine.set_tracks(Some(self.tracks.clone()));
+        if self.defer_tracks {
+            self.tracks_deferred = true;
+            return;
+        }
+
+        self.tracks_generation += 1;
+
+        // stamp the tracks that changed, so they show as pending until they're heard
+        let changes = self.tracks.iter().zip(self.sent_tracks.iter());
+        for (index, (track, sent)) in changes.enumerate() {
+            if !track.sounds_like(sent) {
+                self.track_changes[index] = self.tracks_generation;
+            }
+        }
+
+        self.sent_tracks = self.tracks.clone();
+        self.engine
+            .set_tracks(self.tracks_generation, self.tracks.clone());
+
+        // keep a queued loop change in sync with the tracks
+        if let Some(beats) = self.pending_beats {
+            self.queue_beats(beats);
+        }
     }
 
-    pub fn launch_streams(&mut self) {
-        match self
-            .settings
-            .launch_stream(self.engine.clone(), &self.tracks)
-        {
-            Ok((input_stream, output_stream)) => {
-                self.input_stream = Some(input_stream);
-                self.output_stream = Some(output_stream);
+    /// Returns true if the last change to a track hasn't reached the output callback yet.
+    pub fn is_track_pending(&self, index: usize) -> bool {
+        self.engine.is_recording() && self.track_changes[index] > self.engine.applied_tracks()
+    }
+
+    /// Changes the loop length at the next loop boundary, fitting every clip to it.
+    pub fn queue_beats(&mut self, beats: u64) {
+        // a queued tempo has taken over by the time the loop changes length
+        let frames = self.engine.target_beats_frames(beats);
+        let tracks = self.tracks.fitted(frames);
+
+        self.pending_beats = Some(beats);
+        self.cancel_queued(|action| matches!(action, QueuedAction::Resize { .. }));
+        self.queue_action(QueuedAction::Resize { beats, tracks });
+    }
+
+    pub fn cancel_pending_beats(&mut self) {
+        self.pending_beats = None;
+        self.cancel_queued(|action| matches!(action, QueuedAction::Resize { .. }));
+    }
+
+    /// Queues an action for the next loop boundary, returning false if too much is queued.
+    pub fn queue_action(&mut self, action: QueuedAction) -> bool {
+        let id = self.next_action;
+
+        if !self.engine.queue.push(id, action.clone()) {
+            return false;
+        }
+
+        self.next_action += 1;
+        self.queued.push((id, action));
+        true
+    }
+
+    /// Takes back the queued actions matching `cancel`, keeping the rest queued.
+    pub fn cancel_queued(&mut self, cancel: impl Fn(&QueuedAction) -> bool) {
+        if !self.queued.iter().any(|(_, action)| cancel(action)) {
+            return;
+        }
+
+        self.queued.retain(|(_, action)| !cancel(action));
+        self.engine.queue.cancel();
+
+        for (id, action) in &self.queued {
+            self.engine.queue.push(*id, action.clone());
+        }
+    }
+
+    /// Returns true if an action matching `matches` waits for the loop boundary.
+    pub fn is_queued(&self, matches: impl Fn(&QueuedAction) -> bool) -> bool {
+        self.queued.iter().any(|(_, action)| matches(action))
+    }
+
+    /// Forgets the actions the engine has applied, called every update.
+    pub fn update_queued(&mut self) {
+        let applied = self.engine.queue.applied();
+        self.queued.retain(|&(id, _)| id > applied);
+    }
+
+    /// Launches the streams, unless the running ones were built the same way, in which case
+    /// they keep running without a dropout and only pick up the live settings and the tracks.
+    ///
+    /// Returns true if new streams were launched.
+    pub fn launch_streams(&mut self) -> bool {
+        let spec = match self.settings.stream_spec() {
+            Ok(spec) => spec,
+            Err(err) => {
+                self.error = Some(err);
+                return true;
+            }
+        };
+
+        let running = self.output_stream.is_some() && self.engine.is_recording();
+        if running && self.stream_spec.as_ref() == Some(&spec) {
+            self.apply_live_settings();
+            self.update_tracks();
+            return false;
+        }
+
+        self.build_streams(spec);
+        true
+    }
+
+    /// Launches new streams even if the running ones were built the same way, which brings
+    /// back streams a device stopped serving.
+    pub fn relaunch_streams(&mut self) {
+        match self.settings.stream_spec() {
+            Ok(spec) => self.build_streams(spec),
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn build_streams(&mut self, spec: StreamSpec) {
+        // the new output stream marks itself as recording once it runs
+        self.engine.set_recording(false);
+        self.engine.set_shutting_down(false);
+        self.engine.set_loop_restart(None);
+        self.engine.reset_buffer_sizes();
+
+        let streams = self.settings.launch_stream(
+            &spec,
+            self.engine.clone(),
+            &self.tracks,
+            self.events.clone(),
+        );
+
+        match streams {
+            Ok(streams) => {
+                self.input_stream = Some(streams.input);
+                self.output_stream = Some(streams.output);
+
+                (self.secondary_input_stream, self.secondary_error) = match streams.secondary_input
+                {
+                    Some(Ok(stream)) => (Some(stream), None),
+                    Some(Err(err)) => (None, Some(err)),
+                    None => (None, None),
+                };
+
+                self.stream_spec = Some(spec);
                 self.error = None;
+
+                // the new callback starts out with the tracks as they are
+                self.engine.set_applied_tracks(self.tracks_generation);
             }
             Err(err) => self.error = Some(err),
         }
     }
+
+    /// Fades the output to silence and stops the streams.
+    pub fn shutdown(&mut self) {
+        if self.engine.is_recording() {
+            self.engine.set_shutting_down(true);
+
+            // give the output callback time to finish the fade
+            thread::sleep(Duration::from_secs_f32(SHUTDOWN_RAMP * 2.0));
+        }
+
+        let streams = [
+            self.input_stream.take(),
+            self.secondary_input_stream.take(),
+            self.output_stream.take(),
+        ];
+
+        for stream in streams.into_iter().flatten() {
+            let _ = stream.pause();
+        }
+
+        self.stream_spec = None;
+        self.engine.set_recording(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel;
+
+    use super::*;
+
+    fn spec() -> StreamSpec {
+        let config = |channels| StreamConfig {
+            channels,
+            sample_rate: SampleRate(48000),
+            buffer_size: BufferSize::Fixed(256),
+        };
+
+        StreamSpec {
+            host: cpal::default_host().id(),
+            input_device: String::from("interface"),
+            output_device: String::from("interface"),
+            secondary_input_device: None,
+            input_config: config(2),
+            output_config: config(4),
+            force_mono: false,
+        }
+    }
+
+    #[test]
+    fn only_the_same_spec_keeps_the_streams() {
+        assert_eq!(spec(), spec());
+
+        let changes: [fn(&mut StreamSpec); 5] = [
+            |spec| spec.output_device = String::from("headphones"),
+            |spec| spec.secondary_input_device = Some(String::from("mic")),
+            |spec| spec.input_config.sample_rate = SampleRate(44100),
+            |spec| spec.output_config.buffer_size = BufferSize::Default,
+            |spec| spec.force_mono = true,
+        ];
+
+        for change in changes {
+            let mut changed = spec();
+            change(&mut changed);
+            assert_ne!(changed, spec());
+        }
+    }
+
+    #[test]
+    fn launching_without_devices_keeps_nothing() {
+        let (sender, _receiver) = channel::unbounded();
+        let mut audio = Audio::offline(sender);
+
+        assert!(audio.launch_streams());
+        assert!(audio.error.is_some());
+        assert_eq!(audio.stream_spec, None);
+    }
+
+    // settings offering the given sample rates and buffer sizes, the way a pair of devices
+    // would after being queried
+    fn offering(
+        sample_rates: &[(u32, SupportSide)],
+        buffer_sizes: &[(u32, SupportSide)],
+    ) -> AudioSettings {
+        let mut settings = AudioSettings::new();
+        settings.sample_rates = sample_rates
+            .iter()
+            .map(|&(rate, side)| (SampleRate(rate), side))
+            .collect();
+        settings.buffer_sizes = buffer_sizes.to_vec();
+
+        settings.query_default_sample_rate();
+        settings.query_default_buffer_size();
+        settings
+    }
+
+    #[test]
+    fn support_of_both_devices() {
+        assert_eq!(SupportSide::new(Some(true), Some(true)), SupportSide::Both);
+        assert_eq!(
+            SupportSide::new(Some(true), Some(false)),
+            SupportSide::Input
+        );
+        assert_eq!(SupportSide::new(Some(false), None), SupportSide::Output);
+        assert_eq!(SupportSide::new(None, None), SupportSide::Both);
+
+        assert!(sample_rate_supported(&[44100..48001, 88200..96001], 48000));
+        assert!(!sample_rate_supported(&[44100..48000, 96000..96001], 48000));
+
+        let sizes = [SupportedBufferSize::Range { min: 64, max: 512 }];
+        assert!(buffer_size_supported(&sizes, 64) && buffer_size_supported(&sizes, 512));
+        assert!(!buffer_size_supported(&sizes, 1024));
+        assert!(buffer_size_supported(&[SupportedBufferSize::Unknown], 1024));
+    }
+
+    #[test]
+    fn select_sample_rate_only_takes_what_both_support() {
+        use SupportSide::*;
+
+        let mut settings = offering(&[(44100, Both), (48000, Input), (96000, Both)], &[]);
+
+        // 48 kHz is preferred, but only when both devices take it
+        assert_eq!(settings.get_sample_rate(), Some(SampleRate(44100)));
+
+        assert_eq!(settings.select_sample_rate(96000), Ok(()));
+        assert_eq!(settings.get_sample_rate(), Some(SampleRate(96000)));
+
+        assert_eq!(
+            settings.select_sample_rate(48000),
+            Err(SettingsError::UnsupportedSampleRate(48000, None))
+        );
+        assert_eq!(
+            settings.select_sample_rate(22050),
+            Err(SettingsError::UnsupportedSampleRate(22050, None))
+        );
+        assert_eq!(settings.get_sample_rate(), Some(SampleRate(96000)));
+
+        let settings = offering(&[(44100, Both), (48000, Both)], &[]);
+        assert_eq!(settings.get_sample_rate(), Some(SampleRate(48000)));
+    }
+
+    #[test]
+    fn select_buffer_size_only_takes_what_both_support() {
+        use SupportSide::*;
+
+        let mut settings = offering(&[], &[(64, Output), (128, Neither), (256, Both)]);
+        assert_eq!(settings.get_buffer_size(), Some(BufferSize::Fixed(256)));
+
+        assert_eq!(
+            settings.select_buffer_size(128),
+            Err(SettingsError::UnsupportedBufferSize(128, None))
+        );
+        assert!(settings.select_buffer_size(64).is_err());
+        assert_eq!(settings.get_buffer_size(), Some(BufferSize::Fixed(256)));
+
+        // nothing both devices take leaves it unchosen
+        let settings = offering(&[(48000, Input)], &[(128, Input)]);
+        assert_eq!(settings.get_sample_rate(), None);
+        assert_eq!(settings.get_buffer_size(), None);
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn duplicate_device_names_are_numbered() {
+        let devices = names(&["Scarlett 2i2", "Built-in", "Scarlett 2i2", "Scarlett 2i2"]);
+        let shown = disambiguate_names(&devices);
+        assert_eq!(
+            shown,
+            names(&[
+                "Scarlett 2i2",
+                "Built-in",
+                "Scarlett 2i2 (2)",
+                "Scarlett 2i2 (3)"
+            ])
+        );
+
+        // names that are all different are left alone
+        let devices = names(&["Built-in", "USB (2)"]);
+        assert_eq!(disambiguate_names(&devices), devices);
+    }
+
+    #[test]
+    fn numbered_names_find_their_device() {
+        let shown = disambiguate_names(&names(&["Scarlett 2i2", "Built-in", "Scarlett 2i2"]));
+
+        assert_eq!(name_position(&shown, "Scarlett 2i2"), Some(0));
+        assert_eq!(name_position(&shown, "Scarlett 2i2 (2)"), Some(2));
+        assert_eq!(name_position(&shown, "Built-in"), Some(1));
+
+        // one that's gone falls back to the first of its name, a name that isn't numbered the
+        // same way doesn't
+        assert_eq!(name_position(&shown, "Scarlett 2i2 (3)"), Some(0));
+        assert_eq!(name_position(&shown, "Built-in (x)"), None);
+        assert_eq!(name_position(&shown, "Missing (2)"), None);
+
+        assert_eq!(strip_number("USB (12)"), Some("USB"));
+        assert_eq!(strip_number("USB"), None);
+    }
 }
@@ -0,0 +1,104 @@
+use std::{
+    f32::consts::TAU,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use cpal::{ChannelCount, SampleRate};
+use crossbeam::channel;
+use ringbuf::HeapRb;
+
+use crate::{
+    cli::BenchOptions,
+    clip::Clip,
+    engine::AudioEngine,
+    processor::{OutputProcessor, ProcessorInput},
+    track::Tracks,
+};
+
+const CHANNELS: ChannelCount = 2;
+
+// how much audio is processed, in seconds
+const BENCH_SECONDS: u64 = 10;
+
+// buffers processed before measuring, so allocations settle
+const WARMUP_BUFFERS: usize = 100;
+
+// a sine wave with a different pitch for every track
+fn synthetic_clip(index: usize, frames: u64, sample_rate: SampleRate) -> Clip {
+    let frequency = 110.0 * (index + 1) as f32;
+    let mut samples = Vec::with_capacity(frames as usize * CHANNELS as usize);
+
+    for frame in 0..frames {
+        let time = frame as f32 / sample_rate.0 as f32;
+        let sample = (time * frequency * TAU).sin() * 0.1;
+
+        for _ in 0..CHANNELS {
+            samples.push(sample);
+        }
+    }
+
+    Clip::new(CHANNELS, sample_rate, samples.into())
+}
+
+/// Measures how long the output processor takes per buffer and prints the results.
+pub fn bench(options: &BenchOptions) {
+    let sample_rate = SampleRate(options.sample_rate);
+    let engine = Arc::new(AudioEngine::default());
+
+    let loop_frames = engine.beats() * 60 * sample_rate.0 as u64 / engine.bpm();
+
+    let mut tracks = Tracks::new();
+    for (index, track) in tracks.iter_mut().take(options.tracks).enumerate() {
+        track.clip = Some(synthetic_clip(index, loop_frames, sample_rate));
+    }
+
+    // the input is left empty, the processor holds the last sample in that case
+    let (_, input) = HeapRb::new(1).split();
+    let (events, _) = channel::unbounded();
+    let input = ProcessorInput {
+        consumer: input,
+        channels: CHANNELS,
+        secondary: None,
+    };
+    let mut processor = OutputProcessor::new(engine, tracks, input, sample_rate, CHANNELS, events);
+
+    let mut buffer = vec![0.0; options.frames * CHANNELS as usize];
+
+    for _ in 0..WARMUP_BUFFERS {
+        processor.process(&mut buffer, None);
+    }
+
+    let buffers = (BENCH_SECONDS * sample_rate.0 as u64 / options.frames as u64).max(1);
+    let mut times = Vec::with_capacity(buffers as usize);
+
+    for _ in 0..buffers {
+        let start = Instant::now();
+        processor.process(&mut buffer, None);
+        times.push(start.elapsed());
+    }
+
+    times.sort();
+
+    let total = times.iter().sum::<Duration>();
+    let average = total / times.len() as u32;
+    let median = times[times.len() / 2];
+    let budget = Duration::from_secs_f64(options.frames as f64 / sample_rate.0 as f64);
+
+    println!(
+        "{} tracks, {} Hz, {} frames per buffer, {} buffers",
+        options.tracks, sample_rate.0, options.frames, buffers
+    );
+    println!(
+        "min {} ns, median {} ns, average {} ns, max {} ns per buffer",
+        times[0].as_nanos(),
+        median.as_nanos(),
+        average.as_nanos(),
+        times[times.len() - 1].as_nanos()
+    );
+    println!(
+        "budget {} ns per buffer, {:.2}% used on average",
+        budget.as_nanos(),
+        average.as_secs_f64() / budget.as_secs_f64() * 100.0
+    );
+}
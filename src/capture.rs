@@ -0,0 +1,35 @@
+use crate::{app::App, clip::Clip};
+
+impl App {
+    // keeps what was just played on the selected track, the last loop length of input taken
+    // even though nothing was armed
+    pub fn capture_take(&mut self) {
+        let engine = &self.audio.engine;
+
+        if !engine.is_recording() {
+            self.warn("the input isn't running, nothing to capture");
+            return;
+        }
+
+        // a free loop has no length to capture until it's stopped
+        if engine.is_free_recording() {
+            self.warn("a free recording is running, nothing to capture yet");
+            return;
+        }
+
+        if engine.beats_frames(engine.beats()) == 0 {
+            self.warn("the loop has no length, nothing to capture");
+            return;
+        }
+
+        engine.request_capture(self.selected_track);
+    }
+
+    // called with the last loop length of input once the output callback captured it
+    pub fn captured(&mut self, index: usize, clip: Clip, end: u64) {
+        // the capture ends where playback was, so turn it to start on the loop
+        let clip = clip.rotated(end);
+
+        self.commit_clip(index, clip);
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use crate::wav::{ExportOptions, SampleFormat};
+
+pub const USAGE: &str = "\
+usage:
+    wroom [--safe-mode] [session]
+    wroom new <template>
+    wroom render <session> -o <output.wav> [--stems] [--loops <n>] [--16-bit [--normalize] [--dither]]
+    wroom bench [--tracks <n>] [--sample-rate <hz>] [--frames <n>]
+
+options:
+    --safe-mode            start in the settings without opening any stream or loading the
+                           saved audio settings
+    -o, --output <file>    where to write the rendered mix
+    --stems                also write every track to <output>-track-<n>.wav
+    --loops <n>            how many times to repeat the loop, defaults to 1
+    --16-bit               write 16-bit files instead of 32-bit float
+    --normalize            bring the peak of every 16-bit file to -1 dBFS
+    --dither               add tpdf dither to 16-bit files before rounding
+    --tracks <n>           how many tracks to benchmark with, defaults to 10
+    --sample-rate <hz>     the sample rate to benchmark at, defaults to 48000
+    --frames <n>           the frames per buffer to benchmark with, defaults to 128
+    -h, --help             show this message";
+
+pub struct RunOptions {
+    pub session: Option<PathBuf>,
+    pub safe_mode: bool,
+}
+
+pub struct RenderOptions {
+    pub session: PathBuf,
+    pub output: PathBuf,
+    pub stems: bool,
+    pub loops: u64,
+    pub export: ExportOptions,
+}
+
+pub struct BenchOptions {
+    pub tracks: usize,
+    pub sample_rate: u32,
+    pub frames: usize,
+}
+
+pub enum Command {
+    /// Starts the tui, optionally loading a session.
+    Run(RunOptions),
+    /// Starts the tui with a new session made from a saved template.
+    New(String),
+    Render(RenderOptions),
+    /// Measures the cost of the output callback.
+    Bench(BenchOptions),
+    Help,
+}
+
+impl Command {
+    /// Parses the command line arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+
+        let Some(first) = args.next() else {
+            return Ok(Command::Run(RunOptions {
+                session: None,
+                safe_mode: false,
+            }));
+        };
+
+        match first.as_str() {
+            "-h" | "--help" => Ok(Command::Help),
+            "render" => Self::parse_render(args),
+            "bench" => Self::parse_bench(args),
+            "new" => match (args.next(), args.next()) {
+                (Some(template), None) => Ok(Command::New(template)),
+                (None, _) => Err(String::from("missing template name")),
+                (_, Some(arg)) => Err(format!("unexpected argument `{}`", arg)),
+            },
+            _ => Self::parse_run(std::iter::once(first).chain(args)),
+        }
+    }
+
+    fn parse_run(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut session = None;
+        let mut safe_mode = false;
+
+        for arg in args {
+            match arg.as_str() {
+                "--safe-mode" => safe_mode = true,
+                "-h" | "--help" => return Ok(Command::Help),
+                arg if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if session.is_none() => session = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+
+        Ok(Command::Run(RunOptions { session, safe_mode }))
+    }
+
+    fn parse_render(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut session = None;
+        let mut output = None;
+        let mut stems = false;
+        let mut loops = 1;
+        let mut export = ExportOptions::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    let path = args.next().ok_or("`--output` needs a file")?;
+                    output = Some(PathBuf::from(path));
+                }
+                "--stems" => stems = true,
+                "--16-bit" => export.format = SampleFormat::Int16,
+                "--normalize" => export.normalize = true,
+                "--dither" => export.dither = true,
+                "--loops" => {
+                    let value = args.next().ok_or("`--loops` needs a number")?;
+                    loops = match value.parse::<u64>() {
+                        Ok(loops) if loops > 0 => loops,
+                        _ => return Err(format!("invalid loop count `{}`", value)),
+                    };
+                }
+                "-h" | "--help" => return Ok(Command::Help),
+                arg if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if session.is_none() => session = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+
+        Ok(Command::Render(RenderOptions {
+            session: session.ok_or("missing session file")?,
+            output: output.ok_or("missing output file, use `-o <file>`")?,
+            stems,
+            loops,
+            export,
+        }))
+    }
+
+    fn parse_bench(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = BenchOptions {
+            tracks: 10,
+            sample_rate: 48000,
+            frames: 128,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut number = |range: std::ops::RangeInclusive<usize>| {
+                let value = args.next().ok_or(format!("`{}` needs a number", arg))?;
+
+                match value.parse::<usize>() {
+                    Ok(number) if range.contains(&number) => Ok(number),
+                    _ => Err(format!("invalid value `{}` for `{}`", value, arg)),
+                }
+            };
+
+            match arg.as_str() {
+                "--tracks" => options.tracks = number(0..=10)?,
+                "--sample-rate" => options.sample_rate = number(1..=384000)? as u32,
+                "--frames" => options.frames = number(1..=65536)?,
+                "-h" | "--help" => return Ok(Command::Help),
+                _ => return Err(format!("unknown option `{}`", arg)),
+            }
+        }
+
+        Ok(Command::Bench(options))
+    }
+}
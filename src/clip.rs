@@ -1,31 +1,351 @@
-use std::sync::Arc;
+use std::{mem, ops::Range, sync::Arc};
 
 use cpal::{ChannelCount, SampleRate};
 
+// how many peaks are kept per clip to draw its waveform
+const PEAK_BUCKETS: usize = 256;
+
+// samples louder than this are clamped when a clip is sanitized, about +12 dBFS
+const SANITIZE_LIMIT: f32 = 4.0;
+
+// an average of any channel further from zero than this counts as a dc offset, -20 dBFS
+const DC_THRESHOLD: f32 = 0.1;
+
+// shorter silences stay in the segment around them when compacting, so a clip isn't split at
+// every zero crossing
+const MIN_GAP_FRAMES: u64 = 1024;
+
+/// What [`Clip::sanitized`] found in a clip.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClipReport {
+    /// How many NaN or infinite samples were replaced with silence.
+    pub non_finite: usize,
+    /// How many samples were clamped to the limit.
+    pub clamped: usize,
+    /// The loudest sample before clamping, NaN and infinite samples left out.
+    pub peak: f32,
+    /// The largest average of a channel, positive or negative.
+    pub dc_offset: f32,
+}
+
+impl ClipReport {
+    pub fn is_clipping(&self) -> bool {
+        self.peak > 1.0
+    }
+
+    pub fn has_dc_offset(&self) -> bool {
+        self.dc_offset.abs() > DC_THRESHOLD
+    }
+
+    /// Returns true if the clip is likely broken rather than just loud, like a loose cable.
+    pub fn is_unsafe(&self) -> bool {
+        self.non_finite > 0 || self.has_dc_offset()
+    }
+
+    /// Describes everything worth warning about, if anything.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.non_finite > 0 {
+            problems.push(format!("{} invalid samples", self.non_finite));
+        }
+
+        if self.is_clipping() {
+            let decibels = 20.0 * self.peak.log10();
+            problems.push(format!("a peak of {:+.1} dBFS", decibels));
+        }
+
+        if self.clamped > 0 {
+            problems.push(format!("{} samples clamped", self.clamped));
+        }
+
+        if self.has_dc_offset() {
+            problems.push(format!("a dc offset of {:.2}", self.dc_offset));
+        }
+
+        problems
+    }
+}
+
+/// A stretch of sound in a compacted clip.
+#[derive(Debug)]
+pub struct Segment {
+    /// The frame the segment starts at.
+    pub start: u64,
+    pub samples: Box<[f32]>,
+}
+
+/// The samples of a compacted clip, silent between its segments.
+#[derive(Debug)]
+pub struct SparseSamples {
+    pub frames: u64,
+    /// The segments in order, none of them overlapping.
+    pub segments: Box<[Segment]>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Clip {
     pub channels: ChannelCount,
     pub sample_rate: SampleRate,
+    /// The samples, frame by frame, empty when the clip is compacted.
     pub samples: Arc<[f32]>,
+    /// The samples of a clip made by [`Clip::compacted`].
+    pub sparse: Option<Arc<SparseSamples>>,
+    /// The loudest sample of each slice of the clip, empty until analyzed.
+    pub peaks: Arc<[f32]>,
+}
+
+// returns the loudest sample of every bucket, all channels included
+fn compute_peaks(samples: &[f32], channels: ChannelCount) -> Arc<[f32]> {
+    let frames = samples.len() / channels.max(1) as usize;
+    if frames == 0 {
+        return Arc::from([]);
+    }
+
+    let buckets = PEAK_BUCKETS.min(frames);
+    let mut peaks = vec![0.0f32; buckets];
+
+    for (frame, samples) in samples.chunks(channels.max(1) as usize).enumerate() {
+        let peak = &mut peaks[frame * buckets / frames];
+
+        for sample in samples {
+            *peak = peak.max(sample.abs());
+        }
+    }
+
+    peaks.into()
 }
 
 impl Clip {
     pub fn new(channels: ChannelCount, sample_rate: SampleRate, samples: Arc<[f32]>) -> Self {
         Self {
+            peaks: compute_peaks(&samples, channels),
             channels,
             sample_rate,
             samples,
+            sparse: None,
+        }
+    }
+
+    /// Creates a clip without computing its peaks, for the output callback which can't afford
+    /// a pass over the whole recording. See [`Clip::analyze`].
+    pub fn unanalyzed(
+        channels: ChannelCount,
+        sample_rate: SampleRate,
+        samples: Arc<[f32]>,
+    ) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            samples,
+            sparse: None,
+            peaks: Arc::from([]),
+        }
+    }
+
+    /// Computes the peaks of a clip created with [`Clip::unanalyzed`].
+    pub fn analyze(mut self) -> Self {
+        if self.peaks.is_empty() {
+            self.peaks = compute_peaks(&self.dense_samples(), self.channels);
+        }
+
+        self
+    }
+
+    pub fn is_compacted(&self) -> bool {
+        self.sparse.is_some()
+    }
+
+    /// Returns every sample frame by frame, filling in the silence of a compacted clip.
+    pub fn dense_samples(&self) -> Arc<[f32]> {
+        let Some(ref sparse) = self.sparse else {
+            return self.samples.clone();
+        };
+
+        let channels = self.channels.max(1) as usize;
+        let mut samples = vec![0.0; sparse.frames as usize * channels];
+
+        for segment in sparse.segments.iter() {
+            let start = segment.start as usize * channels;
+            samples[start..start + segment.samples.len()].copy_from_slice(&segment.samples);
+        }
+
+        samples.into()
+    }
+
+    /// Returns the clip with every sample stored, for anything that works on all of them.
+    pub fn densified(&self) -> Self {
+        if !self.is_compacted() {
+            return self.clone();
         }
+
+        Self {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            samples: self.dense_samples(),
+            sparse: None,
+            peaks: self.peaks.clone(),
+        }
+    }
+
+    /// Returns a copy that only stores the stretches louder than `threshold`, the silences of
+    /// 1024 frames or more between them left out and read back as zeros. Returns the clip as
+    /// it is if that wouldn't save anything.
+    pub fn compacted(&self, threshold: f32) -> Self {
+        let channels = self.channels.max(1) as usize;
+        let samples = self.dense_samples();
+        let frames = self.frame_count();
+
+        let is_loud = |frame: u64| {
+            let start = frame as usize * channels;
+            let frame = &samples[start..start + channels];
+            frame.iter().any(|sample| sample.abs() > threshold)
+        };
+
+        // the first and last loud frame of every segment
+        let mut ranges = Vec::new();
+        let mut current: Option<(u64, u64)> = None;
+
+        for frame in (0..frames).filter(|&frame| is_loud(frame)) {
+            current = match current {
+                Some((start, last)) if frame - last - 1 < MIN_GAP_FRAMES => Some((start, frame)),
+                Some(range) => {
+                    ranges.push(range);
+                    Some((frame, frame))
+                }
+                None => Some((frame, frame)),
+            };
+        }
+
+        ranges.extend(current);
+
+        let segments = ranges
+            .into_iter()
+            .map(|(start, last)| Segment {
+                start,
+                samples: samples[start as usize * channels..(last as usize + 1) * channels].into(),
+            })
+            .collect::<Box<[_]>>();
+
+        let sparse = SparseSamples { frames, segments };
+        let clip = Self {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            samples: Arc::from([]),
+            sparse: Some(Arc::new(sparse)),
+            peaks: self.peaks.clone(),
+        };
+
+        if clip.memory_bytes() < self.memory_bytes() {
+            clip
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Returns how many bytes the samples take up.
+    pub fn memory_bytes(&self) -> usize {
+        let dense = self.samples.len() * mem::size_of::<f32>();
+
+        let sparse = self.sparse.as_ref().map_or(0, |sparse| {
+            sparse
+                .segments
+                .iter()
+                .map(|segment| {
+                    mem::size_of::<Segment>() + segment.samples.len() * mem::size_of::<f32>()
+                })
+                .sum()
+        });
+
+        dense + sparse
+    }
+
+    /// Returns a copy with NaN and infinite samples silenced and the rest clamped to a safe
+    /// range, along with what was found.
+    pub fn sanitized(&self) -> (Self, ClipReport) {
+        let mut report = ClipReport::default();
+        let channels = self.channels.max(1) as usize;
+        let mut sums = vec![0.0f64; channels];
+
+        let samples = self
+            .dense_samples()
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| {
+                if !sample.is_finite() {
+                    report.non_finite += 1;
+                    return 0.0;
+                }
+
+                report.peak = report.peak.max(sample.abs());
+                sums[i % channels] += sample as f64;
+
+                if sample.abs() > SANITIZE_LIMIT {
+                    report.clamped += 1;
+                }
+
+                sample.clamp(-SANITIZE_LIMIT, SANITIZE_LIMIT)
+            })
+            .collect::<Vec<_>>();
+
+        let frames = self.frame_count().max(1) as f64;
+        report.dc_offset =
+            sums.iter()
+                .map(|sum| (sum / frames) as f32)
+                .fold(
+                    0.0,
+                    |dc: f32, mean| if mean.abs() > dc.abs() { mean } else { dc },
+                );
+
+        let mut clip = Self::unanalyzed(self.channels, self.sample_rate, samples.into());
+
+        if !self.peaks.is_empty() {
+            clip = clip.analyze();
+        }
+
+        (clip, report)
+    }
+
+    /// Returns the loudest peak between two points of the clip, given from 0 to 1.
+    pub fn peak_between(&self, start: f32, end: f32) -> f32 {
+        let len = self.peaks.len();
+        if len == 0 {
+            return 0.0;
+        }
+
+        let first = ((start * len as f32) as usize).min(len - 1);
+        let last = ((end * len as f32).ceil() as usize).clamp(first + 1, len);
+
+        self.peaks[first..last]
+            .iter()
+            .fold(0.0, |peak, &sample| peak.max(sample))
     }
 
     pub fn frame_count(&self) -> u64 {
-        self.samples.len() as u64 / self.channels as u64
+        match self.sparse {
+            Some(ref sparse) => sparse.frames,
+            None => self.samples.len() as u64 / self.channels as u64,
+        }
     }
 
     pub fn duration(&self) -> f32 {
         self.frame_count() as f32 / self.sample_rate.0 as f32
     }
 
+    /// Returns the frames from the first to the last louder than `threshold` on any channel,
+    /// or nothing if the clip is that quiet throughout.
+    pub fn sounding_frames(&self, threshold: f32) -> Option<Range<u64>> {
+        let loud = |frame: u64| {
+            (0..self.channels).any(|channel| self.raw_sample(frame, channel).abs() > threshold)
+        };
+
+        let frames = self.frame_count();
+        let start = (0..frames).find(|&frame| loud(frame))?;
+        let end = (start..frames).rev().find(|&frame| loud(frame))?;
+
+        Some(start..end + 1)
+    }
+
     fn fade_factor(&self, index: u64) -> f32 {
         let frame_count = self.frame_count();
         let fade_samples = frame_count * self.channels as u64 / 10000;
@@ -38,14 +358,36 @@ impl Clip {
         }
     }
 
-    pub fn sample(&self, index: u64, channel: u16) -> f32 {
-        let sample = self
+    // the stored sample, without the fades at the ends
+    fn raw_sample(&self, index: u64, channel: u16) -> f32 {
+        let channels = self.channels as usize;
+
+        let Some(ref sparse) = self.sparse else {
+            return self
+                .samples
+                .get(index as usize * channels + channel as usize)
+                .copied()
+                .unwrap_or(0.0);
+        };
+
+        // the last segment starting at or before the frame, if the frame is inside it
+        let after = sparse
+            .segments
+            .partition_point(|segment| segment.start <= index);
+
+        let Some(segment) = after.checked_sub(1).map(|i| &sparse.segments[i]) else {
+            return 0.0;
+        };
+
+        segment
             .samples
-            .get(index as usize * self.channels as usize + channel as usize)
+            .get((index - segment.start) as usize * channels + channel as usize)
             .copied()
-            .unwrap_or(0.0);
+            .unwrap_or(0.0)
+    }
 
-        sample * self.fade_factor(index)
+    pub fn sample(&self, index: u64, channel: u16) -> f32 {
+        self.raw_sample(index, channel) * self.fade_factor(index)
     }
 
     /// Returns the average of all channels at the given index.
@@ -57,18 +399,209 @@ impl Clip {
         sum / self.channels as f32
     }
 
+    /// Returns true if both clips share the same samples.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.sparse, &other.sparse) {
+            (Some(sparse), Some(other)) => Arc::ptr_eq(sparse, other),
+            (None, None) => Arc::ptr_eq(&self.samples, &other.samples),
+            _ => false,
+        }
+    }
+
+    /// Returns the root mean square of every sample, 0 for an empty clip.
+    pub fn rms(&self) -> f32 {
+        let samples = self.dense_samples();
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f32 = samples.iter().map(|sample| sample * sample).sum();
+        (sum / samples.len() as f32).sqrt()
+    }
+
+    /// Creates a clip that starts `frames` frames earlier, or later when negative, wrapping
+    /// around its length.
+    pub fn shifted(&self, frames: i64) -> Self {
+        let frame_count = self.frame_count() as i64;
+        if frame_count == 0 {
+            return self.clone();
+        }
+
+        self.rotated((-frames).rem_euclid(frame_count) as u64)
+    }
+
     pub fn add(&self, other: &Self, volume: f32) -> Self {
         assert_eq!(self.channels, other.channels);
 
-        let mut samples = Vec::with_capacity(self.samples.len());
+        let (dense, other_dense) = (self.dense_samples(), other.dense_samples());
+        let mut samples = Vec::with_capacity(dense.len());
 
-        for (sample, other_sample) in self.samples.iter().zip(other.samples.iter()) {
+        for (sample, other_sample) in dense.iter().zip(other_dense.iter()) {
             samples.push(sample + other_sample * volume);
         }
 
         Self::new(self.channels, self.sample_rate, samples.into())
     }
 
+    /// Creates a clip that starts `frames` frames later, its end wrapping around to the start.
+    ///
+    /// The fades at the ends of this clip are kept, so the seam they meet at doesn't click.
+    pub fn rotated(&self, frames: u64) -> Self {
+        let frame_count = self.frame_count();
+        if frame_count == 0 {
+            return self.clone();
+        }
+
+        let offset = frames % frame_count;
+        let samples = (0..frame_count)
+            .flat_map(|frame| {
+                let index = (frame + frame_count - offset) % frame_count;
+                (0..self.channels).map(move |channel| self.sample(index, channel))
+            })
+            .collect::<Vec<_>>();
+
+        Self::new(self.channels, self.sample_rate, samples.into())
+    }
+
+    /// Returns a copy with the audio captured past the end of the loop crossfaded into the
+    /// head, so a note ringing over the wrap carries on into the start instead of being cut.
+    ///
+    /// The tail fades out over the head as the head fades in, over as many frames as the tail
+    /// holds but never more than the clip.
+    pub fn fold_tail(&self, tail: &[f32]) -> Self {
+        let channels = self.channels.max(1) as usize;
+        let frames = (tail.len() / channels).min(self.frame_count() as usize);
+
+        let mut samples = self.dense_samples().to_vec();
+        for frame in 0..frames {
+            let t = frame as f32 / frames as f32;
+
+            for channel in 0..channels {
+                let index = frame * channels + channel;
+                samples[index] = tail[index] * (1.0 - t) + samples[index] * t;
+            }
+        }
+
+        let clip = Self::unanalyzed(self.channels, self.sample_rate, samples.into());
+        if self.peaks.is_empty() {
+            clip
+        } else {
+            clip.analyze()
+        }
+    }
+
+    /// Returns a copy played backwards, frame by frame so the channels stay in place.
+    pub fn reversed(&self) -> Self {
+        let samples = self
+            .dense_samples()
+            .chunks(self.channels.max(1) as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+
+        Self::new(self.channels, self.sample_rate, samples.into())
+    }
+
+    /// Returns a copy through a one-pole low-pass, each sample moving `1 - coefficient` of the
+    /// way from the last output to the input.
+    ///
+    /// The filter runs over the loop twice, keeping only the second pass, so its start picks
+    /// up from the end instead of from silence and the seam doesn't click. Its gain never goes
+    /// above 1, so it can be applied over and over without building up. Samples that aren't
+    /// finite are taken as silence.
+    pub fn damped(&self, coefficient: f32) -> Self {
+        let coefficient = coefficient.clamp(0.0, 1.0);
+        let channels = self.channels.max(1) as usize;
+        let dense = self.dense_samples();
+
+        let mut state = vec![0.0f32; channels];
+        let mut samples = vec![0.0f32; dense.len()];
+
+        for _ in 0..2 {
+            for (index, (&sample, output)) in dense.iter().zip(samples.iter_mut()).enumerate() {
+                let sample = if sample.is_finite() { sample } else { 0.0 };
+                let last = &mut state[index % channels];
+
+                *last = sample + (*last - sample) * coefficient;
+                *output = *last;
+            }
+        }
+
+        Self::new(self.channels, self.sample_rate, samples.into())
+    }
+
+    /// Creates a mono clip out of one channel of this one.
+    pub fn channel(&self, channel: u16) -> Self {
+        let samples = self
+            .dense_samples()
+            .chunks(self.channels as usize)
+            .map(|frame| frame.get(channel as usize).copied().unwrap_or(0.0))
+            .collect::<Vec<_>>();
+
+        Self::new(1, self.sample_rate, samples.into())
+    }
+
+    /// Creates a clip with `channels` channels, mixing down or copying the mix to every channel.
+    pub fn with_channels(&self, channels: ChannelCount) -> Self {
+        if channels == self.channels {
+            return self.clone();
+        }
+
+        let samples = self
+            .dense_samples()
+            .chunks(self.channels as usize)
+            .flat_map(|frame| {
+                let average = frame.iter().sum::<f32>() / frame.len() as f32;
+                (0..channels).map(move |_| average)
+            })
+            .collect::<Vec<_>>();
+
+        Self::new(channels, self.sample_rate, samples.into())
+    }
+
+    /// Creates a clip `frames` long by repeating this one or cutting it off.
+    pub fn fit(&self, frames: u64) -> Self {
+        let frame_count = self.frame_count();
+        if frame_count == 0 {
+            return self.clone();
+        }
+
+        let channels = self.channels as usize;
+        let samples = (0..frames as usize * channels)
+            .map(|i| {
+                let frame = (i / channels) as u64 % frame_count;
+                self.raw_sample(frame, (i % channels) as u16)
+            })
+            .collect::<Vec<_>>();
+
+        Self::new(self.channels, self.sample_rate, samples.into())
+    }
+
+    /// Creates a clip `frames` long by padding this one with silence, or cutting it off with a
+    /// fade out over the last `fade` frames.
+    pub fn fit_padded(&self, frames: u64, fade: u64) -> Self {
+        let cut = self.frame_count() > frames;
+        let fade = fade.min(frames);
+
+        let channels = self.channels as usize;
+        let samples = (0..frames as usize * channels)
+            .map(|i| {
+                let frame = (i / channels) as u64;
+                let sample = self.raw_sample(frame, (i % channels) as u16);
+
+                let left = frames - frame;
+                if cut && left <= fade {
+                    sample * left as f32 / fade as f32
+                } else {
+                    sample
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self::new(self.channels, self.sample_rate, samples.into())
+    }
+
     /// Creates a new clip with the given sample rate.
     /// The new clip will be resampled using linear interpolation.
     pub fn resample(&self, sample_rate: SampleRate) -> Self {
@@ -91,3 +624,283 @@ impl Clip {
         Self::new(self.channels, sample_rate, samples.into())
     }
 }
+
+/// A loop the way the output callback recorded it, along with the input captured past its end
+/// when the tail is folded, which is done off the audio thread by [`RecordedLoop::folded`].
+#[derive(Clone, Debug)]
+pub struct RecordedLoop {
+    pub clip: Clip,
+    pub tail: Option<Arc<[f32]>>,
+}
+
+impl RecordedLoop {
+    pub fn new(clip: Clip) -> Self {
+        Self { clip, tail: None }
+    }
+
+    /// Returns the clip with the tail folded into its head, if there is one.
+    pub fn folded(&self) -> Clip {
+        match self.tail {
+            Some(ref tail) => self.clip.fold_tail(tail),
+            None => self.clip.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: SampleRate = SampleRate(48000);
+
+    fn mono(samples: Vec<f32>) -> Clip {
+        Clip::new(1, RATE, samples.into())
+    }
+
+    #[test]
+    fn sanitizing_a_clean_clip_changes_nothing() {
+        let samples = (0..4800)
+            .map(|i| (i as f32 * 0.01).sin() * 0.8)
+            .collect::<Vec<_>>();
+        let clip = mono(samples.clone());
+        let (sanitized, report) = clip.sanitized();
+
+        assert_eq!(&sanitized.dense_samples()[..], &samples[..]);
+        assert_eq!((report.non_finite, report.clamped), (0, 0));
+        assert!((report.peak - 0.8).abs() < 1e-3);
+        assert!(!report.is_unsafe() && !report.is_clipping());
+        assert!(report.problems().is_empty());
+    }
+
+    #[test]
+    fn sanitizing_silences_what_isnt_finite_and_clamps_the_rest() {
+        let clip = mono(vec![
+            0.5,
+            f32::NAN,
+            -0.5,
+            f32::INFINITY,
+            9.0,
+            f32::NEG_INFINITY,
+            -9.0,
+        ]);
+        let (sanitized, report) = clip.sanitized();
+
+        assert_eq!(
+            &sanitized.dense_samples()[..],
+            &[0.5, 0.0, -0.5, 0.0, SANITIZE_LIMIT, 0.0, -SANITIZE_LIMIT]
+        );
+        assert_eq!((report.non_finite, report.clamped), (3, 2));
+        assert_eq!(report.peak, 9.0);
+        assert!(report.is_unsafe() && report.is_clipping());
+        assert_eq!(report.problems().len(), 3);
+    }
+
+    #[test]
+    fn sanitizing_finds_a_dc_offset_in_any_channel() {
+        // the left channel is clean, the right sits a quarter above zero
+        let samples = (0..4800)
+            .flat_map(|i| {
+                let sine = (i as f32 / 100.0 * std::f32::consts::TAU).sin() * 0.5;
+                [sine, sine + 0.25]
+            })
+            .collect::<Vec<_>>();
+
+        let (sanitized, report) = Clip::new(2, RATE, samples.clone().into()).sanitized();
+        assert_eq!(&sanitized.dense_samples()[..], &samples[..]);
+        assert!((report.dc_offset - 0.25).abs() < 0.01);
+        assert!(report.has_dc_offset() && report.is_unsafe());
+        assert_eq!(report.problems().len(), 1);
+    }
+
+    #[test]
+    fn fold_tail_carries_a_ringing_note_over_the_wrap() {
+        // a note struck late in the loop and still ringing when it wraps
+        let (frames, tail_frames, strike) = (4800, 480, 3600);
+        let note = |frame: usize| {
+            let t = frame.saturating_sub(strike) as f32 / RATE.0 as f32;
+            let sample = (t * 440.0 * std::f32::consts::TAU).sin() * (-t * 10.0).exp();
+            if frame < strike {
+                0.0
+            } else {
+                sample
+            }
+        };
+
+        let clip = mono((0..frames).map(note).collect());
+        let tail = (frames..frames + tail_frames).map(note).collect::<Vec<_>>();
+        let folded = clip.fold_tail(&tail);
+        assert_eq!(folded.frame_count(), clip.frame_count());
+
+        // the head picks up where the end left off, no further than a step of the note
+        let step = 440.0 * std::f32::consts::TAU / RATE.0 as f32;
+        let seam = folded.sample(0, 0) - clip.sample(frames as u64 - 1, 0);
+        assert!(seam.abs() <= step, "seam of {}", seam);
+
+        // the ringing fades out over the head, and the rest of the loop is left alone
+        let head = (0..tail_frames as u64).map(|frame| folded.sample(frame, 0).abs());
+        assert!(head.clone().take(48).any(|sample| sample > 0.1));
+        assert!(head.skip(tail_frames - 5).all(|sample| sample < 0.01));
+
+        for frame in tail_frames as u64..frames as u64 {
+            assert_eq!(folded.sample(frame, 0), clip.sample(frame, 0));
+        }
+    }
+
+    // a noisy sine, for filters to take the noise out of
+    fn noisy_sine(frames: usize) -> Clip {
+        let mut seed = 1u32;
+        let samples = (0..frames)
+            .map(|frame| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+                let t = frame as f32 / RATE.0 as f32;
+                (t * 110.0 * std::f32::consts::TAU).sin() * 0.5 + noise * 0.5
+            })
+            .collect();
+
+        mono(samples)
+    }
+
+    // the energy of the difference between neighbouring frames, which is mostly the highs
+    fn high_energy(clip: &Clip) -> f32 {
+        let samples = clip.dense_samples();
+        samples.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum()
+    }
+
+    #[test]
+    fn damping_takes_more_highs_every_generation() {
+        let mut clip = noisy_sine(RATE.0 as usize);
+        let mut energy = high_energy(&clip);
+
+        for generation in 1..=8 {
+            clip = clip.damped(0.5);
+
+            let damped = high_energy(&clip);
+            assert!(damped < energy, "generation {}", generation);
+            energy = damped;
+        }
+    }
+
+    #[test]
+    fn damping_is_stable_over_many_generations() {
+        let mean = |clip: &Clip| {
+            let samples = clip.dense_samples();
+            samples.iter().sum::<f32>() / samples.len() as f32
+        };
+        let peak = |clip: &Clip| {
+            let samples = clip.dense_samples();
+            samples
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+        };
+
+        // a noisy sine sitting on an offset, with a sample that isn't finite
+        let mut samples = noisy_sine(RATE.0 as usize / 4).dense_samples().to_vec();
+        samples.iter_mut().for_each(|sample| *sample += 0.2);
+        samples[100] = f32::NAN;
+
+        let mut clip = mono(samples).damped(0.9);
+        let offset = mean(&clip);
+        assert!((offset - 0.2).abs() < 0.01);
+
+        for generation in 0..200 {
+            let last = peak(&clip);
+            clip = clip.damped(0.9);
+
+            // the offset passes through as it is, and nothing builds up
+            assert!(
+                (mean(&clip) - offset).abs() < 1e-3,
+                "generation {}",
+                generation
+            );
+            assert!(peak(&clip) <= last, "generation {}", generation);
+        }
+
+        assert!(clip.dense_samples().iter().all(|sample| sample.is_finite()));
+    }
+
+    #[test]
+    fn compacting_keeps_every_sample_and_saves_memory() {
+        // two stereo notes a second apart, with a short gap inside the first
+        let mut samples = vec![0.0; 4 * RATE.0 as usize];
+        let note = |frame: usize| (frame as f32 * 0.01).sin() * 0.5;
+        for frame in (1000..3000).chain(3100..5000).chain(60000..62000) {
+            samples[frame * 2] = note(frame);
+            samples[frame * 2 + 1] = -note(frame);
+        }
+
+        let clip = Clip::new(2, RATE, samples.into());
+        let compacted = clip.compacted(0.0);
+        assert!(compacted.is_compacted());
+
+        // the short gap stays in the first segment
+        let sparse = compacted.sparse.as_ref().unwrap();
+        let starts = sparse.segments.iter().map(|segment| segment.start);
+        assert_eq!(starts.collect::<Vec<_>>(), [1000, 60000]);
+
+        let bits = |clip: &Clip| {
+            let samples = clip.dense_samples();
+            samples
+                .iter()
+                .map(|sample| sample.to_bits())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bits(&compacted), bits(&clip));
+        assert_eq!(compacted.frame_count(), clip.frame_count());
+        assert_eq!(compacted.sample(61000, 1), clip.sample(61000, 1));
+
+        // the silence is left out, only the notes stored
+        assert!(compacted.memory_bytes() < clip.memory_bytes() / 8);
+    }
+
+    #[test]
+    fn compacting_a_clip_without_silence_keeps_it() {
+        let clip = noisy_sine(RATE.0 as usize);
+        let compacted = clip.compacted(0.0);
+
+        assert!(!compacted.is_compacted());
+        assert_eq!(compacted.memory_bytes(), clip.memory_bytes());
+    }
+
+    #[test]
+    fn rotating_wraps_the_end_around_to_the_start() {
+        let clip = mono((0..5).map(|i| i as f32).collect());
+
+        assert_eq!(
+            &clip.rotated(2).dense_samples()[..],
+            &[3.0, 4.0, 0.0, 1.0, 2.0]
+        );
+
+        // whole loops further on land where they started
+        assert_eq!(
+            &clip.rotated(0).dense_samples()[..],
+            &clip.dense_samples()[..]
+        );
+        assert_eq!(
+            &clip.rotated(5).dense_samples()[..],
+            &clip.dense_samples()[..]
+        );
+        assert_eq!(
+            &clip.rotated(12).dense_samples()[..],
+            &clip.rotated(2).dense_samples()[..]
+        );
+
+        // rotations add up
+        assert_eq!(
+            &clip.rotated(3).rotated(4).dense_samples()[..],
+            &clip.rotated(7).dense_samples()[..]
+        );
+
+        assert_eq!(mono(Vec::new()).rotated(3).frame_count(), 0);
+    }
+
+    #[test]
+    fn rotating_keeps_the_channels_of_a_frame_together() {
+        let clip = Clip::new(2, RATE, vec![0.0, 10.0, 1.0, 11.0, 2.0, 12.0].into());
+
+        assert_eq!(
+            &clip.rotated(1).dense_samples()[..],
+            &[2.0, 12.0, 0.0, 10.0, 1.0, 11.0]
+        );
+    }
+}
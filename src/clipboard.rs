@@ -0,0 +1,69 @@
+use crate::{
+    app::{App, EditMode},
+    wav,
+};
+
+impl App {
+    // copies the clip of a track into the clipboard
+    pub fn yank_track(&mut self, index: usize) {
+        self.edit_mode = EditMode::None;
+
+        match self.audio.tracks[index].clip {
+            Some(ref clip) => {
+                self.clipboard = Some(clip.clone());
+                self.info(format!("yanked track {}", (index + 1) % 10));
+            }
+            None => self.warn(format!("track {} is empty", (index + 1) % 10)),
+        }
+    }
+
+    // replaces the clip of a track with the clipboard, asking for confirmation if it has one
+    pub fn paste_track(&mut self, index: usize) {
+        let pending = match self.edit_mode {
+            EditMode::Paste(pending) => pending,
+            _ => None,
+        };
+
+        let Some(mut clip) = self.clipboard.clone() else {
+            self.edit_mode = EditMode::None;
+            self.warn("the clipboard is empty");
+            return;
+        };
+
+        if self.audio.tracks[index].clip.is_some() && pending != Some(index) {
+            self.edit_mode = EditMode::Paste(Some(index));
+            return;
+        }
+
+        // the clip might come from a session with another sample rate
+        if let Some(sample_rate) = self.audio.settings.get_sample_rate() {
+            if clip.sample_rate != sample_rate {
+                clip = clip.resample(sample_rate);
+            }
+        }
+
+        self.stop_preview();
+        let track = &mut self.audio.tracks[index];
+        track.clip = Some(clip);
+        track.color.get_or_insert(index);
+        self.audio.update_tracks();
+        self.forget_layers(index);
+
+        self.edit_mode = EditMode::None;
+        self.info(format!("pasted into track {}", (index + 1) % 10));
+    }
+
+    pub fn export_clipboard(&mut self) {
+        let Some(ref clip) = self.clipboard else {
+            self.warn("the clipboard is empty");
+            return;
+        };
+
+        let path = wav::unique_path("clipboard", "wav");
+
+        match wav::save_clip(&path, clip) {
+            Ok(()) => self.info(format!("exported clipboard to {}", path.display())),
+            Err(err) => self.error(format!("failed to export clipboard: {}", err)),
+        }
+    }
+}
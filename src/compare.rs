@@ -0,0 +1,172 @@
+use crate::{app::App, clip::Clip, track::ClipFade};
+
+// how long flipping between the two versions of a track crossfades, in seconds
+const COMPARE_FADE: f32 = 0.02;
+
+fn same_clip(a: &Option<Clip>, b: &Option<Clip>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.ptr_eq(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// A track flipping between its clip and the one it had before its last layer.
+pub struct Compare {
+    pub track: usize,
+    /// The version that isn't heard right now.
+    pub other: Option<Clip>,
+    /// Whether the version before the last layer is the one heard, "B".
+    pub previous: bool,
+    // the version that is heard, to tell when the track was changed some other way
+    shown: Option<Clip>,
+}
+
+impl Compare {
+    pub fn label(&self) -> &'static str {
+        if self.previous {
+            "B"
+        } else {
+            "A"
+        }
+    }
+}
+
+impl App {
+    // flips the selected track between its last layer and the clip before it, the switch
+    // landing on the next beat like every other change
+    pub fn toggle_compare(&mut self) {
+        self.drop_stale_compare();
+
+        if self.compare.is_none() {
+            self.start_compare(self.selected_track);
+            return;
+        }
+
+        let Some(ref mut compare) = self.compare else {
+            return;
+        };
+
+        let index = compare.track;
+        let track = &mut self.audio.tracks[index];
+
+        std::mem::swap(&mut track.clip, &mut compare.other);
+        compare.previous = !compare.previous;
+        compare.shown = track.clip.clone();
+
+        let from = compare.other.clone();
+        let message = if compare.previous {
+            format!("track {}: B, without the last layer", (index + 1) % 10)
+        } else {
+            format!("track {}: A, with the last layer", (index + 1) % 10)
+        };
+
+        self.fade_clip(index, from);
+        self.info(message);
+    }
+
+    fn start_compare(&mut self, index: usize) {
+        let Some(layer) = self.layers.iter().rposition(|layer| layer.track == index) else {
+            self.warn(format!(
+                "track {} has no layer to compare against",
+                (index + 1) % 10
+            ));
+            return;
+        };
+
+        let previous = self.layers[layer].clip.clone();
+        let track = &mut self.audio.tracks[index];
+        let current = std::mem::replace(&mut track.clip, previous.clone());
+
+        self.compare = Some(Compare {
+            track: index,
+            other: current.clone(),
+            previous: true,
+            shown: previous,
+        });
+
+        self.fade_clip(index, current);
+        self.info(format!(
+            "track {}: B, without the last layer",
+            (index + 1) % 10
+        ));
+    }
+
+    // keeps the version of the compared track that is heard, throwing the other one away
+    pub fn keep_compared(&mut self) {
+        self.drop_stale_compare();
+
+        let Some(compare) = self.compare.take() else {
+            self.warn("not comparing any track");
+            return;
+        };
+
+        // either way the layer is settled, so there's nothing left to undo
+        if let Some(layer) = self
+            .layers
+            .iter()
+            .rposition(|layer| layer.track == compare.track)
+        {
+            self.layers.remove(layer);
+        }
+
+        let number = (compare.track + 1) % 10;
+        if compare.previous {
+            self.info(format!("dropped the last layer of track {}", number));
+        } else {
+            self.info(format!("kept the last layer of track {}", number));
+        }
+    }
+
+    // stops comparing a track whose layers are touched some other way
+    pub fn end_compare(&mut self, index: usize) {
+        if matches!(self.compare, Some(ref compare) if compare.track == index) {
+            self.compare = None;
+        }
+    }
+
+    // forgets the comparison if the track's clip was changed behind its back
+    fn drop_stale_compare(&mut self) {
+        let Some(ref compare) = self.compare else {
+            return;
+        };
+
+        if !same_clip(&self.audio.tracks[compare.track].clip, &compare.shown) {
+            self.compare = None;
+        }
+    }
+
+    // crossfades a track from `from` to its clip once the output callback picks it up
+    fn fade_clip(&mut self, index: usize, from: Option<Clip>) {
+        let sample_rate = self.audio.engine.sample_rate() as f32;
+
+        self.audio.tracks[index].fade = Some(ClipFade {
+            from,
+            start: None,
+            frames: (sample_rate * COMPARE_FADE) as u64,
+        });
+
+        self.audio.update_tracks();
+    }
+
+    // drops the fades the output callback has picked up, so they aren't played again with
+    // the next tracks sent
+    pub fn finish_fades(&mut self) {
+        for index in 0..self.audio.tracks.len() {
+            if !self.audio.is_track_pending(index) {
+                self.audio.tracks[index].fade = None;
+            }
+        }
+    }
+
+    // the label of a track being compared, if it is
+    pub fn compare_label(&self, index: usize) -> Option<&'static str> {
+        let compare = self
+            .compare
+            .as_ref()
+            .filter(|compare| compare.track == index)?;
+        let track = self.audio.tracks.get(index)?;
+
+        same_clip(&track.clip, &compare.shown).then(|| compare.label())
+    }
+}
@@ -0,0 +1,73 @@
+use std::{env, path::PathBuf};
+
+/// Returns the directory wroom stores its configuration files in.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("wroom"));
+    }
+
+    #[cfg(target_os = "windows")]
+    if let Some(dir) = env::var_os("APPDATA") {
+        return Some(PathBuf::from(dir).join("wroom"));
+    }
+
+    let home = env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".config").join("wroom"))
+}
+
+/// Returns the path of a file in the config directory.
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    Some(config_dir()?.join(name))
+}
+
+// removes a `#` comment that isn't inside a string
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+// parses either a single string or an array of strings
+pub fn parse_value(value: &str) -> Option<Vec<String>> {
+    let (inner, is_array) = match value.strip_prefix('[') {
+        Some(inner) => (inner.strip_suffix(']')?, true),
+        None => (value, false),
+    };
+
+    let mut keys = Vec::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut key = String::new();
+
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        c => key.push(c),
+                    }
+                }
+
+                keys.push(key);
+            }
+            ',' if is_array => {}
+            c if c.is_whitespace() => {}
+            _ => return None,
+        }
+    }
+
+    if keys.is_empty() || (!is_array && keys.len() > 1) {
+        return None;
+    }
+
+    Some(keys)
+}
@@ -0,0 +1,69 @@
+use crate::app::{App, EditMode};
+
+/// A track armed to record a number of loops in a row, each overdubbed onto the last.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CountOut {
+    pub track: usize,
+    pub loops: u32,
+    /// How many of the loops have been committed so far.
+    pub recorded: u32,
+}
+
+impl App {
+    // arms a track to record `loops` loops from the next one on, each committed as it ends
+    pub fn arm_loops(&mut self, index: usize, loops: u32) {
+        self.edit_mode = EditMode::None;
+
+        let engine = &self.audio.engine;
+
+        // every pass starts with the loop, a punch in would only line up the first
+        engine.set_armed_recording(false);
+        engine.set_punch_in(false);
+        engine.set_armed_track(Some(index));
+        engine.set_armed_loops(loops.saturating_sub(1) as u64);
+
+        self.count_out = Some(CountOut {
+            track: index,
+            loops,
+            recorded: 0,
+        });
+
+        self.info(format!(
+            "armed track {} for {} loops, recording starts with the next loop",
+            (index + 1) % 10,
+            loops
+        ));
+    }
+
+    // counts a loop recorded while counting out, called once it's committed
+    pub fn count_recorded_loop(&mut self) {
+        let Some(ref mut count) = self.count_out else {
+            return;
+        };
+
+        count.recorded += 1;
+
+        if count.recorded >= count.loops {
+            let count = *count;
+            self.count_out = None;
+            self.audio.engine.request_chime();
+            self.info(format!(
+                "done, recorded {} loops into track {}",
+                count.loops,
+                (count.track + 1) % 10
+            ));
+        }
+    }
+
+    // stops counting out, the loops committed so far stay
+    pub fn cancel_count_out(&mut self) {
+        self.audio.engine.set_armed_loops(0);
+
+        if let Some(count) = self.count_out.take() {
+            self.info(format!(
+                "stopped after {} of {} loops, kept what was recorded",
+                count.recorded, count.loops
+            ));
+        }
+    }
+}
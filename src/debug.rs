@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::{App, FRAME_RATES};
+
+// how often the measured frame rate is updated
+const MEASURE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts drawn frames to measure the actual frame rate.
+pub struct FrameCounter {
+    frames: u32,
+    since: Instant,
+    fps: f32,
+}
+
+impl FrameCounter {
+    pub fn new() -> Self {
+        Self {
+            frames: 0,
+            since: Instant::now(),
+            fps: 0.0,
+        }
+    }
+
+    /// Counts a frame, returns true when a new measurement was made.
+    pub fn tick(&mut self) -> bool {
+        self.frames += 1;
+
+        let elapsed = self.since.elapsed();
+        if elapsed < MEASURE_INTERVAL {
+            return false;
+        }
+
+        self.fps = self.frames as f32 / elapsed.as_secs_f32();
+        self.frames = 0;
+        self.since = Instant::now();
+
+        true
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+impl App {
+    pub fn toggle_debug(&mut self) {
+        self.show_debug = !self.show_debug;
+    }
+
+    // counts a drawn frame, and takes the callback timings whenever the frame rate is measured
+    pub fn count_frame(&mut self) {
+        if self.frame_counter.tick() {
+            self.callback_stats = self.audio.engine.take_callback_stats();
+        }
+    }
+
+    // draws the measured frame rate and callback timings in the top right corner
+    pub fn render_debug<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if !self.show_debug {
+            return;
+        }
+
+        let mut text = format!(
+            " {:.1} fps / {} ",
+            self.frame_counter.fps(),
+            FRAME_RATES[self.frame_rate_index]
+        );
+
+        if let Some(stats) = self.callback_stats {
+            text.push_str(&format!(
+                "| callback {}/{}/{} µs ",
+                stats.min.as_micros(),
+                stats.average.as_micros(),
+                stats.max.as_micros()
+            ));
+        }
+
+        let size = frame.size();
+        let width = (text.chars().count() as u16).min(size.width);
+        let area = Rect::new(size.x + size.width - width, size.y, width, 1);
+
+        let paragraph = Paragraph::new(text)
+            .style(self.theme.highlight)
+            .alignment(Alignment::Right);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
@@ -0,0 +1,66 @@
+// the longest delay in beats, two bars of four beats
+pub const MAX_DELAY_BEATS: u64 = 8;
+
+// the slowest tempo the buffer fits the longest delay at, slower tempos shorten the delay
+const MIN_DELAY_BPM: u64 = 60;
+
+/// An echo shared by all tracks, fed by the sends of the tracks.
+///
+/// The buffer is interleaved like the output, so every channel echoes onto itself.
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+    // whether the buffer holds anything, so it's only cleared once when the sends stop
+    dirty: bool,
+}
+
+impl DelayLine {
+    /// Allocates a delay line long enough for [`MAX_DELAY_BEATS`] at any usual tempo.
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let seconds = MAX_DELAY_BEATS * 60 / MIN_DELAY_BPM;
+        let len = seconds as usize * sample_rate as usize * channels as usize;
+
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            position: 0,
+            dirty: false,
+        }
+    }
+
+    /// Returns how many interleaved samples `beats` beats are, limited to the buffer.
+    pub fn delay_samples(&self, beats: u64, bpm: u64, sample_rate: u32, channels: u16) -> usize {
+        if bpm == 0 {
+            return 0;
+        }
+
+        let frames = beats * 60 * sample_rate as u64 / bpm;
+        let samples = frames as usize * channels as usize;
+
+        samples.min(self.buffer.len() - self.buffer.len() % channels as usize)
+    }
+
+    /// Feeds one sample into the line and returns the echo to mix with the output.
+    pub fn process(&mut self, input: f32, delay: usize, feedback: f32) -> f32 {
+        if delay == 0 {
+            return 0.0;
+        }
+
+        let len = self.buffer.len();
+        let read = (self.position + len - delay) % len;
+        let echo = self.buffer[read];
+
+        self.buffer[self.position] = input + echo * feedback;
+        self.position = (self.position + 1) % len;
+        self.dirty = true;
+
+        echo
+    }
+
+    /// Silences the line, only touching the buffer if anything was written since.
+    pub fn clear(&mut self) {
+        if self.dirty {
+            self.buffer.fill(0.0);
+            self.dirty = false;
+        }
+    }
+}
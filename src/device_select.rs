@@ -1,104 +1,157 @@
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
     widgets::{Block, Borders, List, ListItem},
     Frame,
 };
 
-use crate::app::{App, EditMode};
+use crate::{
+    app::{settings_column_width, App, EditMode},
+    keymap::Action,
+};
 
 impl App {
     pub fn render_device_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let width = settings_column_width(area);
+
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Length(20),
-                Constraint::Length(20),
-                Constraint::Length(20),
+                Constraint::Length(width),
+                Constraint::Length(width),
+                Constraint::Length(width),
+                Constraint::Length(width),
             ])
             .split(area);
 
+        // the second input shares the column of the first, it's rarely more than none
+        let inputs = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(chunks[1]);
+
         self.render_host_select(frame, chunks[0]);
-        self.render_input_device_select(frame, chunks[1]);
+        self.render_input_device_select(frame, inputs[0]);
+        self.render_secondary_input_device_select(frame, inputs[1]);
         self.render_output_device_select(frame, chunks[2]);
+        self.render_midi_port_select(frame, chunks[3]);
     }
 
     pub fn render_host_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let host_names = self
-            .audio
-            .settings
-            .host_names()
-            .into_iter()
-            .map(|name| ListItem::new(name));
+        let settings = self.shown_settings();
+        let host_names = settings.host_names().into_iter().map(ListItem::new);
 
-        self.settings
-            .host_state
-            .select(Some(self.audio.settings.host_index()));
+        let selected = settings.host_index();
+        self.settings.host_state.select(Some(selected));
 
-        let mut block = Block::default().title("Host 'h'").borders(Borders::ALL);
+        let staged = self.is_staged(|s| s.host_id());
+        let mut block = Block::default()
+            .title(self.staged_title("Host", Action::EditHost, staged))
+            .borders(Borders::ALL);
 
         if self.edit_mode == EditMode::Host {
-            block = block.border_style(Style::default().fg(Color::Red));
+            block = block.border_style(self.theme.active);
         }
 
         let list = List::new(host_names.collect::<Vec<_>>())
             .block(block)
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            .highlight_style(self.theme.highlight);
 
         frame.render_stateful_widget(list, area, &mut self.settings.host_state);
     }
 
     pub fn render_input_device_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let input_device_names = self
-            .audio
-            .settings
-            .input_device_names()
-            .into_iter()
-            .map(|name| ListItem::new(name));
+        let settings = self.shown_settings();
+        let input_device_names = settings.input_device_names().into_iter().map(ListItem::new);
 
-        self.settings
-            .input_device_state
-            .select(self.audio.settings.input_device);
+        let selected = settings.input_device_index();
+        self.settings.input_device_state.select(selected);
 
+        let staged = self.is_staged(|s| s.input_device_name());
         let mut block = Block::default()
-            .title("Input Device 'i'")
+            .title(self.staged_title("Input Device", Action::EditInputDevice, staged))
             .borders(Borders::ALL);
 
         if self.edit_mode == EditMode::InputDevice {
-            block = block.border_style(Style::default().fg(Color::Red));
+            block = block.border_style(self.theme.active);
         }
 
         let list = List::new(input_device_names.collect::<Vec<_>>())
             .block(block)
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            .highlight_style(self.theme.highlight);
 
         frame.render_stateful_widget(list, area, &mut self.settings.input_device_state);
     }
 
+    pub fn render_secondary_input_device_select<B: Backend>(
+        &mut self,
+        frame: &mut Frame<B>,
+        area: Rect,
+    ) {
+        let settings = self.shown_settings();
+        let primary = settings.input_device_index();
+
+        // the first device is listed too, so the positions line up, but can't be chosen
+        let names = settings
+            .input_device_names()
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let item = ListItem::new(name);
+
+                if Some(index) == primary {
+                    item.style(self.theme.empty)
+                } else {
+                    item
+                }
+            });
+        let items = std::iter::once(ListItem::new("none").style(self.theme.empty))
+            .chain(names)
+            .collect::<Vec<_>>();
+
+        let selected = settings.secondary_input_device_index().map_or(0, |i| i + 1);
+        self.settings
+            .secondary_input_device_state
+            .select(Some(selected));
+
+        let staged = self.is_staged(|s| s.secondary_input_device_name());
+        let mut block = Block::default()
+            .title(self.staged_title("Second Input", Action::EditSecondaryInputDevice, staged))
+            .borders(Borders::ALL);
+
+        if self.edit_mode == EditMode::SecondaryInputDevice {
+            block = block.border_style(self.theme.active);
+        }
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(list, area, &mut self.settings.secondary_input_device_state);
+    }
+
     pub fn render_output_device_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let output_device_names = self
-            .audio
-            .settings
+        let settings = self.shown_settings();
+        let output_device_names = settings
             .output_device_names()
             .into_iter()
-            .map(|name| ListItem::new(name));
+            .map(ListItem::new);
 
-        self.settings
-            .output_device_state
-            .select(self.audio.settings.output_device);
+        let selected = settings.output_device_index();
+        self.settings.output_device_state.select(selected);
 
+        let staged = self.is_staged(|s| s.output_device_name());
         let mut block = Block::default()
-            .title("Output Device 'o'")
+            .title(self.staged_title("Output Device", Action::EditOutputDevice, staged))
             .borders(Borders::ALL);
 
         if self.edit_mode == EditMode::OutputDevice {
-            block = block.border_style(Style::default().fg(Color::Red));
+            block = block.border_style(self.theme.active);
         }
 
         let list = List::new(output_device_names.collect::<Vec<_>>())
             .block(block)
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            .highlight_style(self.theme.highlight);
 
         frame.render_stateful_widget(list, area, &mut self.settings.output_device_state);
     }
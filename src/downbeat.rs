@@ -0,0 +1,45 @@
+use crate::app::App;
+
+impl App {
+    // moves the downbeat `beats` beats later into the loop, or earlier when negative, by turning
+    // every clip so what played there starts the loop
+    pub fn shift_downbeat(&mut self, beats: i64) {
+        if self.audio.tracks.iter().all(|track| track.clip.is_none()) {
+            self.warn("there's no loop to move the downbeat of");
+            return;
+        }
+
+        let frames = self.audio.engine.beats_frames(beats.unsigned_abs()) as i64 * beats.signum();
+        if frames == 0 {
+            return;
+        }
+
+        self.audio.tracks = self.audio.tracks.shifted(frames);
+
+        // undoing a layer would otherwise bring back a clip on the old downbeat
+        for layer in self.layers.iter_mut() {
+            if let Some(ref mut clip) = layer.clip {
+                *clip = clip.shifted(frames);
+            }
+        }
+
+        self.audio.update_tracks();
+
+        let direction = if beats > 0 { "later" } else { "earlier" };
+        self.info(format!(
+            "moved the downbeat {} beat{} {}",
+            beats.abs(),
+            if beats.abs() == 1 { "" } else { "s" },
+            direction
+        ));
+    }
+
+    pub fn rotate_downbeat(&mut self, offset: i32) {
+        let beats_per_bar = self.audio.engine.beats_per_bar() as i64;
+        self.shift_downbeat(-offset as i64 * beats_per_bar);
+    }
+
+    pub fn rotate_downbeat_fine(&mut self, offset: i32) {
+        self.shift_downbeat(-offset as i64);
+    }
+}
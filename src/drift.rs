@@ -0,0 +1,218 @@
+// how long the fill level is averaged over at a time, in seconds
+const DRIFT_WINDOW: u64 = 5;
+
+// how close in parts per million the estimate has to be before it's used. The input arrives
+// a buffer at a time, so the fill level only shows the drift to within a buffer
+const DRIFT_RESOLUTION: f64 = 20.0;
+
+// the largest drift taken as real in parts per million, anything further off comes from
+// dropouts rather than clocks
+const MAX_DRIFT_PPM: f64 = 1000.0;
+
+/// Follows the fill level of the input buffer to estimate the drift between the clocks.
+pub struct DriftMeter {
+    window_frames: u64,
+    // the frames played and the sum of the fill levels seen in the current window
+    frames: u64,
+    fill_sum: f64,
+    callbacks: u64,
+    // the longest buffer played, which bounds how precise a measurement can be
+    buffer_frames: usize,
+    // the average fill level of the window the measurement started from, and of the last one
+    reference: Option<f64>,
+    last_fill: f64,
+    // the frames played, and dropped minus repeated, since the reference window
+    elapsed: u64,
+    corrected: i64,
+    window_corrected: i64,
+    ppm: Option<f64>,
+    // how many frames are owed to the drift, one being made up whenever it reaches a whole
+    debt: f64,
+}
+
+impl DriftMeter {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            window_frames: sample_rate as u64 * DRIFT_WINDOW,
+            frames: 0,
+            fill_sum: 0.0,
+            callbacks: 0,
+            buffer_frames: 0,
+            reference: None,
+            last_fill: 0.0,
+            elapsed: 0,
+            corrected: 0,
+            window_corrected: 0,
+            ppm: None,
+            debt: 0.0,
+        }
+    }
+
+    /// Returns the estimated drift in parts per million, positive when the input runs fast,
+    /// or `None` until it was measured for long enough.
+    pub fn ppm(&self) -> Option<f64> {
+        self.ppm
+    }
+
+    /// Notes the fill level of the input buffer in frames at the start of a callback playing
+    /// `frames` frames.
+    pub fn observe(&mut self, fill: f64, frames: usize) {
+        self.fill_sum += fill;
+        self.callbacks += 1;
+        self.frames += frames as u64;
+        self.buffer_frames = self.buffer_frames.max(frames);
+
+        if self.frames < self.window_frames {
+            return;
+        }
+
+        let average = self.fill_sum / self.callbacks as f64;
+        self.finish_window(average);
+
+        self.frames = 0;
+        self.fill_sum = 0.0;
+        self.callbacks = 0;
+        self.window_corrected = 0;
+    }
+
+    fn finish_window(&mut self, average: f64) {
+        let Some(reference) = self.reference else {
+            self.restart(average);
+            return;
+        };
+
+        // without the corrections the buffer would have moved by that much more
+        let jump = average - self.last_fill + self.window_corrected as f64;
+        let largest =
+            2.0 * self.buffer_frames as f64 + self.frames as f64 * MAX_DRIFT_PPM / 1_000_000.0;
+
+        // a dropout moves the buffer at once, the measurement starts over without it
+        if jump.abs() > largest {
+            self.restart(average);
+            return;
+        }
+
+        self.last_fill = average;
+        self.elapsed += self.frames;
+        self.corrected += self.window_corrected;
+
+        let resolution = self.buffer_frames as f64 / self.elapsed as f64 * 1_000_000.0;
+        if resolution > DRIFT_RESOLUTION {
+            return;
+        }
+
+        let drift = average - reference + self.corrected as f64;
+        let ppm = drift / self.elapsed as f64 * 1_000_000.0;
+        self.ppm = Some(ppm.clamp(-MAX_DRIFT_PPM, MAX_DRIFT_PPM));
+    }
+
+    // measures from this window on, keeping the last estimate until there's a new one
+    fn restart(&mut self, average: f64) {
+        self.reference = Some(average);
+        self.last_fill = average;
+        self.elapsed = 0;
+        self.corrected = 0;
+    }
+
+    /// Advances a frame, returning 1 when a frame should be dropped to make up for the drift,
+    /// -1 when one should be repeated and 0 otherwise.
+    pub fn step(&mut self) -> i64 {
+        let Some(ppm) = self.ppm else {
+            return 0;
+        };
+
+        self.debt += ppm / 1_000_000.0;
+
+        if self.debt >= 1.0 {
+            self.debt -= 1.0;
+            1
+        } else if self.debt <= -1.0 {
+            self.debt += 1.0;
+            -1
+        } else {
+            0
+        }
+    }
+
+    /// Notes frames dropped, or repeated when negative, so they aren't taken for drift.
+    pub fn corrected(&mut self, frames: i64) {
+        self.window_corrected += frames;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+    const BUFFER: usize = 256;
+    const START_FILL: f64 = 16384.0;
+
+    // plays `seconds` of output from an input running `ppm` fast, both in buffers of the same
+    // size, making up for the drift the way the output callback does. Returns the estimate
+    // and the fill level at every callback
+    fn run(ppm: f64, seconds: u32) -> (Option<f64>, Vec<f64>) {
+        let mut meter = DriftMeter::new(RATE);
+        let mut fill = START_FILL;
+        let mut arriving = 0.0;
+        let mut fills = Vec::new();
+
+        for _ in 0..RATE as usize * seconds as usize / BUFFER {
+            // the input arrives a whole buffer at a time, as the clock of its device goes
+            arriving += BUFFER as f64 * (1.0 + ppm / 1_000_000.0);
+            while arriving >= BUFFER as f64 {
+                fill += BUFFER as f64;
+                arriving -= BUFFER as f64;
+            }
+
+            meter.observe(fill, BUFFER);
+
+            for _ in 0..BUFFER {
+                let correction = meter.step();
+                fill -= (1 + correction) as f64;
+                meter.corrected(correction);
+            }
+
+            fills.push(fill);
+        }
+
+        (meter.ppm(), fills)
+    }
+
+    fn spread(fills: &[f64]) -> f64 {
+        let max = fills.iter().copied().fold(f64::MIN, f64::max);
+        let min = fills.iter().copied().fold(f64::MAX, f64::min);
+        max - min
+    }
+
+    #[test]
+    fn matched_clocks_have_no_drift() {
+        let (ppm, fills) = run(0.0, 300);
+
+        assert!(ppm.unwrap().abs() < 1.0);
+        assert!(spread(&fills) <= 2.0 * BUFFER as f64);
+    }
+
+    #[test]
+    fn mismatched_clocks_keep_the_fill_bounded() {
+        for drift in [-300.0, 150.0, 500.0] {
+            let (ppm, fills) = run(drift, 900);
+
+            let ppm = ppm.unwrap();
+            assert!((ppm - drift).abs() < 5.0, "{} measured as {}", drift, ppm);
+
+            // it moves until the drift is measured, which takes a few minutes with a buffer
+            // this long, then stays put for the rest
+            let measuring = drift.abs() / 1_000_000.0 * RATE as f64 * 300.0;
+            let moved = fills.iter().map(|fill| (fill - START_FILL).abs());
+            assert!(moved.fold(0.0, f64::max) < measuring + 2.0 * BUFFER as f64);
+
+            let settled = fills.len() / 2;
+            assert!(
+                spread(&fills[settled..]) <= 3.0 * BUFFER as f64,
+                "{}",
+                drift
+            );
+        }
+    }
+}
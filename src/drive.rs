@@ -0,0 +1,35 @@
+// the gain in front of the curve at full drive, about +30 dB
+const MAX_DRIVE_GAIN: f32 = 32.0;
+
+/// Saturates a sample by `drive`, from 0 for none up to 1, passing it on untouched without.
+pub fn saturate(sample: f32, drive: f32) -> f32 {
+    if drive <= 0.0 {
+        return sample;
+    }
+
+    // turned back down by the square root of the gain, so a loop peaking around -15 dB comes
+    // out about as loud as it went in
+    let gain = MAX_DRIVE_GAIN.powf(drive.min(1.0));
+    (sample * gain).tanh() / gain.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_drive_is_transparent() {
+        for sample in [-8.0, -0.3, 0.0, 1e-20, 0.3, 8.0] {
+            assert_eq!(saturate(sample, 0.0).to_bits(), sample.to_bits());
+        }
+    }
+
+    #[test]
+    fn output_stays_under_full_scale() {
+        for drive in [0.05, 0.5, 1.0] {
+            for sample in [-8.0, -1.0, -0.1, 0.1, 1.0, 8.0] {
+                assert!(saturate(sample, drive).abs() <= 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App},
+    engine::{loop_seconds, LoopFit},
+    notes::edit_text,
+};
+
+// the size of the loop length window
+const DURATION_WIDTH: u16 = 56;
+const DURATION_HEIGHT: u16 = 7;
+
+/// A length for the loop in seconds being typed.
+#[derive(Default)]
+pub struct DurationPrompt {
+    pub text: String,
+    pub fit: LoopFit,
+}
+
+impl DurationPrompt {
+    // the typed length, a trailing `s` allowed
+    fn seconds(&self) -> Option<f64> {
+        let text = self.text.trim();
+        let text = text.strip_suffix('s').unwrap_or(text).trim_end();
+
+        text.parse()
+            .ok()
+            .filter(|seconds: &f64| *seconds > 0.0 && seconds.is_finite())
+    }
+}
+
+impl App {
+    pub fn open_duration_prompt(&mut self) {
+        self.duration_prompt = Some(DurationPrompt::default());
+    }
+
+    // handles a key while the loop length window is open
+    pub fn duration_key(&mut self, key: KeyEvent) {
+        let Some(ref mut prompt) = self.duration_prompt else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.duration_prompt = None,
+            KeyCode::Tab => prompt.fit = prompt.fit.toggled(),
+            KeyCode::Enter => {
+                let Some(seconds) = prompt.seconds() else {
+                    self.warn("type the length of the loop in seconds");
+                    return;
+                };
+
+                let fit = prompt.fit;
+                self.duration_prompt = None;
+                self.set_loop_duration(seconds, fit);
+            }
+            _ => {
+                edit_text(&mut prompt.text, key);
+            }
+        }
+    }
+
+    // fits the loop as close to `seconds` long as whole beats and tempos get
+    fn set_loop_duration(&mut self, seconds: f64, fit: LoopFit) {
+        let (bpm, beats) = self.audio.engine.loop_for_duration(seconds, fit);
+
+        match fit {
+            LoopFit::KeepBeats => self.change_bpm(bpm, false),
+            LoopFit::KeepBpm => {
+                self.audio.engine.set_beats(beats);
+                self.audio.cancel_pending_beats();
+            }
+        }
+
+        let actual = loop_seconds(bpm, beats);
+        self.info(format!(
+            "the loop is now {} beats at {} bpm, {:.3}s ({:+.3}s off)",
+            beats,
+            bpm,
+            actual,
+            actual - seconds
+        ));
+    }
+
+    pub fn render_duration<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let Some(ref prompt) = self.duration_prompt else {
+            return;
+        };
+
+        let area = centered_rect(frame.size(), DURATION_WIDTH, DURATION_HEIGHT);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Loop Length")
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        // what the typed length comes out as, whole beats and tempos rarely hitting it exactly
+        let result = match prompt.seconds() {
+            Some(seconds) => {
+                let (bpm, beats) = self.audio.engine.loop_for_duration(seconds, prompt.fit);
+                let actual = loop_seconds(bpm, beats);
+
+                Span::raw(format!(
+                    "{} beats at {} bpm, {:.3}s ({:+.3}s off)",
+                    beats,
+                    bpm,
+                    actual,
+                    actual - seconds
+                ))
+            }
+            None => Span::styled("type a length like 12 or 7.5s", self.theme.empty),
+        };
+
+        let text = vec![
+            Spans::from(vec![
+                Span::raw("seconds: "),
+                Span::styled(prompt.text.clone(), self.theme.active),
+                Span::styled("_", self.theme.empty),
+            ]),
+            Spans::from(format!("fit: {}", prompt.fit.name())),
+            Spans::from(result),
+        ];
+
+        frame.render_widget(Paragraph::new(text), rows[0]);
+
+        let footer = Paragraph::new("enter to apply, tab to change the fit, esc to cancel")
+            .style(self.theme.empty);
+        frame.render_widget(footer, rows[1]);
+    }
+}
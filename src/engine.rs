@@ -1,38 +1,481 @@
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
+use cpal::StreamInstant;
 use crossbeam::atomic::AtomicCell;
 
-use crate::{clip::Clip, track::Tracks};
+use crate::{
+    analysis::InputSnapshot,
+    clip::{Clip, RecordedLoop},
+    gate::{GateMode, DEFAULT_GATE_THRESHOLD},
+    loudness::OutputSnapshot,
+    meter::TrackMeter,
+    processor::CUE_CHANNEL,
+    queue::ActionQueue,
+    replace::ReplaceBuffer,
+    stutter::Stutter,
+    track::{InputFollow, Tracks, TRACK_COUNT},
+};
 
+/// How long the output callback took over a period of time.
+#[derive(Clone, Copy, Debug)]
+pub struct CallbackStats {
+    pub min: Duration,
+    pub average: Duration,
+    pub max: Duration,
+}
+
+/// Whether the output callback is running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    #[default]
+    Stopped,
+    Running,
+    /// Fading out before the streams are dropped.
+    Stopping,
+}
+
+/// When a tempo change that isn't applied right away takes over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TempoTiming {
+    /// At the next loop boundary, so every pass has a single length.
+    #[default]
+    Loop,
+    /// At the next beat, the rest of the pass playing at the new tempo.
+    Beat,
+}
+
+impl TempoTiming {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Loop => "Next loop",
+            Self::Beat => "Next beat",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Loop => Self::Beat,
+            Self::Beat => Self::Loop,
+        }
+    }
+}
+
+/// What gives when the loop is fitted to a length in seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopFit {
+    /// The tempo changes, the loop keeping its beats.
+    #[default]
+    KeepBeats,
+    /// The beats change, the loop keeping its tempo.
+    KeepBpm,
+}
+
+impl LoopFit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KeepBeats => "Keep beats",
+            Self::KeepBpm => "Keep BPM",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::KeepBeats => Self::KeepBpm,
+            Self::KeepBpm => Self::KeepBeats,
+        }
+    }
+}
+
+/// Returns how long a loop of `beats` beats at `bpm` is in seconds.
+pub fn loop_seconds(bpm: u64, beats: u64) -> f64 {
+    match bpm {
+        0 => 0.0,
+        bpm => beats as f64 * 60.0 / bpm as f64,
+    }
+}
+
+/// Where in the loop the current tempo took over, so the beats played before it keep the
+/// length they had.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TempoOrigin {
+    pub sample: u64,
+    pub beat: u64,
+}
+
+impl TempoOrigin {
+    /// Returns the beat `sample` lands on at `bpm`.
+    pub fn beat_at(self, sample: u64, sample_rate: u64, bpm: u64) -> f32 {
+        if sample_rate == 0 {
+            return self.beat as f32;
+        }
+
+        let seconds = sample.saturating_sub(self.sample) as f32 / sample_rate as f32;
+        self.beat as f32 + seconds * bpm as f32 / 60.0
+    }
+}
+
+/// How many beats make up a bar until another meter is chosen.
+pub const DEFAULT_BEATS_PER_BAR: u64 = 4;
+
+/// When the metronome clicks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetronomePattern {
+    #[default]
+    Off,
+    Always,
+    /// Only while a track is armed or a free loop records.
+    RecordOnly,
+    /// Only through the first bar of the loop.
+    FirstBar,
+}
+
+impl MetronomePattern {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Always => "Always",
+            Self::RecordOnly => "Recording",
+            Self::FirstBar => "First bar",
+        }
+    }
+
+    /// Returns the name the pattern is saved under in sessions.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Always => "always",
+            Self::RecordOnly => "record_only",
+            Self::FirstBar => "first_bar",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        [Self::Off, Self::Always, Self::RecordOnly, Self::FirstBar]
+            .into_iter()
+            .find(|pattern| pattern.key() == key)
+    }
+
+    pub fn cycled(self) -> Self {
+        match self {
+            Self::Off => Self::Always,
+            Self::Always => Self::RecordOnly,
+            Self::RecordOnly => Self::FirstBar,
+            Self::FirstBar => Self::Off,
+        }
+    }
+
+    /// Returns true if the metronome clicks at a beat of a loop of `beats` beats, in bars of
+    /// `beats_per_bar` beats.
+    pub fn clicks(self, beat: f32, beats: u64, beats_per_bar: u64, recording: bool) -> bool {
+        match self {
+            Self::Off => false,
+            Self::Always => true,
+            Self::RecordOnly => recording,
+            Self::FirstBar => beat.rem_euclid(beats.max(1) as f32) < beats_per_bar as f32,
+        }
+    }
+}
+
+/// The moment the loop last wrapped around, as heard at the output.
+#[derive(Clone, Copy, Debug)]
+pub struct LoopRestart {
+    /// When the first frame of the loop reaches the speakers.
+    pub instant: Instant,
+    /// The same moment on the clock of the output stream, if the host reports one.
+    pub stream: Option<StreamInstant>,
+}
+
+/// The state of the engine the ui draws from, read once per frame so everything derived from
+/// it describes the same moment.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EngineSnapshot {
+    pub bpm: u64,
+    pub beats: u64,
+    pub beats_per_bar: u64,
+    pub sample: u64,
+    pub sample_rate: u64,
+    /// The tempo queued for the next loop or beat, if any.
+    pub pending_bpm: Option<u64>,
+    pub tempo_timing: TempoTiming,
+    pub tempo_origin: TempoOrigin,
+    pub metronome: MetronomePattern,
+    pub transport: Transport,
+    pub killed: bool,
+    pub master_volume: u64,
+    /// The gain of a fade out, 1 when not fading.
+    pub fade_gain: f32,
+    pub fading: bool,
+    /// How far a crossfade into a new scene is, from 0 to 1, `None` when not fading.
+    pub scene_fade: Option<f32>,
+    /// When the loop last wrapped around, `None` until it has.
+    pub loop_restart: Option<Instant>,
+}
+
+impl EngineSnapshot {
+    pub fn is_running(&self) -> bool {
+        self.transport != Transport::Stopped
+    }
+
+    pub fn seconds(&self) -> f32 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+
+        self.sample as f32 / self.sample_rate as f32
+    }
+
+    pub fn beat(&self) -> f32 {
+        self.tempo_origin
+            .beat_at(self.sample, self.sample_rate, self.bpm)
+    }
+
+    /// Returns how far into the loop playback is, from 0 to 1.
+    pub fn loop_progress(&self) -> f32 {
+        let progress = self.beat() / self.beats as f32;
+
+        if progress.is_finite() {
+            progress.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the beat playback has reached by `now`, counted from the last loop restart so
+    /// it keeps moving between callbacks. Falls back to [`EngineSnapshot::beat`] when there's
+    /// nothing to count from.
+    pub fn beat_at(&self, now: Instant) -> f32 {
+        let beat = self.beat();
+
+        let Some(restart) = self.loop_restart else {
+            return beat;
+        };
+
+        if !self.is_running() || self.beats == 0 {
+            return beat;
+        }
+
+        let elapsed = now.saturating_duration_since(restart).as_secs_f32();
+        let estimate = (elapsed * self.bpm as f32 / 60.0) % self.beats as f32;
+
+        // a tempo change or a wrap the snapshot hasn't caught up with throws the estimate off
+        if (estimate - beat).abs() < 1.0 {
+            estimate
+        } else {
+            beat
+        }
+    }
+}
+
+/// The state shared by the ui thread and the audio callbacks, all of it behind atomics so
+/// neither side locks.
 pub struct AudioEngine {
     pub bpm: AtomicU64,
+    pub pending_bpm: AtomicCell<Option<u64>>,
+    pub tempo_timing: AtomicCell<TempoTiming>,
+    pub tempo_origin: AtomicCell<TempoOrigin>,
     pub beats: AtomicU64,
+    pub beats_per_bar: AtomicU64,
     pub sample: AtomicU64,
+    pub played: AtomicU64,
+    pub loop_count: AtomicU64,
     pub sample_rate: AtomicU64,
-    pub metronome: AtomicBool,
-    pub tracks: AtomicCell<Option<Tracks>>,
-    pub recorded_clip: AtomicCell<Option<Clip>>,
+    pub metronome: AtomicCell<MetronomePattern>,
+    pub recording: AtomicBool,
+    pub killed: AtomicBool,
+    pub shutting_down: AtomicBool,
+    pub master_volume: AtomicU64,
+    pub fade_frames: AtomicU64,
+    pub fade_gain: AtomicU32,
+    pub fade_finished: AtomicBool,
+    pub scene_fade_beats: AtomicU64,
+    pub scene_fade: AtomicCell<Option<f32>>,
+    pub delay_beats: AtomicU64,
+    pub delay_feedback: AtomicU64,
+    pub delay_mix: AtomicU64,
+    pub callback_min: AtomicU64,
+    pub callback_max: AtomicU64,
+    pub callback_total: AtomicU64,
+    pub callback_count: AtomicU64,
+    pub buffer_min: AtomicU64,
+    pub buffer_max: AtomicU64,
+    pub buffer_fallback: AtomicBool,
+    pub tracks: AtomicCell<Option<(u64, Tracks)>>,
+    pub applied_tracks: AtomicU64,
+    pub recorded_clip: AtomicCell<Option<RecordedLoop>>,
+    pub capture_request: AtomicCell<Option<usize>>,
+    pub chime_request: AtomicBool,
+    pub metronome_clip: AtomicCell<Option<Clip>>,
+    pub preview_clip: AtomicCell<Option<Clip>>,
+    pub previewing: AtomicBool,
+    pub preview_dim: AtomicBool,
+    pub preview_handoff: AtomicBool,
+    pub gate_mode: AtomicCell<GateMode>,
+    pub gate_threshold: AtomicI64,
+    pub gate_closed: AtomicBool,
+    pub record_dry: AtomicBool,
+    pub input_follow: AtomicCell<InputFollow>,
+    pub stutter: AtomicCell<Option<Stutter>>,
+    pub armed_track: AtomicCell<Option<usize>>,
+    pub armed_recording: AtomicBool,
+    pub stacking: AtomicBool,
+    pub armed_loops: AtomicU64,
+    pub free_recording: AtomicBool,
+    pub punch_in: AtomicBool,
+    pub arm_offset: AtomicU64,
+    pub tail_fold: AtomicU64,
+    pub cue_channel: AtomicU64,
+    pub monitor_delay: AtomicU64,
+    pub input_drift: AtomicCell<Option<f32>>,
+    pub secondary_channels: AtomicU64,
+    pub secondary_drift: AtomicCell<Option<f32>>,
+    pub input_snapshot: InputSnapshot,
+    pub output_snapshot: OutputSnapshot,
+    pub input_peak: AtomicU32,
+    pub input_channels: AtomicU64,
+    pub output_channels: AtomicU64,
+    pub mono_input: AtomicBool,
+    pub test_tone: AtomicBool,
+    pub replace_buffer: AtomicCell<Option<Arc<ReplaceBuffer>>>,
+    pub replace_returned: AtomicCell<Option<Arc<ReplaceBuffer>>>,
+    pub replace_held: AtomicBool,
+    pub replace_finished: AtomicBool,
+    pub replace_committed: AtomicBool,
+    pub queue: ActionQueue,
+    pub track_meters: [TrackMeter; TRACK_COUNT],
+    pub started: Instant,
+    pub loop_restart: AtomicCell<Option<LoopRestart>>,
 }
 
 impl Default for AudioEngine {
     fn default() -> Self {
         Self {
             bpm: AtomicU64::new(120),
+            pending_bpm: AtomicCell::new(None),
+            tempo_timing: AtomicCell::new(TempoTiming::Loop),
+            tempo_origin: AtomicCell::new(TempoOrigin::default()),
             beats: AtomicU64::new(16),
+            beats_per_bar: AtomicU64::new(DEFAULT_BEATS_PER_BAR),
             sample: AtomicU64::new(0),
+            played: AtomicU64::new(0),
+            loop_count: AtomicU64::new(0),
             sample_rate: AtomicU64::new(0),
-            metronome: AtomicBool::new(false),
+            metronome: AtomicCell::new(MetronomePattern::Off),
+            recording: AtomicBool::new(false),
+            killed: AtomicBool::new(false),
+            shutting_down: AtomicBool::new(false),
+            master_volume: AtomicU64::new(100),
+            fade_frames: AtomicU64::new(0),
+            fade_gain: AtomicU32::new(1.0f32.to_bits()),
+            fade_finished: AtomicBool::new(false),
+            scene_fade_beats: AtomicU64::new(0),
+            scene_fade: AtomicCell::new(None),
+            delay_beats: AtomicU64::new(2),
+            delay_feedback: AtomicU64::new(40),
+            delay_mix: AtomicU64::new(50),
+            callback_min: AtomicU64::new(u64::MAX),
+            callback_max: AtomicU64::new(0),
+            callback_total: AtomicU64::new(0),
+            callback_count: AtomicU64::new(0),
+            buffer_min: AtomicU64::new(u64::MAX),
+            buffer_max: AtomicU64::new(0),
+            buffer_fallback: AtomicBool::new(false),
             tracks: AtomicCell::new(None),
+            applied_tracks: AtomicU64::new(0),
             recorded_clip: AtomicCell::new(None),
+            capture_request: AtomicCell::new(None),
+            chime_request: AtomicBool::new(false),
+            metronome_clip: AtomicCell::new(None),
+            preview_clip: AtomicCell::new(None),
+            previewing: AtomicBool::new(false),
+            preview_dim: AtomicBool::new(false),
+            preview_handoff: AtomicBool::new(false),
+            gate_mode: AtomicCell::new(GateMode::Off),
+            input_follow: AtomicCell::new(InputFollow::Off),
+            gate_threshold: AtomicI64::new(DEFAULT_GATE_THRESHOLD),
+            gate_closed: AtomicBool::new(false),
+            record_dry: AtomicBool::new(false),
+            stutter: AtomicCell::new(None),
+            armed_track: AtomicCell::new(None),
+            armed_recording: AtomicBool::new(false),
+            stacking: AtomicBool::new(false),
+            armed_loops: AtomicU64::new(0),
+            free_recording: AtomicBool::new(false),
+            punch_in: AtomicBool::new(false),
+            arm_offset: AtomicU64::new(0),
+            tail_fold: AtomicU64::new(0),
+            cue_channel: AtomicU64::new(CUE_CHANNEL as u64),
+            monitor_delay: AtomicU64::new(0),
+            input_drift: AtomicCell::new(None),
+            secondary_channels: AtomicU64::new(0),
+            secondary_drift: AtomicCell::new(None),
+            input_snapshot: InputSnapshot::default(),
+            output_snapshot: OutputSnapshot::default(),
+            input_peak: AtomicU32::new(0),
+            input_channels: AtomicU64::new(0),
+            output_channels: AtomicU64::new(0),
+            mono_input: AtomicBool::new(false),
+            test_tone: AtomicBool::new(false),
+            replace_buffer: AtomicCell::new(None),
+            replace_returned: AtomicCell::new(None),
+            replace_held: AtomicBool::new(false),
+            replace_finished: AtomicBool::new(false),
+            replace_committed: AtomicBool::new(false),
+            queue: ActionQueue::default(),
+            track_meters: Default::default(),
+            started: Instant::now(),
+            loop_restart: AtomicCell::new(None),
         }
     }
 }
 
 impl AudioEngine {
+    // reads the state the ui draws from, each value exactly once
+    pub fn snapshot(&self) -> EngineSnapshot {
+        let transport = match (self.is_recording(), self.is_shutting_down()) {
+            (false, _) => Transport::Stopped,
+            (true, false) => Transport::Running,
+            (true, true) => Transport::Stopping,
+        };
+
+        EngineSnapshot {
+            bpm: self.bpm(),
+            beats: self.beats(),
+            beats_per_bar: self.beats_per_bar(),
+            sample: self.sample(),
+            sample_rate: self.sample_rate(),
+            pending_bpm: self.pending_bpm(),
+            tempo_timing: self.tempo_timing(),
+            tempo_origin: self.tempo_origin(),
+            metronome: self.metronome(),
+            transport,
+            killed: self.is_killed(),
+            master_volume: self.master_volume(),
+            fade_gain: self.fade_gain(),
+            fading: self.fade_frames() > 0,
+            scene_fade: self.scene_fade(),
+            loop_restart: self.loop_restart().map(|restart| restart.instant),
+        }
+    }
+
     pub fn sample(&self) -> u64 {
         self.sample.load(Ordering::Acquire)
     }
 
+    // frames played in all, counting on past the loop
+    pub fn played(&self) -> u64 {
+        self.played.load(Ordering::Acquire)
+    }
+
+    pub fn loop_count(&self) -> u64 {
+        self.loop_count.load(Ordering::Acquire)
+    }
+
     pub fn bpm(&self) -> u64 {
         self.bpm.load(Ordering::Acquire)
     }
@@ -45,59 +488,870 @@ impl AudioEngine {
         self.beats.load(Ordering::Acquire)
     }
 
+    pub fn beats_frames(&self, beats: u64) -> u64 {
+        match self.bpm() {
+            0 => 0,
+            bpm => beats * 60 * self.sample_rate() / bpm,
+        }
+    }
+
+    pub fn target_beats_frames(&self, beats: u64) -> u64 {
+        match self.target_bpm() {
+            0 => 0,
+            bpm => beats * 60 * self.sample_rate() / bpm,
+        }
+    }
+
     pub fn seconds(&self) -> f32 {
+        // nothing has played before the streams are launched
+        if self.sample_rate() == 0 {
+            return 0.0;
+        }
+
         self.sample() as f32 / self.sample_rate() as f32
     }
 
-    pub fn metronome(&self) -> bool {
-        self.metronome.load(Ordering::Acquire)
+    pub fn metronome(&self) -> MetronomePattern {
+        self.metronome.load()
+    }
+
+    pub fn metronome_clicks(&self) -> bool {
+        let recording = self.armed_track().is_some() || self.is_free_recording();
+        self.metronome()
+            .clicks(self.beat(), self.beats(), self.beats_per_bar(), recording)
     }
 
     pub fn beat(&self) -> f32 {
-        self.seconds() * self.bpm() as f32 / 60.0
+        self.tempo_origin()
+            .beat_at(self.sample(), self.sample_rate(), self.bpm())
+    }
+
+    // the frame the loop wraps at, the beats played before a tempo change this pass keeping
+    // their length
+    pub fn loop_frames(&self) -> u64 {
+        let origin = self.tempo_origin();
+        origin.sample + self.beats_frames(self.beats().saturating_sub(origin.beat))
+    }
+
+    // the tempo and beats of the loop closest to `seconds` long, neither going below 1, and a
+    // length that isn't above 0 keeping the loop as it is
+    pub fn loop_for_duration(&self, seconds: f64, fit: LoopFit) -> (u64, u64) {
+        let (bpm, beats) = (self.target_bpm(), self.beats());
+
+        if !(seconds > 0.0 && seconds.is_finite()) {
+            return (bpm, beats);
+        }
+
+        match fit {
+            LoopFit::KeepBeats => {
+                let bpm = (beats as f64 * 60.0 / seconds).round() as u64;
+                (bpm.max(1), beats)
+            }
+            LoopFit::KeepBpm => {
+                let beats = (seconds * bpm as f64 / 60.0).round() as u64;
+                (bpm, beats.max(1))
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Acquire)
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Acquire)
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Acquire)
+    }
+
+    pub fn master_volume(&self) -> u64 {
+        self.master_volume.load(Ordering::Acquire)
+    }
+
+    pub fn delay_beats(&self) -> u64 {
+        self.delay_beats.load(Ordering::Acquire)
+    }
+
+    pub fn delay_feedback(&self) -> u64 {
+        self.delay_feedback.load(Ordering::Acquire)
+    }
+
+    pub fn delay_mix(&self) -> u64 {
+        self.delay_mix.load(Ordering::Acquire)
     }
 
     pub fn sample_rate(&self) -> u64 {
         self.sample_rate.load(Ordering::Acquire)
     }
 
+    pub fn input_channels(&self) -> u16 {
+        self.input_channels.load(Ordering::Acquire) as u16
+    }
+
+    pub fn secondary_channels(&self) -> u16 {
+        self.secondary_channels.load(Ordering::Acquire) as u16
+    }
+
+    pub fn set_secondary_channels(&self, channels: u16) {
+        self.secondary_channels
+            .store(channels as u64, Ordering::Release);
+    }
+
+    pub fn output_channels(&self) -> u16 {
+        self.output_channels.load(Ordering::Acquire) as u16
+    }
+
+    pub fn set_output_channels(&self, channels: u16) {
+        self.output_channels
+            .store(channels as u64, Ordering::Release);
+    }
+
+    pub fn is_mono_input(&self) -> bool {
+        self.mono_input.load(Ordering::Acquire)
+    }
+
+    pub fn test_tone(&self) -> bool {
+        self.test_tone.load(Ordering::Acquire)
+    }
+
+    // milliseconds since the engine was created, what track meters note clipping at
+    pub fn clock(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    pub fn is_clip_held(&self, track: usize) -> bool {
+        self.track_meters
+            .get(track)
+            .is_some_and(|meter| meter.is_clip_held(self.clock()))
+    }
+
+    pub fn take_input_peak(&self) -> f32 {
+        f32::from_bits(self.input_peak.swap(0, Ordering::AcqRel))
+    }
+
     pub fn take_tracks(&self) -> Option<Tracks> {
-        self.tracks.take()
+        let (generation, tracks) = self.tracks.take()?;
+        self.set_applied_tracks(generation);
+
+        Some(tracks)
+    }
+
+    pub fn applied_tracks(&self) -> u64 {
+        self.applied_tracks.load(Ordering::Acquire)
+    }
+
+    pub fn set_applied_tracks(&self, generation: u64) {
+        self.applied_tracks.store(generation, Ordering::Release);
     }
 
     pub fn is_on_beat(&self) -> bool {
         self.beat() % 1.0 < 0.25
     }
 
+    // changes the tempo right away, dropping any queued change
     pub fn set_bpm(&self, bpm: u64) {
+        self.pending_bpm.store(None);
         self.bpm.store(bpm, Ordering::Release);
     }
 
+    pub fn pending_bpm(&self) -> Option<u64> {
+        self.pending_bpm.load()
+    }
+
+    pub fn target_bpm(&self) -> u64 {
+        self.pending_bpm().unwrap_or_else(|| self.bpm())
+    }
+
+    /// Queues a tempo change for the output callback to apply at the next loop boundary, or
+    /// the next beat, depending on the [`TempoTiming`].
+    pub fn queue_bpm(&self, bpm: u64) {
+        let pending = (bpm != self.bpm()).then_some(bpm);
+        self.pending_bpm.store(pending);
+    }
+
+    // called by the output callback between frames, the current frame starting `beat`
+    pub fn apply_pending_bpm(&self, beat: u64) -> bool {
+        let Some(bpm) = self.pending_bpm.take() else {
+            return false;
+        };
+
+        self.tempo_origin.store(TempoOrigin {
+            sample: self.sample(),
+            beat,
+        });
+        self.bpm.store(bpm, Ordering::Release);
+
+        true
+    }
+
+    pub fn tempo_timing(&self) -> TempoTiming {
+        self.tempo_timing.load()
+    }
+
+    pub fn set_tempo_timing(&self, timing: TempoTiming) {
+        self.tempo_timing.store(timing);
+    }
+
+    pub fn tempo_origin(&self) -> TempoOrigin {
+        self.tempo_origin.load()
+    }
+
     pub fn set_beats(&self, beats: u64) {
         self.beats.store(beats, Ordering::Release);
     }
 
+    pub fn beats_per_bar(&self) -> u64 {
+        self.beats_per_bar.load(Ordering::Acquire).max(1)
+    }
+
+    // returns how many beats the loop takes to stay as many bars long, which the caller
+    // resizes it to at the next loop boundary
+    pub fn set_beats_per_bar(&self, beats_per_bar: u64) -> u64 {
+        let bars = self.bars();
+
+        self.beats_per_bar
+            .store(beats_per_bar.max(1), Ordering::Release);
+        self.bars_beats(bars)
+    }
+
+    // a bar only partly played counts as a whole one, which is how a loop saved as a number of
+    // beats reads in bars
+    pub fn bars(&self) -> u64 {
+        self.beats().div_ceil(self.beats_per_bar())
+    }
+
+    pub fn set_bars(&self, bars: u64) {
+        self.set_beats(self.bars_beats(bars));
+    }
+
+    pub fn bars_beats(&self, bars: u64) -> u64 {
+        bars * self.beats_per_bar()
+    }
+
+    // the current tempo counts from the start of the loop
     pub fn set_sample(&self, sample: u64) {
+        self.tempo_origin.store(TempoOrigin::default());
         self.sample.store(sample, Ordering::Release);
     }
 
+    pub fn loop_restart(&self) -> Option<LoopRestart> {
+        self.loop_restart.load()
+    }
+
+    pub fn set_loop_restart(&self, restart: Option<LoopRestart>) {
+        self.loop_restart.store(restart);
+    }
+
+    pub fn loop_duration(&self) -> Duration {
+        match self.sample_rate() {
+            0 => Duration::ZERO,
+            rate => Duration::from_secs_f64(self.beats_frames(self.beats()) as f64 / rate as f64),
+        }
+    }
+
+    pub fn predicted_next_loop(&self) -> Duration {
+        self.predicted_next_loop_at(Instant::now())
+    }
+
+    // before the first restart the prediction goes by the frames left in the loop
+    pub fn predicted_next_loop_at(&self, now: Instant) -> Duration {
+        let length = self.loop_duration();
+        if length.is_zero() {
+            return Duration::ZERO;
+        }
+
+        let Some(restart) = self.loop_restart() else {
+            let frames = self
+                .beats_frames(self.beats())
+                .saturating_sub(self.sample());
+            return Duration::from_secs_f64(frames as f64 / self.sample_rate() as f64);
+        };
+
+        // the restart is stamped with when it's heard, which can still be ahead
+        if let Some(until) = restart.instant.checked_duration_since(now) {
+            if !until.is_zero() {
+                return until;
+            }
+        }
+
+        let elapsed = now.duration_since(restart.instant).as_nanos();
+        let into = elapsed % length.as_nanos();
+
+        length - Duration::from_nanos(into as u64)
+    }
+
     pub fn set_sample_rate(&self, sample_rate: u64) {
         self.sample_rate.store(sample_rate, Ordering::Release);
     }
 
-    pub fn set_metronome(&self, metronome: bool) {
-        self.metronome.store(metronome, Ordering::Release);
+    pub fn set_input_layout(&self, channels: u16, mono: bool) {
+        self.input_channels
+            .store(channels as u64, Ordering::Release);
+        self.mono_input.store(mono, Ordering::Release);
     }
 
-    pub fn set_tracks(&self, tracks: Option<Tracks>) {
-        self.tracks.store(tracks);
+    pub fn set_test_tone(&self, test_tone: bool) {
+        self.test_tone.store(test_tone, Ordering::Release);
     }
 
-    pub fn set_recorded_clip(&self, clip: Option<Clip>) {
-        self.recorded_clip.store(clip);
+    pub fn raise_input_peak(&self, peak: f32) {
+        // the bits of non negative floats order the same way as the floats themselves
+        self.input_peak
+            .fetch_max(peak.abs().to_bits(), Ordering::AcqRel);
+    }
+
+    pub fn set_metronome(&self, pattern: MetronomePattern) {
+        self.metronome.store(pattern);
+    }
+
+    pub fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::Release);
+    }
+
+    pub fn set_killed(&self, killed: bool) {
+        self.killed.store(killed, Ordering::Release);
+    }
+
+    pub fn set_shutting_down(&self, shutting_down: bool) {
+        self.shutting_down.store(shutting_down, Ordering::Release);
+    }
+
+    pub fn set_master_volume(&self, volume: u64) {
+        self.master_volume.store(volume, Ordering::Release);
+    }
+
+    pub fn fade_frames(&self) -> u64 {
+        self.fade_frames.load(Ordering::Acquire)
+    }
+
+    // fades out over `frames` frames, or back in over a beat with 0
+    pub fn set_fade_frames(&self, frames: u64) {
+        self.fade_frames.store(frames, Ordering::Release);
+    }
+
+    pub fn fade_gain(&self) -> f32 {
+        f32::from_bits(self.fade_gain.load(Ordering::Acquire))
+    }
+
+    pub fn set_fade_gain(&self, gain: f32) {
+        self.fade_gain.store(gain.to_bits(), Ordering::Release);
+    }
+
+    pub fn take_fade_finished(&self) -> bool {
+        self.fade_finished.swap(false, Ordering::AcqRel)
+    }
+
+    pub fn set_fade_finished(&self, finished: bool) {
+        self.fade_finished.store(finished, Ordering::Release);
+    }
+
+    pub fn scene_fade_beats(&self) -> u64 {
+        self.scene_fade_beats.load(Ordering::Acquire)
+    }
+
+    pub fn set_scene_fade_beats(&self, beats: u64) {
+        self.scene_fade_beats.store(beats, Ordering::Release);
+    }
+
+    pub fn scene_fade(&self) -> Option<f32> {
+        self.scene_fade.load()
+    }
+
+    pub fn set_scene_fade(&self, progress: Option<f32>) {
+        self.scene_fade.store(progress);
+    }
+
+    pub fn set_delay_beats(&self, beats: u64) {
+        self.delay_beats.store(beats, Ordering::Release);
+    }
+
+    pub fn set_delay_feedback(&self, feedback: u64) {
+        self.delay_feedback.store(feedback, Ordering::Release);
+    }
+
+    pub fn set_delay_mix(&self, mix: u64) {
+        self.delay_mix.store(mix, Ordering::Release);
+    }
+
+    // not every host sticks to the buffer size asked for
+    pub fn record_buffer_size(&self, frames: u64) {
+        self.buffer_min.fetch_min(frames, Ordering::AcqRel);
+        self.buffer_max.fetch_max(frames, Ordering::AcqRel);
+    }
+
+    pub fn buffer_sizes(&self) -> Option<(u64, u64)> {
+        let min = self.buffer_min.load(Ordering::Acquire);
+        let max = self.buffer_max.load(Ordering::Acquire);
+
+        (min <= max).then_some((min, max))
+    }
+
+    pub fn reset_buffer_sizes(&self) {
+        self.buffer_min.store(u64::MAX, Ordering::Release);
+        self.buffer_max.store(0, Ordering::Release);
+        self.set_buffer_fallback(false);
+    }
+
+    pub fn is_buffer_fallback(&self) -> bool {
+        self.buffer_fallback.load(Ordering::Acquire)
+    }
+
+    pub fn set_buffer_fallback(&self, fallback: bool) {
+        self.buffer_fallback.store(fallback, Ordering::Release);
+    }
+
+    pub fn record_callback_time(&self, duration: Duration) {
+        let nanos = duration.as_nanos() as u64;
+
+        self.callback_min.fetch_min(nanos, Ordering::AcqRel);
+        self.callback_max.fetch_max(nanos, Ordering::AcqRel);
+        self.callback_total.fetch_add(nanos, Ordering::AcqRel);
+        self.callback_count.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn take_callback_stats(&self) -> Option<CallbackStats> {
+        let count = self.callback_count.swap(0, Ordering::AcqRel);
+        let total = self.callback_total.swap(0, Ordering::AcqRel);
+        let min = self.callback_min.swap(u64::MAX, Ordering::AcqRel);
+        let max = self.callback_max.swap(0, Ordering::AcqRel);
+
+        if count == 0 {
+            return None;
+        }
+
+        Some(CallbackStats {
+            min: Duration::from_nanos(min),
+            average: Duration::from_nanos(total / count),
+            max: Duration::from_nanos(max),
+        })
+    }
+
+    // picked up by the output callback on the next beat
+    pub fn set_tracks(&self, generation: u64, tracks: Tracks) {
+        self.tracks.store(Some((generation, tracks)));
+    }
+
+    pub fn stutter(&self) -> Option<Stutter> {
+        self.stutter.load()
+    }
+
+    pub fn set_stutter(&self, stutter: Option<Stutter>) {
+        self.stutter.store(stutter);
+    }
+
+    pub fn is_replace_held(&self) -> bool {
+        self.replace_held.load(Ordering::Acquire)
+    }
+
+    pub fn is_replace_finished(&self) -> bool {
+        self.replace_finished.load(Ordering::Acquire)
+    }
+
+    pub fn is_replace_committed(&self) -> bool {
+        self.replace_committed.load(Ordering::Acquire)
+    }
+
+    pub fn set_replace_held(&self, held: bool) {
+        self.replace_held.store(held, Ordering::Release);
+    }
+
+    pub fn set_replace_finished(&self, finished: bool) {
+        self.replace_finished.store(finished, Ordering::Release);
+    }
+
+    pub fn set_replace_committed(&self, committed: bool) {
+        self.replace_committed.store(committed, Ordering::Release);
+    }
+
+    pub fn armed_track(&self) -> Option<usize> {
+        self.armed_track.load()
+    }
+
+    pub fn is_armed_recording(&self) -> bool {
+        self.armed_recording.load(Ordering::Acquire)
+    }
+
+    pub fn is_punch_in(&self) -> bool {
+        self.punch_in.load(Ordering::Acquire)
+    }
+
+    pub fn arm_offset(&self) -> u64 {
+        self.arm_offset.load(Ordering::Acquire)
+    }
+
+    pub fn set_punch_in(&self, punch_in: bool) {
+        self.punch_in.store(punch_in, Ordering::Release);
+    }
+
+    pub fn set_arm_offset(&self, offset: u64) {
+        self.arm_offset.store(offset, Ordering::Release);
+    }
+
+    pub fn tail_fold(&self) -> u64 {
+        self.tail_fold.load(Ordering::Acquire)
+    }
+
+    pub fn set_tail_fold(&self, ms: u64) {
+        self.tail_fold.store(ms, Ordering::Release);
+    }
+
+    pub fn cue_channel(&self) -> u16 {
+        self.cue_channel.load(Ordering::Acquire) as u16
+    }
+
+    pub fn set_cue_channel(&self, channel: u16) {
+        self.cue_channel.store(channel as u64, Ordering::Release);
+    }
+
+    // how far behind the input is heard, in samples
+    pub fn monitor_delay(&self) -> u64 {
+        self.monitor_delay.load(Ordering::Acquire)
+    }
+
+    pub fn set_monitor_delay(&self, samples: u64) {
+        self.monitor_delay.store(samples, Ordering::Release);
+    }
+
+    // in parts per million, positive when the input runs fast
+    pub fn input_drift(&self) -> Option<f32> {
+        self.input_drift.load()
+    }
+
+    pub fn set_input_drift(&self, ppm: Option<f32>) {
+        self.input_drift.store(ppm);
+    }
+
+    pub fn secondary_drift(&self) -> Option<f32> {
+        self.secondary_drift.load()
+    }
+
+    pub fn set_secondary_drift(&self, ppm: Option<f32>) {
+        self.secondary_drift.store(ppm);
+    }
+
+    pub fn set_armed_track(&self, track: Option<usize>) {
+        self.armed_track.store(track);
+    }
+
+    pub fn set_armed_recording(&self, recording: bool) {
+        self.armed_recording.store(recording, Ordering::Release);
+    }
+
+    pub fn is_stacking(&self) -> bool {
+        self.stacking.load(Ordering::Acquire)
+    }
+
+    pub fn set_stacking(&self, stacking: bool) {
+        self.stacking.store(stacking, Ordering::Release);
+    }
+
+    pub fn armed_loops(&self) -> u64 {
+        self.armed_loops.load(Ordering::Acquire)
+    }
+
+    pub fn set_armed_loops(&self, loops: u64) {
+        self.armed_loops.store(loops, Ordering::Release);
+    }
+
+    pub fn take_armed_loop(&self) -> bool {
+        self.armed_loops
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |loops| {
+                loops.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    pub fn is_free_recording(&self) -> bool {
+        self.free_recording.load(Ordering::Acquire)
+    }
+
+    pub fn set_free_recording(&self, recording: bool) {
+        self.free_recording.store(recording, Ordering::Release);
+    }
+
+    pub fn set_recorded_clip(&self, recorded: Option<RecordedLoop>) {
+        self.recorded_clip.store(recorded);
+    }
+
+    pub fn take_capture_request(&self) -> Option<usize> {
+        self.capture_request.take()
+    }
+
+    pub fn request_capture(&self, track: usize) {
+        self.capture_request.store(Some(track));
+    }
+
+    pub fn take_chime_request(&self) -> bool {
+        self.chime_request.swap(false, Ordering::AcqRel)
+    }
+
+    pub fn request_chime(&self) {
+        self.chime_request.store(true, Ordering::Release);
+    }
+
+    pub fn take_metronome_clip(&self) -> Option<Clip> {
+        self.metronome_clip.take()
+    }
+
+    pub fn set_metronome_clip(&self, clip: Option<Clip>) {
+        self.metronome_clip.store(clip);
+    }
+
+    pub fn take_preview_clip(&self) -> Option<Clip> {
+        self.preview_clip.take()
+    }
+
+    pub fn set_preview_clip(&self, clip: Option<Clip>) {
+        self.preview_clip.store(clip);
+    }
+
+    pub fn is_previewing(&self) -> bool {
+        self.previewing.load(Ordering::Acquire)
+    }
+
+    pub fn set_previewing(&self, previewing: bool) {
+        self.previewing.store(previewing, Ordering::Release);
+    }
+
+    pub fn is_preview_handoff(&self) -> bool {
+        self.preview_handoff.load(Ordering::Acquire)
+    }
+
+    pub fn set_preview_handoff(&self, handoff: bool) {
+        self.preview_handoff.store(handoff, Ordering::Release);
+    }
+
+    pub fn preview_dim(&self) -> bool {
+        self.preview_dim.load(Ordering::Acquire)
+    }
+
+    pub fn set_preview_dim(&self, dim: bool) {
+        self.preview_dim.store(dim, Ordering::Release);
+    }
+
+    pub fn gate_mode(&self) -> GateMode {
+        self.gate_mode.load()
+    }
+
+    pub fn set_gate_mode(&self, mode: GateMode) {
+        self.gate_mode.store(mode);
+    }
+
+    pub fn is_recording_dry(&self) -> bool {
+        self.record_dry.load(Ordering::Acquire)
+    }
+
+    pub fn set_recording_dry(&self, dry: bool) {
+        self.record_dry.store(dry, Ordering::Release);
+    }
+
+    pub fn input_follow(&self) -> InputFollow {
+        self.input_follow.load()
+    }
+
+    pub fn set_input_follow(&self, follow: InputFollow) {
+        self.input_follow.store(follow);
+    }
+
+    pub fn gate_threshold(&self) -> i64 {
+        self.gate_threshold.load(Ordering::Acquire)
+    }
+
+    pub fn set_gate_threshold(&self, db: i64) {
+        self.gate_threshold.store(db, Ordering::Release);
+    }
+
+    pub fn is_gate_closed(&self) -> bool {
+        self.gate_closed.load(Ordering::Acquire)
+    }
+
+    pub fn set_gate_closed(&self, closed: bool) {
+        self.gate_closed.store(closed, Ordering::Release);
     }
 
     pub fn should_loop(&self) -> bool {
-        self.beat() >= self.beats() as f32
+        match self.bpm() {
+            // the beat doesn't move without a tempo
+            0 => self.beats() == 0,
+            _ => self.sample() >= self.loop_frames(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_for_duration_fits_whole_numbers() {
+        let engine = AudioEngine::default();
+        engine.set_bpm(120);
+        engine.set_beats(16);
+
+        assert_eq!(engine.loop_for_duration(12.0, LoopFit::KeepBeats), (80, 16));
+        assert_eq!(engine.loop_for_duration(12.0, LoopFit::KeepBpm), (120, 24));
+
+        // as close as whole numbers get, 7.27 and 7.5 seconds
+        assert_eq!(engine.loop_for_duration(7.3, LoopFit::KeepBeats), (132, 16));
+        assert_eq!(engine.loop_for_duration(7.3, LoopFit::KeepBpm), (120, 15));
+
+        assert_eq!(engine.loop_for_duration(0.01, LoopFit::KeepBpm), (120, 1));
+        assert_eq!(
+            engine.loop_for_duration(100000.0, LoopFit::KeepBeats),
+            (1, 16)
+        );
+        assert_eq!(
+            engine.loop_for_duration(f64::NAN, LoopFit::KeepBpm),
+            (120, 16)
+        );
+    }
+
+    #[test]
+    fn meter_change_keeps_the_bars() {
+        let engine = AudioEngine::default();
+        engine.set_bars(4);
+        assert_eq!(engine.beats(), 16);
+
+        // four bars of three beats are 12, the loop is left alone until it's resized
+        assert_eq!(engine.set_beats_per_bar(3), 12);
+        assert_eq!(engine.beats(), 16);
+
+        // a bar only partly played counts
+        engine.set_beats_per_bar(4);
+        engine.set_beats(6);
+        assert_eq!(engine.bars(), 2);
+    }
+
+    // plays frames the way the output callback does until the loop wraps, returning how long
+    // the pass was
+    fn play_pass(engine: &AudioEngine) -> u64 {
+        let mut frames = 0;
+
+        while !engine.should_loop() {
+            engine.sample.fetch_add(1, Ordering::AcqRel);
+            frames += 1;
+        }
+
+        engine.set_sample(0);
+        engine.apply_pending_bpm(0);
+
+        frames
+    }
+
+    #[test]
+    fn beats_frames_at_the_tempo() {
+        let engine = AudioEngine::default();
+        engine.set_bpm(90);
+        engine.set_sample_rate(48000);
+
+        assert_eq!(engine.beats_frames(4), 128000);
+
+        engine.set_bpm(0);
+        assert_eq!(engine.beats_frames(4), 0);
+    }
+
+    #[test]
+    fn queued_tempo_waits_for_the_loop() {
+        let engine = AudioEngine::default();
+        engine.set_sample_rate(48000);
+        engine.set_bpm(120);
+        engine.set_beats(4);
+
+        // halfway through a pass, which keeps its length
+        engine.set_sample(48000);
+        engine.queue_bpm(96);
+        assert_eq!(engine.loop_frames(), 96000);
+        assert_eq!(engine.target_bpm(), 96);
+        assert_eq!(play_pass(&engine), 48000);
+
+        // every pass after it is at the new tempo, none mixing the two
+        assert_eq!(engine.bpm(), 96);
+        assert_eq!(engine.pending_bpm(), None);
+        assert_eq!(play_pass(&engine), 120000);
+        assert_eq!(play_pass(&engine), 120000);
+    }
+
+    #[test]
+    fn queueing_the_same_tempo_or_setting_one_drops_the_queue() {
+        let engine = AudioEngine::default();
+        engine.set_bpm(120);
+
+        engine.queue_bpm(120);
+        assert_eq!(engine.pending_bpm(), None);
+
+        engine.queue_bpm(100);
+        engine.set_bpm(140);
+        assert_eq!(engine.pending_bpm(), None);
+        assert!(!engine.apply_pending_bpm(0));
+        assert_eq!(engine.bpm(), 140);
+    }
+
+    #[test]
+    fn tempo_taking_over_at_a_beat_keeps_the_beats_played() {
+        let engine = AudioEngine::default();
+        engine.set_sample_rate(48000);
+        engine.set_bpm(120);
+        engine.set_beats(4);
+        engine.set_tempo_timing(TempoTiming::Beat);
+
+        // the second beat at 120 bpm starts half a second in
+        engine.set_sample(24000);
+        engine.queue_bpm(60);
+        assert!(engine.apply_pending_bpm(1));
+        assert_eq!(engine.beat(), 1.0);
+
+        // the three beats left take a second each
+        assert_eq!(engine.loop_frames(), 24000 + 3 * 48000);
+        engine.sample.store(72000, Ordering::Release);
+        assert_eq!(engine.beat(), 2.0);
+
+        // the pass after it starts over at the new tempo
+        engine
+            .sample
+            .store(engine.loop_frames() - 1, Ordering::Release);
+        assert_eq!(play_pass(&engine), 1);
+        assert_eq!(engine.tempo_origin(), TempoOrigin::default());
+        assert_eq!(engine.loop_frames(), 192000);
+    }
+
+    #[test]
+    fn next_loop_predicted_from_the_last_restart() {
+        let engine = AudioEngine::default();
+        engine.set_sample_rate(48000);
+        engine.set_bpm(120);
+        engine.set_beats(4);
+        let second = Duration::from_secs(1);
+
+        // before the first restart it goes by the frames left
+        engine.set_sample(12000);
+        assert_eq!(
+            engine.predicted_next_loop_at(Instant::now()),
+            second * 7 / 4
+        );
+
+        // a fake clock, the loop having restarted at `start` and lasting two seconds
+        let start = Instant::now();
+        engine.set_loop_restart(Some(LoopRestart {
+            instant: start,
+            stream: None,
+        }));
+
+        let at = |offset: Duration| engine.predicted_next_loop_at(start + offset);
+        assert_eq!(at(Duration::ZERO), second * 2);
+        assert_eq!(at(second / 2), second * 3 / 2);
+
+        // long after the restart it keeps counting whole loops
+        assert_eq!(at(second * 2), second * 2);
+        assert_eq!(at(second * 61 / 2), second * 3 / 2);
+
+        // a restart that's still to be heard is waited for
+        let early = start.checked_sub(second / 4).unwrap();
+        assert_eq!(engine.predicted_next_loop_at(early), second / 4);
+
+        // without a tempo there's no loop to wait for
+        engine.set_bpm(0);
+        assert_eq!(at(second), Duration::ZERO);
     }
 }
@@ -0,0 +1,68 @@
+use crossbeam::channel::{self, Receiver, Sender};
+
+#[cfg(feature = "osc")]
+use crate::osc::OscEvent;
+use crate::{app::App, audio::AudioEvent, midi::MidiMessage, resample::Resampled};
+
+/// Something that happened outside the main loop, handled by the app between frames.
+#[derive(Clone, Debug)]
+pub enum AppEvent {
+    Midi(MidiMessage),
+    /// A background resample finished.
+    Resampled(Resampled),
+    #[cfg(feature = "osc")]
+    Osc(OscEvent),
+}
+
+/// The channels other threads use to send events to the app, the audio callback having one
+/// of its own.
+pub struct Events {
+    pub sender: Sender<AppEvent>,
+    pub receiver: Receiver<AppEvent>,
+    pub audio_sender: Sender<AudioEvent>,
+    pub audio_receiver: Receiver<AudioEvent>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel::unbounded();
+        let (audio_sender, audio_receiver) = channel::unbounded();
+
+        Self {
+            sender,
+            receiver,
+            audio_sender,
+            audio_receiver,
+        }
+    }
+}
+
+impl App {
+    // handles every event received since the last call
+    pub fn app_events(&mut self) {
+        while let Ok(event) = self.events.audio_receiver.try_recv() {
+            match event {
+                AudioEvent::Recorded(index, recorded) => {
+                    self.armed_recorded(index, recorded.folded())
+                }
+                AudioEvent::FreeRecorded(clip) => self.free_recorded(clip),
+                AudioEvent::Captured(index, capture, end) => {
+                    self.captured(index, capture.clip(), end)
+                }
+            }
+
+            self.redraw = true;
+        }
+
+        while let Ok(event) = self.events.receiver.try_recv() {
+            match event {
+                AppEvent::Midi(message) => self.midi_message(message),
+                AppEvent::Resampled(job) => self.resampled(job),
+                #[cfg(feature = "osc")]
+                AppEvent::Osc(event) => self.osc_event(event),
+            }
+
+            self.redraw = true;
+        }
+    }
+}
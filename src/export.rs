@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use tui::{
+    backend::Backend,
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App, EditMode},
+    clip::Clip,
+    keymap::Action,
+    meter::level_db,
+    wav::{self, SampleFormat, WavInfo},
+};
+
+// how long the echoes ring on after the last repeat of an exported mix, in seconds
+const EXPORT_TAIL: u64 = 1;
+
+// the size of the export window
+const EXPORT_WIDTH: u16 = 44;
+const EXPORT_HEIGHT: u16 = 7;
+
+impl App {
+    // changes how the mix is exported while waiting for the number of repeats
+    pub fn export_action(&mut self, action: Action) {
+        if self.edit_mode != EditMode::ExportMix {
+            return;
+        }
+
+        let options = &mut self.export_options;
+
+        match action {
+            Action::CycleExportFormat => options.format = options.format.toggled(),
+            Action::ToggleExportNormalize => options.normalize = !options.normalize,
+            Action::ToggleExportDither => options.dither = !options.dither,
+            Action::ToggleExportStems => self.export_stems = !self.export_stems,
+            _ => {}
+        }
+    }
+
+    // writes a file the way the export window is set, returning how much it was normalized by
+    fn write_export(&self, path: &Path, clip: &Clip, info: &WavInfo) -> std::io::Result<String> {
+        let gain = wav::save_clip_export(path, clip, info, self.export_options)?;
+        Ok(format!("{:+.1} dB", level_db(gain)))
+    }
+
+    // renders the mix with the loop repeated `repeats` times and a tail, and writes it to a
+    // wav file named after the repeat count, along with a file for every track if asked to
+    pub fn export_mix(&mut self, repeats: u32) {
+        self.edit_mode = EditMode::None;
+
+        let session = self.session();
+        let tail_frames = session
+            .format()
+            .map_or(0, |(sample_rate, _)| sample_rate.0 as u64 * EXPORT_TAIL);
+
+        let mix = match session.render_mix_repeats(repeats, tail_frames) {
+            Ok(mix) => mix,
+            Err(err) => {
+                self.warn(format!("nothing to export: {}", err));
+                return;
+            }
+        };
+
+        let stem = format!("mix-{}x", repeats);
+        let path = wav::unique_path(&stem, "wav");
+
+        match self.write_export(&path, &mix, &session.wav_info()) {
+            Ok(gain) => self.info(format!("exported mix to {}, gain {}", path.display(), gain)),
+            Err(err) => {
+                self.error(format!("failed to export mix: {}", err));
+                return;
+            }
+        }
+
+        if !self.export_stems {
+            return;
+        }
+
+        // the stems stop with the last repeat, only the mix has the echoes ringing on
+        let frames = session.loop_frames(mix.sample_rate) * repeats as u64;
+        for (index, track) in session.tracks.iter().enumerate() {
+            if track.clip.is_none() {
+                continue;
+            }
+
+            let clip = track.render(frames, mix.channels, mix.sample_rate);
+            let path = wav::unique_path(&format!("{}-track-{}", stem, index + 1), "wav");
+
+            match self.write_export(&path, &clip, &session.track_wav_info(index)) {
+                Ok(gain) => self.info(format!(
+                    "exported track {} to {}, gain {}",
+                    (index + 1) % 10,
+                    path.display(),
+                    gain
+                )),
+                Err(err) => self.error(format!(
+                    "failed to export track {}: {}",
+                    (index + 1) % 10,
+                    err
+                )),
+            }
+        }
+    }
+
+    pub fn render_export<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if self.edit_mode != EditMode::ExportMix {
+            return;
+        }
+
+        let area = centered_rect(frame.size(), EXPORT_WIDTH, EXPORT_HEIGHT);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Export", Action::ExportMix))
+            .border_style(self.theme.accent);
+
+        // normalizing and dithering only mean something for 16-bit files
+        let options = self.export_options;
+        let int = options.format == SampleFormat::Int16;
+        let check = |label: &str, action: Action, checked: bool, enabled: bool| {
+            let style = if enabled {
+                Style::default()
+            } else {
+                self.theme.empty
+            };
+
+            Spans::from(Span::styled(
+                format!(
+                    "[{}] {} '{}'",
+                    if checked && enabled { 'x' } else { ' ' },
+                    label,
+                    self.keymap.key_name(action)
+                ),
+                style,
+            ))
+        };
+
+        let text = vec![
+            Spans::from(format!(
+                "{} '{}'",
+                options.format.name(),
+                self.keymap.key_name(Action::CycleExportFormat)
+            )),
+            check(
+                &format!("normalize to {} dBFS", wav::NORMALIZE_PEAK_DB),
+                Action::ToggleExportNormalize,
+                options.normalize,
+                int,
+            ),
+            check(
+                "tpdf dither",
+                Action::ToggleExportDither,
+                options.dither,
+                int,
+            ),
+            check("stems", Action::ToggleExportStems, self.export_stems, true),
+            Spans::from(Span::styled(
+                "a digit for how many repeats to export",
+                self.theme.empty,
+            )),
+        ];
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::app::{App, EditMode};
+
+impl App {
+    // asks for the length of a fade out, cancels a running one, or switches to clearing the
+    // tracks once it finishes when pressed again
+    pub fn fade_key(&mut self) {
+        if self.audio.engine.fade_frames() > 0 {
+            self.audio.engine.set_fade_frames(0);
+            self.info("fade cancelled");
+            return;
+        }
+
+        self.edit_mode = match self.edit_mode {
+            EditMode::Fade(clear) => EditMode::Fade(!clear),
+            _ => EditMode::Fade(false),
+        };
+    }
+
+    // fades the output out over `bars` bars
+    pub fn start_fade(&mut self, bars: u64) {
+        let EditMode::Fade(clear) = self.edit_mode else {
+            return;
+        };
+
+        self.edit_mode = EditMode::None;
+
+        let engine = &self.audio.engine;
+        if !engine.is_recording() {
+            self.warn("launch the streams to fade out");
+            return;
+        }
+
+        let frames = engine.beats_frames(bars * engine.beats_per_bar());
+        if frames == 0 {
+            return;
+        }
+
+        engine.set_fade_frames(frames);
+        self.fade_clear = clear;
+
+        let bars = format!("{} bar{}", bars, if bars == 1 { "" } else { "s" });
+        if clear {
+            self.info(format!("fading out over {}, then clearing", bars));
+        } else {
+            self.info(format!("fading out over {}", bars));
+        }
+    }
+
+    // clears the tracks once a fade asked to has gone silent, called every update
+    pub fn update_fade(&mut self) {
+        if !self.audio.engine.take_fade_finished() {
+            return;
+        }
+
+        if !self.fade_clear {
+            self.info("faded out");
+            return;
+        }
+
+        for index in 0..self.audio.tracks.len() {
+            if self.audio.tracks[index].clip.take().is_some() {
+                self.forget_layers(index);
+            }
+        }
+
+        self.audio.update_tracks();
+
+        // the tracks are gone, so come back in for whatever is played next
+        self.audio.engine.set_fade_frames(0);
+        self.fade_clear = false;
+        self.info("faded out and cleared the tracks");
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    app::{App, EditMode, MAX_BEATS},
+    clip::Clip,
+    keymap::Action,
+};
+
+// a guessed beat count puts the tempo at least this fast, and below twice as fast
+const GUESS_MIN_BPM: u64 = 80;
+
+/// Returns the tempo at which `beats` beats last `frames` frames, rounded to a whole bpm.
+pub fn free_loop_bpm(frames: u64, sample_rate: u64, beats: u64) -> u64 {
+    if frames == 0 {
+        return 0;
+    }
+
+    ((beats * 60 * sample_rate) as f64 / frames as f64).round() as u64
+}
+
+/// Guesses how many beats a freely recorded loop spans, the power of two that puts its tempo
+/// between 80 and 160 bpm.
+pub fn guess_free_beats(frames: u64, sample_rate: u64) -> u64 {
+    let mut beats = 1;
+
+    while beats * 2 <= MAX_BEATS && free_loop_bpm(frames, sample_rate, beats) < GUESS_MIN_BPM {
+        beats *= 2;
+    }
+
+    beats
+}
+
+impl App {
+    // starts recording a loop of any length, or stops and derives the tempo from it
+    pub fn toggle_free_recording(&mut self) {
+        let engine = &self.audio.engine;
+
+        if engine.is_free_recording() {
+            // the output callback hands the take back once it stopped
+            engine.set_free_recording(false);
+            return;
+        }
+
+        if !engine.is_recording() {
+            self.warn("launch the streams to record a free loop");
+            return;
+        }
+
+        if self.audio.tracks.iter().any(|track| track.clip.is_some()) {
+            self.warn("the tempo comes from the first loop, clear the tracks to record a free one");
+            return;
+        }
+
+        engine.set_armed_track(None);
+        engine.set_punch_in(false);
+        engine.set_free_recording(true);
+
+        self.info(format!(
+            "recording a free loop, {} again to end it",
+            self.keymap.key_name(Action::FreeRecord)
+        ));
+    }
+
+    // called when a free recording was stopped, fitting the tempo to it
+    pub fn free_recorded(&mut self, clip: Clip) {
+        let frames = clip.frame_count();
+        if frames == 0 {
+            self.warn("the free loop is empty");
+            return;
+        }
+
+        let beats = guess_free_beats(frames, clip.sample_rate.0 as u64);
+        self.free_take = Some(clip.analyze());
+        self.apply_free_loop(beats);
+
+        self.edit_mode = EditMode::FreeBeats;
+    }
+
+    // changes how many beats the free loop is taken to span, refitting the tempo
+    pub fn rotate_free_beats(&mut self, offset: i32) {
+        let beats = self.audio.engine.beats() as i64 - offset as i64;
+        self.apply_free_loop(beats.clamp(1, MAX_BEATS as i64) as u64);
+    }
+
+    // sets the tempo so `beats` beats span the free loop, and records it into track 1
+    fn apply_free_loop(&mut self, beats: u64) {
+        let Some(ref take) = self.free_take else {
+            return;
+        };
+
+        let bpm = free_loop_bpm(take.frame_count(), take.sample_rate.0 as u64, beats);
+
+        let engine = &self.audio.engine;
+        engine.set_bpm(bpm);
+        engine.set_beats(beats);
+
+        // a whole number of bpm rarely lands on the exact length that was played
+        let clip = take.fit(engine.beats_frames(beats));
+
+        // only the last fit is kept, so a changed beat count isn't another layer
+        self.audio.tracks[0].clip = None;
+        self.forget_layers(0);
+        self.audio.cancel_pending_beats();
+        self.commit_clip(0, clip);
+
+        self.info(format!("free loop: {} beats at {} bpm", beats, bpm));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cpal::SampleRate;
+
+    use super::*;
+
+    const RATE: u64 = 48000;
+
+    #[test]
+    fn bpm_of_a_free_loop() {
+        // four beats in two seconds
+        assert_eq!(free_loop_bpm(2 * RATE, RATE, 4), 120);
+
+        // a take a little long or short rounds to the nearest bpm
+        assert_eq!(free_loop_bpm(2 * RATE + 1000, RATE, 4), 119);
+        assert_eq!(free_loop_bpm(2 * RATE - 1000, RATE, 4), 121);
+        assert_eq!(free_loop_bpm(RATE * 60 / 93, RATE, 1), 93);
+
+        assert_eq!(free_loop_bpm(0, RATE, 4), 0);
+    }
+
+    #[test]
+    fn guessed_beats_put_the_tempo_between_80_and_160() {
+        for (seconds, beats) in [(0.5, 1), (1.0, 2), (2.0, 4), (3.5, 8), (8.0, 16)] {
+            let frames = (seconds * RATE as f64) as u64;
+            assert_eq!(guess_free_beats(frames, RATE), beats, "{} s", seconds);
+
+            let bpm = free_loop_bpm(frames, RATE, beats);
+            assert!((GUESS_MIN_BPM..2 * GUESS_MIN_BPM).contains(&bpm));
+        }
+
+        // a loop too long for that stops at the most beats there can be
+        assert_eq!(guess_free_beats(1000 * RATE, RATE), MAX_BEATS);
+    }
+
+    #[test]
+    fn free_loop_sets_the_tempo_and_fills_track_1() {
+        let mut app = App::offline();
+        app.audio.engine.set_sample_rate(RATE);
+
+        // a take of 2.1 seconds, four beats at 114 bpm
+        let take = vec![0.1; (2.1 * RATE as f64) as usize];
+        app.free_recorded(Clip::new(1, SampleRate(RATE as u32), take.into()));
+
+        let engine = &app.audio.engine;
+        assert_eq!((engine.bpm(), engine.beats()), (114, 4));
+        assert_eq!(app.edit_mode, EditMode::FreeBeats);
+
+        // the take is fitted to what the whole bpm makes the loop
+        let clip = app.audio.tracks[0].clip.as_ref().unwrap();
+        assert_eq!(clip.frame_count(), engine.beats_frames(4));
+
+        // taking it as eight beats doubles the tempo
+        app.rotate_free_beats(-4);
+        let engine = &app.audio.engine;
+        assert_eq!((engine.bpm(), engine.beats()), (229, 8));
+
+        let clip = app.audio.tracks[0].clip.as_ref().unwrap();
+        assert_eq!(clip.frame_count(), engine.beats_frames(8));
+    }
+}
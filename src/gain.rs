@@ -0,0 +1,68 @@
+use crate::app::App;
+
+/// The RMS level committed takes are matched to, in dB below full scale.
+const REFERENCE_RMS: f32 = -18.0;
+
+// how far the volume is turned up or down to match the reference, in dB
+const MAX_AUTO_GAIN: f32 = 12.0;
+
+// takes quieter than this are mostly silence, and aren't matched at all
+const SILENCE_RMS: f32 = -60.0;
+
+// the highest volume a track can be set to, in percent
+const MAX_VOLUME: u32 = 200;
+
+/// Returns the volume, in percent, that brings a clip with an RMS level to the reference.
+///
+/// Returns None for a clip that is close to silent.
+pub fn matched_volume(rms: f32) -> Option<u32> {
+    if rms <= 0.0 {
+        return None;
+    }
+
+    let rms_db = 20.0 * rms.log10();
+    if rms_db < SILENCE_RMS {
+        return None;
+    }
+
+    let gain = (REFERENCE_RMS - rms_db).clamp(-MAX_AUTO_GAIN, MAX_AUTO_GAIN);
+    let volume = 100.0 * 10.0f32.powf(gain / 20.0);
+
+    // the volume keys move in steps of 5
+    Some(((volume / 5.0).round() as u32 * 5).min(MAX_VOLUME))
+}
+
+impl App {
+    // matches the volume of a track to the reference level after a take was committed to it,
+    // or only suggests it with auto gain off
+    pub fn auto_gain(&mut self, index: usize) {
+        let track = &self.audio.tracks[index];
+
+        let Some(volume) = track
+            .clip
+            .as_ref()
+            .and_then(|clip| matched_volume(clip.rms()))
+        else {
+            return;
+        };
+
+        if volume == track.volume {
+            return;
+        }
+
+        if self.auto_gain {
+            self.audio.tracks[index].volume = volume;
+            self.info(format!(
+                "set track {} volume to {} to match the other layers",
+                (index + 1) % 10,
+                volume
+            ));
+        } else {
+            self.info(format!(
+                "set track {} volume to {}?",
+                (index + 1) % 10,
+                volume
+            ));
+        }
+    }
+}
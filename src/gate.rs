@@ -0,0 +1,169 @@
+// how long the gate takes to open and to close, in seconds
+const GATE_ATTACK: f32 = 0.002;
+const GATE_RELEASE: f32 = 0.1;
+
+// how long the envelope takes to fall by about two thirds once the input goes quiet, in seconds
+const ENVELOPE_RELEASE: f32 = 0.02;
+
+/// The threshold the gate starts with, in dB below full scale.
+pub const DEFAULT_GATE_THRESHOLD: i64 = -50;
+
+// the range the threshold can be set in, in dB
+pub const MIN_GATE_THRESHOLD: i64 = -90;
+pub const MAX_GATE_THRESHOLD: i64 = 0;
+
+/// What the noise gate is applied to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GateMode {
+    #[default]
+    Off,
+    /// Only what's recorded is gated, the input is still heard as it is.
+    Record,
+    /// What's heard is gated along with what's recorded.
+    Monitor,
+}
+
+impl GateMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Record => "Recording",
+            Self::Monitor => "Rec + monitor",
+        }
+    }
+
+    pub fn cycled(self) -> Self {
+        match self {
+            Self::Off => Self::Record,
+            Self::Record => Self::Monitor,
+            Self::Monitor => Self::Off,
+        }
+    }
+}
+
+/// Silences the input between phrases, so the hum of an idle instrument doesn't build up
+/// with every layer.
+///
+/// Follows the envelope of the interleaved input, fading in and out instead of cutting.
+pub struct NoiseGate {
+    threshold: f32,
+    envelope: f32,
+    envelope_decay: f32,
+    gain: f32,
+    attack_step: f32,
+    release_step: f32,
+}
+
+impl NoiseGate {
+    /// Creates a closed gate, `samples_per_second` counting every channel.
+    pub fn new(samples_per_second: f32) -> Self {
+        let samples_per_second = samples_per_second.max(1.0);
+
+        Self {
+            threshold: db_to_gain(DEFAULT_GATE_THRESHOLD),
+            envelope: 0.0,
+            envelope_decay: (-1.0 / (samples_per_second * ENVELOPE_RELEASE)).exp(),
+            gain: 0.0,
+            attack_step: 1.0 / (samples_per_second * GATE_ATTACK),
+            release_step: 1.0 / (samples_per_second * GATE_RELEASE),
+        }
+    }
+
+    pub fn set_threshold(&mut self, db: i64) {
+        self.threshold = db_to_gain(db);
+    }
+
+    /// Returns the sample with the gain of the gate applied.
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let level = sample.abs();
+
+        self.envelope = if level > self.envelope {
+            level
+        } else {
+            self.envelope * self.envelope_decay
+        };
+
+        self.gain = if self.envelope > self.threshold {
+            (self.gain + self.attack_step).min(1.0)
+        } else {
+            (self.gain - self.release_step).max(0.0)
+        };
+
+        sample * self.gain
+    }
+
+    /// Returns true once the gate has fully closed.
+    pub fn is_closed(&self) -> bool {
+        self.gain == 0.0
+    }
+}
+
+fn db_to_gain(db: i64) -> f32 {
+    10.0f32.powf(db as f32 / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+
+    const RATE: f32 = 48000.0;
+
+    // runs `frames` frames of a sine at `level` through the gate, returning its gain after each
+    fn run(gate: &mut NoiseGate, level: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|frame| {
+                let sample = (frame as f32 / 48.0 * TAU).sin() * level;
+                gate.process(sample);
+                gate.gain
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gate_stays_shut_under_the_threshold() {
+        let mut gate = NoiseGate::new(RATE);
+        gate.set_threshold(-40);
+
+        // -46 dB of hum never opens it
+        assert!(run(&mut gate, 0.005, 48000).iter().all(|&gain| gain == 0.0));
+        assert_eq!(gate.process(0.005), 0.0);
+        assert!(gate.is_closed());
+    }
+
+    #[test]
+    fn gate_opens_fast_and_releases_slowly() {
+        let mut gate = NoiseGate::new(RATE);
+        gate.set_threshold(-40);
+
+        // open fully within the attack, passing the signal untouched
+        let attack = (RATE * GATE_ATTACK).ceil() as usize;
+        let gains = run(&mut gate, 0.5, 4800);
+        assert!(gains[attack / 2] > 0.0 && gains[attack / 2] < 1.0);
+        assert_eq!(gains[attack + 1], 1.0);
+        assert_eq!(gate.process(0.3), 0.3);
+
+        // falling under the threshold, it stays open while the envelope falls, then fades out
+        // over the release a step at a time
+        let release = (RATE * GATE_RELEASE) as usize;
+        let gains = run(&mut gate, 0.001, 12000);
+        assert_eq!(gains[100], 1.0);
+
+        let steps = gains.windows(2).map(|pair| pair[0] - pair[1]);
+        assert!(steps.fold(0.0, f32::max) <= 1.01 / release as f32);
+
+        // the envelope takes about 80 ms to fall from the sine's peak to the threshold
+        let falling = (RATE * ENVELOPE_RELEASE * (0.5f32 / 0.01).ln()) as usize;
+        let closed = gains.iter().position(|&gain| gain == 0.0).unwrap();
+        assert!(
+            closed.abs_diff(falling + release) < 480,
+            "closed after {}",
+            closed
+        );
+        assert!(gate.is_closed());
+
+        // and shuts out the quiet signal entirely
+        assert_eq!(gate.process(0.001), 0.0);
+    }
+}
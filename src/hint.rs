@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    text::{Span, Spans},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::{
+    app::{App, EditMode, Tab},
+    keymap::Action,
+    macros::MACRO_COUNT,
+};
+
+// how long a log message replaces the hint for
+const CONFIRMATION_TIME: Duration = Duration::from_secs(3);
+
+impl App {
+    // describes what the next input does in the current edit mode
+    pub fn hint(&self) -> String {
+        let key = |action| self.keymap.key_name(action);
+        let adjust = format!("{}/{}", key(Action::RotateUp), key(Action::RotateDown));
+        let cancel = key(Action::Cancel);
+        let apply = key(Action::ApplySettings);
+
+        let setting = |name: &str| {
+            format!(
+                "{}: {} to choose, {} to apply, {} to revert",
+                name, adjust, apply, cancel
+            )
+        };
+
+        match self.edit_mode {
+            EditMode::None => match self.tab {
+                Tab::Play => format!(
+                    "{}/{} select a track, {} record, {} volume, {} mute, digits mute a track",
+                    key(Action::SelectPrevious),
+                    key(Action::SelectNext),
+                    key(Action::RecordSelected),
+                    key(Action::VolumeSelected),
+                    key(Action::MuteSelected),
+                ),
+                Tab::Settings => String::from("press the key shown in a title to edit it"),
+            },
+            EditMode::Host => setting("host"),
+            EditMode::InputDevice => setting("input device"),
+            EditMode::SecondaryInputDevice => setting("second input device"),
+            EditMode::OutputDevice => setting("output device"),
+            EditMode::SampleRate => setting("sample rate"),
+            EditMode::BufferSize => setting("buffer size"),
+            EditMode::Delay => setting("delay"),
+            EditMode::TailFold => setting("tail fold"),
+            EditMode::CueChannel => setting("first cue channel"),
+            EditMode::Bpm => format!(
+                "bpm: {} to change at the {}, {}/{} right away, {} to finish",
+                adjust,
+                self.audio.engine.tempo_timing().name().to_lowercase(),
+                key(Action::FineUp),
+                key(Action::FineDown),
+                cancel
+            ),
+            EditMode::Bars => format!("bars: {} to adjust, {} to finish", adjust, cancel),
+            EditMode::Meter => format!(
+                "meter: {} to change the beats in a bar, the loop keeps its bars from the next loop, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::RecordTrack if self.forward_recording => format!(
+                "record: press a track number to record the next loop into it, {} to cancel",
+                cancel
+            ),
+            EditMode::RecordTrack => format!(
+                "record: press a track number to commit the last loop, {} to cancel",
+                cancel
+            ),
+            EditMode::RemoveTrack(None) => {
+                format!("remove: press a track number, {} to cancel", cancel)
+            }
+            EditMode::RemoveTrack(Some(index)) => format!(
+                "remove: press {} again to delete track {}, {} to cancel",
+                (index + 1) % 10,
+                (index + 1) % 10,
+                cancel
+            ),
+            EditMode::ArchiveTrack => format!(
+                "archive: press a track number to move its clip into the archive, {} to cancel",
+                cancel
+            ),
+            EditMode::TrackVolume(None) => format!(
+                "volume: press a track number, then {} to adjust, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackVolume(Some(index)) => format!(
+                "volume of track {}: {} to adjust, {}/{} to fade over a loop, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                key(Action::FineUp),
+                key(Action::FineDown),
+                cancel
+            ),
+            EditMode::TrackSend(None) => format!(
+                "send: press a track number, then {} to adjust, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackSend(Some(index)) => format!(
+                "delay send of track {}: {} to adjust, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::TrackInput(None) => format!(
+                "input: press a track number, then {} to pick a channel, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackInput(Some(index)) => format!(
+                "input of track {}: {} to pick a channel, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::TrackOffset(None) => format!(
+                "offset: press a track number, then {} to shift it a beat, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackOffset(Some(index)) => format!(
+                "offset of track {}: {} to shift a beat, {}/{} by 10 ms, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                key(Action::FineUp),
+                key(Action::FineDown),
+                cancel
+            ),
+            EditMode::TrackColor(None) => format!(
+                "color: press a track number, then {} to change it, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackColor(Some(index)) => format!(
+                "color of track {}: {} to change it, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::TrackGroup(None) => format!(
+                "group: press a track number, then {} to change it, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackGroup(Some(index)) => format!(
+                "group of track {}: {} to change it, unmuting a track mutes the rest of its group, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::TrackDamping(None) => format!(
+                "damping: press a track number, then {} to adjust, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackDamping(Some(index)) => format!(
+                "damping of track {}: {} to adjust how much older layers are low-passed with every overdub, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::TrackDrive(None) => format!(
+                "drive: press a track number, then {} to adjust, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackDrive(Some(index)) => format!(
+                "drive of track {}: {} to adjust how hard it saturates, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::TrackPattern(None) => format!(
+                "pattern: press a track number, then {} to edit it, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackRegion(None) => format!(
+                "region: press a track number, then {} to move its end, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::TrackRegion(Some(index)) => format!(
+                "region of track {}: {} to move the end a beat, {}/{} the start, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                key(Action::FineUp),
+                key(Action::FineDown),
+                cancel
+            ),
+            EditMode::TrackPattern(Some(index)) => format!(
+                "pattern of track {}: digits or space toggle steps, left/right move, {} to shift it, delete to clear, {} to finish",
+                (index + 1) % 10,
+                adjust,
+                cancel
+            ),
+            EditMode::DelayBeats => {
+                format!("delay time: {} to adjust, {} to finish", adjust, cancel)
+            }
+            EditMode::DelayFeedback => {
+                format!("delay feedback: {} to adjust, {} to finish", adjust, cancel)
+            }
+            EditMode::DelayMix => format!("delay mix: {} to adjust, {} to finish", adjust, cancel),
+            EditMode::LoudnessTarget => format!(
+                "loudness target: {} to adjust by 1 LU, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::SceneFade => format!(
+                "scene crossfade: {} to adjust by a beat, {} to finish",
+                adjust, cancel
+            ),
+            EditMode::FreeBeats => format!(
+                "free loop: {} to change how many beats it spans, {} to keep it",
+                adjust, cancel
+            ),
+            EditMode::Downbeat => format!(
+                "downbeat: {} to move it a bar, {}/{} a beat, {} to finish",
+                adjust,
+                key(Action::FineUp),
+                key(Action::FineDown),
+                cancel
+            ),
+            EditMode::GateThreshold => {
+                format!("gate threshold: {} to adjust, {} to finish", adjust, cancel)
+            }
+            EditMode::MidiLearn(learn) => format!(
+                "midi learn: move a control to map it to {}, {} for the next kind, {} to cancel",
+                learn.target(self.selected_track).name(),
+                key(Action::MidiLearn),
+                cancel
+            ),
+            EditMode::MidiPort => format!("midi port: {} to choose, {} to finish", adjust, cancel),
+            EditMode::Yank => format!("yank: press a track number, {} to cancel", cancel),
+            EditMode::Paste(None) => {
+                format!("paste: press a track number, {} to cancel", cancel)
+            }
+            EditMode::Paste(Some(index)) => format!(
+                "paste: press {} again to replace track {}, {} to cancel",
+                (index + 1) % 10,
+                (index + 1) % 10,
+                cancel
+            ),
+            EditMode::Fade(false) => format!(
+                "fade: press a digit for how many bars, {} again to also clear, {} to cancel",
+                key(Action::FadeOut),
+                cancel
+            ),
+            EditMode::Fade(true) => format!(
+                "fade and clear: press a digit for how many bars, {} to cancel",
+                cancel
+            ),
+            EditMode::Scene(true) => format!(
+                "save scene: press a digit to save the tracks into, {} to switch instead, {} to cancel",
+                key(Action::SwitchScene),
+                cancel
+            ),
+            EditMode::Scene(false) => format!(
+                "switch scene: press a digit to switch to with the next loop, {} to save instead, {} to cancel",
+                key(Action::SaveScene),
+                cancel
+            ),
+            EditMode::ExportMix => format!(
+                "export mix: press a digit for how many times to repeat the loop, the keys in the window to change how, {} to cancel",
+                cancel
+            ),
+            EditMode::ArmLoops(None) => format!(
+                "count out: press a digit for how many loops to record, {} to cancel",
+                cancel
+            ),
+            EditMode::ArmLoops(Some(loops)) => format!(
+                "count out: press a track number to record {} loops into, {} to cancel",
+                loops, cancel
+            ),
+            EditMode::SaveMacro => format!(
+                "store macro: press {} to {} or a digit up to {} to store it in, {} to throw it away",
+                key(Action::Macro(0)),
+                key(Action::Macro(MACRO_COUNT - 1)),
+                MACRO_COUNT,
+                cancel
+            ),
+        }
+    }
+
+    pub fn render_hint<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        // show recent confirmations in place of the hint
+        let spans = match self.log.last() {
+            Some((time, level, message)) if time.elapsed() < CONFIRMATION_TIME => {
+                Spans::from(Span::styled(message.clone(), self.level_style(*level)))
+            }
+            _ => Spans::from(self.hint()),
+        };
+
+        frame.render_widget(Paragraph::new(spans), area);
+    }
+}
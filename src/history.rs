@@ -0,0 +1,197 @@
+use std::time::Instant;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App},
+    clip::Clip,
+    keymap::Action,
+    pedal::LooperPedalState,
+};
+
+/// A change of the clip of a track that can be undone.
+#[derive(Clone)]
+pub struct Layer {
+    pub track: usize,
+    /// The clip the track had before the change.
+    pub clip: Option<Clip>,
+    /// What the change was, like "overdub track 2".
+    pub label: String,
+    pub time: Instant,
+}
+
+impl Layer {
+    pub fn new(track: usize, clip: Option<Clip>, label: String) -> Self {
+        Self {
+            track,
+            clip,
+            label,
+            time: Instant::now(),
+        }
+    }
+}
+
+impl App {
+    pub fn toggle_history(&mut self) {
+        self.show_history = !self.show_history;
+        self.history_confirm = None;
+
+        // start at the latest change
+        let latest = self.layers.len().checked_sub(1);
+        self.history_state.select(latest);
+    }
+
+    // handles a key while the undo history is open
+    pub fn history_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.toggle_history(),
+            KeyCode::Enter => self.restore_selected(),
+            _ => match self.keymap.action(self.tab, key) {
+                Some(Action::RotateUp) => self.scroll_history(1),
+                Some(Action::RotateDown) => self.scroll_history(-1),
+                Some(Action::ToggleHistory) => self.toggle_history(),
+                _ => {}
+            },
+        }
+    }
+
+    fn scroll_history(&mut self, offset: i32) {
+        if self.layers.is_empty() {
+            return;
+        }
+
+        let selected = self.history_state.selected().unwrap_or(0) as i32;
+        let index = (selected - offset).clamp(0, self.layers.len() as i32 - 1);
+        self.history_state.select(Some(index as usize));
+        self.history_confirm = None;
+    }
+
+    // undoes the selected change and everything after it, the first press only asks for
+    // confirmation since the undone changes can't be brought back
+    fn restore_selected(&mut self) {
+        let Some(selected) = self.history_state.selected() else {
+            return;
+        };
+
+        if selected >= self.layers.len() {
+            return;
+        }
+
+        if self.history_confirm != Some(selected) {
+            self.history_confirm = Some(selected);
+            return;
+        }
+
+        let count = self.layers.len() - selected;
+        let label = self.layers[selected].label.clone();
+        self.undo_layers(count);
+
+        self.info(format!(
+            "went back to before {}, undoing {} change{}",
+            label,
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
+
+        self.show_history = false;
+        self.history_confirm = None;
+    }
+
+    // undoes the last `count` changes at once, so they're all heard together on the next beat
+    pub fn undo_layers(&mut self, count: usize) {
+        let keep = self.layers.len().saturating_sub(count);
+
+        for layer in self.layers.split_off(keep).into_iter().rev() {
+            self.end_compare(layer.track);
+            self.audio.tracks[layer.track].clip = layer.clip;
+
+            if self.audio.tracks[layer.track].clip.is_none()
+                && self.pedal == LooperPedalState::Playing(layer.track)
+            {
+                self.pedal = LooperPedalState::Idle;
+            }
+        }
+
+        self.audio.update_tracks();
+    }
+
+    pub fn render_history<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if !self.show_history {
+            return;
+        }
+
+        let size = frame.size();
+        let area = centered_rect(size, size.width / 2, size.height * 3 / 4);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Undo History", Action::ToggleHistory))
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        // the changes that would be undone are dimmed
+        let selected = self.history_state.selected();
+        let items = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(index, layer)| {
+                let seconds = layer.time.duration_since(self.start).as_secs();
+                let style = match selected {
+                    Some(selected) if index > selected => self.theme.empty,
+                    _ => Style::default(),
+                };
+
+                ListItem::new(Spans::from(vec![
+                    Span::styled(
+                        format!("{:02}:{:02} ", seconds / 60, seconds % 60),
+                        self.theme.empty,
+                    ),
+                    Span::styled(layer.label.clone(), style),
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            let empty = Paragraph::new("nothing to undo").style(self.theme.empty);
+            frame.render_widget(empty, rows[0]);
+        } else {
+            let list = List::new(items).highlight_style(self.theme.highlight);
+            frame.render_stateful_widget(list, rows[0], &mut self.history_state);
+        }
+
+        let footer = match (self.history_confirm, selected) {
+            (Some(confirm), Some(selected)) if confirm == selected => {
+                let count = self.layers.len().saturating_sub(selected);
+                let plural = if count == 1 { "" } else { "s" };
+
+                Span::styled(
+                    format!("enter again to undo {} change{}, for good", count, plural),
+                    self.theme.warning,
+                )
+            }
+            _ => Span::styled(
+                "enter to go back to before a change, esc to close",
+                self.theme.empty,
+            ),
+        };
+
+        frame.render_widget(Paragraph::new(Spans::from(footer)), rows[1]);
+    }
+}
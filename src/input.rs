@@ -0,0 +1,158 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event, KeyEvent};
+
+use crate::{app::App, keymap::Action};
+
+// the most terminal events handled in one frame, so a flood of them can't hold off drawing
+const MAX_EVENTS: usize = 256;
+
+/// A terminal event, or a run of rotate keys merged into one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    Event(Event),
+    /// The net offset of consecutive rotate keys.
+    Rotate(i32),
+}
+
+/// Merges every run of consecutive rotate keys into their net offset, leaving everything
+/// else in order.
+///
+/// `rotation` returns the offset a key rotates by, if it's a rotate key at all. Any other
+/// event ends a run, so rotations are never merged across a change of edit mode.
+pub fn coalesce(
+    events: impl IntoIterator<Item = Event>,
+    rotation: impl Fn(&KeyEvent) -> Option<i32>,
+) -> Vec<Input> {
+    let mut inputs = Vec::new();
+
+    for event in events {
+        let offset = match event {
+            Event::Key(ref key) => rotation(key),
+            _ => None,
+        };
+
+        match (offset, inputs.last_mut()) {
+            (Some(offset), Some(Input::Rotate(net))) => *net += offset,
+            (Some(offset), _) => inputs.push(Input::Rotate(offset)),
+            (None, _) => inputs.push(Input::Event(event)),
+        }
+    }
+
+    inputs
+}
+
+impl App {
+    // waits up to `timeout` for terminal events, then handles every one that's pending with
+    // held rotate keys merged, so holding one doesn't queue up work
+    pub fn terminal_events(&mut self, timeout: Duration) -> io::Result<()> {
+        if !event::poll(timeout)? {
+            return Ok(());
+        }
+
+        let mut events = vec![event::read()?];
+        while events.len() < MAX_EVENTS && event::poll(Duration::ZERO)? {
+            events.push(event::read()?);
+        }
+
+        // a prompt or popup takes keys one at a time
+        let modal = !self.warnings.is_empty()
+            || self.confirm_quit
+            || self.setup.is_some()
+            || self.template.is_some();
+
+        let inputs = coalesce(events, |key| match self.keymap.action(self.tab, *key) {
+            _ if modal => None,
+            Some(Action::RotateUp) => Some(1),
+            Some(Action::RotateDown) => Some(-1),
+            _ => None,
+        });
+
+        for input in inputs {
+            match input {
+                Input::Event(event) => self.event(event),
+                Input::Rotate(offset) => self.rotate_keys(offset),
+            }
+        }
+
+        Ok(())
+    }
+
+    // does what `offset` presses of the rotate keys would, in a single rotation
+    fn rotate_keys(&mut self, offset: i32) {
+        let action = if offset > 0 {
+            Action::RotateUp
+        } else {
+            Action::RotateDown
+        };
+
+        for _ in 0..offset.abs() {
+            self.record_macro_action(action);
+        }
+
+        self.last_key = Instant::now();
+        self.redraw = true;
+
+        if offset != 0 {
+            self.rotate_key(offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    fn key(code: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(code), KeyModifiers::NONE))
+    }
+
+    // 'k' rotates by one and 'j' back by one, '.' stands for an event that isn't a key
+    fn coalesce_keys(keys: &str) -> Vec<Input> {
+        let events = keys.chars().map(|code| match code {
+            '.' => Event::FocusGained,
+            code => key(code),
+        });
+
+        coalesce(events, |key| match key.code {
+            KeyCode::Char('k') => Some(1),
+            KeyCode::Char('j') => Some(-1),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn runs_of_rotate_keys_merge() {
+        assert_eq!(coalesce_keys(""), []);
+        assert_eq!(coalesce_keys("kkkjk"), [Input::Rotate(3)]);
+
+        // a run can cancel out, which is still one rotation
+        assert_eq!(coalesce_keys("kj"), [Input::Rotate(0)]);
+    }
+
+    #[test]
+    fn other_events_end_a_run_and_keep_their_order() {
+        assert_eq!(
+            coalesce_keys("kkvjj3k.k"),
+            [
+                Input::Rotate(2),
+                Input::Event(key('v')),
+                Input::Rotate(-2),
+                Input::Event(key('3')),
+                Input::Rotate(1),
+                Input::Event(Event::FocusGained),
+                Input::Rotate(1),
+            ]
+        );
+
+        assert_eq!(
+            coalesce_keys("vv"),
+            [Input::Event(key('v')), Input::Event(key('v'))]
+        );
+    }
+}
@@ -0,0 +1,1024 @@
+use std::{fmt, fs};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    app::Tab,
+    config::{self, parse_value, strip_comment},
+    macros::MACRO_COUNT,
+};
+
+const KEYMAP_FILE: &str = "keymap.toml";
+
+/// Where an action can be triggered from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Context {
+    Global,
+    Play,
+    Settings,
+    /// Only while the export window is open, taking precedence over the play tab.
+    Export,
+}
+
+impl Context {
+    pub fn overlaps(self, other: Context) -> bool {
+        self == Context::Global || other == Context::Global || self == other
+    }
+
+    pub fn is_active(self, tab: Tab) -> bool {
+        match self {
+            Context::Global => true,
+            Context::Play => tab == Tab::Play,
+            Context::Settings => tab == Tab::Settings,
+            Context::Export => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    LaunchStreams,
+    ToggleLog,
+    Kill,
+    ToggleDebug,
+    SaveSession,
+    SaveTemplate,
+    LoadTemplate,
+    Cancel,
+    NextTab,
+    RotateUp,
+    RotateDown,
+    FineUp,
+    FineDown,
+    EditBpm,
+    /// Edits how many bars the loop spans, in the current meter.
+    EditBars,
+    /// Edits how many beats make up a bar, the loop keeping its bars from the next loop on.
+    EditMeter,
+    /// Types the length of the loop in seconds, fitting the tempo or the beats to it.
+    EditLoopDuration,
+    RecordTrack,
+    RemoveTrack,
+    /// Moves the clip of a track into the archive instead of deleting it.
+    ArchiveTrack,
+    TrackVolume,
+    TrackSend,
+    TrackInput,
+    TrackOffset,
+    EditDelayBeats,
+    EditDelayFeedback,
+    EditDelayMix,
+    /// Edits the loudness the mix is aimed at, marked on the loudness meter.
+    EditLoudnessTarget,
+    /// Edits how many beats a scene switch crossfades over, none switching at once.
+    EditSceneFade,
+    /// Cycles when the metronome clicks: always, while recording, in the first bar or never.
+    ToggleMetronome,
+    ToggleZoom,
+    /// Cycles between the full, tracks focus and minimal layouts of the play tab.
+    CycleLayout,
+    Track(usize),
+    SelectPrevious,
+    SelectNext,
+    MuteSelected,
+    SyncSelected,
+    /// Plays the selected track backwards, or forwards again.
+    ReverseSelected,
+    CueSelected,
+    VolumeSelected,
+    SendSelected,
+    InputSelected,
+    OffsetSelected,
+    ColorSelected,
+    /// Puts the selected track in an exclusive group, where unmuting it mutes the others.
+    GroupSelected,
+    /// Adjusts how much older layers of the selected track are damped with every overdub.
+    DampingSelected,
+    /// Adjusts how hard the selected track is driven into saturation.
+    DriveSelected,
+    /// Edits the steps of the selected track's pattern, which silences it on the steps that
+    /// are off.
+    PatternSelected,
+    /// Moves the edges of the part of the selected track's clip that's heard.
+    RegionSelected,
+    RecordSelected,
+    RemoveSelected,
+    Yank,
+    Paste,
+    ExportClipboard,
+    /// Asks for how many times to repeat the loop in an exported mix.
+    ExportMix,
+    /// Switches the export between float and 16-bit files.
+    CycleExportFormat,
+    ToggleExportNormalize,
+    ToggleExportDither,
+    /// Writes a file for every track along with the exported mix.
+    ToggleExportStems,
+    /// Arms a track to record a number of loops in a row.
+    ArmLoops,
+    /// Arms the lowest track without a clip.
+    ArmEmpty,
+    /// Keeps the last loop length of input on the selected track, though nothing was armed.
+    CaptureTake,
+    /// Loads a folder of wavs onto the empty tracks.
+    ImportKit,
+    /// Opens the title and notes of the session and the note of the selected track.
+    EditNotes,
+    MidiLearn,
+    Pedal,
+    UndoLayer,
+    /// Opens the list of changes that can be undone, to go back more than one at a time.
+    ToggleHistory,
+    /// Opens the archived clips, to audition, restore or delete them.
+    ToggleArchive,
+    /// Flips the selected track between its last layer and the clip before it.
+    ToggleCompare,
+    /// Keeps the version of the compared track that is heard.
+    KeepCompared,
+    ToggleStutter,
+    CycleStutterLength,
+    ToggleReplace,
+    FadeOut,
+    ToggleMeterMode,
+    CycleGate,
+    /// Cycles how much of the armed track's settings the monitored input goes through.
+    CycleInputFollow,
+    CycleStack,
+    EditGateThreshold,
+    ShiftDownbeat,
+    FreeRecord,
+    SaveScene,
+    SwitchScene,
+    UndoScene,
+    TogglePreview,
+    TogglePreviewDim,
+    CycleAnalysis,
+    ResampleTracks,
+    HalveLoop,
+    DoubleLoop,
+    RecordMacro,
+    Macro(usize),
+    EditHost,
+    EditInputDevice,
+    /// Edits the second input device recorded along with the first, if any.
+    EditSecondaryInputDevice,
+    EditOutputDevice,
+    EditSampleRate,
+    EditBufferSize,
+    EditDelay,
+    EditTailFold,
+    /// Edits which output channel the cue bus starts on.
+    EditCueChannel,
+    EditMidiPort,
+    ToggleMono,
+    CycleTheme,
+    CycleFrameRate,
+    ToggleForwardRecording,
+    ToggleGuard,
+    ToggleAutoGain,
+    ToggleSuspendMute,
+    /// Switches tempo changes between landing on the next loop and the next beat.
+    ToggleTempoTiming,
+    /// Records the input before the gate instead of after it.
+    ToggleDryRecording,
+    /// Lists what the selected devices support.
+    ToggleProbe,
+    Suspend,
+    /// Locks everything but the actions safe to use mid-performance, or unlocks.
+    ToggleLock,
+    ApplySettings,
+}
+
+const ACTIONS: &[(Action, &str)] = &[
+    (Action::Quit, "quit"),
+    (Action::LaunchStreams, "launch_streams"),
+    (Action::ToggleLog, "toggle_log"),
+    (Action::Kill, "kill"),
+    (Action::ToggleDebug, "toggle_debug"),
+    (Action::SaveSession, "save_session"),
+    (Action::SaveTemplate, "save_template"),
+    (Action::LoadTemplate, "load_template"),
+    (Action::Cancel, "cancel"),
+    (Action::NextTab, "next_tab"),
+    (Action::RotateUp, "rotate_up"),
+    (Action::RotateDown, "rotate_down"),
+    (Action::FineUp, "fine_up"),
+    (Action::FineDown, "fine_down"),
+    (Action::EditBpm, "edit_bpm"),
+    (Action::EditBars, "edit_bars"),
+    // the name from before the loop was counted in bars, so keymaps written with it still work
+    (Action::EditBars, "edit_beats"),
+    (Action::EditMeter, "edit_meter"),
+    (Action::EditLoopDuration, "edit_loop_duration"),
+    (Action::RecordTrack, "record_track"),
+    (Action::RemoveTrack, "remove_track"),
+    (Action::ArchiveTrack, "archive_track"),
+    (Action::TrackVolume, "track_volume"),
+    (Action::TrackSend, "track_send"),
+    (Action::TrackInput, "track_input"),
+    (Action::TrackOffset, "track_offset"),
+    (Action::EditDelayBeats, "edit_delay_beats"),
+    (Action::EditDelayFeedback, "edit_delay_feedback"),
+    (Action::EditDelayMix, "edit_delay_mix"),
+    (Action::EditLoudnessTarget, "edit_loudness_target"),
+    (Action::EditSceneFade, "edit_scene_fade"),
+    (Action::ToggleMetronome, "toggle_metronome"),
+    (Action::ToggleZoom, "toggle_zoom"),
+    (Action::CycleLayout, "cycle_layout"),
+    (Action::SelectPrevious, "select_previous"),
+    (Action::SelectNext, "select_next"),
+    (Action::MuteSelected, "mute_selected"),
+    (Action::SyncSelected, "sync_selected"),
+    (Action::ReverseSelected, "reverse_selected"),
+    (Action::CueSelected, "cue_selected"),
+    (Action::VolumeSelected, "volume_selected"),
+    (Action::SendSelected, "send_selected"),
+    (Action::InputSelected, "input_selected"),
+    (Action::OffsetSelected, "offset_selected"),
+    (Action::ColorSelected, "color_selected"),
+    (Action::GroupSelected, "group_selected"),
+    (Action::DampingSelected, "damping_selected"),
+    (Action::DriveSelected, "drive_selected"),
+    (Action::PatternSelected, "pattern_selected"),
+    (Action::RegionSelected, "region_selected"),
+    (Action::RecordSelected, "record_selected"),
+    (Action::RemoveSelected, "remove_selected"),
+    (Action::Yank, "yank"),
+    (Action::Paste, "paste"),
+    (Action::ExportClipboard, "export_clipboard"),
+    (Action::ExportMix, "export_mix"),
+    (Action::CycleExportFormat, "cycle_export_format"),
+    (Action::ToggleExportNormalize, "toggle_export_normalize"),
+    (Action::ToggleExportDither, "toggle_export_dither"),
+    (Action::ToggleExportStems, "toggle_export_stems"),
+    (Action::ArmLoops, "arm_loops"),
+    (Action::ArmEmpty, "arm_empty"),
+    (Action::CaptureTake, "capture_take"),
+    (Action::ImportKit, "import_kit"),
+    (Action::EditNotes, "edit_notes"),
+    (Action::MidiLearn, "midi_learn"),
+    (Action::Pedal, "pedal"),
+    (Action::UndoLayer, "undo_layer"),
+    (Action::ToggleHistory, "toggle_history"),
+    (Action::ToggleArchive, "toggle_archive"),
+    (Action::ToggleCompare, "toggle_compare"),
+    (Action::KeepCompared, "keep_compared"),
+    (Action::ToggleStutter, "toggle_stutter"),
+    (Action::CycleStutterLength, "cycle_stutter_length"),
+    (Action::ToggleReplace, "toggle_replace"),
+    (Action::FadeOut, "fade_out"),
+    (Action::ToggleMeterMode, "toggle_meter_mode"),
+    (Action::CycleGate, "cycle_gate"),
+    (Action::CycleInputFollow, "cycle_input_follow"),
+    (Action::CycleStack, "cycle_stack"),
+    (Action::EditGateThreshold, "edit_gate_threshold"),
+    (Action::ShiftDownbeat, "shift_downbeat"),
+    (Action::FreeRecord, "free_record"),
+    (Action::SaveScene, "save_scene"),
+    (Action::SwitchScene, "switch_scene"),
+    (Action::UndoScene, "undo_scene"),
+    (Action::TogglePreview, "toggle_preview"),
+    (Action::TogglePreviewDim, "toggle_preview_dim"),
+    (Action::CycleAnalysis, "cycle_analysis"),
+    (Action::ResampleTracks, "resample_tracks"),
+    (Action::HalveLoop, "halve_loop"),
+    (Action::DoubleLoop, "double_loop"),
+    (Action::RecordMacro, "record_macro"),
+    (Action::EditHost, "edit_host"),
+    (Action::EditInputDevice, "edit_input_device"),
+    (
+        Action::EditSecondaryInputDevice,
+        "edit_secondary_input_device",
+    ),
+    (Action::EditOutputDevice, "edit_output_device"),
+    (Action::EditSampleRate, "edit_sample_rate"),
+    (Action::EditBufferSize, "edit_buffer_size"),
+    (Action::EditDelay, "edit_delay"),
+    (Action::EditTailFold, "edit_tail_fold"),
+    (Action::EditCueChannel, "edit_cue_channel"),
+    (Action::EditMidiPort, "edit_midi_port"),
+    (Action::ToggleMono, "toggle_mono"),
+    (Action::CycleTheme, "cycle_theme"),
+    (Action::CycleFrameRate, "cycle_frame_rate"),
+    (Action::ToggleForwardRecording, "toggle_forward_recording"),
+    (Action::ToggleGuard, "toggle_guard"),
+    (Action::ToggleAutoGain, "toggle_auto_gain"),
+    (Action::ToggleSuspendMute, "toggle_suspend_mute"),
+    (Action::ToggleTempoTiming, "toggle_tempo_timing"),
+    (Action::ToggleDryRecording, "toggle_dry_recording"),
+    (Action::ToggleProbe, "toggle_probe"),
+    (Action::Suspend, "suspend"),
+    (Action::ToggleLock, "toggle_lock"),
+    (Action::ApplySettings, "apply_settings"),
+];
+
+// the number of tracks that can be addressed with a binding
+const TRACK_ACTIONS: usize = 10;
+
+impl Action {
+    pub fn name(self) -> String {
+        if let Action::Track(index) = self {
+            return format!("track_{}", index + 1);
+        }
+
+        if let Action::Macro(index) = self {
+            return format!("macro_{}", index + 1);
+        }
+
+        let (_, name) = ACTIONS.iter().find(|(action, _)| *action == self).unwrap();
+        name.to_string()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(number) = name.strip_prefix("track_") {
+            let number = number.parse::<usize>().ok()?;
+
+            return if (1..=TRACK_ACTIONS).contains(&number) {
+                Some(Action::Track(number - 1))
+            } else {
+                None
+            };
+        }
+
+        if let Some(number) = name.strip_prefix("macro_") {
+            let number = number.parse::<usize>().ok()?;
+
+            return if (1..=MACRO_COUNT).contains(&number) {
+                Some(Action::Macro(number - 1))
+            } else {
+                None
+            };
+        }
+
+        let (action, _) = ACTIONS.iter().find(|(_, n)| *n == name)?;
+        Some(*action)
+    }
+
+    pub fn context(self) -> Context {
+        match self {
+            Action::Quit
+            | Action::LaunchStreams
+            | Action::ToggleLog
+            | Action::Kill
+            | Action::ToggleDebug
+            | Action::SaveSession
+            | Action::SaveTemplate
+            | Action::LoadTemplate
+            | Action::Cancel
+            | Action::NextTab
+            | Action::RotateUp
+            | Action::RotateDown
+            | Action::FineUp
+            | Action::FineDown
+            | Action::Suspend
+            | Action::ToggleLock
+            | Action::Macro(_) => Context::Global,
+            Action::EditBpm
+            | Action::EditBars
+            | Action::EditMeter
+            | Action::EditLoopDuration
+            | Action::RecordTrack
+            | Action::RemoveTrack
+            | Action::ArchiveTrack
+            | Action::TrackVolume
+            | Action::TrackSend
+            | Action::TrackInput
+            | Action::TrackOffset
+            | Action::EditDelayBeats
+            | Action::EditDelayFeedback
+            | Action::EditDelayMix
+            | Action::EditLoudnessTarget
+            | Action::EditSceneFade
+            | Action::ToggleMetronome
+            | Action::ToggleZoom
+            | Action::CycleLayout
+            | Action::Track(_)
+            | Action::SelectPrevious
+            | Action::SelectNext
+            | Action::MuteSelected
+            | Action::SyncSelected
+            | Action::ReverseSelected
+            | Action::CueSelected
+            | Action::VolumeSelected
+            | Action::SendSelected
+            | Action::InputSelected
+            | Action::OffsetSelected
+            | Action::ColorSelected
+            | Action::GroupSelected
+            | Action::DampingSelected
+            | Action::DriveSelected
+            | Action::PatternSelected
+            | Action::RegionSelected
+            | Action::RecordSelected
+            | Action::RemoveSelected
+            | Action::Yank
+            | Action::Paste
+            | Action::ExportClipboard
+            | Action::ExportMix
+            | Action::ArmLoops
+            | Action::ArmEmpty
+            | Action::CaptureTake
+            | Action::ImportKit
+            | Action::EditNotes
+            | Action::MidiLearn
+            | Action::Pedal
+            | Action::UndoLayer
+            | Action::ToggleHistory
+            | Action::ToggleArchive
+            | Action::ToggleCompare
+            | Action::KeepCompared
+            | Action::ToggleStutter
+            | Action::CycleStutterLength
+            | Action::ToggleReplace
+            | Action::FadeOut
+            | Action::ToggleMeterMode
+            | Action::CycleGate
+            | Action::CycleInputFollow
+            | Action::CycleStack
+            | Action::EditGateThreshold
+            | Action::ShiftDownbeat
+            | Action::FreeRecord
+            | Action::SaveScene
+            | Action::SwitchScene
+            | Action::UndoScene
+            | Action::TogglePreview
+            | Action::TogglePreviewDim
+            | Action::CycleAnalysis
+            | Action::ResampleTracks
+            | Action::HalveLoop
+            | Action::DoubleLoop
+            | Action::RecordMacro => Context::Play,
+            Action::EditHost
+            | Action::EditInputDevice
+            | Action::EditSecondaryInputDevice
+            | Action::EditOutputDevice
+            | Action::EditSampleRate
+            | Action::EditBufferSize
+            | Action::EditDelay
+            | Action::EditTailFold
+            | Action::EditCueChannel
+            | Action::EditMidiPort
+            | Action::ToggleMono
+            | Action::CycleTheme
+            | Action::CycleFrameRate
+            | Action::ToggleForwardRecording
+            | Action::ToggleGuard
+            | Action::ToggleAutoGain
+            | Action::ToggleSuspendMute
+            | Action::ToggleTempoTiming
+            | Action::ToggleDryRecording
+            | Action::ToggleProbe
+            | Action::ApplySettings => Context::Settings,
+            Action::CycleExportFormat
+            | Action::ToggleExportNormalize
+            | Action::ToggleExportDither
+            | Action::ToggleExportStems => Context::Export,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+
+        // the case of a character already encodes shift, as does back tab
+        if let KeyCode::Char(_) | KeyCode::BackTab = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+
+        Self { code, modifiers }
+    }
+
+    pub fn key(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    pub fn char(c: char) -> Self {
+        Self::key(KeyCode::Char(c))
+    }
+
+    /// Parses a binding like `r`, `F5`, `ctrl+k` or `shift+tab`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (modifier_names, key) = match text.rsplit_once('+') {
+            Some((modifiers, key)) if !key.is_empty() => (modifiers, key),
+            _ => ("", text),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            match name.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                _ => return None,
+            }
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                // terminals report shift+tab as a key of its own
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" | "return" => KeyCode::Enter,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                name => {
+                    let number = name.strip_prefix('f')?.parse::<u8>().ok()?;
+                    KeyCode::F(number)
+                }
+            },
+        };
+
+        Some(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+pub struct Keymap {
+    pub bindings: Vec<(Action, KeyBinding)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut bindings = vec![
+            (Action::Quit, KeyBinding::char('q')),
+            (
+                Action::Quit,
+                KeyBinding::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            ),
+            (Action::LaunchStreams, KeyBinding::key(KeyCode::F(5))),
+            (Action::ToggleLog, KeyBinding::char('l')),
+            (Action::Kill, KeyBinding::char('!')),
+            (
+                Action::Kill,
+                KeyBinding::new(KeyCode::Char('k'), KeyModifiers::CONTROL),
+            ),
+            (Action::ToggleDebug, KeyBinding::key(KeyCode::F(12))),
+            (
+                Action::SaveSession,
+                KeyBinding::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+            ),
+            (
+                Action::SaveTemplate,
+                KeyBinding::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+            ),
+            (
+                Action::LoadTemplate,
+                KeyBinding::new(KeyCode::Char('n'), KeyModifiers::CONTROL),
+            ),
+            // raw mode turns ctrl+z into a key instead of a signal
+            (
+                Action::Suspend,
+                KeyBinding::new(KeyCode::Char('z'), KeyModifiers::CONTROL),
+            ),
+            (
+                Action::ToggleLock,
+                KeyBinding::new(KeyCode::Char('l'), KeyModifiers::CONTROL),
+            ),
+            (Action::Cancel, KeyBinding::key(KeyCode::Esc)),
+            (Action::NextTab, KeyBinding::key(KeyCode::Tab)),
+            (Action::RotateUp, KeyBinding::key(KeyCode::Up)),
+            (Action::RotateUp, KeyBinding::char('j')),
+            (Action::RotateDown, KeyBinding::key(KeyCode::Down)),
+            (Action::RotateDown, KeyBinding::char('k')),
+            (
+                Action::FineUp,
+                KeyBinding::new(KeyCode::Up, KeyModifiers::SHIFT),
+            ),
+            (
+                Action::FineDown,
+                KeyBinding::new(KeyCode::Down, KeyModifiers::SHIFT),
+            ),
+            (Action::EditBpm, KeyBinding::char('b')),
+            (Action::EditBars, KeyBinding::char('B')),
+            (Action::EditMeter, KeyBinding::char('%')),
+            (Action::EditLoopDuration, KeyBinding::char(':')),
+            (
+                Action::RecordTrack,
+                KeyBinding::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+            ),
+            (Action::RemoveTrack, KeyBinding::char('R')),
+            (
+                Action::ArchiveTrack,
+                KeyBinding::new(KeyCode::Char('R'), KeyModifiers::ALT),
+            ),
+            (Action::TrackVolume, KeyBinding::char('V')),
+            (Action::TrackSend, KeyBinding::char('S')),
+            (Action::TrackInput, KeyBinding::char('I')),
+            (Action::TrackOffset, KeyBinding::char('O')),
+            (Action::EditDelayBeats, KeyBinding::char('d')),
+            (Action::EditDelayFeedback, KeyBinding::char('D')),
+            (Action::EditDelayMix, KeyBinding::char('X')),
+            (Action::EditLoudnessTarget, KeyBinding::char('^')),
+            (Action::EditSceneFade, KeyBinding::char('&')),
+            (Action::ToggleMetronome, KeyBinding::char('M')),
+            (Action::ToggleZoom, KeyBinding::char('z')),
+            (Action::ToggleZoom, KeyBinding::key(KeyCode::F(11))),
+            (Action::CycleLayout, KeyBinding::key(KeyCode::F(2))),
+            (Action::SelectPrevious, KeyBinding::key(KeyCode::Left)),
+            (Action::SelectNext, KeyBinding::key(KeyCode::Right)),
+            (Action::MuteSelected, KeyBinding::char('m')),
+            (Action::SyncSelected, KeyBinding::char('~')),
+            (Action::ReverseSelected, KeyBinding::char('<')),
+            (Action::CueSelected, KeyBinding::char('Q')),
+            // the selection gets the plain keys, the mode keys followed by a digit the shifted
+            // and control variants
+            (Action::VolumeSelected, KeyBinding::char('v')),
+            (Action::SendSelected, KeyBinding::char('s')),
+            (Action::InputSelected, KeyBinding::char('i')),
+            (Action::OffsetSelected, KeyBinding::char('o')),
+            (Action::ColorSelected, KeyBinding::char('#')),
+            (Action::GroupSelected, KeyBinding::char('n')),
+            (Action::RecordSelected, KeyBinding::char('r')),
+            (Action::RemoveSelected, KeyBinding::key(KeyCode::Delete)),
+            (Action::MidiLearn, KeyBinding::char('L')),
+            // footswitches usually send space or enter
+            (Action::Pedal, KeyBinding::char(' ')),
+            (Action::Pedal, KeyBinding::key(KeyCode::Enter)),
+            (Action::UndoLayer, KeyBinding::char('u')),
+            (Action::UndoLayer, KeyBinding::key(KeyCode::Backspace)),
+            (
+                Action::ToggleHistory,
+                KeyBinding::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+            ),
+            (
+                Action::ToggleArchive,
+                KeyBinding::new(KeyCode::Char('a'), KeyModifiers::CONTROL),
+            ),
+            (Action::ToggleCompare, KeyBinding::char('h')),
+            (Action::KeepCompared, KeyBinding::char('p')),
+            (Action::ToggleStutter, KeyBinding::char('t')),
+            (Action::CycleStutterLength, KeyBinding::char('T')),
+            (Action::ToggleReplace, KeyBinding::char('e')),
+            (Action::FadeOut, KeyBinding::char('F')),
+            (Action::ToggleMeterMode, KeyBinding::char('A')),
+            (Action::CycleGate, KeyBinding::char('N')),
+            (Action::CycleInputFollow, KeyBinding::char('f')),
+            (Action::CycleStack, KeyBinding::char('Y')),
+            (Action::EditGateThreshold, KeyBinding::char('E')),
+            (Action::ShiftDownbeat, KeyBinding::char('W')),
+            (Action::FreeRecord, KeyBinding::char('J')),
+            (Action::SaveScene, KeyBinding::char('C')),
+            (Action::SwitchScene, KeyBinding::char('G')),
+            (Action::UndoScene, KeyBinding::char('U')),
+            (Action::TogglePreview, KeyBinding::char('H')),
+            (Action::TogglePreviewDim, KeyBinding::char('K')),
+            (Action::CycleAnalysis, KeyBinding::char('a')),
+            (Action::ResampleTracks, KeyBinding::char('=')),
+            (Action::HalveLoop, KeyBinding::char('[')),
+            (Action::DoubleLoop, KeyBinding::char(']')),
+            (Action::RecordMacro, KeyBinding::char('Z')),
+            (Action::Yank, KeyBinding::char('y')),
+            (Action::Paste, KeyBinding::char('P')),
+            (
+                Action::ExportClipboard,
+                KeyBinding::new(KeyCode::Char('e'), KeyModifiers::CONTROL),
+            ),
+            (Action::ExportMix, KeyBinding::char('x')),
+            (Action::CycleExportFormat, KeyBinding::char('f')),
+            (Action::ToggleExportNormalize, KeyBinding::char('n')),
+            (Action::ToggleExportDither, KeyBinding::char('d')),
+            (Action::ToggleExportStems, KeyBinding::char('s')),
+            (Action::ArmLoops, KeyBinding::char('c')),
+            (Action::ArmEmpty, KeyBinding::char('g')),
+            (Action::CaptureTake, KeyBinding::char('.')),
+            (Action::ImportKit, KeyBinding::char('/')),
+            (Action::DampingSelected, KeyBinding::char(';')),
+            (Action::DriveSelected, KeyBinding::char('*')),
+            (Action::PatternSelected, KeyBinding::char(',')),
+            (Action::RegionSelected, KeyBinding::char('|')),
+            (Action::EditNotes, KeyBinding::char('w')),
+            (Action::EditHost, KeyBinding::char('h')),
+            (Action::EditInputDevice, KeyBinding::char('i')),
+            (Action::EditSecondaryInputDevice, KeyBinding::char('I')),
+            (Action::EditOutputDevice, KeyBinding::char('o')),
+            (Action::EditSampleRate, KeyBinding::char('r')),
+            (Action::EditBufferSize, KeyBinding::char('b')),
+            (Action::EditDelay, KeyBinding::char('d')),
+            (Action::EditTailFold, KeyBinding::char('c')),
+            (Action::EditCueChannel, KeyBinding::char('u')),
+            (Action::EditMidiPort, KeyBinding::char('p')),
+            (Action::ToggleMono, KeyBinding::char('m')),
+            (Action::CycleTheme, KeyBinding::char('t')),
+            (Action::CycleFrameRate, KeyBinding::char('f')),
+            (Action::ToggleForwardRecording, KeyBinding::char('w')),
+            (Action::ToggleGuard, KeyBinding::char('g')),
+            (Action::ToggleAutoGain, KeyBinding::char('a')),
+            (Action::ToggleSuspendMute, KeyBinding::char('z')),
+            (Action::ToggleTempoTiming, KeyBinding::char('e')),
+            (Action::ToggleDryRecording, KeyBinding::char('y')),
+            (Action::ToggleProbe, KeyBinding::char('D')),
+            (Action::ApplySettings, KeyBinding::key(KeyCode::Enter)),
+        ];
+
+        for i in 0..TRACK_ACTIONS {
+            let digit = char::from_digit((i as u32 + 1) % 10, 10).unwrap();
+            bindings.push((Action::Track(i), KeyBinding::char(digit)));
+        }
+
+        for i in 0..MACRO_COUNT {
+            let key = KeyCode::F(i as u8 + 1);
+            bindings.push((Action::Macro(i), KeyBinding::new(key, KeyModifiers::SHIFT)));
+        }
+
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Loads the keymap from the config dir, falling back to the defaults.
+    ///
+    /// Returns the keymap along with warnings about lines that were ignored.
+    pub fn load() -> (Self, Vec<String>) {
+        let Some(path) = config::config_file(KEYMAP_FILE) else {
+            return (Self::default(), Vec::new());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(source) => Self::parse(&source),
+            Err(_) => (Self::default(), Vec::new()),
+        }
+    }
+
+    /// Parses a keymap file, overriding the default bindings of every action it mentions.
+    ///
+    /// The format is a flat TOML table of `action = "key"` or `action = ["key", ...]`.
+    pub fn parse(source: &str) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut warnings = Vec::new();
+
+        // bindings from the file along with the line they were defined on
+        let mut overrides: Vec<(Action, KeyBinding, usize)> = Vec::new();
+        let mut overridden: Vec<(Action, usize)> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                warnings.push(format!("line {}: expected `action = \"key\"`", number));
+                continue;
+            };
+
+            let name = name.trim();
+            let Some(action) = Action::from_name(name) else {
+                warnings.push(format!("line {}: unknown action `{}`", number, name));
+                continue;
+            };
+
+            if let Some((_, line)) = overridden.iter().find(|(a, _)| *a == action) {
+                warnings.push(format!(
+                    "line {}: `{}` is already bound on line {}",
+                    number, name, line
+                ));
+                continue;
+            }
+
+            let Some(keys) = parse_value(value.trim()) else {
+                warnings.push(format!("line {}: invalid value for `{}`", number, name));
+                continue;
+            };
+
+            overridden.push((action, number));
+
+            for key in keys {
+                let Some(binding) = KeyBinding::parse(&key) else {
+                    warnings.push(format!("line {}: unknown key `{}`", number, key));
+                    continue;
+                };
+
+                let duplicate = overrides.iter().find(|(other, other_binding, _)| {
+                    *other_binding == binding && other.context().overlaps(action.context())
+                });
+
+                if let Some((other, _, line)) = duplicate {
+                    warnings.push(format!(
+                        "line {}: `{}` is already bound to `{}` on line {}",
+                        number,
+                        key,
+                        other.name(),
+                        line
+                    ));
+                    continue;
+                }
+
+                overrides.push((action, binding, number));
+            }
+        }
+
+        // drop the defaults of overridden actions and any default that collides with the file
+        keymap.bindings.retain(|(action, binding)| {
+            if overridden.iter().any(|(a, _)| a == action) {
+                return false;
+            }
+
+            let collision = overrides.iter().find(|(other, other_binding, _)| {
+                other_binding == binding && other.context().overlaps(action.context())
+            });
+
+            if let Some((other, _, line)) = collision {
+                warnings.push(format!(
+                    "line {}: `{}` replaces the default binding of `{}` to `{}`",
+                    line,
+                    other.name(),
+                    action.name(),
+                    binding
+                ));
+                return false;
+            }
+
+            true
+        });
+
+        let overrides = overrides
+            .into_iter()
+            .map(|(action, binding, _)| (action, binding));
+        keymap.bindings.extend(overrides);
+
+        (keymap, warnings)
+    }
+
+    /// Returns the action bound to `key` in the given tab.
+    pub fn action(&self, tab: Tab, key: KeyEvent) -> Option<Action> {
+        let binding = KeyBinding::from(key);
+
+        let actions = || {
+            self.bindings
+                .iter()
+                .filter(move |(action, b)| *b == binding && action.context().is_active(tab))
+                .map(|(action, _)| *action)
+        };
+
+        // prefer tab specific actions over global ones
+        actions()
+            .find(|action| action.context() != Context::Global)
+            .or_else(|| actions().next())
+    }
+
+    /// Returns the action bound to `key` in a context that isn't a tab, like the export window.
+    pub fn context_action(&self, context: Context, key: KeyEvent) -> Option<Action> {
+        let binding = KeyBinding::from(key);
+
+        self.bindings
+            .iter()
+            .find(|(action, b)| *b == binding && action.context() == context)
+            .map(|(action, _)| *action)
+    }
+
+    /// Returns the bindings of an action.
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = &KeyBinding> {
+        self.bindings
+            .iter()
+            .filter(move |(a, _)| *a == action)
+            .map(|(_, binding)| binding)
+    }
+
+    /// Returns the name of the first key bound to an action, for display.
+    pub fn key_name(&self, action: Action) -> String {
+        match self.keys(action).next() {
+            Some(binding) => binding.to_string(),
+            None => String::from("-"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(keymap: &Keymap, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        keymap.action(Tab::Play, KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn plain_keys_act_on_the_selected_track() {
+        let keymap = Keymap::default();
+        let plain = |c| press(&keymap, KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert_eq!(plain('m'), Some(Action::MuteSelected));
+        assert_eq!(plain('v'), Some(Action::VolumeSelected));
+        assert_eq!(plain('r'), Some(Action::RecordSelected));
+        assert_eq!(
+            press(&keymap, KeyCode::Delete, KeyModifiers::NONE),
+            Some(Action::RemoveSelected)
+        );
+
+        // the modes waiting for a digit move to the shifted and control keys
+        assert_eq!(plain('V'), Some(Action::TrackVolume));
+        assert_eq!(
+            press(&keymap, KeyCode::Char('r'), KeyModifiers::CONTROL),
+            Some(Action::RecordTrack)
+        );
+    }
+
+    #[test]
+    fn no_default_bindings_collide() {
+        let keymap = Keymap::default();
+
+        for (index, (action, binding)) in keymap.bindings.iter().enumerate() {
+            let collision = keymap.bindings[index + 1..]
+                .iter()
+                .find(|(other, other_binding)| {
+                    other_binding == binding && other.context().overlaps(action.context())
+                });
+
+            assert_eq!(collision, None, "{} is bound to {}", binding, action.name());
+        }
+    }
+
+    #[test]
+    fn export_toggles_are_only_bound_in_the_export_window() {
+        let keymap = Keymap::default();
+        let key = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE);
+
+        assert_eq!(
+            keymap.context_action(Context::Export, key),
+            Some(Action::ToggleExportStems)
+        );
+        assert_eq!(keymap.action(Tab::Play, key), Some(Action::SendSelected));
+    }
+
+    #[test]
+    fn shift_tab_parses_to_back_tab() {
+        let back_tab = Some(KeyBinding::key(KeyCode::BackTab));
+
+        assert_eq!(KeyBinding::parse("shift+tab"), back_tab);
+        assert_eq!(KeyBinding::parse("Shift+Tab"), back_tab);
+        assert_eq!(KeyBinding::parse("backtab"), back_tab);
+        assert_eq!(
+            KeyBinding::parse("ctrl+shift+tab"),
+            Some(KeyBinding::new(KeyCode::BackTab, KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            KeyBinding::parse("tab"),
+            Some(KeyBinding::key(KeyCode::Tab))
+        );
+
+        // what crossterm sends for the keys
+        let event = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(Some(KeyBinding::from(event)), back_tab);
+        assert_eq!(KeyBinding::from(event).to_string(), "Shift+Tab");
+    }
+}
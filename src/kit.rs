@@ -0,0 +1,215 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App},
+    notes::edit_text,
+    wav,
+};
+
+// the size of the kit window
+const KIT_WIDTH: u16 = 60;
+const KIT_HEIGHT: u16 = 6;
+
+// how long a sample longer than the loop fades out where it's cut off, in seconds
+const KIT_FADE: f32 = 0.01;
+
+// the wav files of a folder, sorted by name
+fn kit_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("wav"))
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+    Ok(files)
+}
+
+// a leading `~` stands for the home directory, like in a shell
+fn expand_home(path: &str) -> PathBuf {
+    let home = env::var_os("HOME");
+
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+impl App {
+    pub fn open_kit_prompt(&mut self) {
+        self.kit_prompt = Some(String::new());
+    }
+
+    // handles a key while the kit window is open
+    pub fn kit_key(&mut self, key: KeyEvent) {
+        let Some(ref mut path) = self.kit_prompt else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.kit_prompt = None,
+            KeyCode::Enter => {
+                let path = path.trim().to_string();
+                if path.is_empty() {
+                    self.warn("type the folder to import the samples of");
+                    return;
+                }
+
+                self.kit_prompt = None;
+                self.import_kit(&expand_home(&path));
+            }
+            _ => {
+                edit_text(path, key);
+            }
+        }
+    }
+
+    // loads the wavs of a folder in name order onto the empty tracks, each fitted to a loop:
+    // a shorter one plays once from the start of the loop, a longer one is cut off
+    pub fn import_kit(&mut self, dir: &Path) {
+        let files = match kit_files(dir) {
+            Ok(files) => files,
+            Err(err) => {
+                self.error(format!("failed to read {}: {}", dir.display(), err));
+                return;
+            }
+        };
+
+        if files.is_empty() {
+            self.warn(format!("no wav files in {}", dir.display()));
+            return;
+        }
+
+        let engine = &self.audio.engine;
+        let (bpm, beats) = (engine.bpm(), engine.beats());
+        if bpm == 0 || beats == 0 {
+            self.warn("the loop has no length to fit the samples to");
+            return;
+        }
+
+        let sample_rate = self.audio.settings.get_sample_rate();
+        let mut loaded = 0;
+        let mut left = files.len();
+
+        for path in &files {
+            let Some(index) = self.audio.tracks.first_empty() else {
+                break;
+            };
+
+            left -= 1;
+
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let mut clip = match wav::load_clip(path) {
+                Ok(clip) => clip,
+                Err(err) => {
+                    self.error(format!("failed to load {}: {}", name, err));
+                    continue;
+                }
+            };
+
+            // the sample might have been made at another sample rate
+            if let Some(sample_rate) = sample_rate {
+                if clip.sample_rate != sample_rate {
+                    clip = clip.resample(sample_rate);
+                }
+            }
+
+            let rate = clip.sample_rate.0 as u64;
+            let frames = beats * 60 * rate / bpm;
+            let cut = clip.frame_count() > frames;
+            let clip = clip.fit_padded(frames, (rate as f32 * KIT_FADE) as u64);
+
+            let track = &mut self.audio.tracks[index];
+            track.clip = Some(clip);
+            track.offset_frames = 0;
+            track.color.get_or_insert(index);
+            track.note = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            self.forget_layers(index);
+
+            let number = (index + 1) % 10;
+            if cut {
+                self.info(format!("{} on track {}, cut off at the loop", name, number));
+            } else {
+                self.info(format!("{} on track {}", name, number));
+            }
+
+            loaded += 1;
+        }
+
+        if left > 0 {
+            self.warn(format!("no empty track left for {} more files", left));
+        }
+
+        if loaded > 0 {
+            self.audio.update_tracks();
+            self.info(format!(
+                "imported {} of {} samples from {}",
+                loaded,
+                files.len(),
+                dir.display()
+            ));
+        }
+    }
+
+    pub fn render_kit<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let Some(ref path) = self.kit_prompt else {
+            return;
+        };
+
+        let area = centered_rect(frame.size(), KIT_WIDTH, KIT_HEIGHT);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Import Kit")
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        // the end of a long path is the part being typed
+        let width = rows[0].width.saturating_sub(1) as usize;
+        let skipped = path.chars().count().saturating_sub(width);
+        let shown = path.chars().skip(skipped).collect::<String>();
+
+        let text = vec![
+            Spans::from("folder of wavs, loaded in name order onto the empty tracks:"),
+            Spans::from(vec![
+                Span::styled(shown, self.theme.active),
+                Span::styled("_", self.theme.empty),
+            ]),
+        ];
+
+        frame.render_widget(Paragraph::new(text), rows[0]);
+
+        let footer = Paragraph::new("enter to import, esc to cancel").style(self.theme.empty);
+        frame.render_widget(footer, rows[1]);
+    }
+}
@@ -0,0 +1,62 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    Frame,
+};
+
+use crate::app::App;
+
+/// How the play tab is laid out, to fit anything from a wide monitor to a small tmux pane.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlayLayout {
+    #[default]
+    Full,
+    /// The settings are hidden and the tracks take the whole height.
+    TracksFocus,
+    /// A single row of track states under the beat.
+    Minimal,
+}
+
+impl PlayLayout {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::TracksFocus => "tracks focus",
+            Self::Minimal => "minimal",
+        }
+    }
+
+    pub fn cycled(self) -> Self {
+        match self {
+            Self::Full => Self::TracksFocus,
+            Self::TracksFocus => Self::Minimal,
+            Self::Minimal => Self::Full,
+        }
+    }
+}
+
+impl App {
+    pub fn cycle_layout(&mut self) {
+        self.layout = self.layout.cycled();
+        self.info(format!("{} layout", self.layout.name()));
+    }
+
+    // the loop progress and beat dots above one row of track states
+    pub fn render_minimal<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .margin(1)
+            .horizontal_margin(2)
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(2),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        self.render_loop_progress(frame, chunks[0]);
+        self.render_beat_dots(frame, chunks[1]);
+        self.render_track_states(frame, chunks[2]);
+    }
+}
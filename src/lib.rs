@@ -0,0 +1,30 @@
+pub mod aggregate;
+pub mod analysis;
+pub mod audio;
+pub mod clip;
+pub mod config;
+pub mod delay;
+pub mod drift;
+pub mod drive;
+pub mod engine;
+pub mod gate;
+pub mod loudness;
+pub mod meter;
+pub mod pre_roll;
+pub mod processor;
+pub mod queue;
+pub mod replace;
+pub mod stutter;
+pub mod track;
+pub mod wav;
+
+// silences what alsa prints to the terminal while devices are queried
+#[macro_export]
+macro_rules! gag {
+    () => {
+        #[cfg(target_os = "linux")]
+        let _gag_stdout = gag::Gag::stdout();
+        #[cfg(target_os = "linux")]
+        let _gag_stderr = gag::Gag::stderr();
+    };
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    app::{App, EditMode},
+    keymap::Action,
+};
+
+// how long the status bar shows that an action was blocked
+const LOCKED_FLASH: Duration = Duration::from_millis(800);
+
+// whether an action is safe to use mid-performance, nothing that deletes clips or changes
+// the setup
+fn allowed_when_locked(action: Action) -> bool {
+    matches!(
+        action,
+        Action::ToggleLock
+            | Action::Kill
+            | Action::Cancel
+            | Action::NextTab
+            | Action::RotateUp
+            | Action::RotateDown
+            | Action::ToggleLog
+            | Action::ToggleDebug
+            | Action::ToggleZoom
+            | Action::CycleLayout
+            | Action::CycleAnalysis
+            | Action::ToggleMeterMode
+            | Action::Track(_)
+            | Action::SelectPrevious
+            | Action::SelectNext
+            | Action::MuteSelected
+            | Action::CueSelected
+            | Action::SwitchScene
+            | Action::RecordTrack
+            | Action::RecordSelected
+            | Action::ArmLoops
+            | Action::ArmEmpty
+            | Action::FreeRecord
+            | Action::Pedal
+            | Action::ToggleStutter
+            | Action::FadeOut
+            | Action::Macro(_)
+    )
+}
+
+impl App {
+    pub fn toggle_lock(&mut self) {
+        self.locked = !self.locked;
+
+        // an edit started before locking would keep changing things
+        if self.locked {
+            self.edit_mode = EditMode::None;
+            self.info("locked, only performance keys work until unlocked");
+        } else {
+            self.info("unlocked");
+        }
+    }
+
+    // returns true if the lock keeps an action from running, flashing the status bar
+    pub fn is_locked_out(&mut self, action: Action) -> bool {
+        if !self.locked || allowed_when_locked(action) {
+            return false;
+        }
+
+        self.locked_flash = Some(Instant::now());
+        true
+    }
+
+    // whether the status bar shows that an action was just blocked
+    pub fn is_locked_flash(&self) -> bool {
+        self.locked_flash
+            .is_some_and(|time| time.elapsed() < LOCKED_FLASH)
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Instant;
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App},
+    keymap::Action,
+};
+
+// the number of entries kept in the log
+pub const LOG_CAPACITY: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+impl App {
+    pub fn log(&mut self, level: Level, message: impl Into<String>) {
+        self.log.push((Instant::now(), level, message.into()));
+
+        if self.log.len() > LOG_CAPACITY {
+            let overflow = self.log.len() - LOG_CAPACITY;
+            self.log.drain(..overflow);
+        }
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.log(Level::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.log(Level::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.log(Level::Error, message);
+    }
+
+    /// Returns the latest error, unless something else has been logged since.
+    pub fn latest_error(&self) -> Option<&str> {
+        match self.log.last() {
+            Some((_, Level::Error, message)) => Some(message),
+            _ => None,
+        }
+    }
+
+    pub fn toggle_log(&mut self) {
+        self.show_log = !self.show_log;
+
+        // start at the newest entry
+        let newest = self.log.len().checked_sub(1);
+        self.log_state.select(newest);
+    }
+
+    pub fn scroll_log(&mut self, offset: i32) {
+        if self.log.is_empty() {
+            return;
+        }
+
+        let selected = self.log_state.selected().unwrap_or(0) as i32;
+        let index = (selected - offset).clamp(0, self.log.len() as i32 - 1);
+        self.log_state.select(Some(index as usize));
+    }
+
+    pub fn level_style(&self, level: Level) -> Style {
+        match level {
+            Level::Info => Style::default(),
+            Level::Warning => self.theme.warning,
+            Level::Error => self.theme.error,
+        }
+    }
+
+    fn log_spans(&self, time: Instant, level: Level, message: &str) -> Spans<'static> {
+        let seconds = time.duration_since(self.start).as_secs();
+
+        Spans::from(vec![
+            Span::raw(format!("{:02}:{:02} ", seconds / 60, seconds % 60)),
+            Span::styled(format!("{:<5} ", level.name()), self.level_style(level)),
+            Span::raw(message.to_string()),
+        ])
+    }
+
+    pub fn render_status<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        // the title and note of the session take up to half the bar on the right
+        let summary = self.notes.summary();
+        let width = (summary.chars().count() as u16 + 1).min(area.width / 2);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(width)])
+            .split(area);
+
+        if !summary.is_empty() {
+            let paragraph = Paragraph::new(summary)
+                .style(self.theme.empty)
+                .alignment(Alignment::Right);
+            frame.render_widget(paragraph, columns[1]);
+        }
+
+        // a blocked action takes over the bar for a moment
+        if self.is_locked_flash() {
+            let locked = Paragraph::new(format!(
+                "locked, {} to unlock",
+                self.keymap.key_name(Action::ToggleLock)
+            ))
+            .style(self.theme.warning);
+            frame.render_widget(locked, columns[0]);
+            return;
+        }
+
+        let Some((time, level, message)) = self.log.last() else {
+            return;
+        };
+
+        let paragraph = Paragraph::new(self.log_spans(*time, *level, message));
+        frame.render_widget(paragraph, columns[0]);
+    }
+
+    pub fn render_log<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if !self.show_log {
+            return;
+        }
+
+        let size = frame.size();
+        let area = centered_rect(size, size.width * 3 / 4, size.height * 3 / 4);
+
+        let items = self
+            .log
+            .iter()
+            .map(|(time, level, message)| ListItem::new(self.log_spans(*time, *level, message)))
+            .collect::<Vec<_>>();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Log", Action::ToggleLog))
+            .border_style(self.theme.accent);
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(self.theme.highlight);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.log_state);
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::VecDeque,
+    f64::consts::PI,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+// the most channels of the output that are measured, the main mix without the cue bus
+pub const MEASURED_CHANNELS: u16 = 2;
+
+// how long the short-term loudness is measured over, in seconds
+pub const SHORT_TERM_WINDOW: f64 = 3.0;
+
+// anything quieter than this, in LUFS, is silence as far as loudness goes
+pub const LOUDNESS_FLOOR: f64 = -70.0;
+
+// how many samples of the output are kept, enough for the ui to fall behind by a good while
+const SNAPSHOT_LEN: usize = 1 << 16;
+
+// how many blocks the window is measured in, each a tenth of a second
+const WINDOW_BLOCKS: usize = 30;
+
+/// Converts the mean square of the K-weighted channels, summed over them, to LUFS as ITU-R
+/// BS.1770 measures it.
+pub fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// The latest output samples of the measured channels, written by the output callback and
+/// read by the ui thread without locking.
+pub struct OutputSnapshot {
+    samples: Box<[AtomicU32]>,
+    written: AtomicUsize,
+    channels: AtomicU32,
+}
+
+impl Default for OutputSnapshot {
+    fn default() -> Self {
+        Self {
+            samples: (0..SNAPSHOT_LEN).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicUsize::new(0),
+            channels: AtomicU32::new(MEASURED_CHANNELS as u32),
+        }
+    }
+}
+
+impl OutputSnapshot {
+    pub fn set_channels(&self, channels: u16) {
+        let channels = channels.clamp(1, MEASURED_CHANNELS);
+        self.channels.store(channels as u32, Ordering::Release);
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels.load(Ordering::Acquire) as u16
+    }
+
+    pub fn push(&self, sample: f32) {
+        let written = self.written.load(Ordering::Relaxed);
+
+        self.samples[written % SNAPSHOT_LEN].store(sample.to_bits(), Ordering::Relaxed);
+        self.written
+            .store(written.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Appends the samples written since `position` to `samples`, moving `position` past them.
+    pub fn read_since(&self, position: &mut usize, samples: &mut Vec<f32>) {
+        // a reader more than half the snapshot behind skips ahead, so it never reads samples
+        // being overwritten
+        let written = self.written.load(Ordering::Acquire);
+        let behind = written.wrapping_sub(*position).min(SNAPSHOT_LEN / 2);
+
+        // whole frames only, so the channels stay in order after skipping
+        let channels = self.channels() as usize;
+        let behind = behind - behind % channels;
+
+        samples.extend((written.wrapping_sub(behind)..written).map(|i| {
+            let sample = &self.samples[i % SNAPSHOT_LEN];
+            f32::from_bits(sample.load(Ordering::Relaxed))
+        }));
+
+        *position = written;
+    }
+}
+
+// a second order filter in direct form 1
+#[derive(Clone, Copy, Debug, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+
+        y
+    }
+}
+
+/// The K-weighting filter of BS.1770, a high shelf for the head followed by a high pass.
+#[derive(Clone, Copy, Debug)]
+pub struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    // the standard only gives coefficients for 48 kHz, so they're derived from the analog
+    // filters for whatever rate the engine runs at
+    pub fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1) as f64;
+
+        let frequency = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+
+        let k = (PI * frequency / sample_rate).tan();
+        let high = 10f64.powf(gain / 20.0);
+        let band = high.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+
+        let shelf = Biquad {
+            b: [
+                (high + band * k / q + k * k) / a0,
+                2.0 * (k * k - high) / a0,
+                (high - band * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Default::default()
+        };
+
+        let frequency = 38.13547087602444;
+        let q = 0.5003270373238773;
+
+        let k = (PI * frequency / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+
+        let high_pass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Default::default()
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    pub fn process(&mut self, sample: f32) -> f64 {
+        self.high_pass.process(self.shelf.process(sample as f64))
+    }
+}
+
+/// The short-term loudness of interleaved audio, measured in blocks of a tenth of a second.
+#[derive(Clone, Debug)]
+pub struct ShortTermLoudness {
+    sample_rate: u32,
+    filters: Vec<KWeighting>,
+    channel: usize,
+    // the energy of the block being measured, summed over the channels
+    block_energy: f64,
+    block_frames: u64,
+    block_len: u64,
+    blocks: VecDeque<f64>,
+}
+
+impl ShortTermLoudness {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let block_len = (sample_rate as f64 * SHORT_TERM_WINDOW / WINDOW_BLOCKS as f64) as u64;
+
+        Self {
+            sample_rate,
+            filters: vec![KWeighting::new(sample_rate); channels.max(1) as usize],
+            channel: 0,
+            block_energy: 0.0,
+            block_frames: 0,
+            block_len: block_len.max(1),
+            blocks: VecDeque::with_capacity(WINDOW_BLOCKS),
+        }
+    }
+
+    pub fn measures(&self, sample_rate: u32, channels: u16) -> bool {
+        self.sample_rate == sample_rate && self.filters.len() == channels.max(1) as usize
+    }
+
+    pub fn add(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            let weighted = self.filters[self.channel].process(sample);
+            self.block_energy += weighted * weighted;
+
+            self.channel += 1;
+            if self.channel < self.filters.len() {
+                continue;
+            }
+
+            self.channel = 0;
+            self.block_frames += 1;
+
+            if self.block_frames == self.block_len {
+                if self.blocks.len() == WINDOW_BLOCKS {
+                    self.blocks.pop_front();
+                }
+
+                self.blocks.push_back(self.block_energy);
+                self.block_energy = 0.0;
+                self.block_frames = 0;
+            }
+        }
+    }
+
+    // nothing until a whole window was measured, or while it's quieter than the floor
+    pub fn lufs(&self) -> Option<f64> {
+        if self.blocks.len() < WINDOW_BLOCKS {
+            return None;
+        }
+
+        let frames = (WINDOW_BLOCKS as u64 * self.block_len) as f64;
+        let lufs = loudness(self.blocks.iter().sum::<f64>() / frames);
+
+        (lufs > LOUDNESS_FLOOR).then_some(lufs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::*;
+
+    // a sine on every channel, interleaved
+    fn sine(
+        frequency: f64,
+        level: f64,
+        sample_rate: u32,
+        channels: usize,
+        frames: usize,
+    ) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let x = level * (TAU * frequency * i as f64 / sample_rate as f64).sin();
+                vec![x as f32; channels]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn k_weighting_at_1_khz() {
+        // a 1 kHz tone comes out about 0.69 dB louder at any rate, which `loudness` takes off
+        for sample_rate in [44100, 48000, 96000] {
+            let mut filter = KWeighting::new(sample_rate);
+            let frames = sample_rate as usize;
+
+            // measured after the filter settles
+            let energy = sine(1000.0, 1.0, sample_rate, 1, frames)
+                .into_iter()
+                .map(|x| filter.process(x))
+                .skip(frames / 2)
+                .map(|y| y * y)
+                .sum::<f64>();
+
+            let gain = 10.0 * (energy / (frames / 2) as f64 / 0.5).log10();
+            assert!(
+                (gain - 0.69).abs() < 0.05,
+                "{} dB at {} Hz",
+                gain,
+                sample_rate
+            );
+        }
+    }
+
+    #[test]
+    fn short_term_window_of_a_sine() {
+        // 20 dB under full scale on both channels
+        let samples = sine(1000.0, 0.1, 48000, 2, 48000 * 4);
+        let mut meter = ShortTermLoudness::new(48000, 2);
+
+        // nothing is measured until the window is full
+        meter.add(&samples[..48000 * 2 * 2]);
+        assert_eq!(meter.lufs(), None);
+
+        // each channel reads 3 dB under the peak, and the two add up to it again
+        meter.add(&samples[48000 * 2 * 2..]);
+        let lufs = meter.lufs().unwrap();
+        assert!((lufs - -20.0).abs() < 0.1, "{} LUFS", lufs);
+
+        // a single channel of a full scale sine is the calibration level of the standard
+        let mut meter = ShortTermLoudness::new(48000, 1);
+        meter.add(&sine(1000.0, 1.0, 48000, 1, 48000 * 3));
+        let lufs = meter.lufs().unwrap();
+        assert!((lufs - -3.01).abs() < 0.1, "{} LUFS", lufs);
+
+        // and silence is below the floor
+        let mut meter = ShortTermLoudness::new(48000, 1);
+        meter.add(&vec![0.0; 48000 * 3]);
+        assert_eq!(meter.lufs(), None);
+    }
+}
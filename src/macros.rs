@@ -0,0 +1,218 @@
+use std::{fs, io, mem};
+
+use crate::{
+    app::{App, EditMode, Tab},
+    config::{self, parse_value, strip_comment},
+    keymap::{Action, Context},
+    queue::QueuedAction,
+};
+
+const MACRO_FILE: &str = "macros.toml";
+
+/// How many macros can be stored, one for every key from Shift+F1 to Shift+F8.
+pub const MACRO_COUNT: usize = 8;
+
+/// A sequence of actions played back with one key.
+pub type Macro = Vec<Action>;
+
+// everything played in the play tab can be recorded, except the macro keys themselves
+fn is_recordable(action: Action) -> bool {
+    match action {
+        Action::RecordMacro | Action::Macro(_) => false,
+        Action::RotateUp
+        | Action::RotateDown
+        | Action::FineUp
+        | Action::FineDown
+        | Action::Cancel => true,
+        action => action.context() == Context::Play,
+    }
+}
+
+/// Loads the macros from the config dir.
+///
+/// Returns the macros along with warnings about lines that were ignored.
+pub fn load_macros() -> (Vec<Option<Macro>>, Vec<String>) {
+    let mut macros = vec![None; MACRO_COUNT];
+    let mut warnings = Vec::new();
+
+    let Some(source) = config::config_file(MACRO_FILE).and_then(|p| fs::read_to_string(p).ok())
+    else {
+        return (macros, warnings);
+    };
+
+    // a flat TOML table of `macro_1 = ["action", ...]` lines
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let line = strip_comment(line).trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            warnings.push(format!("line {}: expected `macro = [\"action\"]`", number));
+            continue;
+        };
+
+        let name = name.trim();
+        let Some(Action::Macro(slot)) = Action::from_name(name) else {
+            warnings.push(format!("line {}: unknown macro `{}`", number, name));
+            continue;
+        };
+
+        let Some(names) = parse_value(value.trim()) else {
+            warnings.push(format!("line {}: invalid value for `{}`", number, name));
+            continue;
+        };
+
+        let mut actions = Vec::new();
+        for name in names {
+            match Action::from_name(&name).filter(|&action| is_recordable(action)) {
+                Some(action) => actions.push(action),
+                None => warnings.push(format!("line {}: can't play `{}`", number, name)),
+            }
+        }
+
+        macros[slot] = Some(actions);
+    }
+
+    (macros, warnings)
+}
+
+fn save_macros(macros: &[Option<Macro>]) -> io::Result<()> {
+    let Some(dir) = config::config_dir() else {
+        return Ok(());
+    };
+
+    let mut source = String::new();
+
+    for (slot, actions) in macros.iter().enumerate() {
+        let Some(actions) = actions else {
+            continue;
+        };
+
+        let names = actions
+            .iter()
+            .map(|action| format!("\"{}\"", action.name()))
+            .collect::<Vec<_>>();
+
+        let name = Action::Macro(slot).name();
+        source.push_str(&format!("{} = [{}]\n", name, names.join(", ")));
+    }
+
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(MACRO_FILE), source)
+}
+
+impl App {
+    // starts capturing the actions played, or stops and asks where to store them
+    pub fn toggle_macro_recording(&mut self) {
+        let Some(actions) = self.macro_recording.take() else {
+            self.macro_recording = Some(Vec::new());
+            self.recorded_macro = None;
+            self.info("recording a macro");
+            return;
+        };
+
+        if actions.is_empty() {
+            self.warn("the macro is empty, nothing to store");
+            return;
+        }
+
+        self.recorded_macro = Some(actions);
+        self.edit_mode = EditMode::SaveMacro;
+    }
+
+    // notes an action while a macro is being recorded, called for every action
+    pub fn record_macro_action(&mut self, action: Action) {
+        if let Some(ref mut actions) = self.macro_recording {
+            if self.tab == Tab::Play && is_recordable(action) {
+                actions.push(action);
+            }
+        }
+    }
+
+    // stores the recorded macro when waiting for a slot, plays the macro otherwise
+    pub fn macro_key(&mut self, slot: usize) {
+        if self.edit_mode == EditMode::SaveMacro {
+            self.store_macro(slot);
+        } else {
+            self.play_macro(slot);
+        }
+    }
+
+    pub fn store_macro(&mut self, slot: usize) {
+        self.edit_mode = EditMode::None;
+
+        let Some(actions) = self.recorded_macro.take() else {
+            return;
+        };
+
+        if slot >= MACRO_COUNT {
+            self.warn(format!("there are only {} macros", MACRO_COUNT));
+            return;
+        }
+
+        let count = actions.len();
+        self.macros[slot] = Some(actions);
+
+        if let Err(err) = save_macros(&self.macros) {
+            self.error(format!("failed to save macros: {}", err));
+        }
+
+        self.info(format!("stored {} actions as macro {}", count, slot + 1));
+    }
+
+    // plays the actions of a macro, the track changes they make landing together with the
+    // next loop
+    pub fn play_macro(&mut self, slot: usize) {
+        let Some(Some(actions)) = self.macros.get(slot).cloned() else {
+            self.warn(format!("macro {} is empty", slot + 1));
+            return;
+        };
+
+        let running = self.audio.engine.is_recording();
+
+        // a macro played while recording one isn't recorded into it
+        let recording = self.macro_recording.take();
+        let tab = self.tab;
+
+        self.tab = Tab::Play;
+        self.edit_mode = EditMode::None;
+        self.audio.defer_tracks = running;
+
+        for action in actions {
+            self.action(action);
+        }
+
+        self.audio.defer_tracks = false;
+        self.tab = tab;
+        self.edit_mode = EditMode::None;
+        self.macro_recording = recording;
+
+        // without a running loop, or anything changed on the tracks, there's nothing to wait
+        // for
+        if !mem::take(&mut self.audio.tracks_deferred) {
+            self.info(format!("played macro {}", slot + 1));
+            return;
+        }
+
+        let action = QueuedAction::Macro {
+            slot,
+            tracks: self.audio.tracks.clone(),
+        };
+
+        if !self.audio.queue_action(action) {
+            self.warn("too much is queued for the next loop, playing the macro now");
+            self.audio.update_tracks();
+            return;
+        }
+
+        // the queued loop change holds the tracks from before the macro
+        if let Some(beats) = self.audio.pending_beats {
+            self.audio.queue_beats(beats);
+        }
+
+        self.info(format!("macro {} plays with the next loop", slot + 1));
+    }
+}
@@ -1,32 +1,73 @@
 #![allow(dead_code)]
 
-use std::io;
+use std::{
+    env,
+    io::{self, Stdout},
+    panic, process,
+};
 
 use app::App;
-use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use cli::Command;
+use terminal::{restore_terminal, setup_terminal};
 use tui::{backend::CrosstermBackend, Terminal};
 
+// the engine lives in the library, brought in here so the ui reaches it through `crate::`
+use wroom::{
+    analysis, audio, clip, config, delay, engine, gate, loudness, meter, processor, queue, replace,
+    stutter, track, wav,
+};
+
 mod app;
-mod audio;
-mod clip;
+mod archive;
+mod bench;
+mod capture;
+mod cli;
+mod clipboard;
+mod compare;
+mod count;
+mod debug;
 mod device_select;
-mod engine;
+mod downbeat;
+mod duration;
+mod event;
+mod export;
+mod fade;
+mod free;
+mod gain;
+mod hint;
+mod history;
+mod input;
+mod keymap;
+mod kit;
+mod layout;
+mod lock;
+mod log;
+mod macros;
+mod metronome;
+mod midi;
+mod notes;
+#[cfg(feature = "osc")]
+mod osc;
+mod pattern;
+mod pedal;
 mod play;
-mod track;
-
-#[macro_export]
-macro_rules! gag {
-    () => {
-        #[cfg(target_os = "linux")]
-        let _gag_stdout = gag::Gag::stdout();
-        #[cfg(target_os = "linux")]
-        let _gag_stderr = gag::Gag::stderr();
-    };
-}
+mod preview;
+mod probe;
+mod quit;
+mod ramp;
+mod region;
+mod render;
+mod resample;
+mod safe_mode;
+mod scene;
+mod session;
+mod setup;
+mod stack;
+mod suspend;
+mod template;
+mod terminal;
+mod theme;
+mod zoom;
 
 #[cfg(target_os = "linux")]
 unsafe extern "C" fn alsa_handler(
@@ -41,33 +82,146 @@ unsafe extern "C" fn alsa_handler(
 
 #[cfg(target_os = "linux")]
 unsafe fn set_alsa_handler() {
-    let handler = std::mem::transmute(alsa_handler as usize);
+    use std::ffi::{c_char, c_int};
+
+    // the handler ignores the variadic arguments, so it's declared without them
+    type Handler =
+        unsafe extern "C" fn(*const c_char, c_int, *const c_char, c_int, *const c_char, ...);
+    let handler = std::mem::transmute::<*const (), Handler>(alsa_handler as *const ());
 
     alsa_sys::snd_lib_error_set_handler(Some(handler));
 }
 
+// makes sure the terminal is restored before the panic message is printed
+fn install_panic_hook() {
+    chain_panic_hook(restore_terminal);
+}
+
+// runs `before` ahead of the panic hook already installed
+fn chain_panic_hook(before: impl Fn() + Send + Sync + 'static) {
+    let hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        before();
+        hook(info);
+    }));
+}
+
+/// Puts the terminal in raw mode and the alternate screen for as long as it's alive.
+struct TerminalGuard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        setup_terminal()?;
+        let backend = CrosstermBackend::new(io::stdout());
+        let terminal = Terminal::new(backend)?;
+
+        Ok(Self { terminal })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+// used to check that the terminal is restored when panicking
+#[cfg(feature = "panic-test")]
+fn panic_test() {
+    panic!("intentional panic from the `panic-test` feature");
+}
+
 fn main() -> io::Result<()> {
+    let (session, template, safe_mode) = match Command::parse(env::args().skip(1)) {
+        Ok(Command::Run(options)) => (options.session, None, options.safe_mode),
+        Ok(Command::New(template)) => (None, Some(template), false),
+        Ok(Command::Render(options)) => {
+            if let Err(err) = render::render(&options) {
+                eprintln!("error: {:#}", err);
+                process::exit(1);
+            }
+
+            return Ok(());
+        }
+        Ok(Command::Bench(options)) => {
+            bench::bench(&options);
+            return Ok(());
+        }
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
     #[cfg(target_os = "linux")]
     unsafe {
         set_alsa_handler();
     }
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    install_panic_hook();
+
+    // route ctrl+c and termination signals through the quit confirmation
+    let _ = ctrlc::set_handler(quit::interrupt);
+
+    // stop with the terminal restored instead of leaving it in raw mode
+    suspend::register()?;
+
+    let mut guard = TerminalGuard::new()?;
+
+    #[cfg(feature = "panic-test")]
+    panic_test();
+
+    // a start that didn't finish last time falls back to safe mode
+    let crashed = safe_mode::begin_startup();
+
+    let mut app = if safe_mode || crashed {
+        App::safe_mode(crashed)
+    } else {
+        App::new()
+    };
+
+    safe_mode::finish_startup();
+
+    if let Some(path) = session {
+        app.load_session(&path);
+    }
+
+    if let Some(name) = template {
+        app.new_from_template(&name);
+    }
+
+    app.run(&mut guard.terminal)
+}
 
-    let mut app = App::new();
-    let res = app.run(&mut terminal);
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture,
-    )?;
-    terminal.show_cursor()?;
+    use super::*;
 
-    res
+    #[test]
+    fn panicking_restores_the_terminal_before_the_message() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        // stands in for the default hook printing the message
+        let printed = calls.clone();
+        panic::set_hook(Box::new(move |_| printed.lock().unwrap().push("message")));
+
+        let restored = calls.clone();
+        chain_panic_hook(move || restored.lock().unwrap().push("restore"));
+
+        let result = panic::catch_unwind(|| panic!("test"));
+        let _ = panic::take_hook();
+
+        assert!(result.is_err());
+        assert_eq!(*calls.lock().unwrap(), ["restore", "message"]);
+    }
 }
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+// how long a track's meter shows that it clipped, in milliseconds
+pub const CLIP_HOLD: u64 = 2000;
+
+/// The quietest and loudest levels the meters show, in dB.
+pub const METER_FLOOR: f32 = -60.0;
+pub const METER_CEILING: f32 = 6.0;
+
+/// Converts a linear level to dB, silence being the meter floor.
+pub fn level_db(level: f32) -> f32 {
+    if level <= 0.0 {
+        return METER_FLOOR;
+    }
+
+    (20.0 * level.log10()).max(METER_FLOOR)
+}
+
+/// The loudness of a track's contribution over one output buffer, built up by the callback.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LevelAccumulator {
+    pub peak: f32,
+    pub square_sum: f32,
+    pub count: u32,
+}
+
+impl LevelAccumulator {
+    pub fn add(&mut self, sample: f32) {
+        self.peak = self.peak.max(sample.abs());
+        self.square_sum += sample * sample;
+        self.count += 1;
+    }
+
+    /// Returns the root mean square of everything added, 0 if nothing was.
+    pub fn rms(&self) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        (self.square_sum / self.count as f32).sqrt()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The levels of a track the output callback publishes for the ui, which takes them.
+#[derive(Default)]
+pub struct TrackMeter {
+    peak: AtomicU32,
+    square_sum: AtomicU32,
+    count: AtomicU32,
+    clipped_at: AtomicU64,
+}
+
+impl TrackMeter {
+    /// Adds the levels of a buffer, noting the time if the track went over full scale.
+    pub fn publish(&self, levels: &LevelAccumulator, time: u64) {
+        // the bits of non negative floats order the same way as the floats themselves
+        self.peak.fetch_max(levels.peak.to_bits(), Ordering::AcqRel);
+
+        let square_sum = levels.square_sum;
+        let _ = self
+            .square_sum
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+                Some((f32::from_bits(bits) + square_sum).to_bits())
+            });
+        self.count.fetch_add(levels.count, Ordering::AcqRel);
+
+        if levels.peak > 1.0 {
+            // zero means the track never clipped
+            self.clipped_at.store(time.max(1), Ordering::Release);
+        }
+    }
+
+    /// Returns what was published since the last call.
+    pub fn take(&self) -> LevelAccumulator {
+        LevelAccumulator {
+            peak: f32::from_bits(self.peak.swap(0, Ordering::AcqRel)),
+            square_sum: f32::from_bits(self.square_sum.swap(0, Ordering::AcqRel)),
+            count: self.count.swap(0, Ordering::AcqRel),
+        }
+    }
+
+    /// Returns true if the track went over full scale within [`CLIP_HOLD`] of `time`.
+    pub fn is_clip_held(&self, time: u64) -> bool {
+        let clipped_at = self.clipped_at.load(Ordering::Acquire);
+        clipped_at > 0 && time.saturating_sub(clipped_at) < CLIP_HOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{SQRT_2, TAU};
+
+    use super::*;
+
+    #[test]
+    fn rms_of_known_signals() {
+        let accumulate = |samples: &mut dyn Iterator<Item = f32>| {
+            let mut levels = LevelAccumulator::default();
+            samples.for_each(|sample| levels.add(sample));
+            levels
+        };
+
+        assert_eq!(LevelAccumulator::default().rms(), 0.0);
+
+        // a square wave is as loud as its peak
+        let levels = accumulate(&mut (0..1000).map(|i| if i % 2 == 0 { 0.5 } else { -0.5 }));
+        assert_eq!((levels.peak, levels.rms(), levels.count), (0.5, 0.5, 1000));
+
+        // a sine of whole cycles is its peak over the square root of two
+        let levels = accumulate(&mut (0..4800).map(|i| (i as f32 / 48.0 * TAU).sin() * 0.8));
+        assert!((levels.rms() - 0.8 / SQRT_2).abs() < 1e-4);
+        assert!((levels.peak - 0.8).abs() < 1e-4);
+
+        let mut levels = levels;
+        levels.clear();
+        assert_eq!((levels.rms(), levels.count), (0.0, 0));
+    }
+
+    #[test]
+    fn published_levels_add_up_until_taken() {
+        let meter = TrackMeter::default();
+
+        let mut levels = LevelAccumulator::default();
+        (0..100).for_each(|_| levels.add(0.25));
+        meter.publish(&levels, 10);
+
+        levels.clear();
+        (0..100).for_each(|_| levels.add(-0.75));
+        meter.publish(&levels, 20);
+
+        // two buffers of different loudness make up one
+        let taken = meter.take();
+        assert_eq!((taken.peak, taken.count), (0.75, 200));
+        assert!((taken.rms() - ((0.0625 + 0.5625) / 2.0f32).sqrt()).abs() < 1e-6);
+
+        assert_eq!(meter.take().count, 0);
+        assert!(!meter.is_clip_held(20));
+    }
+
+    #[test]
+    fn clip_is_held_for_a_while() {
+        let meter = TrackMeter::default();
+        let mut levels = LevelAccumulator::default();
+        levels.add(1.5);
+        meter.publish(&levels, 1000);
+
+        assert!(meter.is_clip_held(1000));
+        assert!(meter.is_clip_held(1000 + CLIP_HOLD - 1));
+        assert!(!meter.is_clip_held(1000 + CLIP_HOLD));
+    }
+}
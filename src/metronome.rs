@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::Style,
+    text::{Span, Spans},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::{app::App, engine::MetronomePattern, wav};
+
+// how long the border stays lit after a beat
+const FLASH_DURATION: Duration = Duration::from_millis(100);
+
+// the longest a loaded click plays, in seconds
+const MAX_CLICK_LENGTH: f32 = 0.2;
+
+impl App {
+    // loads the click set in the audio settings for the metronome, resampled to the streams,
+    // the built in click plays when there is none or it fails to load
+    pub fn load_click(&mut self) {
+        let Some(ref path) = self.audio.settings.click else {
+            return;
+        };
+
+        let Some(sample_rate) = self.audio.settings.get_sample_rate() else {
+            return;
+        };
+
+        let clip = match wav::load_clip(path) {
+            Ok(clip) => clip,
+            Err(err) => {
+                let message = format!(
+                    "failed to load click {}: {}, using the built in click",
+                    path.display(),
+                    err
+                );
+                self.error(message);
+                return;
+            }
+        };
+
+        // cut it short before resampling, so a long file doesn't take long
+        let frames = (clip.sample_rate.0 as f32 * MAX_CLICK_LENGTH) as u64;
+        let mut clip = clip.fit(frames.min(clip.frame_count()));
+
+        if clip.sample_rate != sample_rate {
+            clip = clip.resample(sample_rate);
+        }
+
+        self.audio.engine.set_metronome_clip(Some(clip));
+        self.info(format!("loaded click {}", path.display()));
+    }
+
+    // starts a flash when the integer beat changes, so it fires once per beat at any frame rate
+    pub fn update_beat_flash(&mut self) {
+        let snapshot = self.audio.engine.snapshot();
+
+        if !snapshot.is_running() {
+            self.last_beat = None;
+            return;
+        }
+
+        let beat = snapshot.beat().floor() as u64;
+
+        if self.last_beat != Some(beat) {
+            // the first frame after launching only starts counting
+            if self.last_beat.is_some() && snapshot.metronome == MetronomePattern::Off {
+                self.beat_flash = Some((Instant::now(), beat == 0));
+                self.redraw = true;
+            }
+
+            self.last_beat = Some(beat);
+        }
+
+        if let Some((start, _)) = self.beat_flash {
+            if start.elapsed() >= FLASH_DURATION {
+                self.beat_flash = None;
+                self.redraw = true;
+            }
+        }
+    }
+
+    // the border style of the play tab while a beat flash is lit
+    pub fn beat_flash_style(&self) -> Option<Style> {
+        match self.beat_flash {
+            Some((_, true)) => Some(self.theme.downbeat),
+            Some((_, false)) => Some(self.theme.beat),
+            None => None,
+        }
+    }
+
+    // a dot for every beat of the loop, filled up to the current one
+    pub fn render_beat_dots<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let snapshot = self.snapshot;
+
+        let beats = snapshot.beats as usize;
+        let current = self.current_beat().floor() as usize;
+
+        // space the dots out when there's room for it
+        let spacing = if beats * 2 <= area.width as usize {
+            " "
+        } else {
+            ""
+        };
+
+        let spans = (0..beats.min(area.width as usize))
+            .map(|beat| {
+                let (dot, style) = if beat == current && snapshot.is_running() {
+                    ('●', self.theme.accent)
+                } else if beat < current {
+                    ('●', self.theme.meter(self.theme.track_color(0)))
+                } else {
+                    ('○', self.theme.empty)
+                };
+
+                Span::styled(format!("{}{}", dot, spacing), style)
+            })
+            .collect::<Vec<_>>();
+
+        frame.render_widget(Paragraph::new(Spans::from(spans)), area);
+    }
+}
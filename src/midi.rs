@@ -0,0 +1,492 @@
+use std::{fmt, fs, io};
+
+use anyhow::anyhow;
+use crossbeam::channel::Sender;
+#[cfg(feature = "midi")]
+use midir::{MidiInput, MidiInputConnection};
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    widgets::{Block, Borders, List, ListItem},
+    Frame,
+};
+
+use crate::{
+    app::{App, EditMode},
+    config::{self, parse_value, strip_comment},
+    event::AppEvent,
+    keymap::Action,
+};
+
+const MIDI_FILE: &str = "midi.toml";
+#[cfg(feature = "midi")]
+const CLIENT_NAME: &str = "wroom";
+
+// the number of tracks that can be addressed with a mapping
+const TRACK_TARGETS: usize = 10;
+
+/// A control on a midi controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiSource {
+    Note { channel: u8, note: u8 },
+    Control { channel: u8, control: u8 },
+}
+
+impl MidiSource {
+    /// Parses `note <channel> <note>` or `cc <channel> <control>`, channels start at 1.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut words = text.split_whitespace();
+        let kind = words.next()?;
+        let channel = words.next()?.parse::<u8>().ok()?.checked_sub(1)?;
+        let number = words.next()?.parse::<u8>().ok()?;
+
+        if words.next().is_some() || channel > 15 || number > 127 {
+            return None;
+        }
+
+        match kind {
+            "note" => Some(MidiSource::Note {
+                channel,
+                note: number,
+            }),
+            "cc" => Some(MidiSource::Control {
+                channel,
+                control: number,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MidiSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiSource::Note { channel, note } => write!(f, "note {} {}", channel + 1, note),
+            MidiSource::Control { channel, control } => {
+                write!(f, "cc {} {}", channel + 1, control)
+            }
+        }
+    }
+}
+
+/// Something in the app a midi control can be mapped to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiTarget {
+    Mute(usize),
+    Record(usize),
+    Volume(usize),
+    MasterVolume,
+}
+
+impl MidiTarget {
+    pub fn name(self) -> String {
+        match self {
+            MidiTarget::Mute(index) => format!("mute_{}", index + 1),
+            MidiTarget::Record(index) => format!("record_{}", index + 1),
+            MidiTarget::Volume(index) => format!("volume_{}", index + 1),
+            MidiTarget::MasterVolume => String::from("master_volume"),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "master_volume" {
+            return Some(MidiTarget::MasterVolume);
+        }
+
+        let (kind, number) = name.rsplit_once('_')?;
+        let number = number.parse::<usize>().ok()?;
+
+        if !(1..=TRACK_TARGETS).contains(&number) {
+            return None;
+        }
+
+        match kind {
+            "mute" => Some(MidiTarget::Mute(number - 1)),
+            "record" => Some(MidiTarget::Record(number - 1)),
+            "volume" => Some(MidiTarget::Volume(number - 1)),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of control the next incoming message is mapped to in learn mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LearnTarget {
+    Mute,
+    Record,
+    Volume,
+    MasterVolume,
+}
+
+impl LearnTarget {
+    pub fn name(self) -> &'static str {
+        match self {
+            LearnTarget::Mute => "mute",
+            LearnTarget::Record => "record",
+            LearnTarget::Volume => "volume",
+            LearnTarget::MasterVolume => "master volume",
+        }
+    }
+
+    /// Returns the next kind of control, or `None` after the last one.
+    pub fn next(self) -> Option<Self> {
+        match self {
+            LearnTarget::Mute => Some(LearnTarget::Record),
+            LearnTarget::Record => Some(LearnTarget::Volume),
+            LearnTarget::Volume => Some(LearnTarget::MasterVolume),
+            LearnTarget::MasterVolume => None,
+        }
+    }
+
+    pub fn target(self, track: usize) -> MidiTarget {
+        match self {
+            LearnTarget::Mute => MidiTarget::Mute(track),
+            LearnTarget::Record => MidiTarget::Record(track),
+            LearnTarget::Volume => MidiTarget::Volume(track),
+            LearnTarget::MasterVolume => MidiTarget::MasterVolume,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MidiMessage {
+    pub source: MidiSource,
+    pub value: u8,
+}
+
+impl MidiMessage {
+    /// Decodes note on and control change messages, everything else is ignored.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let [status, number, value] = *bytes else {
+            return None;
+        };
+
+        let channel = status & 0x0f;
+
+        match status & 0xf0 {
+            // a note on with zero velocity is a note off
+            0x90 if value > 0 => Some(Self {
+                source: MidiSource::Note {
+                    channel,
+                    note: number,
+                },
+                value,
+            }),
+            0xb0 => Some(Self {
+                source: MidiSource::Control {
+                    channel,
+                    control: number,
+                },
+                value,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct Midi {
+    pub ports: Vec<String>,
+    pub port: Option<String>,
+    pub mappings: Vec<(MidiTarget, MidiSource)>,
+    #[cfg(feature = "midi")]
+    pub connection: Option<MidiInputConnection<()>>,
+    pub sender: Sender<AppEvent>,
+}
+
+impl Midi {
+    pub fn new(sender: Sender<AppEvent>) -> Self {
+        Self {
+            ports: Vec::new(),
+            port: None,
+            mappings: Vec::new(),
+            #[cfg(feature = "midi")]
+            connection: None,
+            sender,
+        }
+    }
+
+    /// Loads the port and mappings from the config file, along with any warnings.
+    pub fn load(sender: Sender<AppEvent>) -> (Self, Vec<String>) {
+        let mut midi = Self::new(sender);
+
+        let Some(path) = config::config_file(MIDI_FILE) else {
+            return (midi, Vec::new());
+        };
+
+        let warnings = match fs::read_to_string(path) {
+            Ok(source) => midi.parse(&source),
+            Err(_) => Vec::new(),
+        };
+
+        (midi, warnings)
+    }
+
+    /// Parses a flat TOML table of `port = "name"` and `target = "note 1 36"` lines.
+    pub fn parse(&mut self, source: &str) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                warnings.push(format!("line {}: expected `target = \"source\"`", number));
+                continue;
+            };
+
+            let name = name.trim();
+            let value = match parse_value(value.trim()) {
+                Some(values) if values.len() == 1 => values[0].clone(),
+                _ => {
+                    warnings.push(format!("line {}: invalid value for `{}`", number, name));
+                    continue;
+                }
+            };
+
+            if name == "port" {
+                self.port = Some(value);
+                continue;
+            }
+
+            let Some(target) = MidiTarget::from_name(name) else {
+                warnings.push(format!("line {}: unknown target `{}`", number, name));
+                continue;
+            };
+
+            let Some(source) = MidiSource::parse(&value) else {
+                warnings.push(format!("line {}: invalid midi source `{}`", number, value));
+                continue;
+            };
+
+            self.bind(target, source);
+        }
+
+        warnings
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(dir) = config::config_dir() else {
+            return Ok(());
+        };
+
+        let mut source = String::new();
+
+        if let Some(ref port) = self.port {
+            source.push_str(&format!("port = \"{}\"\n", port));
+        }
+
+        for (target, midi_source) in self.mappings.iter() {
+            source.push_str(&format!("{} = \"{}\"\n", target.name(), midi_source));
+        }
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(MIDI_FILE), source)
+    }
+
+    #[cfg(feature = "midi")]
+    pub fn refresh_ports(&mut self) -> anyhow::Result<()> {
+        let input = MidiInput::new(CLIENT_NAME)?;
+
+        self.ports = input
+            .ports()
+            .iter()
+            .filter_map(|port| input.port_name(port).ok())
+            .collect();
+
+        Ok(())
+    }
+
+    pub fn port_index(&self) -> Option<usize> {
+        let port = self.port.as_ref()?;
+        self.ports.iter().position(|name| name == port)
+    }
+
+    /// Connects to a port, incoming messages are sent to the app as events.
+    #[cfg(feature = "midi")]
+    pub fn connect(&mut self, index: usize) -> anyhow::Result<()> {
+        // close the old connection first, some backends only allow one
+        self.connection = None;
+
+        let name = self.ports.get(index).ok_or(anyhow!("no such midi port"))?;
+        let input = MidiInput::new(CLIENT_NAME)?;
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|port| input.port_name(port).ok().as_ref() == Some(name))
+            .ok_or(anyhow!("midi port `{}` is gone", name))?;
+
+        let sender = self.sender.clone();
+        let connection = input
+            .connect(
+                &port,
+                CLIENT_NAME,
+                move |_, bytes, _| {
+                    if let Some(message) = MidiMessage::decode(bytes) {
+                        let _ = sender.send(AppEvent::Midi(message));
+                    }
+                },
+                (),
+            )
+            .map_err(|err| anyhow!("{}", err))?;
+
+        self.port = Some(name.clone());
+        self.connection = Some(connection);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "midi")]
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    // without midi support there are never any ports to connect to
+    #[cfg(not(feature = "midi"))]
+    pub fn refresh_ports(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(feature = "midi"))]
+    pub fn connect(&mut self, _index: usize) -> anyhow::Result<()> {
+        Err(anyhow!("wroom was built without midi support"))
+    }
+
+    #[cfg(not(feature = "midi"))]
+    pub fn is_connected(&self) -> bool {
+        false
+    }
+
+    pub fn target(&self, source: MidiSource) -> Option<MidiTarget> {
+        let (target, _) = self.mappings.iter().find(|(_, s)| *s == source)?;
+        Some(*target)
+    }
+
+    /// Maps a source to a target, replacing any mapping either of them had.
+    pub fn bind(&mut self, target: MidiTarget, source: MidiSource) {
+        self.mappings.retain(|(t, s)| *t != target && *s != source);
+        self.mappings.push((target, source));
+    }
+}
+
+// scales a 7 bit midi value to a volume in percent
+fn midi_volume(value: u8) -> u32 {
+    value as u32 * 200 / 127
+}
+
+impl App {
+    // reconnects to the port from the config file, if it's still there
+    pub fn connect_midi(&mut self) {
+        if let Err(err) = self.midi.refresh_ports() {
+            self.warn(format!("midi unavailable: {}", err));
+            return;
+        }
+
+        if let Some(index) = self.midi.port_index() {
+            match self.midi.connect(index) {
+                Ok(()) => self.info(format!("connected to midi port {}", self.midi.ports[index])),
+                Err(err) => self.warn(format!("failed to connect midi: {}", err)),
+            }
+        }
+    }
+
+    pub fn midi_message(&mut self, message: MidiMessage) {
+        if let EditMode::MidiLearn(learn) = self.edit_mode {
+            let target = learn.target(self.selected_track);
+            self.midi.bind(target, message.source);
+            self.edit_mode = EditMode::None;
+
+            self.info(format!("mapped {} to {}", message.source, target.name()));
+            self.save_midi();
+            return;
+        }
+
+        let Some(target) = self.midi.target(message.source) else {
+            return;
+        };
+
+        match target {
+            MidiTarget::Mute(index) => self.toggle_mute(index),
+            MidiTarget::Record(index) => self.record_track(index),
+            MidiTarget::Volume(index) => {
+                self.audio.tracks[index].volume = midi_volume(message.value);
+                self.audio.update_tracks();
+            }
+            MidiTarget::MasterVolume => {
+                let volume = midi_volume(message.value);
+                self.audio.engine.set_master_volume(volume as u64);
+            }
+        }
+    }
+
+    // enters learn mode, or moves on to the next kind of control
+    pub fn midi_learn(&mut self) {
+        self.edit_mode = match self.edit_mode {
+            EditMode::MidiLearn(learn) => match learn.next() {
+                Some(next) => EditMode::MidiLearn(next),
+                None => EditMode::None,
+            },
+            _ => EditMode::MidiLearn(LearnTarget::Mute),
+        };
+    }
+
+    pub fn save_midi(&mut self) {
+        if let Err(err) = self.midi.save() {
+            self.error(format!("failed to save midi mappings: {}", err));
+        }
+    }
+
+    pub fn rotate_midi_port(&mut self, offset: i32) {
+        if let Err(err) = self.midi.refresh_ports() {
+            self.error(format!("midi unavailable: {}", err));
+            return;
+        }
+
+        let count = self.midi.ports.len() as i32;
+        if count == 0 {
+            return;
+        }
+
+        let index = match self.midi.port_index() {
+            Some(index) => (index as i32 - offset).rem_euclid(count) as usize,
+            None => 0,
+        };
+
+        match self.midi.connect(index) {
+            Ok(()) => {
+                self.info(format!("connected to midi port {}", self.midi.ports[index]));
+                self.save_midi();
+            }
+            Err(err) => self.error(format!("failed to connect midi: {}", err)),
+        }
+    }
+
+    pub fn render_midi_port_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let ports = self
+            .midi
+            .ports
+            .iter()
+            .map(|name| ListItem::new(name.as_str()))
+            .collect::<Vec<_>>();
+
+        let selected = self.midi.port_index().filter(|_| self.midi.is_connected());
+        self.settings.midi_port_state.select(selected);
+
+        let mut block = Block::default()
+            .title(self.key_title("MIDI", Action::EditMidiPort))
+            .borders(Borders::ALL);
+
+        if self.edit_mode == EditMode::MidiPort {
+            block = block.border_style(self.theme.active);
+        }
+
+        let list = List::new(ports)
+            .block(block)
+            .highlight_style(self.theme.highlight);
+
+        frame.render_stateful_widget(list, area, &mut self.settings.midi_port_state);
+    }
+}
@@ -0,0 +1,164 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::{centered_rect, App};
+
+// the longest text a field takes, in characters
+const TEXT_LENGTH: usize = 80;
+
+// title, artist, note and the track note
+const FIELD_COUNT: usize = 4;
+
+// the size of the notes window
+const NOTES_WIDTH: u16 = 60;
+const NOTES_HEIGHT: u16 = 12;
+
+/// Types a key into a line of text, returning whether it was taken.
+pub fn edit_text(text: &mut String, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            if text.chars().count() < TEXT_LENGTH {
+                text.push(c);
+            }
+        }
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+/// The window editing the title, artist and note of the session and the note of a track.
+pub struct NotesDialog {
+    pub track: usize,
+    /// The field being typed into, in the order of `fields`.
+    pub field: usize,
+    pub title: String,
+    pub artist: String,
+    pub note: String,
+    pub track_note: String,
+}
+
+impl NotesDialog {
+    fn fields(&self) -> [(String, &str); FIELD_COUNT] {
+        [
+            (String::from("title"), &self.title),
+            (String::from("artist"), &self.artist),
+            (String::from("note"), &self.note),
+            (
+                format!("track {} note", (self.track + 1) % 10),
+                &self.track_note,
+            ),
+        ]
+    }
+
+    fn text(&mut self) -> &mut String {
+        match self.field {
+            0 => &mut self.title,
+            1 => &mut self.artist,
+            2 => &mut self.note,
+            _ => &mut self.track_note,
+        }
+    }
+}
+
+impl App {
+    pub fn open_notes(&mut self) {
+        let track = self.selected_track;
+
+        self.notes_dialog = Some(NotesDialog {
+            track,
+            field: 0,
+            title: self.notes.title.clone(),
+            artist: self.notes.artist.clone(),
+            note: self.notes.note.clone(),
+            track_note: self.audio.tracks[track].note.clone(),
+        });
+    }
+
+    // handles a key while the notes window is open
+    pub fn notes_key(&mut self, key: KeyEvent) {
+        let Some(ref mut dialog) = self.notes_dialog else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.notes_dialog = None,
+            KeyCode::Up | KeyCode::BackTab => {
+                dialog.field = (dialog.field + FIELD_COUNT - 1) % FIELD_COUNT
+            }
+            KeyCode::Down | KeyCode::Tab => dialog.field = (dialog.field + 1) % FIELD_COUNT,
+            KeyCode::Enter => self.apply_notes(),
+            _ => {
+                edit_text(dialog.text(), key);
+            }
+        }
+    }
+
+    fn apply_notes(&mut self) {
+        let Some(dialog) = self.notes_dialog.take() else {
+            return;
+        };
+
+        self.notes.title = dialog.title.trim().to_string();
+        self.notes.artist = dialog.artist.trim().to_string();
+        self.notes.note = dialog.note.trim().to_string();
+        self.audio.tracks[dialog.track].note = dialog.track_note.trim().to_string();
+
+        self.info("updated the notes");
+    }
+
+    pub fn render_notes<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let Some(ref dialog) = self.notes_dialog else {
+            return;
+        };
+
+        let area = centered_rect(frame.size(), NOTES_WIDTH, NOTES_HEIGHT);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Notes")
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let mut text = Vec::new();
+        for (index, (name, value)) in dialog.fields().into_iter().enumerate() {
+            let selected = index == dialog.field;
+            let style = if selected {
+                self.theme.highlight
+            } else {
+                self.theme.empty
+            };
+
+            let mut line = vec![Span::styled(value, self.theme.active)];
+            if selected {
+                line.push(Span::styled("_", self.theme.empty));
+            }
+
+            text.push(Spans::from(Span::styled(format!("{}:", name), style)));
+            text.push(Spans::from(line));
+        }
+
+        frame.render_widget(Paragraph::new(text), rows[0]);
+
+        let footer = Paragraph::new("up/down to choose, enter to save, esc to cancel")
+            .style(self.theme.empty);
+        frame.render_widget(footer, rows[1]);
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    fs, io,
+    net::{SocketAddr, UdpSocket},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crossbeam::channel::Sender;
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    text::Spans,
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{App, EditMode},
+    config::{self, parse_value, strip_comment},
+    engine::AudioEngine,
+    event::AppEvent,
+};
+
+const OSC_FILE: &str = "osc.toml";
+const DEFAULT_PORT: u16 = 9000;
+
+// how long the server waits for a packet before checking the beat again
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Bool(bool),
+}
+
+impl OscArg {
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            OscArg::Int(value) => Some(value as f32),
+            OscArg::Float(value) => Some(value),
+            OscArg::Bool(value) => Some(value as u8 as f32),
+            OscArg::String(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+// reads a null terminated string padded to 4 bytes
+fn read_string(bytes: &[u8], offset: &mut usize) -> Option<String> {
+    let rest = bytes.get(*offset..)?;
+    let length = rest.iter().position(|&byte| byte == 0)?;
+    let string = std::str::from_utf8(&rest[..length]).ok()?.to_string();
+
+    *offset += (length + 4) & !3;
+    Some(string)
+}
+
+fn read_bytes<const N: usize>(bytes: &[u8], offset: &mut usize) -> Option<[u8; N]> {
+    let value = bytes.get(*offset..*offset + N)?.try_into().ok()?;
+    *offset += N;
+    Some(value)
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend_from_slice(string.as_bytes());
+    bytes.resize(bytes.len() + 4 - string.len() % 4, 0);
+}
+
+impl OscMessage {
+    pub fn new(address: &str, args: Vec<OscArg>) -> Self {
+        Self {
+            address: address.to_string(),
+            args,
+        }
+    }
+
+    /// Decodes a packet, which is either a message or a bundle of them.
+    ///
+    /// Returns `None` if the packet is malformed.
+    pub fn decode(bytes: &[u8]) -> Option<Vec<Self>> {
+        if bytes.starts_with(b"#bundle\0") {
+            // skip the time tag, bundles are handled right away
+            let mut offset = 16;
+            let mut messages = Vec::new();
+
+            while offset < bytes.len() {
+                let size = u32::from_be_bytes(read_bytes(bytes, &mut offset)?) as usize;
+                let element = bytes.get(offset..offset.checked_add(size)?)?;
+                messages.extend(Self::decode(element)?);
+                offset += size;
+            }
+
+            return Some(messages);
+        }
+
+        let mut offset = 0;
+        let address = read_string(bytes, &mut offset)?;
+
+        if !address.starts_with('/') {
+            return None;
+        }
+
+        // some old clients leave out the type tags when there are no arguments
+        if offset >= bytes.len() {
+            return Some(vec![Self::new(&address, Vec::new())]);
+        }
+
+        let tags = read_string(bytes, &mut offset)?;
+        let mut args = Vec::new();
+
+        for tag in tags.strip_prefix(',')?.chars() {
+            let arg = match tag {
+                'i' => OscArg::Int(i32::from_be_bytes(read_bytes(bytes, &mut offset)?)),
+                'f' => OscArg::Float(f32::from_be_bytes(read_bytes(bytes, &mut offset)?)),
+                's' => OscArg::String(read_string(bytes, &mut offset)?),
+                'T' => OscArg::Bool(true),
+                'F' => OscArg::Bool(false),
+                _ => return None,
+            };
+
+            args.push(arg);
+        }
+
+        Some(vec![Self::new(&address, args)])
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, &self.address);
+
+        let mut tags = String::from(",");
+        for arg in self.args.iter() {
+            tags.push(match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Float(_) => 'f',
+                OscArg::String(_) => 's',
+                OscArg::Bool(true) => 'T',
+                OscArg::Bool(false) => 'F',
+            });
+        }
+
+        write_string(&mut bytes, &tags);
+
+        for arg in self.args.iter() {
+            match arg {
+                OscArg::Int(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Float(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+                OscArg::String(value) => write_string(&mut bytes, value),
+                OscArg::Bool(_) => {}
+            }
+        }
+
+        bytes
+    }
+}
+
+/// What a remote client asked the app to do.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscCommand {
+    /// Sets the mute state of a track, or toggles it without a value.
+    Mute(usize, Option<bool>),
+    /// Sets the volume of a track, from 0 to 1 for the full range.
+    Volume(usize, f32),
+    Bpm(f32),
+    Arm(usize),
+    Start,
+    Stop,
+}
+
+impl OscCommand {
+    pub fn parse(message: &OscMessage) -> Option<Self> {
+        let parts = message.address.split('/').skip(1).collect::<Vec<_>>();
+        let value = message.args.first().and_then(OscArg::as_f32);
+
+        // tracks are numbered from 1 like on the keyboard
+        let track = |number: &str| -> Option<usize> {
+            let number = number.parse::<usize>().ok()?;
+            (1..=10).contains(&number).then(|| number - 1)
+        };
+
+        match parts.as_slice() {
+            ["track", number, "mute"] => Some(OscCommand::Mute(
+                track(number)?,
+                value.map(|value| value > 0.5),
+            )),
+            ["track", number, "volume"] => Some(OscCommand::Volume(track(number)?, value?)),
+            ["bpm"] => Some(OscCommand::Bpm(value?)),
+            ["record", "arm", number] => Some(OscCommand::Arm(track(number)?)),
+            ["transport", "start"] => Some(OscCommand::Start),
+            ["transport", "stop"] => Some(OscCommand::Stop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum OscEvent {
+    Command(OscCommand),
+    Client(SocketAddr),
+}
+
+/// The state of the osc server shown in the settings tab.
+pub struct Osc {
+    pub port: u16,
+    pub client: Option<SocketAddr>,
+}
+
+#[derive(Default)]
+pub struct OscConfig {
+    pub port: Option<u16>,
+    pub client: Option<SocketAddr>,
+}
+
+impl OscConfig {
+    /// Loads the config file, along with any warnings.
+    pub fn load() -> (Self, Vec<String>) {
+        let mut osc_config = Self::default();
+        let mut warnings = Vec::new();
+
+        let Some(source) =
+            config::config_file(OSC_FILE).and_then(|path| fs::read_to_string(path).ok())
+        else {
+            return (osc_config, warnings);
+        };
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                warnings.push(format!("line {}: expected `name = \"value\"`", number));
+                continue;
+            };
+
+            let name = name.trim();
+            let value = match parse_value(value.trim()) {
+                Some(values) if values.len() == 1 => values[0].clone(),
+                _ => {
+                    warnings.push(format!("line {}: invalid value for `{}`", number, name));
+                    continue;
+                }
+            };
+
+            match name {
+                "port" => match value.parse() {
+                    Ok(port) => osc_config.port = Some(port),
+                    Err(_) => warnings.push(format!("line {}: invalid port `{}`", number, value)),
+                },
+                "client" => match value.parse() {
+                    Ok(client) => osc_config.client = Some(client),
+                    Err(_) => {
+                        warnings.push(format!("line {}: invalid address `{}`", number, value))
+                    }
+                },
+                _ => warnings.push(format!("line {}: unknown setting `{}`", number, name)),
+            }
+        }
+
+        (osc_config, warnings)
+    }
+}
+
+// receives commands and sends the beat back to the client until the app goes away
+fn serve(
+    socket: UdpSocket,
+    mut client: Option<SocketAddr>,
+    engine: Arc<AudioEngine>,
+    sender: Sender<AppEvent>,
+) {
+    let mut buffer = [0; 1536];
+    let mut last_beat = None;
+
+    loop {
+        if let Ok((length, address)) = socket.recv_from(&mut buffer) {
+            // anything that isn't valid osc is dropped
+            let messages = OscMessage::decode(&buffer[..length]).unwrap_or_default();
+
+            if client != Some(address) && !messages.is_empty() {
+                client = Some(address);
+
+                let event = AppEvent::Osc(OscEvent::Client(address));
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+
+            for command in messages.iter().filter_map(OscCommand::parse) {
+                if sender
+                    .send(AppEvent::Osc(OscEvent::Command(command)))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+
+        let snapshot = engine.snapshot();
+        let beat = snapshot.is_running().then(|| snapshot.beat() as i32);
+
+        if beat != last_beat {
+            last_beat = beat;
+
+            if let (Some(beat), Some(client)) = (beat, client) {
+                let progress = snapshot.loop_progress();
+
+                for message in [
+                    OscMessage::new("/beat", vec![OscArg::Int(beat + 1)]),
+                    OscMessage::new("/loop", vec![OscArg::Float(progress)]),
+                ] {
+                    let _ = socket.send_to(&message.encode(), client);
+                }
+            }
+        }
+    }
+}
+
+impl Osc {
+    /// Binds the socket and starts the server thread.
+    pub fn start(
+        osc_config: &OscConfig,
+        engine: Arc<AudioEngine>,
+        sender: Sender<AppEvent>,
+    ) -> io::Result<Self> {
+        let port = osc_config.port.unwrap_or(DEFAULT_PORT);
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+
+        let client = osc_config.client;
+        thread::spawn(move || serve(socket, client, engine, sender));
+
+        Ok(Self { port, client })
+    }
+}
+
+impl App {
+    pub fn start_osc(&mut self) {
+        let (osc_config, warnings) = OscConfig::load();
+
+        for warning in warnings {
+            self.warn(format!("osc: {}", warning));
+        }
+
+        let engine = self.audio.engine.clone();
+        match Osc::start(&osc_config, engine, self.events.sender.clone()) {
+            Ok(osc) => {
+                self.info(format!("osc listening on port {}", osc.port));
+                self.osc = Some(osc);
+            }
+            Err(err) => self.error(format!("failed to start osc server: {}", err)),
+        }
+    }
+
+    pub fn osc_event(&mut self, event: OscEvent) {
+        match event {
+            OscEvent::Client(address) => {
+                if let Some(ref mut osc) = self.osc {
+                    osc.client = Some(address);
+                }
+            }
+            OscEvent::Command(command) => self.osc_command(command),
+        }
+    }
+
+    pub fn osc_command(&mut self, command: OscCommand) {
+        match command {
+            OscCommand::Mute(index, muted) => {
+                if muted != Some(self.audio.tracks[index].muted) {
+                    self.toggle_mute(index);
+                }
+            }
+            OscCommand::Volume(index, volume) => {
+                self.audio.tracks[index].volume = (volume.clamp(0.0, 1.0) * 200.0) as u32;
+                self.audio.update_tracks();
+            }
+            OscCommand::Bpm(bpm) => {
+                let bpm = bpm.round().clamp(1.0, 999.0) as u64;
+                self.change_bpm(bpm, false);
+            }
+            OscCommand::Arm(index) => {
+                self.selected_track = index;
+                self.edit_mode = EditMode::RecordTrack;
+            }
+            OscCommand::Start => self.launch_streams(),
+            OscCommand::Stop => {
+                self.audio.shutdown();
+                self.info("streams stopped");
+            }
+        }
+    }
+
+    pub fn render_osc_status<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title("OSC");
+
+        let text = match self.osc {
+            Some(ref osc) => {
+                let client = match osc.client {
+                    Some(client) => client.to_string(),
+                    None => String::from("none"),
+                };
+
+                vec![
+                    Spans::from(format!("port: {}", osc.port)),
+                    Spans::from(format!("client: {}", client)),
+                ]
+            }
+            None => vec![Spans::from("not running")],
+        };
+
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
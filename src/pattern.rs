@@ -0,0 +1,88 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::text::{Span, Spans};
+
+use crate::{
+    app::{App, EditMode},
+    track::{Track, PATTERN_STEPS},
+};
+
+impl App {
+    // handles a key while editing the pattern of a track, returning false for the keys left
+    // to the keymap
+    pub fn pattern_key(&mut self, index: usize, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Left => self.move_pattern_step(-1),
+            KeyCode::Right => self.move_pattern_step(1),
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_step(index, self.pattern_step),
+            // the digits reach the first ten steps, 0 being the tenth like with the tracks
+            KeyCode::Char(digit) if digit.is_ascii_digit() => {
+                let digit = digit.to_digit(10).unwrap_or(0) as usize;
+                self.pattern_step = (digit + 9) % 10;
+                self.toggle_step(index, self.pattern_step);
+            }
+            KeyCode::Delete => {
+                self.audio.tracks[index].pattern = None;
+                self.audio.update_tracks();
+                self.info(format!("cleared the pattern of track {}", (index + 1) % 10));
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn move_pattern_step(&mut self, offset: i32) {
+        let step = self.pattern_step as i32 + offset;
+        self.pattern_step = step.rem_euclid(PATTERN_STEPS as i32) as usize;
+    }
+
+    // turns a step on or off, every step starting out on
+    fn toggle_step(&mut self, index: usize, step: usize) {
+        let track = &mut self.audio.tracks[index];
+        let pattern = track.pattern.get_or_insert([true; PATTERN_STEPS]);
+        pattern[step] = !pattern[step];
+
+        self.audio.update_tracks();
+    }
+
+    // moves the pattern of a track `offset` steps earlier, or later when negative
+    pub fn rotate_pattern(&mut self, index: usize, offset: i32) {
+        let Some(ref mut pattern) = self.audio.tracks[index].pattern else {
+            return;
+        };
+
+        let offset = offset.rem_euclid(PATTERN_STEPS as i32) as usize;
+        pattern.rotate_left(offset);
+        self.audio.update_tracks();
+    }
+
+    // the steps of a track's pattern as a row of blocks, the one being edited highlighted
+    pub fn pattern_spans(&self, index: usize, track: &Track) -> Spans<'static> {
+        let mut spans = vec![Span::raw("pattern: ")];
+        let editing = self.edit_mode == EditMode::TrackPattern(Some(index));
+
+        // every step is heard without a pattern, which is how a new one starts out
+        let pattern = match track.pattern {
+            Some(pattern) => pattern,
+            None if editing => [true; PATTERN_STEPS],
+            None => {
+                spans.push(Span::raw("off"));
+                return Spans::from(spans);
+            }
+        };
+
+        for (step, &on) in pattern.iter().enumerate() {
+            let style = if editing && step == self.pattern_step {
+                self.theme.active
+            } else if on {
+                self.theme.accent
+            } else {
+                self.theme.empty
+            };
+
+            spans.push(Span::styled(if on { "█" } else { "·" }, style));
+        }
+
+        Spans::from(spans)
+    }
+}
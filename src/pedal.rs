@@ -0,0 +1,195 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{app::App, history::Layer, keymap::Action};
+
+// how many recorded layers can be undone
+const MAX_UNDO_LAYERS: usize = 32;
+
+/// Where the single key looping workflow is, driven by [`App::pedal`].
+///
+/// Recordings always span whole loops, so every transition that starts or ends a recording
+/// waits for the loop to wrap around.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LooperPedalState {
+    #[default]
+    Idle,
+    /// Waiting for the next loop to start recording into a track.
+    Armed(usize),
+    /// Capturing the loop, the next press ends it at the loop boundary.
+    Recording(usize),
+    /// Waiting for the loop boundary to commit the recording to the track.
+    Stopping(usize),
+    /// Playing back the track, the next press overdubs onto it.
+    Playing(usize),
+}
+
+impl LooperPedalState {
+    pub fn track(self) -> Option<usize> {
+        match self {
+            Self::Idle => None,
+            Self::Armed(index)
+            | Self::Recording(index)
+            | Self::Stopping(index)
+            | Self::Playing(index) => Some(index),
+        }
+    }
+
+    /// Returns true while input is or will be captured into a track.
+    pub fn is_capturing(self) -> bool {
+        matches!(
+            self,
+            Self::Armed(_) | Self::Recording(_) | Self::Stopping(_)
+        )
+    }
+}
+
+impl App {
+    // advances the pedal with a single press, meant for a footswitch
+    pub fn pedal(&mut self) {
+        if !self.audio.engine.is_recording() {
+            self.warn("launch the streams to use the pedal");
+            return;
+        }
+
+        self.pedal = match self.pedal {
+            LooperPedalState::Idle => match self.audio.tracks.first_empty() {
+                Some(index) => {
+                    self.selected_track = index;
+                    self.info(format!("pedal: armed track {}", (index + 1) % 10));
+                    LooperPedalState::Armed(index)
+                }
+                None => {
+                    self.warn("pedal: every track has a clip");
+                    LooperPedalState::Idle
+                }
+            },
+            // a second press before the loop starts cancels the recording
+            LooperPedalState::Armed(index) => {
+                self.info("pedal: cancelled");
+
+                if self.audio.tracks[index].clip.is_some() {
+                    LooperPedalState::Playing(index)
+                } else {
+                    LooperPedalState::Idle
+                }
+            }
+            LooperPedalState::Recording(index) => LooperPedalState::Stopping(index),
+            LooperPedalState::Stopping(index) => LooperPedalState::Stopping(index),
+            LooperPedalState::Playing(index) => {
+                self.selected_track = index;
+                self.info(format!("pedal: overdubbing track {}", (index + 1) % 10));
+                LooperPedalState::Armed(index)
+            }
+        };
+    }
+
+    // moves the pedal along when the loop wraps around, called every update
+    pub fn update_pedal(&mut self) {
+        let loop_count = self.audio.engine.loop_count();
+
+        if loop_count == self.pedal_loop {
+            return;
+        }
+
+        self.pedal_loop = loop_count;
+
+        self.pedal = match self.pedal {
+            LooperPedalState::Armed(index) => LooperPedalState::Recording(index),
+            LooperPedalState::Stopping(index) => {
+                self.commit_recording(index);
+                LooperPedalState::Playing(index)
+            }
+            state => state,
+        };
+    }
+
+    // remembers the clip of a track before it's changed, `label` saying how
+    pub fn push_layer(&mut self, index: usize, label: String) {
+        if self.layers.len() == MAX_UNDO_LAYERS {
+            self.layers.remove(0);
+        }
+
+        self.end_compare(index);
+
+        let clip = self.audio.tracks[index].clip.clone();
+        self.layers.push(Layer::new(index, clip, label));
+    }
+
+    // drops the undo history of a track whose clip was replaced some other way
+    pub fn forget_layers(&mut self, index: usize) {
+        self.end_compare(index);
+        self.layers.retain(|layer| layer.track != index);
+    }
+
+    // restores the clip a track had before the last change
+    pub fn undo_layer(&mut self) {
+        let Some(layer) = self.layers.last() else {
+            self.warn("nothing to undo");
+            return;
+        };
+
+        let message = format!("undid {}", layer.label);
+        self.undo_layers(1);
+        self.info(message);
+    }
+
+    pub fn render_pedal<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Pedal", Action::Pedal));
+
+        let bold = |style: Style| style.add_modifier(Modifier::BOLD);
+
+        let (state, style, next) = match self.pedal {
+            LooperPedalState::Idle => (
+                String::from("IDLE"),
+                self.theme.empty,
+                "press to record into the first empty track",
+            ),
+            LooperPedalState::Armed(index) => (
+                format!("ARMED {}", (index + 1) % 10),
+                bold(self.theme.warning),
+                "recording starts with the next loop, press to cancel",
+            ),
+            LooperPedalState::Recording(index) => (
+                format!("RECORDING {}", (index + 1) % 10),
+                bold(self.theme.error),
+                "press to stop at the end of the loop",
+            ),
+            LooperPedalState::Stopping(index) => (
+                format!("STOPPING {}", (index + 1) % 10),
+                bold(self.theme.error),
+                "the recording ends with this loop",
+            ),
+            LooperPedalState::Playing(index) => (
+                format!("PLAYING {}", (index + 1) % 10),
+                bold(self.theme.accent),
+                "press to overdub",
+            ),
+        };
+
+        let text = vec![
+            Spans::from(Span::styled(state, style)),
+            Spans::from(""),
+            Spans::from(next),
+            Spans::from(format!(
+                "{} to undo the last layer",
+                self.keymap.key_name(Action::UndoLayer)
+            )),
+        ];
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+}
@@ -1,28 +1,47 @@
+use std::time::Instant;
+
 use tui::{
     backend::Backend,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    text::Spans,
-    widgets::{BarChart, Block, Borders, Paragraph},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{BarChart, Block, Borders, Gauge, Paragraph, Wrap},
     Frame,
 };
 
 use crate::{
-    app::{App, EditMode},
-    track::Track,
+    app::{
+        analysis::AnalysisView,
+        beat::BeatBar,
+        meter::{db_meter_ratio, LOUDNESS_SCALE},
+        App, EditMode,
+    },
+    clip::Clip,
+    engine::loop_seconds,
+    gate::GateMode,
+    keymap::Action,
+    layout::PlayLayout,
+    track::{InputFollow, Track},
 };
 
-const RAINBOW: [Color; 6] = [
-    Color::Red,
-    Color::Yellow,
-    Color::Green,
-    Color::Cyan,
-    Color::Blue,
-    Color::Magenta,
-];
+// the levels of the waveform drawn in a track, from quiet to loud
+const SPARK_LEVELS: [char; 5] = ['▁', '▂', '▃', '▅', '▇'];
+
+// track columns shorter than this show a single level meter instead of the waveform
+const MIN_SPARKLINE_HEIGHT: u16 = 4;
 
 impl App {
     pub fn render_play<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        if self.zoomed {
+            self.render_zoomed(frame, area);
+            return;
+        }
+
+        if self.layout == PlayLayout::Minimal {
+            self.render_minimal(frame, area);
+            return;
+        }
+
         let chunks = Layout::default()
             .margin(1)
             .direction(Direction::Horizontal)
@@ -34,6 +53,12 @@ impl App {
     }
 
     pub fn render_right<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        // the tracks take the settings' half too
+        if self.layout == PlayLayout::TracksFocus {
+            self.render_tracks(frame, area);
+            return;
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
@@ -44,12 +69,25 @@ impl App {
     }
 
     pub fn render_bottom<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut constraints = vec![Constraint::Length(30), Constraint::Min(1)];
+
+        // the analysis panel takes what's left next to the pedal
+        if self.analysis != AnalysisView::Off {
+            constraints[1] = Constraint::Length(30);
+            constraints.push(Constraint::Min(1));
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(30), Constraint::Min(1)])
+            .constraints(constraints)
             .split(area);
 
         self.render_play_settings(frame, chunks[0]);
+        self.render_pedal(frame, chunks[1]);
+
+        if self.analysis != AnalysisView::Off {
+            self.render_analysis(frame, chunks[2]);
+        }
     }
 
     pub fn render_play_settings<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
@@ -61,6 +99,13 @@ impl App {
             .margin(1)
             .direction(Direction::Vertical)
             .constraints([
+                Constraint::Length(3),
+                Constraint::Length(4),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(5),
+                Constraint::Length(3),
+                Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Length(3),
@@ -69,30 +114,251 @@ impl App {
             .split(area);
 
         self.render_bpm_select(frame, chunks[0]);
-        self.render_beats_select(frame, chunks[1]);
-        self.render_metronome_select(frame, chunks[2]);
+        self.render_bars_select(frame, chunks[1]);
+        self.render_loop_length(frame, chunks[2]);
+        self.render_metronome_select(frame, chunks[3]);
+        self.render_delay_settings(frame, chunks[4]);
+        self.render_master_meter(frame, chunks[5]);
+        self.render_loudness(frame, chunks[6]);
+        self.render_meter_mode_select(frame, chunks[7]);
+        self.render_gate_select(frame, chunks[8]);
+        self.render_input_follow_select(frame, chunks[9]);
+    }
+
+    pub fn render_input_follow_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Input follows", Action::CycleInputFollow));
+
+        let engine = &self.audio.engine;
+        let follow = engine.input_follow();
+
+        let mut spans = vec![Span::raw(follow.name())];
+
+        if follow != InputFollow::Off {
+            match engine.armed_track() {
+                Some(index) => spans.push(Span::raw(format!(", track {}", (index + 1) % 10))),
+                None => spans.push(Span::styled(", nothing armed", self.theme.empty)),
+            }
+        }
+
+        let paragraph = Paragraph::new(Spans::from(spans)).block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_gate_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Gate", Action::CycleGate));
+
+        if self.edit_mode == EditMode::GateThreshold {
+            block = block.style(self.theme.active);
+        }
+
+        let engine = &self.audio.engine;
+        let mode = engine.gate_mode();
+
+        let mut spans = vec![Span::raw(mode.name())];
+
+        if mode != GateMode::Off {
+            let label = self.key_title("threshold", Action::EditGateThreshold);
+            spans.push(Span::raw(format!(
+                ", {}: {} dB",
+                label,
+                engine.gate_threshold()
+            )));
+        }
+
+        if engine.is_gate_closed() {
+            spans.push(Span::styled(" closed", self.theme.warning));
+        }
+
+        let paragraph = Paragraph::new(Spans::from(spans)).block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_meter_mode_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Meters", Action::ToggleMeterMode));
+
+        let paragraph = Paragraph::new(Spans::from(self.meter_mode.name())).block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    // the master volume, shrinking along with a fade out
+    pub fn render_master_meter<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Master", Action::FadeOut));
+
+        if matches!(self.edit_mode, EditMode::Fade(_)) {
+            block = block.style(self.theme.active);
+        }
+
+        let snapshot = self.snapshot;
+        let volume = snapshot.master_volume as f32 / 100.0 * snapshot.fade_gain;
+
+        let label = if snapshot.fading {
+            format!("fading {:.0}%", volume * 100.0)
+        } else {
+            format!("{:.0}%", volume * 100.0)
+        };
+
+        let color = self.theme.track_color(0);
+        let meter = Gauge::default()
+            .block(block)
+            .gauge_style(self.theme.meter(color))
+            .ratio(volume.clamp(0.0, 1.0) as f64)
+            .label(label);
+
+        frame.render_widget(meter, area);
+    }
+
+    // the short-term loudness of the mix next to a bar, the target marked on it
+    pub fn render_loudness<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Loudness", Action::EditLoudnessTarget));
+
+        if self.edit_mode == EditMode::LoudnessTarget {
+            block = block.style(self.theme.active);
+        }
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(15), Constraint::Min(1)])
+            .split(inner);
+
+        let lufs = self.loudness.lufs().filter(|_| self.snapshot.is_running());
+        let target = self.loudness_target as f64;
+
+        // louder than the target gets turned down once it's shared, so it's warned about
+        let (text, style) = match lufs {
+            Some(lufs) if lufs > target + 1.0 => {
+                (format!("S: {:.1} LUFS", lufs), self.theme.warning)
+            }
+            Some(lufs) => (format!("S: {:.1} LUFS", lufs), Style::default()),
+            None => (String::from("S: -- LUFS"), self.theme.empty),
+        };
+
+        frame.render_widget(Paragraph::new(Span::styled(text, style)), chunks[0]);
+
+        // editing the target shows it instead of the bar
+        if self.edit_mode == EditMode::LoudnessTarget {
+            let text = format!("target {}", self.loudness_target);
+            frame.render_widget(Paragraph::new(text), chunks[1]);
+            return;
+        }
+
+        let (min, max) = LOUDNESS_SCALE;
+        let cells = chunks[1].width as usize;
+        let cell = |lufs: f64| ((lufs - min) / (max - min) * cells as f64).max(0.0) as usize;
+
+        let filled = lufs.map_or(0, cell);
+        let target_cell = cell(target).min(cells.saturating_sub(1));
+        let color = self.theme.track_color(0);
+
+        let spans = (0..cells)
+            .map(|index| {
+                if index == target_cell {
+                    Span::styled("│", self.theme.accent)
+                } else if index < filled {
+                    Span::styled("█", self.theme.meter(color))
+                } else {
+                    Span::styled("·", self.theme.empty)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        frame.render_widget(Paragraph::new(Spans::from(spans)), chunks[1]);
     }
 
     pub fn render_bpm_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let mut block = Block::default().borders(Borders::ALL).title("BPM 'b'");
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("BPM", Action::EditBpm));
 
         if self.edit_mode == EditMode::Bpm {
-            block = block.style(Style::default().fg(Color::Red));
+            block = block.style(self.theme.active);
         }
 
-        let text = format!("{}", self.audio.engine.bpm());
+        let bpm = self.snapshot.bpm;
+        let text = match self.snapshot.pending_bpm {
+            Some(pending) => format!(
+                "{} → {} ({})",
+                bpm,
+                pending,
+                self.snapshot.tempo_timing.name().to_lowercase()
+            ),
+            None => format!("{}", bpm),
+        };
         let paragraph = Paragraph::new(Spans::from(text)).block(block);
         frame.render_widget(paragraph, area);
     }
 
-    pub fn render_beats_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let mut block = Block::default().borders(Borders::ALL).title("Beats 'B'");
+    pub fn render_bars_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let title = format!(
+            "{}, {}",
+            self.key_title("Bars", Action::EditBars),
+            self.key_title("Meter", Action::EditMeter)
+        );
+        let mut block = Block::default().borders(Borders::ALL).title(title);
 
-        if self.edit_mode == EditMode::Beats {
-            block = block.style(Style::default().fg(Color::Red));
+        if matches!(self.edit_mode, EditMode::Bars | EditMode::Meter) {
+            block = block.style(self.theme.active);
         }
 
-        let text = format!("{}", self.audio.engine.beats());
+        // the bars are rounded up, a loop of raw beats may end partway through one
+        let beats_per_bar = self.snapshot.beats_per_bar.max(1);
+        let beats = self.snapshot.beats;
+        let bars = beats.div_ceil(beats_per_bar);
+        let bars = match self.audio.pending_beats {
+            Some(pending) => format!(
+                "{} → {} bars of {} beats (pending)",
+                bars,
+                pending.div_ceil(beats_per_bar),
+                beats_per_bar
+            ),
+            None => format!("{} bars of {} beats", bars, beats_per_bar),
+        };
+
+        let beats = match self.audio.pending_beats {
+            Some(pending) => format!("{} → {} beats", beats, pending),
+            None => format!("{} beats", beats),
+        };
+
+        let text = vec![
+            Spans::from(bars),
+            Spans::from(Span::styled(beats, self.theme.empty)),
+        ];
+        let paragraph = Paragraph::new(text).block(block);
+        frame.render_widget(paragraph, area);
+    }
+
+    pub fn render_loop_length<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Length", Action::EditLoopDuration));
+
+        if self.duration_prompt.is_some() {
+            block = block.style(self.theme.active);
+        }
+
+        // the frames only exist at a sample rate, once the streams are running
+        let engine = &self.audio.engine;
+        let seconds = loop_seconds(self.snapshot.bpm, self.snapshot.beats);
+        let text = match engine.beats_frames(self.snapshot.beats) {
+            0 => format!("{:.3}s", seconds),
+            frames => format!("{:.3}s, {} frames", seconds, frames),
+        };
+
         let paragraph = Paragraph::new(Spans::from(text)).block(block);
         frame.render_widget(paragraph, area);
     }
@@ -100,59 +366,246 @@ impl App {
     pub fn render_metronome_select<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
         let block = Block::default()
             .borders(Borders::ALL)
-            .title("Metronome 'M'");
+            .title(self.key_title("Metronome", Action::ToggleMetronome));
 
-        let text = if self.audio.engine.metronome() {
-            "On"
-        } else {
-            "Off"
-        };
+        let text = self.snapshot.metronome.name();
 
         let paragraph = Paragraph::new(Spans::from(text)).block(block);
 
         frame.render_widget(paragraph, area);
     }
 
+    pub fn render_delay_settings<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let engine = &self.audio.engine;
+
+        let beats = engine.delay_beats();
+        let lines = [
+            (
+                "time",
+                Action::EditDelayBeats,
+                EditMode::DelayBeats,
+                format!("{} beat{}", beats, if beats == 1 { "" } else { "s" }),
+            ),
+            (
+                "feedback",
+                Action::EditDelayFeedback,
+                EditMode::DelayFeedback,
+                format!("{}%", engine.delay_feedback()),
+            ),
+            (
+                "mix",
+                Action::EditDelayMix,
+                EditMode::DelayMix,
+                format!("{}%", engine.delay_mix()),
+            ),
+        ];
+
+        let text = lines
+            .into_iter()
+            .map(|(label, action, mode, value)| {
+                let text = format!("{}: {}", self.key_title(label, action), value);
+
+                if self.edit_mode == mode {
+                    Spans::from(Span::styled(text, self.theme.active))
+                } else {
+                    Spans::from(text)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let block = Block::default().borders(Borders::ALL).title("Delay");
+        let paragraph = Paragraph::new(text).block(block);
+
+        frame.render_widget(paragraph, area);
+    }
+
+    // the beat playback has reached by now, moving between callbacks instead of jumping with
+    // them
+    pub fn current_beat(&self) -> f32 {
+        self.snapshot.beat_at(Instant::now())
+    }
+
     pub fn render_beat<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let half_beat = (self.audio.engine.beat() * 2.0).round() as usize;
-        let color = RAINBOW[half_beat % RAINBOW.len()];
+        let snapshot = self.snapshot;
+        let current = self.current_beat();
+        let half_beat = (current * 2.0).round() as usize;
+        let color = self.theme.track_color(half_beat);
 
-        let beat = self.audio.engine.beat().round() as u64;
-        let data = [("+====+", beat)];
-        let bar = BarChart::default()
-            .block(Block::default().borders(Borders::ALL))
-            .data(&data)
-            .bar_width(6)
-            .bar_gap(0)
-            .max(self.audio.engine.beats())
-            .bar_style(Style::default().fg(color))
-            .value_style(Style::default().fg(Color::White).bg(color));
+        let block = Block::default().borders(Borders::ALL);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let bar = BeatBar::new(current, snapshot.beats)
+            .beats_per_bar(snapshot.beats_per_bar)
+            .style(self.theme.meter(color))
+            .tick_style(self.theme.empty)
+            .caption_style(self.theme.meter(color));
 
-        frame.render_widget(bar, area);
+        frame.render_widget(bar, inner);
     }
 
     pub fn render_tracks<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let block = Block::default().borders(Borders::ALL).title("Tracks");
-        frame.render_widget(block, area);
+        let mut title = vec![Span::raw("Tracks")];
 
-        let mut constraints = vec![Constraint::Length(6); self.audio.tracks.len()];
-        constraints.push(Constraint::Length(30));
-        constraints.push(Constraint::Min(1));
+        // pulse with the beat while capturing
+        if self.snapshot.is_running() && self.current_beat().fract() < 0.5 {
+            let style = self.theme.error.add_modifier(Modifier::REVERSED);
 
-        let chunks = Layout::default()
+            title.push(Span::raw(" "));
+            title.push(Span::styled(" REC ", style));
+        }
+
+        // which of the loops counted out is being recorded
+        if let Some(count) = self.count_out {
+            if self.audio.engine.armed_track().is_some() {
+                let style = self.theme.error.add_modifier(Modifier::REVERSED);
+
+                title.push(Span::raw(" "));
+                title.push(Span::styled(
+                    format!(
+                        " {} of {} ",
+                        (count.recorded + 1).min(count.loops),
+                        count.loops
+                    ),
+                    style,
+                ));
+            }
+        }
+
+        if self.audio.engine.is_free_recording() {
+            let style = self.theme.error.add_modifier(Modifier::REVERSED);
+
+            title.push(Span::raw(" "));
+            title.push(Span::styled(" FREE ", style));
+        }
+
+        if self.stack_mode.is_on() {
+            let style = self.theme.accent.add_modifier(Modifier::REVERSED);
+
+            title.push(Span::raw(" "));
+            title.push(Span::styled(
+                format!(" {} ", self.stack_mode.name().to_uppercase()),
+                style,
+            ));
+        }
+
+        if self.macro_recording.is_some() {
+            let style = self.theme.warning.add_modifier(Modifier::REVERSED);
+
+            title.push(Span::raw(" "));
+            title.push(Span::styled(" MACRO ", style));
+        }
+
+        if self.preview.is_some() {
+            let style = self.theme.accent.add_modifier(Modifier::REVERSED);
+
+            title.push(Span::raw(" "));
+            title.push(Span::styled(" PREVIEW ", style));
+        }
+
+        if let Some(status) = self.resample_status() {
+            title.push(Span::raw(" "));
+            title.push(status);
+        }
+
+        title.extend(self.scene_strip());
+
+        // everything that happens when the loop wraps around
+        if !self.audio.queued.is_empty() {
+            let labels = self
+                .audio
+                .queued
+                .iter()
+                .map(|(_, action)| action.label())
+                .collect::<Vec<_>>();
+
+            let text = format!(" next loop: {}", labels.join(", "));
+            title.push(Span::styled(text, self.theme.warning));
+        }
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Spans::from(title));
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
             .margin(1)
             .horizontal_margin(2)
-            .direction(Direction::Horizontal)
-            .constraints(constraints.clone())
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
             .split(area);
 
+        self.render_loop_progress(frame, rows[0]);
+        self.render_beat_dots(frame, rows[1]);
+
+        let track_count = self.audio.tracks.len();
+
+        // hide the edit and info panels when they don't fit next to the tracks
+        let tracks_width = track_count as u16 * 6 + 4;
+        let show_edit = area.width >= tracks_width + 30;
+        let show_info = area.width >= tracks_width + 30 + 20;
+
+        let mut constraints = vec![Constraint::Length(6); track_count];
+        if show_edit {
+            constraints.push(Constraint::Length(30));
+        }
+        constraints.push(Constraint::Min(0));
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(rows[2]);
+
         for (i, track) in self.audio.tracks.iter().enumerate() {
-            let color = RAINBOW[i % RAINBOW.len()];
+            let color = self.theme.color_of(track, i);
             self.render_track(frame, chunks[i], i, track, color);
         }
 
-        self.render_track_edit(frame, chunks[chunks.len() - 2]);
-        self.render_track_info(frame, chunks[chunks.len() - 1]);
+        if show_edit {
+            self.render_track_edit(frame, chunks[track_count]);
+        }
+
+        if show_info {
+            self.render_track_info(frame, chunks[track_count + 1]);
+        }
+    }
+
+    pub fn render_loop_progress<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let width = area.width as usize;
+        let beats = self.snapshot.beats as usize;
+        let filled = (self.snapshot.loop_progress() * width as f32) as usize;
+
+        let mut progress = String::with_capacity(filled * 3);
+        let mut rest = String::with_capacity((width - filled) * 3);
+
+        for x in 0..width {
+            // mark the cells where a new beat starts
+            let tick = x > 0 && beats > 0 && x * beats / width != (x - 1) * beats / width;
+
+            let cell = match (x < filled, tick) {
+                (true, true) => '┿',
+                (true, false) => '━',
+                (false, true) => '┼',
+                (false, false) => '─',
+            };
+
+            if x < filled {
+                progress.push(cell);
+            } else {
+                rest.push(cell);
+            }
+        }
+
+        let spans = Spans::from(vec![
+            Span::styled(progress, self.theme.accent),
+            Span::styled(rest, self.theme.empty),
+        ]);
+
+        frame.render_widget(Paragraph::new(spans), area);
     }
 
     pub fn render_track_edit<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
@@ -167,39 +620,99 @@ impl App {
                 Constraint::Length(1),
                 Constraint::Length(1),
                 Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
             ])
             .split(area);
 
         self.render_track_volume(frame, chunks[0]);
-        self.render_track_record(frame, chunks[1]);
-        self.render_track_remove(frame, chunks[2]);
+        self.render_track_send(frame, chunks[1]);
+        self.render_track_input(frame, chunks[2]);
+        self.render_track_offset(frame, chunks[3]);
+        self.render_track_color(frame, chunks[4]);
+        self.render_track_group(frame, chunks[5]);
+        self.render_track_record(frame, chunks[6]);
+        self.render_track_remove(frame, chunks[7]);
     }
 
     pub fn render_track_volume<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let mut volume = Paragraph::new("volume 'v'");
+        let mut volume = Paragraph::new(self.key_title("volume", Action::VolumeSelected));
 
         if matches!(self.edit_mode, EditMode::TrackVolume(_)) {
-            volume = volume.style(Style::default().fg(Color::Red));
+            volume = volume.style(self.theme.active);
         }
 
         frame.render_widget(volume, area);
     }
 
+    pub fn render_track_send<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut send = Paragraph::new(self.key_title("delay send", Action::SendSelected));
+
+        if matches!(self.edit_mode, EditMode::TrackSend(_)) {
+            send = send.style(self.theme.active);
+        }
+
+        frame.render_widget(send, area);
+    }
+
+    pub fn render_track_input<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut input = Paragraph::new(self.key_title("input", Action::InputSelected));
+
+        if matches!(self.edit_mode, EditMode::TrackInput(_)) {
+            input = input.style(self.theme.active);
+        }
+
+        frame.render_widget(input, area);
+    }
+
+    pub fn render_track_offset<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut offset = Paragraph::new(self.key_title("offset", Action::OffsetSelected));
+
+        if matches!(self.edit_mode, EditMode::TrackOffset(_)) {
+            offset = offset.style(self.theme.active);
+        }
+
+        frame.render_widget(offset, area);
+    }
+
+    pub fn render_track_color<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut color = Paragraph::new(self.key_title("color", Action::ColorSelected));
+
+        if matches!(self.edit_mode, EditMode::TrackColor(_)) {
+            color = color.style(self.theme.active);
+        }
+
+        frame.render_widget(color, area);
+    }
+
+    pub fn render_track_group<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut group = Paragraph::new(self.key_title("group", Action::GroupSelected));
+
+        if matches!(self.edit_mode, EditMode::TrackGroup(_)) {
+            group = group.style(self.theme.active);
+        }
+
+        frame.render_widget(group, area);
+    }
+
     pub fn render_track_record<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let mut record = Paragraph::new("record 'r'");
+        let mut record = Paragraph::new(self.key_title("record", Action::RecordSelected));
 
         if matches!(self.edit_mode, EditMode::RecordTrack) {
-            record = record.style(Style::default().fg(Color::Red));
+            record = record.style(self.theme.active);
         }
 
         frame.render_widget(record, area);
     }
 
     pub fn render_track_remove<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        let mut remove = Paragraph::new("remove 'R'");
+        let mut remove = Paragraph::new(self.key_title("remove", Action::RemoveSelected));
 
-        if matches!(self.edit_mode, EditMode::RemoveTrack) {
-            remove = remove.style(Style::default().fg(Color::Red));
+        if matches!(self.edit_mode, EditMode::RemoveTrack(_)) {
+            remove = remove.style(self.theme.active);
         }
 
         frame.render_widget(remove, area);
@@ -207,7 +720,127 @@ impl App {
 
     pub fn render_track_info<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
         let block = Block::default().borders(Borders::ALL).title("Info");
-        frame.render_widget(block, area);
+
+        let confirmation = match self.edit_mode {
+            EditMode::RemoveTrack(Some(index)) => Some(format!(
+                "remove track {}? press again to delete, Esc to cancel",
+                (index + 1) % 10
+            )),
+            EditMode::Paste(Some(index)) => Some(format!(
+                "replace track {} with the clipboard? press again to paste, Esc to cancel",
+                (index + 1) % 10
+            )),
+            _ => None,
+        };
+
+        if let Some(text) = confirmation {
+            let paragraph = Paragraph::new(text)
+                .style(self.theme.error)
+                .wrap(Wrap { trim: true })
+                .block(block);
+
+            frame.render_widget(paragraph, area);
+            return;
+        }
+
+        let Some(track) = self.audio.tracks.get(self.selected_track) else {
+            frame.render_widget(block, area);
+            return;
+        };
+
+        let clip = match track.clip {
+            Some(ref clip) => format!("{:.2}s, {}ch", clip.duration(), clip.channels),
+            None => String::from("empty"),
+        };
+
+        let stutter = match self.audio.engine.stutter() {
+            Some(stutter) if stutter.track == self.selected_track => {
+                format!("{} beat", stutter.length.name())
+            }
+            _ => format!("off ({} beat)", self.stutter_length.name()),
+        };
+
+        let input = match track.input_channel {
+            Some(channel) => self.input_channel_text(channel),
+            None => String::from("all"),
+        };
+
+        // in beats, along with the time for offsets off the beat
+        let offset = match track.clip {
+            Some(ref clip) if track.offset_frames != 0 => {
+                let seconds = track.offset_frames as f32 / clip.sample_rate.0 as f32;
+                let beats = seconds * self.snapshot.bpm as f32 / 60.0;
+                format!("{:+.2} beats ({:+.0} ms)", beats, seconds * 1000.0)
+            }
+            _ => String::from("none"),
+        };
+
+        let group = match track.exclusive_group {
+            Some(group) => format!("{}", group + 1),
+            None => String::from("none"),
+        };
+
+        let clipboard = match self.clipboard {
+            Some(ref clip) => format!("{:.2}s, {}ch", clip.duration(), clip.channels),
+            None => String::from("empty"),
+        };
+
+        let text = vec![
+            Spans::from(format!("track {}", (self.selected_track + 1) % 10)),
+            Spans::from(format!("clip: {}", clip)),
+            Spans::from(
+                match (track.ramp, self.ramp_beats_left(self.selected_track)) {
+                    (Some(ramp), Some(beats)) => format!(
+                        "volume: {}% → {}% in {:.1} beats",
+                        ramp.from, ramp.target, beats
+                    ),
+                    _ => format!("volume: {}%", track.volume),
+                },
+            ),
+            Spans::from(format!("muted: {}", if track.muted { "yes" } else { "no" })),
+            Spans::from(format!("cue: {}", if track.cue { "yes" } else { "no" })),
+            Spans::from(format!("send: {:.0}%", track.send * 100.0)),
+            Spans::from(format!("input: {}", input)),
+            Spans::from(format!("offset: {}", offset)),
+            Spans::from(format!(
+                "sync: {}",
+                if track.synced { "loop" } else { "free" }
+            )),
+            Spans::from(format!(
+                "direction: {}",
+                if track.reversed {
+                    "backwards"
+                } else {
+                    "forwards"
+                }
+            )),
+            Spans::from(format!("group: {}", group)),
+            Spans::from(match track.generation_damping {
+                Some(damping) => format!("damping: {:.0}%", damping * 100.0),
+                None => String::from("damping: off"),
+            }),
+            Spans::from(if track.drive > 0.0 {
+                format!("drive: {:.0}%", track.drive * 100.0)
+            } else {
+                String::from("drive: off")
+            }),
+            self.pattern_spans(self.selected_track, track),
+            Spans::from(format!("region: {}", self.region_text(track))),
+            Spans::from(format!(
+                "note: {}",
+                if track.note.is_empty() {
+                    "none"
+                } else {
+                    &track.note
+                }
+            )),
+            Spans::from(format!("stutter: {}", stutter)),
+            Spans::from(format!("clipboard: {}", clipboard)),
+            Spans::from(format!("master: {}%", self.snapshot.master_volume)),
+        ];
+
+        let paragraph = Paragraph::new(text).block(block);
+        frame.render_widget(paragraph, area);
     }
 
     pub fn render_track<B: Backend>(
@@ -218,55 +851,200 @@ impl App {
         track: &Track,
         color: Color,
     ) {
-        area.width = 6;
+        area.width = area.width.min(6);
 
-        let block = Block::default()
+        // the number of an empty track is dimmed, so the free ones stand out at a glance
+        let number = ((index + 1) % 10).to_string();
+        let mut title = if track.clip.is_some() {
+            vec![Span::raw(number)]
+        } else {
+            vec![Span::styled(number, self.theme.empty)]
+        };
+
+        // mark the track the next recording lands on
+        let engine = &self.audio.engine;
+        let pedal_track = self.pedal.is_capturing() && self.pedal.track() == Some(index);
+
+        if engine.armed_track() == Some(index) && !engine.is_armed_recording() {
+            title.push(Span::styled("○", self.theme.warning));
+        } else if engine.armed_track() == Some(index)
+            || pedal_track
+            || self.edit_mode == EditMode::RecordTrack && self.selected_track == index
+        {
+            title.push(Span::styled("●", self.theme.error));
+        }
+
+        // flipping between the last layer and the clip before it
+        if let Some(label) = self.compare_label(index) {
+            title.push(Span::styled(label, self.theme.active));
+        }
+
+        // the volume is fading up or down
+        if let Some(ramp) = track.ramp {
+            let arrow = if ramp.target > ramp.from {
+                "↑"
+            } else {
+                "↓"
+            };
+            title.push(Span::styled(arrow, self.theme.accent));
+        }
+
+        // the clip loops on its own length
+        if !track.synced {
+            title.push(Span::styled("~", self.theme.accent));
+        }
+
+        // the clip plays backwards
+        if track.reversed {
+            title.push(Span::styled("<", self.theme.accent));
+        }
+
+        // only one track of the group plays at a time
+        if let Some(group) = track.exclusive_group {
+            let style = if self.edit_mode == EditMode::TrackGroup(Some(index)) {
+                self.theme.active
+            } else {
+                self.theme.accent
+            };
+
+            title.push(Span::styled((group + 1).to_string(), style));
+        }
+
+        // older layers recede with every overdub
+        if track.generation_damping.is_some() {
+            let style = if self.edit_mode == EditMode::TrackDamping(Some(index)) {
+                self.theme.active
+            } else {
+                self.theme.accent
+            };
+
+            title.push(Span::styled("≈", style));
+        }
+
+        // pre-listened on the cue channels, shaped like a pair of headphones
+        if track.cue {
+            title.push(Span::styled("Ω", self.theme.accent));
+        }
+
+        // the clip would play at the wrong speed
+        if track.rate_mismatch(self.snapshot.sample_rate) {
+            title.push(Span::styled("!", self.theme.warning));
+        }
+
+        // show the slice length while the track repeats it
+        match self.audio.engine.stutter() {
+            Some(stutter) if stutter.track == index => {
+                title.push(Span::styled(stutter.length.name(), self.theme.accent));
+            }
+            _ => {}
+        }
+
+        let mut block = Block::default()
             .borders(Borders::ALL)
-            .title(((index + 1) % 10).to_string());
+            .title(Spans::from(title));
+
+        let confirm_pending = matches!(
+            self.edit_mode,
+            EditMode::RemoveTrack(Some(i)) | EditMode::Paste(Some(i)) if i == index
+        );
+
+        let mut border = if confirm_pending {
+            self.theme.error
+        } else if self.selected_track == index {
+            self.theme.accent
+        } else {
+            Style::default()
+        };
+
+        // what's heard lags behind until the callback picks up the change on the next beat
+        if self.audio.is_track_pending(index) {
+            border = border.add_modifier(Modifier::DIM);
+        }
+
+        block = block.border_style(border);
 
         frame.render_widget(block, area);
 
+        // a replace in progress or the input channel takes the bottom row of the column, the
+        // drive the row above it
+        let replacing = matches!(self.replace, Some(ref buffer) if buffer.track == index);
+        let label = if replacing {
+            Some(Span::styled("REPL", self.theme.error))
+        } else if let Some(channel) = track.input_channel {
+            let style = if self.edit_mode == EditMode::TrackInput(Some(index)) {
+                self.theme.active
+            } else {
+                self.theme.empty
+            };
+
+            Some(Span::styled(format!("in{}", channel + 1), style))
+        } else {
+            None
+        };
+
+        let drive = (track.drive > 0.0 || self.edit_mode == EditMode::TrackDrive(Some(index)))
+            .then(|| {
+                let style = if self.edit_mode == EditMode::TrackDrive(Some(index)) {
+                    self.theme.active
+                } else {
+                    self.theme.accent
+                };
+
+                Span::styled(format!("d{:.0}", track.drive * 100.0), style)
+            });
+
+        for label in [label, drive].into_iter().flatten() {
+            let inner = area.inner(&Margin {
+                vertical: 1,
+                horizontal: 1,
+            });
+
+            if inner.height > 1 {
+                let mut label_area = inner;
+                label_area.y += inner.height - 1;
+                label_area.height = 1;
+                area.height -= 1;
+
+                frame.render_widget(Paragraph::new(Spans::from(label)), label_area);
+            }
+        }
+
         let chunks = Layout::default()
             .margin(1)
             .direction(Direction::Horizontal)
             .constraints([Constraint::Length(3), Constraint::Length(1)])
             .split(area);
 
-        let sample_index = self.audio.engine.sample();
-
         if let Some(ref clip) = track.clip {
-            let mut sample = 0.0f32;
-            for i in 0..1024 {
-                let s = clip.average_sample(sample_index as u64 + i);
-                sample = sample.max(s.abs());
+            if chunks[0].height >= MIN_SPARKLINE_HEIGHT {
+                self.render_sparkline(frame, chunks[0], clip, track, color);
+            } else {
+                self.render_level(frame, chunks[0], index, color);
             }
+        }
 
-            sample *= track.volume_factor();
-
-            let data = [("", (sample * 200.0) as u64)];
-            let bar = BarChart::default()
-                .data(&data)
-                .bar_width(3)
-                .bar_gap(0)
-                .max(100)
-                .bar_style(Style::default().fg(color))
-                .value_style(Style::default().fg(Color::White).bg(color));
-
-            frame.render_widget(bar, chunks[0]);
+        // latch a cell at the top of the meter for a while after the track clipped
+        if self.audio.engine.is_clip_held(index) && chunks[0].height > 0 {
+            let cell = Rect {
+                height: 1,
+                ..chunks[0]
+            };
+            let text = "█".repeat(cell.width as usize);
+            frame.render_widget(Paragraph::new(Span::styled(text, self.theme.error)), cell);
         }
 
-        let mut volume_color = Color::White;
+        let mut volume_style = self.theme.volume;
 
         if track.muted {
-            volume_color = Color::Gray;
+            volume_style = self.theme.muted;
         }
 
         if track.clip.is_none() {
-            volume_color = Color::DarkGray;
+            volume_style = self.theme.empty;
         }
 
         if self.edit_mode == EditMode::TrackVolume(Some(index)) {
-            volume_color = Color::Red;
+            volume_style = self.theme.active;
         }
 
         let data = [("", track.volume as u64)];
@@ -275,8 +1053,73 @@ impl App {
             .bar_width(1)
             .bar_gap(0)
             .max(200)
-            .bar_style(Style::default().fg(volume_color));
+            .bar_style(volume_style);
 
         frame.render_widget(bar, chunks[1]);
     }
+
+    // draws the envelope of the whole clip top to bottom, highlighting the playhead
+    pub fn render_sparkline<B: Backend>(
+        &self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        clip: &Clip,
+        track: &Track,
+        color: Color,
+    ) {
+        let rows = area.height as usize;
+        let frames = clip.frame_count().max(1);
+        let playhead = (self.snapshot.sample % frames * rows as u64 / frames) as usize;
+
+        let style = self.theme.meter(color);
+        let text = (0..rows)
+            .map(|row| {
+                let start = row as f32 / rows as f32;
+                let end = (row + 1) as f32 / rows as f32;
+                let peak = clip.peak_between(start, end) * track.volume_factor();
+
+                // the same scale as the level meter
+                let level = db_meter_ratio(peak) * (SPARK_LEVELS.len() - 1) as f32;
+                let cell = if peak > 0.0 {
+                    SPARK_LEVELS[level.round() as usize]
+                } else {
+                    ' '
+                };
+
+                let line = cell.to_string().repeat(area.width as usize);
+
+                // rows outside the region are silent, so they're drawn dim
+                let middle = (2 * row as u64 + 1) * frames / (2 * rows as u64);
+                let style = match track.region {
+                    Some(ref region) if !region.contains(&middle) => self.theme.empty,
+                    _ => style,
+                };
+
+                if row == playhead {
+                    Spans::from(Span::styled(line, style.add_modifier(Modifier::REVERSED)))
+                } else {
+                    Spans::from(Span::styled(line, style))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        frame.render_widget(Paragraph::new(text), area);
+    }
+
+    // draws the level of what the track plays as a single bar, its peak or rms as chosen
+    pub fn render_level<B: Backend>(
+        &self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        index: usize,
+        color: Color,
+    ) {
+        let level = self
+            .track_levels
+            .get(index)
+            .map_or(0.0, |level| level.get(self.meter_mode));
+
+        let meter = self.theme.db_meter(level, color, true);
+        frame.render_widget(meter, area);
+    }
 }
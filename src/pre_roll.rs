@@ -0,0 +1,133 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use cpal::{ChannelCount, SampleRate};
+
+use crate::clip::Clip;
+
+// how much input the buffer holds past the limit, so the callback doesn't write over a
+// capture while the app copies it, in seconds
+const HEADROOM: u64 = 1;
+
+/// A circular buffer holding the most recent input, shared between the output callback
+/// writing into it and the app copying captures out of it, so neither allocates nor locks.
+pub struct PreRoll {
+    channels: ChannelCount,
+    sample_rate: SampleRate,
+    // how many frames a capture takes at most
+    limit: u64,
+    samples: Box<[AtomicU32]>,
+    // how many samples were ever written
+    written: AtomicU64,
+}
+
+impl PreRoll {
+    /// Allocates a buffer for captures of up to `limit` frames.
+    pub fn new(channels: ChannelCount, sample_rate: SampleRate, limit: u64) -> Self {
+        let frames = limit + sample_rate.0 as u64 * HEADROOM;
+        let len = frames as usize * channels.max(1) as usize;
+
+        Self {
+            channels: channels.max(1),
+            sample_rate,
+            limit,
+            samples: (0..len).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicU64::new(0),
+        }
+    }
+
+    /// Writes the next sample, over the oldest one once the buffer is full. Only called by
+    /// the output callback.
+    pub fn push(&self, sample: f32) {
+        let written = self.written.load(Ordering::Relaxed);
+        let position = (written % self.samples.len() as u64) as usize;
+
+        self.samples[position].store(sample.to_bits(), Ordering::Relaxed);
+        self.written.store(written + 1, Ordering::Release);
+    }
+
+    /// Marks the last `frames` frames of input written so far to be copied out later.
+    pub fn capture(self: &Arc<Self>, frames: u64) -> PreRollCapture {
+        PreRollCapture {
+            pre_roll: self.clone(),
+            written: self.written.load(Ordering::Acquire),
+            frames,
+        }
+    }
+}
+
+/// The last loop length of input at the moment a capture was asked for.
+#[derive(Clone)]
+pub struct PreRollCapture {
+    pre_roll: Arc<PreRoll>,
+    written: u64,
+    frames: u64,
+}
+
+impl fmt::Debug for PreRollCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreRollCapture")
+            .field("written", &self.written)
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
+
+impl PreRollCapture {
+    /// Copies the captured input out, oldest first, with silence in front of what the buffer
+    /// doesn't hold.
+    pub fn clip(&self) -> Clip {
+        let pre_roll = &self.pre_roll;
+        let channels = pre_roll.channels as u64;
+        let len = pre_roll.samples.len() as u64;
+
+        let mut samples = vec![0.0; (self.frames * channels) as usize];
+
+        let count = self.written.min(self.frames.min(pre_roll.limit) * channels);
+        let start = samples.len() - count as usize;
+
+        for (index, target) in samples[start..].iter_mut().enumerate() {
+            let position = (self.written - count + index as u64) % len;
+            *target = f32::from_bits(pre_roll.samples[position as usize].load(Ordering::Relaxed));
+        }
+
+        Clip::unanalyzed(pre_roll.channels, pre_roll.sample_rate, samples.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_takes_the_latest_input() {
+        let pre_roll = Arc::new(PreRoll::new(1, SampleRate(10), 20));
+
+        // a little input, silence in front of it
+        (0..5).for_each(|sample| pre_roll.push(sample as f32));
+        let clip = pre_roll.capture(8).clip();
+        assert_eq!(
+            &clip.dense_samples()[..],
+            &[0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 4.0]
+        );
+
+        // around the end of the buffer a few times, writing on after the capture
+        (5..100).for_each(|sample| pre_roll.push(sample as f32));
+        let capture = pre_roll.capture(8);
+        (100..105).for_each(|sample| pre_roll.push(sample as f32));
+
+        let expected = (92..100).map(|sample| sample as f32).collect::<Vec<_>>();
+        assert_eq!(&capture.clip().dense_samples()[..], &expected[..]);
+
+        // a loop longer than the limit only gets the limit
+        let clip = pre_roll.capture(25).clip();
+        assert_eq!(clip.frame_count(), 25);
+        assert_eq!(clip.dense_samples()[4], 0.0);
+        assert_eq!(clip.dense_samples()[5], 85.0);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{app::App, clip::Clip};
+
+/// Where a previewed clip came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewSource {
+    Recording,
+    Clipboard,
+    /// The last recording, played while the track to commit it to is chosen.
+    Pending,
+    /// A clip of the archive, auditioned from its browser.
+    Archive,
+}
+
+impl App {
+    // plays the last recording on its own, or the clipboard if nothing was recorded, or stops
+    // the preview
+    pub fn toggle_preview(&mut self) {
+        if self.preview.is_some() {
+            self.stop_preview();
+            self.info("stopped the preview");
+            return;
+        }
+
+        let engine = &self.audio.engine;
+        if !engine.is_recording() {
+            self.warn("launch the streams to preview");
+            return;
+        }
+
+        let (clip, source) = match self.audio.get_clip() {
+            Some(clip) => (clip, PreviewSource::Recording),
+            None => match self.clipboard {
+                Some(ref clip) => (clip.clone(), PreviewSource::Clipboard),
+                None => {
+                    self.warn("nothing to preview, record a loop or yank a track first");
+                    return;
+                }
+            },
+        };
+
+        self.start_preview(clip, source);
+
+        match source {
+            PreviewSource::Recording => self.info("previewing the last recording"),
+            PreviewSource::Clipboard => self.info("previewing the clipboard"),
+            PreviewSource::Pending | PreviewSource::Archive => {}
+        }
+    }
+
+    // plays the last recording while the track to commit it to is chosen, unless something
+    // is already previewed
+    pub fn preview_pending(&mut self) {
+        if self.preview.is_some() || !self.audio.engine.is_recording() {
+            return;
+        }
+
+        if let Some(clip) = self.audio.get_clip() {
+            self.start_preview(clip, PreviewSource::Pending);
+        }
+    }
+
+    pub fn start_preview(&mut self, clip: Clip, source: PreviewSource) {
+        let engine = &self.audio.engine;
+
+        // the ui keeps a copy, so the callback never frees the clip
+        self.preview = Some((clip.clone(), source));
+        engine.set_preview_handoff(false);
+        engine.set_preview_clip(Some(clip));
+        engine.set_previewing(true);
+    }
+
+    pub fn toggle_preview_dim(&mut self) {
+        let dim = !self.audio.engine.preview_dim();
+        self.audio.engine.set_preview_dim(dim);
+
+        if dim {
+            self.info("the tracks are dimmed while previewing");
+        } else {
+            self.info("the tracks play at full volume while previewing");
+        }
+    }
+
+    // ends the preview, called when the capture is discarded
+    pub fn stop_preview(&mut self) {
+        if self.preview.take().is_none() {
+            return;
+        }
+
+        self.audio.engine.set_preview_handoff(false);
+        self.audio.engine.set_previewing(false);
+        self.audio.engine.set_preview_clip(None);
+    }
+
+    // ends the preview the moment the output callback picks up the tracks sent last, called
+    // when the capture is committed
+    pub fn hand_off_preview(&mut self) {
+        let Some((clip, _)) = self.preview.take() else {
+            return;
+        };
+
+        self.handed_off_preview = Some(clip);
+        self.audio.engine.set_preview_handoff(true);
+    }
+
+    // lets go of a handed off preview once the callback is done with it, called every update
+    pub fn update_preview(&mut self) {
+        if self.handed_off_preview.is_some() && !self.audio.engine.is_previewing() {
+            self.handed_off_preview = None;
+        }
+    }
+
+    // stops the preview, throwing away the recording if that's what was previewed
+    pub fn discard_preview(&mut self) {
+        let Some((_, source)) = self.preview else {
+            return;
+        };
+
+        self.stop_preview();
+
+        if source == PreviewSource::Recording {
+            self.audio.clip = None;
+            self.info("discarded the recording");
+        } else {
+            self.info("stopped the preview");
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use tui::{
+    backend::Backend,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App},
+    audio::DeviceCapabilities,
+    keymap::Action,
+};
+
+impl App {
+    // asks the selected devices what they support, or closes the list
+    pub fn toggle_probe(&mut self) {
+        if self.probe.take().is_some() {
+            return;
+        }
+
+        let settings = self.shown_settings();
+        let probe = [
+            ("Input", settings.input_capabilities()),
+            ("Output", settings.output_capabilities()),
+        ]
+        .into_iter()
+        .filter_map(|(direction, capabilities)| Some((direction, capabilities?)))
+        .collect::<Vec<_>>();
+
+        if probe.is_empty() {
+            self.warn("no device to probe");
+            return;
+        }
+
+        self.probe = Some(probe);
+        self.probe_state.select(Some(0));
+    }
+
+    pub fn scroll_probe(&mut self, offset: i32) {
+        let Some(ref probe) = self.probe else {
+            return;
+        };
+
+        let len = self.probe_items(probe).len() as i32;
+        let selected = self.probe_state.selected().unwrap_or(0) as i32;
+        let index = (selected - offset).clamp(0, len - 1);
+        self.probe_state.select(Some(index as usize));
+    }
+
+    // a line for the device, one for its default config and one for each supported config,
+    // with a failed query shown in place of what it would have found
+    fn probe_items(&self, probe: &[(&str, DeviceCapabilities)]) -> Vec<ListItem<'static>> {
+        let mut items = Vec::new();
+
+        for (direction, capabilities) in probe {
+            items.push(ListItem::new(Spans::from(vec![
+                Span::styled(format!("{}: ", direction), self.theme.accent),
+                Span::raw(capabilities.device.clone()),
+            ])));
+
+            items.push(match capabilities.default {
+                Ok(ref config) => ListItem::new(format!("  default  {}", config.describe())),
+                Err(ref err) => ListItem::new(Span::styled(
+                    format!("  default  {}", err),
+                    self.theme.error,
+                )),
+            });
+
+            match capabilities.supported {
+                Ok(ref configs) if configs.is_empty() => {
+                    items.push(ListItem::new(Span::styled(
+                        "  supports nothing",
+                        self.theme.warning,
+                    )));
+                }
+                Ok(ref configs) => {
+                    for config in configs {
+                        items.push(ListItem::new(format!("  supports {}", config.describe())));
+                    }
+                }
+                Err(ref err) => items.push(ListItem::new(Span::styled(
+                    format!("  supports {}", err),
+                    self.theme.error,
+                ))),
+            }
+        }
+
+        items
+    }
+
+    pub fn render_probe<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let Some(ref probe) = self.probe else {
+            return;
+        };
+
+        let size = frame.size();
+        let area = centered_rect(size, size.width * 3 / 4, size.height * 3 / 4);
+
+        let items = self.probe_items(probe);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.key_title("Device capabilities", Action::ToggleProbe))
+            .border_style(self.theme.accent);
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(self.theme.highlight);
+
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut self.probe_state);
+    }
+}
@@ -0,0 +1,1418 @@
+use std::{
+    f32::consts::TAU,
+    mem,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use cpal::{OutputStreamTimestamp, SampleRate};
+use crossbeam::channel::Sender;
+use ringbuf::HeapConsumer;
+
+use crate::{
+    aggregate::SecondaryInput,
+    audio::AudioEvent,
+    clip::{Clip, RecordedLoop},
+    delay::DelayLine,
+    drift::DriftMeter,
+    engine::{AudioEngine, LoopRestart, TempoTiming},
+    gate::{GateMode, NoiseGate},
+    meter::LevelAccumulator,
+    pre_roll::PreRoll,
+    queue::QueuedAction,
+    replace::{ReplaceBuffer, REPLACE_FADE},
+    stutter::ActiveStutter,
+    track::{InputFollow, Tracks, TRACK_COUNT},
+};
+
+// the time it takes to fade out the output when killed, in seconds
+const KILL_RAMP: f32 = 0.005;
+
+// the time it takes the monitored input to move to or from the armed track's settings, in
+// seconds
+const FOLLOW_RAMP: f32 = 0.02;
+
+// the time it takes to fade out the output when quitting, in seconds
+pub const SHUTDOWN_RAMP: f32 = 0.05;
+
+// the pitch and level of the test tone played during setup
+pub const TEST_TONE_FREQUENCY: f32 = 440.0;
+const TEST_TONE_LEVEL: f32 = 0.25;
+
+// the most input the pre roll holds, in seconds, anything further back in a longer loop is
+// captured as silence
+const PRE_ROLL_LIMIT: u64 = 60;
+
+// how much louder a loaded click plays on the first beat of the loop, about +6 dB
+const CLICK_ACCENT: f32 = 2.0;
+
+// how much the tracks are turned down while previewing with dimming on, about -12 dB
+pub const PREVIEW_DIM: f32 = 0.25;
+
+/// The first output channel of the cue bus unless set otherwise, the ones before it play the
+/// main mix.
+pub const CUE_CHANNEL: u16 = 2;
+
+fn metronome(time: f32) -> f32 {
+    const A6: f32 = 1760.0;
+
+    (time * A6).sin() * (1.0 - time * 2.0).clamp(0.0, 1.0) * 0.5
+}
+
+// how long the chime rings, in seconds
+const CHIME_LENGTH: f32 = 0.6;
+
+// two rising notes, each dying away
+fn chime(time: f32) -> f32 {
+    const E6: f32 = 1318.5;
+    const A6: f32 = 1760.0;
+
+    let half = CHIME_LENGTH / 2.0;
+    let (frequency, time) = if time < half {
+        (E6, time)
+    } else {
+        (A6, time - half)
+    };
+
+    (time * frequency * TAU).sin() * (1.0 - time / half).clamp(0.0, 1.0) * 0.3
+}
+
+// the tracks a scene switch moved away from, playing on under the new ones until they've
+// faded in
+struct SceneFade {
+    tracks: Tracks,
+    // the frame of the played count the fade started at
+    start: u64,
+    frames: u64,
+}
+
+impl SceneFade {
+    // how much of the new scene is heard at a played frame, from 0 to 1
+    fn mix_at(&self, frame: u64) -> f32 {
+        let elapsed = frame.saturating_sub(self.start);
+        (elapsed as f32 / self.frames.max(1) as f32).min(1.0)
+    }
+}
+
+/// Everything the output callback works on, separate from cpal so it can be driven without a
+/// stream.
+pub struct OutputProcessor {
+    engine: Arc<AudioEngine>,
+    tracks: Tracks,
+    sends: bool,
+    cues: bool,
+    delay: DelayLine,
+    stutter: Option<ActiveStutter>,
+    scene_fade: Option<SceneFade>,
+    // where in its period the test tone is, from 0 to 1
+    tone_phase: f32,
+    // the loaded click if any, the frame of it playing, how loud, and the beat it played on
+    click: Option<Clip>,
+    click_position: Option<u64>,
+    click_gain: f32,
+    click_beat: u64,
+    // how many frames of the chime have played, while it plays
+    chime: Option<u64>,
+    // the clip played on its own while previewing
+    preview: Option<Clip>,
+    // the armed track whose loop is being recorded
+    armed: Option<usize>,
+    // the loop that just ended and the track it was armed for, held back until enough of the
+    // next loop is captured to fold its tail into the head
+    post_roll: Option<(Clip, Option<usize>)>,
+    // the armed track recording from where it was armed, along with what it captured so far
+    // and how many frames make up a whole loop
+    punch_in: Option<usize>,
+    // whether a loop is recorded without a length, the loop not wrapping until it's stopped
+    free: bool,
+    capture: Vec<f32>,
+    capture_frames: u64,
+    // the last loop of input, kept whether anything is armed or not
+    pre_roll: Arc<PreRoll>,
+    // the working copy of a track being replaced, and how far the input has faded into it
+    replace: Option<Arc<ReplaceBuffer>>,
+    replace_ramp: f32,
+    replace_step: f32,
+    events: Sender<AudioEvent>,
+    input: HeapConsumer<f32>,
+    // the second input device, its channels following the first's
+    secondary: Option<SecondaryInput>,
+    recording: Vec<f32>,
+    sample_rate: SampleRate,
+    // the channels of both input devices, and of the first alone
+    input_channels: u16,
+    device_channels: u16,
+    output_channels: u16,
+    feedback_channels: u16,
+    is_mono: bool,
+    channel: u16,
+    feedback: f32,
+    // the last frame popped from the input, played again while it runs dry or is held back
+    last_frame: Vec<f32>,
+    // how many frames the input is ahead of the monitoring delay, behind when negative
+    monitor_error: i64,
+    holding: bool,
+    correction_phase: bool,
+    drift: DriftMeter,
+    gate: NoiseGate,
+    gate_mode: GateMode,
+    // whether the input is recorded before the gate instead of after
+    record_dry: bool,
+    kill_step: f32,
+    shutdown_step: f32,
+    gain: f32,
+    fade_gain: f32,
+    // the volume and send the monitored input plays at, following the armed track
+    follow_volume: f32,
+    follow_send: f32,
+    follow_step: f32,
+    // what every track added to the current buffer
+    levels: [LevelAccumulator; TRACK_COUNT],
+}
+
+/// What the output callback reads the input from, the buffer the first device's callback fills
+/// and the second device if there is one.
+pub struct ProcessorInput {
+    pub consumer: HeapConsumer<f32>,
+    /// The channels of the first device.
+    pub channels: u16,
+    pub secondary: Option<SecondaryInput>,
+}
+
+impl OutputProcessor {
+    pub fn new(
+        engine: Arc<AudioEngine>,
+        mut tracks: Tracks,
+        input: ProcessorInput,
+        sample_rate: SampleRate,
+        output_channels: u16,
+        events: Sender<AudioEvent>,
+    ) -> Self {
+        let ProcessorInput {
+            consumer: input,
+            channels: input_channels,
+            secondary,
+        } = input;
+
+        engine.set_sample_rate(sample_rate.0 as u64);
+        engine.set_input_drift(None);
+        engine.set_secondary_drift(None);
+        engine.set_scene_fade(None);
+        engine
+            .output_snapshot
+            .set_channels(output_channels.min(engine.cue_channel()));
+        tracks.advance_ramps(engine.played());
+        tracks.advance_fades(engine.played());
+
+        // the second device's channels are recorded and monitored after the first's
+        let device_channels = input_channels;
+        let input_channels =
+            input_channels + secondary.as_ref().map_or(0, |input| input.channels());
+
+        // the input is mixed down when its channels don't line up with the output
+        let is_mono = input_channels != output_channels || engine.is_mono_input();
+        let feedback_channels = if is_mono { 1 } else { input_channels };
+
+        // fade the output over 5ms when killed, to avoid clicking
+        let samples_per_second = sample_rate.0 as f32 * output_channels as f32;
+
+        Self {
+            engine,
+            sends: tracks.has_sends(),
+            cues: tracks.has_cues(),
+            tracks,
+            delay: DelayLine::new(sample_rate.0, output_channels),
+            stutter: None,
+            scene_fade: None,
+            tone_phase: 0.0,
+            click: None,
+            click_position: None,
+            click_gain: 1.0,
+            click_beat: u64::MAX,
+            chime: None,
+            preview: None,
+            armed: None,
+            post_roll: None,
+            punch_in: None,
+            free: false,
+            capture: Vec::new(),
+            capture_frames: 0,
+            pre_roll: Arc::new(PreRoll::new(
+                input_channels,
+                sample_rate,
+                sample_rate.0 as u64 * PRE_ROLL_LIMIT,
+            )),
+            replace: None,
+            replace_ramp: 0.0,
+            replace_step: 1.0 / (sample_rate.0 as f32 * REPLACE_FADE),
+            events,
+            input,
+            secondary,
+            recording: Vec::new(),
+            sample_rate,
+            input_channels,
+            device_channels,
+            output_channels,
+            feedback_channels,
+            is_mono,
+            channel: 0,
+            feedback: 0.0,
+            last_frame: vec![0.0; input_channels as usize],
+            monitor_error: 0,
+            holding: false,
+            correction_phase: false,
+            drift: DriftMeter::new(sample_rate.0),
+            gate: NoiseGate::new(sample_rate.0 as f32 * input_channels as f32),
+            gate_mode: GateMode::Off,
+            record_dry: false,
+            kill_step: 1.0 / (samples_per_second * KILL_RAMP),
+            shutdown_step: 1.0 / (samples_per_second * SHUTDOWN_RAMP),
+            gain: 1.0,
+            fade_gain: 1.0,
+            follow_volume: 1.0,
+            follow_send: 0.0,
+            follow_step: 1.0 / (samples_per_second * FOLLOW_RAMP),
+            levels: [LevelAccumulator::default(); TRACK_COUNT],
+        }
+    }
+
+    // pops the next input sample of a channel, repeating the last one if the input is behind
+    // or held back, the channels past the first device's coming from the second
+    fn next_feedback(&mut self, channel: usize) {
+        let last = self.last_frame.get(channel).copied().unwrap_or(0.0);
+        let device_channels = self.device_channels as usize;
+
+        self.feedback = match self.secondary {
+            Some(ref mut secondary) if channel >= device_channels => {
+                secondary.pop(channel - device_channels)
+            }
+            _ if self.holding => last,
+            _ => self.input.pop().unwrap_or(last),
+        };
+
+        if let Some(last) = self.last_frame.get_mut(channel) {
+            *last = self.feedback;
+        }
+
+        // the gate is on the recording, and on what's heard only if asked to
+        let dry = self.feedback;
+        let wet = match self.gate_mode {
+            GateMode::Off => self.feedback,
+            mode => {
+                let gated = self.gate.process(self.feedback);
+
+                if mode == GateMode::Monitor {
+                    self.feedback = gated;
+                }
+
+                gated
+            }
+        };
+
+        // recorded dry, the take can be processed again later while what's heard stays wet
+        let recorded = if self.record_dry { dry } else { wet };
+
+        self.recording.push(recorded);
+        self.pre_roll.push(recorded);
+
+        if self.punch_in.is_some() {
+            self.capture.push(recorded);
+        }
+    }
+
+    // pops a whole input frame and mixes it down, every channel is still recorded
+    fn next_mono_feedback(&mut self) {
+        let mut sum = 0.0;
+
+        for channel in 0..self.input_channels {
+            self.next_feedback(channel as usize);
+            sum += self.feedback;
+        }
+
+        self.feedback = sum / self.input_channels.max(1) as f32;
+    }
+
+    // measures how far the input is from the monitoring delay, ignoring the jitter of one
+    // buffer since the input and output callbacks don't run in step
+    fn measure_monitor_delay(&mut self, buffer_frames: usize) {
+        // the delay is of both devices together, each buffer holds its own channels of it
+        let delay_frames = self.engine.monitor_delay() / self.input_channels.max(1) as u64;
+        let channels = self.device_channels.max(1) as i64;
+        let target = delay_frames as i64 * channels;
+        let error = self.input.len() as i64 - target;
+
+        self.drift
+            .observe(self.input.len() as f64 / channels as f64, buffer_frames);
+        self.engine
+            .set_input_drift(self.drift.ppm().map(|ppm| ppm as f32));
+
+        if let Some(ref mut secondary) = self.secondary {
+            secondary.measure(delay_frames, buffer_frames);
+            self.engine
+                .set_secondary_drift(secondary.ppm().map(|ppm| ppm as f32));
+        }
+
+        self.monitor_error = if error.abs() > buffer_frames as i64 * channels {
+            error / channels
+        } else {
+            0
+        };
+    }
+
+    // moves the input a frame toward the monitoring delay every other frame, dropping frames
+    // when it's too far behind and holding them when it's too close. Otherwise the drift of
+    // the clocks is made up a frame at a time, spread out so it can't be heard
+    fn correct_monitor_delay(&mut self) {
+        self.holding = false;
+        self.correction_phase = !self.correction_phase;
+
+        let mut correction = self.drift.step();
+        if self.correction_phase && self.monitor_error != 0 {
+            correction = self.monitor_error.signum();
+            self.monitor_error -= correction;
+        }
+
+        match correction {
+            1 => {
+                self.input.skip(self.device_channels as usize);
+            }
+            -1 => self.holding = true,
+            _ => {}
+        }
+
+        self.drift.corrected(correction);
+
+        if let Some(ref mut secondary) = self.secondary {
+            secondary.correct(self.correction_phase);
+        }
+    }
+
+    // returns the track the loop that just ended was armed for, and starts recording the next
+    // loop for a track armed since
+    fn finish_armed_loop(&mut self) -> Option<usize> {
+        let engine = &self.engine;
+
+        // a track disarmed during its loop doesn't get the recording
+        let recorded = self
+            .armed
+            .take()
+            .filter(|&track| engine.armed_track() == Some(track));
+
+        // a stacking track keeps recording every loop, and one counting out until it's done
+        if recorded.is_some() && !engine.is_stacking() && !engine.take_armed_loop() {
+            engine.set_armed_track(None);
+        }
+
+        // a punch in starts on its own, without waiting for the loop
+        if engine.is_punch_in() {
+            return recorded;
+        }
+
+        self.armed = engine.armed_track();
+        engine.set_arm_offset(0);
+        engine.set_armed_recording(self.armed.is_some());
+
+        recorded
+    }
+
+    // switches to a tempo queued for the next beat once playback lands on one, the beats
+    // already played keeping their length
+    fn update_tempo(&mut self) {
+        let engine = &self.engine;
+        if engine.tempo_timing() != TempoTiming::Beat || engine.pending_bpm().is_none() {
+            return;
+        }
+
+        let origin = engine.tempo_origin();
+        let played = engine.sample().saturating_sub(origin.sample);
+
+        // the whole beats played at the current tempo, the next one starting on a frame that
+        // may round down to this one
+        let beats = played * engine.bpm() / (60 * engine.sample_rate()).max(1);
+        let beat =
+            (beats..=beats + 1).find(|&beat| beat > 0 && engine.beats_frames(beat) == played);
+
+        if let Some(beat) = beat {
+            engine.apply_pending_bpm(origin.beat + beat);
+        }
+    }
+
+    // hands the last loop of input to the app when a capture is asked for, which copies it
+    // out of the pre roll itself
+    fn update_pre_roll(&mut self) {
+        let engine = &self.engine;
+
+        let Some(track) = engine.take_capture_request() else {
+            return;
+        };
+
+        let frames = engine.beats_frames(engine.beats());
+        let capture = self.pre_roll.capture(frames);
+        let end = engine.sample() % frames.max(1);
+
+        let _ = self.events.send(AudioEvent::Captured(track, capture, end));
+    }
+
+    // hands a finished loop to the app, along with the track it was armed for
+    fn publish_loop(&mut self, recorded: RecordedLoop, track: Option<usize>) {
+        if let Some(track) = track {
+            let _ = self
+                .events
+                .send(AudioEvent::Recorded(track, recorded.clone()));
+        }
+
+        self.engine.set_recorded_clip(Some(recorded));
+    }
+
+    // the number of frames captured past the wrap that are folded into the loop
+    fn tail_fold_frames(&self) -> usize {
+        (self.engine.tail_fold() * self.sample_rate.0 as u64 / 1000) as usize
+    }
+
+    // folds the start of the next loop into the held back one once enough of it is captured
+    fn finish_post_roll(&mut self) {
+        let tail = self.tail_fold_frames() * self.input_channels as usize;
+        if self.recording.len() < tail {
+            return;
+        }
+
+        let Some((clip, track)) = self.post_roll.take() else {
+            return;
+        };
+
+        // only the tail is copied here, the app folds it into the loop
+        let recorded = RecordedLoop {
+            clip,
+            tail: Some(Arc::from(&self.recording[..tail])),
+        };
+        self.publish_loop(recorded, track);
+    }
+
+    // starts capturing a free loop from scratch, or hands it to the app once it's stopped and
+    // restarts the loop with it
+    fn update_free_recording(&mut self, restart: LoopRestart) {
+        let engine = &self.engine;
+        let free = engine.is_free_recording();
+
+        if free == self.free {
+            return;
+        }
+
+        self.free = free;
+        self.post_roll = None;
+
+        if free {
+            self.recording.clear();
+            engine.set_sample(0);
+            return;
+        }
+
+        let clip = Clip::unanalyzed(
+            self.input_channels,
+            self.sample_rate,
+            Arc::from(mem::take(&mut self.recording)),
+        );
+        let _ = self.events.send(AudioEvent::FreeRecorded(clip));
+
+        engine.set_sample(0);
+        engine.set_loop_restart(Some(restart));
+        engine.loop_count.fetch_add(1, Ordering::AcqRel);
+    }
+
+    // starts capturing right away for a track armed to punch in, or drops the capture of a
+    // track that was disarmed since
+    fn update_punch_in(&mut self) {
+        let engine = &self.engine;
+        let armed = engine.armed_track();
+
+        if self.punch_in.is_some() && (self.punch_in != armed || !engine.is_punch_in()) {
+            self.punch_in = None;
+            self.capture.clear();
+        }
+
+        if self.punch_in.is_some() || !engine.is_punch_in() || armed.is_none() {
+            return;
+        }
+
+        let frames = engine.beats_frames(engine.beats());
+        if frames == 0 {
+            return;
+        }
+
+        self.punch_in = armed;
+        self.capture_frames = frames;
+        self.capture.clear();
+
+        engine.set_arm_offset(engine.sample());
+        engine.set_armed_recording(true);
+    }
+
+    // hands a punch in to the app once it has captured a whole loop
+    fn finish_punch_in(&mut self) {
+        let whole = self.capture_frames * self.input_channels as u64;
+        if self.capture.len() < whole as usize {
+            return;
+        }
+
+        let Some(track) = self.punch_in.take() else {
+            return;
+        };
+
+        let clip = Clip::unanalyzed(
+            self.input_channels,
+            self.sample_rate,
+            Arc::from(mem::take(&mut self.capture)),
+        );
+
+        let engine = &self.engine;
+        let _ = self
+            .events
+            .send(AudioEvent::Recorded(track, RecordedLoop::new(clip)));
+
+        engine.set_armed_track(None);
+        engine.set_armed_recording(false);
+        engine.set_punch_in(false);
+    }
+
+    // picks up the buffer of a track as soon as replacing it is held
+    fn update_replace(&mut self) {
+        let engine = &self.engine;
+
+        if self.replace.is_none() && engine.is_replace_held() {
+            self.replace = engine.replace_buffer.take();
+            self.replace_ramp = 0.0;
+        }
+    }
+
+    // writes the input frame into the replace buffer at the playhead, fading in while held and
+    // out once released, after which the app commits the buffer
+    fn replace_frame(&mut self) {
+        let Some(ref buffer) = self.replace else {
+            return;
+        };
+
+        let engine = &self.engine;
+        let held = engine.is_replace_held();
+
+        self.replace_ramp = if held {
+            (self.replace_ramp + self.replace_step).min(1.0)
+        } else {
+            (self.replace_ramp - self.replace_step).max(0.0)
+        };
+
+        if !held && self.replace_ramp == 0.0 {
+            engine.set_replace_finished(true);
+            return;
+        }
+
+        let channels = self.input_channels as usize;
+        let Some(start) = self.recording.len().checked_sub(channels) else {
+            return;
+        };
+
+        let frame = &self.recording[start..];
+        let track = self.tracks.get(buffer.track);
+        let input = match track.and_then(|track| track.input_channel) {
+            Some(channel) if (channel as usize) < frame.len() => {
+                &frame[channel as usize..=channel as usize]
+            }
+            _ => frame,
+        };
+
+        // the buffer is laid out like the clip, so it follows the offset or phase of the track
+        let index = track.map_or(engine.sample(), |track| {
+            track.playing_index(engine.sample())
+        });
+        buffer.replace(index, input, self.replace_ramp);
+    }
+
+    // the volume and send the monitored input should play at, the armed track's when following
+    fn follow_targets(&self) -> (f32, f32) {
+        let follow = self.engine.input_follow();
+        if follow == InputFollow::Off {
+            return (1.0, 0.0);
+        }
+
+        let armed = self.engine.armed_track();
+        let Some(track) = armed.and_then(|index| self.tracks.get(index)) else {
+            return (1.0, 0.0);
+        };
+
+        // the volume is followed even while muted, so the input stays audible
+        let volume = match follow {
+            InputFollow::SendAndVolume => track.volume as f32 / 100.0,
+            _ => 1.0,
+        };
+
+        (volume, track.send)
+    }
+
+    // moves the monitoring toward the targets a step at a time, so arming doesn't click
+    fn advance_follow(&mut self, (volume, send): (f32, f32)) {
+        let step = self.follow_step;
+
+        self.follow_volume += (volume - self.follow_volume).clamp(-step, step);
+        self.follow_send += (send - self.follow_send).clamp(-step, step);
+    }
+
+    // the channel of the output frame the current sample goes to
+    fn output_position(&self) -> u16 {
+        (self.channel + self.output_channels - 1) % self.output_channels
+    }
+
+    // switches to new tracks, handing the replace buffer back once they hold the replaced clip,
+    // and returns the ones played before
+    fn swap_tracks(&mut self, mut tracks: Tracks, committed: bool) -> Tracks {
+        tracks.carry_phases(&self.tracks, self.engine.sample());
+
+        self.sends = tracks.has_sends();
+        self.cues = tracks.has_cues();
+
+        // a committed take takes over from its preview right here, without a gap or playing
+        // twice
+        if self.engine.is_preview_handoff() {
+            self.engine.set_preview_handoff(false);
+            self.engine.set_previewing(false);
+            self.preview = None;
+        }
+        let previous = mem::replace(&mut self.tracks, tracks);
+        self.tracks.advance_ramps(self.engine.played());
+        self.tracks.advance_fades(self.engine.played());
+
+        if committed && self.replace.is_some() {
+            self.engine.replace_returned.store(self.replace.take());
+        }
+
+        previous
+    }
+
+    // keeps the tracks of the last scene playing under the new ones for as many beats as
+    // asked, at most a loop so it's over before the next switch, the clips being shared
+    // between both
+    fn start_scene_fade(&mut self, previous: Tracks) {
+        let engine = &self.engine;
+        let frames = engine
+            .beats_frames(engine.scene_fade_beats())
+            .min(engine.loop_frames());
+
+        // the echoes of the last scene fade out too
+        if frames > 0 && previous.has_sends() {
+            self.sends = true;
+        }
+
+        self.scene_fade = (frames > 0).then(|| SceneFade {
+            tracks: previous,
+            start: engine.played(),
+            frames,
+        });
+    }
+
+    // moves the tracks faded out along with the new ones, letting go of them once they're
+    // silent
+    fn advance_scene_fade(&mut self, played: u64) {
+        let Some(ref mut fade) = self.scene_fade else {
+            return;
+        };
+
+        if fade.mix_at(played) >= 1.0 {
+            self.scene_fade = None;
+            self.sends = self.tracks.has_sends();
+            return;
+        }
+
+        fade.tracks.advance_phases();
+        fade.tracks.advance_ramps(played);
+        fade.tracks.advance_fades(played);
+    }
+
+    // starts the loaded click whenever a new beat begins, accented on the first of the loop
+    fn advance_click(&mut self) {
+        if let (Some(ref clip), Some(position)) = (&self.click, self.click_position) {
+            let next = position + 1;
+            self.click_position = (next < clip.frame_count()).then_some(next);
+        }
+
+        let beat = self.engine.beat().floor() as u64;
+        if beat == self.click_beat {
+            return;
+        }
+
+        self.click_beat = beat;
+
+        if self.click.is_some() {
+            self.click_position = Some(0);
+            self.click_gain = if beat == 0 { CLICK_ACCENT } else { 1.0 };
+        }
+    }
+
+    fn advance_chime(&mut self) {
+        let length = (CHIME_LENGTH * self.sample_rate.0 as f32) as u64;
+        self.chime = self
+            .chime
+            .map(|frame| frame + 1)
+            .filter(|&frame| frame < length);
+    }
+
+    fn chime_sample(&self) -> f32 {
+        self.chime
+            .map_or(0.0, |frame| chime(frame as f32 / self.sample_rate.0 as f32))
+    }
+
+    // the metronome at the playhead, the loaded click if there is one
+    fn metronome_sample(&self) -> f32 {
+        let engine = &self.engine;
+
+        // a free loop has no tempo to click to yet
+        if self.free || !engine.metronome_clicks() {
+            return 0.0;
+        }
+
+        let Some(ref clip) = self.click else {
+            let beat_offset = engine.beat().fract() / engine.bps();
+            return metronome(beat_offset);
+        };
+
+        let Some(position) = self.click_position else {
+            return 0.0;
+        };
+
+        let sample = if clip.channels == self.feedback_channels {
+            clip.sample(position, self.channel)
+        } else {
+            clip.average_sample(position)
+        };
+
+        sample * self.click_gain
+    }
+
+    // the previewed clip at the playhead, looping with the loop
+    fn preview_sample(&self) -> f32 {
+        let Some(ref clip) = self.preview else {
+            return 0.0;
+        };
+
+        let frames = clip.frame_count();
+        if frames == 0 {
+            return 0.0;
+        }
+
+        let index = self.engine.sample() % frames;
+        if clip.channels == self.feedback_channels {
+            clip.sample(index, self.channel)
+        } else {
+            clip.average_sample(index)
+        }
+    }
+
+    // moves the fade out one frame closer to silence, or back to unity over a beat once it's
+    // cancelled
+    fn advance_fade(&mut self) {
+        let engine = &self.engine;
+        let frames = engine.fade_frames();
+
+        if frames > 0 {
+            if self.fade_gain > 0.0 {
+                self.fade_gain = (self.fade_gain - 1.0 / frames as f32).max(0.0);
+
+                if self.fade_gain == 0.0 {
+                    engine.set_fade_finished(true);
+                }
+            }
+        } else if self.fade_gain < 1.0 {
+            let beat = engine.beats_frames(1).max(1);
+            self.fade_gain = (self.fade_gain + 1.0 / beat as f32).min(1.0);
+        }
+    }
+
+    // starts repeating from the playhead when a stutter begins, the playhead itself keeps
+    // moving so the loop picks up where it would have been once the stutter ends
+    fn update_stutter(&mut self) {
+        let stutter = self.engine.stutter();
+
+        match (stutter, &self.stutter) {
+            (Some(stutter), Some(active)) if active.stutter == stutter => {}
+            (Some(stutter), _) => {
+                self.stutter = Some(ActiveStutter::new(
+                    stutter,
+                    self.engine.sample(),
+                    self.engine.bpm(),
+                    self.sample_rate.0 as u64,
+                ));
+            }
+            (None, _) => self.stutter = None,
+        }
+    }
+
+    /// Fills an interleaved output buffer, `timestamp` being when the host plays it if it
+    /// says.
+    pub fn process(&mut self, data: &mut [f32], timestamp: Option<OutputStreamTimestamp>) {
+        let start = Instant::now();
+        let engine = self.engine.clone();
+
+        engine.record_buffer_size((data.len() / self.output_channels.max(1) as usize) as u64);
+
+        // the loop restart is stamped with when it's heard, not when it's computed
+        let latency = timestamp
+            .and_then(|timestamp| timestamp.playback.duration_since(&timestamp.callback))
+            .unwrap_or_default();
+        let mut frame = 0;
+
+        engine.set_recording(true);
+
+        let (target_gain, step) = if engine.is_shutting_down() {
+            (0.0, self.shutdown_step)
+        } else if engine.is_killed() {
+            (0.0, self.kill_step)
+        } else {
+            (1.0, self.kill_step)
+        };
+
+        let delay = self.delay.delay_samples(
+            engine.delay_beats(),
+            engine.bpm(),
+            self.sample_rate.0,
+            self.output_channels,
+        );
+        let delay_feedback = engine.delay_feedback() as f32 / 100.0;
+        let delay_mix = engine.delay_mix() as f32 / 100.0;
+
+        let test_tone = engine.test_tone();
+        let sample_rate = self.sample_rate.0 as f32;
+
+        // the cue bus can be moved while playing, the loudness following the main mix
+        let cue_start = engine.cue_channel();
+        engine
+            .output_snapshot
+            .set_channels(self.output_channels.min(cue_start));
+
+        self.measure_monitor_delay(data.len() / self.output_channels.max(1) as usize);
+        self.gate_mode = engine.gate_mode();
+        self.record_dry = engine.is_recording_dry();
+        self.gate.set_threshold(engine.gate_threshold());
+        self.update_stutter();
+        self.update_free_recording(LoopRestart {
+            instant: start + latency,
+            stream: timestamp.map(|timestamp| timestamp.playback),
+        });
+        self.update_punch_in();
+        self.update_replace();
+        self.update_pre_roll();
+        engine.queue.drop_cancelled();
+
+        if let Some(click) = engine.take_metronome_clip() {
+            self.click = Some(click);
+            self.click_position = None;
+        }
+
+        if let Some(preview) = engine.take_preview_clip() {
+            self.preview = Some(preview);
+        }
+
+        if engine.take_chime_request() {
+            self.chime = Some(0);
+        }
+
+        if !engine.is_previewing() {
+            self.preview = None;
+        }
+
+        let dim = if self.preview.is_some() && engine.preview_dim() {
+            PREVIEW_DIM
+        } else {
+            1.0
+        };
+
+        let follow = self.follow_targets();
+
+        // without sends the delay is skipped entirely
+        if !self.sends {
+            self.delay.clear();
+        }
+
+        for target in data {
+            if engine.is_on_beat() {
+                // tracks sent after a replace was committed hold the replaced clip
+                let committed = engine.is_replace_committed();
+
+                // if tracks have been updated, use them
+                if let Some(new_tracks) = engine.take_tracks() {
+                    self.swap_tracks(new_tracks, committed);
+                }
+            }
+
+            self.channel += 1;
+
+            if !self.is_mono {
+                self.next_feedback(self.channel as usize - 1);
+            }
+
+            if self.channel == self.output_channels {
+                if self.is_mono {
+                    self.next_mono_feedback();
+                }
+
+                self.replace_frame();
+
+                engine.sample.fetch_add(1, Ordering::AcqRel);
+                let played = engine.played.fetch_add(1, Ordering::AcqRel) + 1;
+                self.channel = 0;
+                frame += 1;
+
+                self.update_tempo();
+
+                self.correct_monitor_delay();
+
+                if let Some(ref mut stutter) = self.stutter {
+                    stutter.advance();
+                }
+
+                self.advance_click();
+                self.advance_chime();
+                self.advance_fade();
+                self.tracks.advance_phases();
+                self.tracks.advance_ramps(played);
+                self.tracks.advance_fades(played);
+                self.advance_scene_fade(played);
+
+                if self.punch_in.is_some() {
+                    self.finish_punch_in();
+                }
+
+                if self.post_roll.is_some() {
+                    self.finish_post_roll();
+                }
+
+                self.tone_phase = (self.tone_phase + TEST_TONE_FREQUENCY / sample_rate).fract();
+            }
+
+            self.gain = if self.gain < target_gain {
+                (self.gain + step).min(target_gain)
+            } else {
+                (self.gain - step).max(target_gain)
+            };
+
+            let stutter = self.stutter.as_ref().and_then(ActiveStutter::index);
+            let replacing = self.replace.as_ref().map(|buffer| buffer.track);
+
+            // the cued tracks play on their own channels, or take over the mix when the output
+            // has none to spare
+            let cue_bus = self.output_channels > cue_start;
+            let cue_channel = self.cues && cue_bus && self.output_position() >= cue_start;
+            let solo = self.cues && !cue_bus;
+
+            // the track being replaced plays from its buffer, fading out as the input fades in
+            let replaced = match self.replace {
+                Some(ref buffer) if !cue_channel => {
+                    let track = self.tracks.get(buffer.track);
+                    let volume = match track {
+                        Some(track) if solo => track.cue as u8 as f32,
+                        Some(track) => track.volume_factor(),
+                        None => 0.0,
+                    };
+                    let index = track.map_or(engine.sample(), |track| {
+                        track.playing_index(engine.sample())
+                    });
+                    let sample = buffer.sample(index, self.channel, self.feedback_channels);
+                    let sample = track.map_or(sample, |track| {
+                        let gain = track.playing_pattern_gain(engine.sample());
+                        track.driven(sample) * gain * track.region_gain(index)
+                    });
+                    let sample = sample * volume * (1.0 - self.replace_ramp);
+
+                    if let Some(level) = self.levels.get_mut(buffer.track) {
+                        level.add(sample);
+                    }
+
+                    sample
+                }
+                _ => 0.0,
+            };
+
+            let mut tracks = if cue_channel || solo {
+                self.tracks.cue_sample(
+                    engine.sample(),
+                    self.channel,
+                    self.feedback_channels,
+                    stutter,
+                    replacing,
+                    solo.then_some(&mut self.levels[..]),
+                )
+            } else {
+                self.tracks.mix_sample(
+                    engine.sample(),
+                    self.channel,
+                    self.feedback_channels,
+                    stutter,
+                    replacing,
+                    &mut self.levels,
+                )
+            };
+
+            // during a scene switch the last scene fades out as the new one fades in
+            let scene_mix = self.scene_fade.as_ref().map(|fade| {
+                let outgoing = if cue_channel || solo {
+                    fade.tracks.cue_sample(
+                        engine.sample(),
+                        self.channel,
+                        self.feedback_channels,
+                        stutter,
+                        replacing,
+                        None,
+                    )
+                } else {
+                    fade.tracks.mix_sample(
+                        engine.sample(),
+                        self.channel,
+                        self.feedback_channels,
+                        stutter,
+                        replacing,
+                        &mut [],
+                    )
+                };
+
+                (fade.mix_at(engine.played()), outgoing)
+            });
+
+            if let Some((mix, outgoing)) = scene_mix {
+                tracks = tracks * mix + outgoing * (1.0 - mix);
+            }
+
+            self.advance_follow(follow);
+            let monitored = self.feedback * self.follow_volume;
+
+            let mut sample = monitored
+                + (tracks + replaced) * dim
+                + self.preview_sample()
+                + self.metronome_sample()
+                + self.chime_sample();
+
+            if self.sends {
+                let mut send = self.tracks.send_sample(
+                    engine.sample(),
+                    self.channel,
+                    self.feedback_channels,
+                    stutter,
+                    replacing,
+                );
+
+                if let Some((mix, _)) = scene_mix {
+                    let outgoing = self.scene_fade.as_ref().map_or(0.0, |fade| {
+                        fade.tracks.send_sample(
+                            engine.sample(),
+                            self.channel,
+                            self.feedback_channels,
+                            stutter,
+                            replacing,
+                        )
+                    });
+
+                    send = send * mix + outgoing * (1.0 - mix);
+                }
+
+                if let Some(track) = replacing.and_then(|track| self.tracks.get(track)) {
+                    send += replaced * track.send;
+                }
+
+                send += monitored * self.follow_send;
+
+                // the cue is a dry pre-listen, the echoes stay on the main mix
+                let wet = self.delay.process(send * dim, delay, delay_feedback) * delay_mix;
+                if !cue_channel && !solo {
+                    sample += wet;
+                }
+            }
+
+            if test_tone {
+                sample += (self.tone_phase * TAU).sin() * TEST_TONE_LEVEL;
+            }
+
+            let gain = self.gain * self.fade_gain;
+            *target = sample * gain * engine.master_volume() as f32 / 100.0;
+
+            // the loudness is of the main mix, not the cue
+            if self.output_position() < cue_start {
+                engine.output_snapshot.push(*target);
+            }
+
+            if !self.free && engine.should_loop() {
+                engine.set_sample(0);
+
+                // a tempo queued for the loop starts with this pass, so no pass mixes two
+                engine.apply_pending_bpm(0);
+
+                let offset = Duration::from_secs_f64(frame as f64 / self.sample_rate.0 as f64);
+                engine.set_loop_restart(Some(LoopRestart {
+                    instant: start + latency + offset,
+                    stream: timestamp.and_then(|timestamp| timestamp.playback.add(offset)),
+                }));
+
+                // whatever was queued for the next loop starts with this pass
+                let committed = engine.is_replace_committed();
+                engine.queue.drain(|action| match action {
+                    QueuedAction::Resize { beats, tracks } => {
+                        engine.set_beats(beats);
+                        self.swap_tracks(tracks, committed);
+                    }
+                    QueuedAction::Scene { tracks, .. } => {
+                        let previous = self.swap_tracks(tracks, committed);
+                        self.start_scene_fade(previous);
+                    }
+                    QueuedAction::Macro { tracks, .. } => {
+                        self.swap_tracks(tracks, committed);
+                    }
+                });
+
+                let clip = Clip::unanalyzed(
+                    self.input_channels,
+                    self.sample_rate,
+                    Arc::from(mem::take(&mut self.recording)),
+                );
+
+                let track = self.finish_armed_loop();
+
+                // a loop too short to fill the post roll goes out without its tail
+                if let Some((clip, track)) = self.post_roll.take() {
+                    self.publish_loop(RecordedLoop::new(clip), track);
+                }
+
+                if self.tail_fold_frames() == 0 {
+                    self.publish_loop(RecordedLoop::new(clip), track);
+                } else {
+                    self.post_roll = Some((clip, track));
+                }
+                engine.loop_count.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+
+        engine.set_fade_gain(self.fade_gain);
+        engine.set_scene_fade(
+            self.scene_fade
+                .as_ref()
+                .map(|fade| fade.mix_at(engine.played())),
+        );
+        engine.set_gate_closed(self.gate_mode != GateMode::Off && self.gate.is_closed());
+
+        let time = engine.clock();
+        for (meter, level) in engine.track_meters.iter().zip(&mut self.levels) {
+            meter.publish(level, time);
+            level.clear();
+        }
+
+        engine.record_callback_time(start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel;
+    use ringbuf::{HeapProducer, HeapRb};
+
+    use super::*;
+
+    const RATE: usize = 48000;
+
+    // a processor playing mono at 48 kHz from a mono input holding `input`, along with the
+    // end of the input buffer to feed it more
+    fn processor(
+        engine: &Arc<AudioEngine>,
+        tracks: Tracks,
+        input: &[f32],
+    ) -> (OutputProcessor, HeapProducer<f32>) {
+        let (mut producer, consumer) = HeapRb::new(input.len().max(RATE)).split();
+        producer.push_slice(input);
+
+        let input = ProcessorInput {
+            consumer,
+            channels: 1,
+            secondary: None,
+        };
+
+        let (events, _) = channel::unbounded();
+        let sample_rate = SampleRate(RATE as u32);
+        let processor = OutputProcessor::new(engine.clone(), tracks, input, sample_rate, 1, events);
+
+        (processor, producer)
+    }
+
+    #[test]
+    fn input_follow_ramps_to_the_armed_track() {
+        let engine = Arc::new(AudioEngine::default());
+        engine.set_input_follow(InputFollow::SendAndVolume);
+
+        let mut tracks = Tracks::default();
+        tracks[2].volume = 50;
+        tracks[2].send = 0.5;
+        let (mut processor, _) = processor(&engine, tracks, &[]);
+
+        // nothing armed, the input is monitored plainly
+        assert_eq!(processor.follow_targets(), (1.0, 0.0));
+
+        engine.set_armed_track(Some(2));
+        let targets = processor.follow_targets();
+        assert_eq!(targets, (0.5, 0.5));
+
+        // half the way takes half the ramp, give or take a frame of rounding, moving toward
+        // the targets every frame and landing right on them without going past
+        let frames = (RATE as f32 * FOLLOW_RAMP / 2.0).round() as usize;
+        let mut last = (processor.follow_volume, processor.follow_send);
+        let mut steps = 0;
+
+        while last != targets {
+            processor.advance_follow(targets);
+            let current = (processor.follow_volume, processor.follow_send);
+            assert!(current != last && current.0 <= last.0 && current.1 >= last.1);
+            assert!(current.0 >= targets.0 && current.1 <= targets.1);
+
+            last = current;
+            steps += 1;
+            assert!(steps <= frames + 1);
+        }
+
+        assert!(steps >= frames);
+
+        processor.advance_follow(targets);
+        assert_eq!((processor.follow_volume, processor.follow_send), targets);
+
+        // only the send follows without the volume
+        engine.set_input_follow(InputFollow::Send);
+        assert_eq!(processor.follow_targets(), (1.0, 0.5));
+
+        // disarming ramps back rather than jumping
+        engine.set_armed_track(None);
+        let targets = processor.follow_targets();
+        processor.advance_follow(targets);
+        assert!(processor.follow_volume < 1.0 && processor.follow_send > 0.0);
+
+        (0..frames).for_each(|_| processor.advance_follow(targets));
+        assert_eq!((processor.follow_volume, processor.follow_send), (1.0, 0.0));
+    }
+
+    // records `input` through a gate shutting out anything under -20 dB
+    fn record_gated(input: &[f32], dry: bool) -> (Vec<f32>, Clip) {
+        let engine = Arc::new(AudioEngine::default());
+        let (mut processor, _) = processor(&engine, Tracks::default(), input);
+
+        processor.gate_mode = GateMode::Record;
+        processor.gate.set_threshold(-20);
+        processor.record_dry = dry;
+
+        let heard = (0..input.len())
+            .map(|_| {
+                processor.next_feedback(0);
+                processor.feedback
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(processor.recording.len(), input.len());
+        let capture = processor.pre_roll.capture(input.len() as u64);
+        (heard, capture.clip())
+    }
+
+    #[test]
+    fn dry_recording_keeps_the_raw_input() {
+        // a loud burst, then a quiet tail the gate closes on
+        let input = (0..19200)
+            .map(|frame| {
+                let level = if frame < 4800 { 0.5 } else { 0.01 };
+                (frame as f32 * 0.05).sin() * level
+            })
+            .collect::<Vec<_>>();
+
+        let (heard, dry) = record_gated(&input, true);
+        assert_eq!(heard, input);
+        assert_eq!(&dry.dense_samples()[..], &input[..]);
+
+        // the gate is on the take otherwise, while what's heard is the same
+        let (heard, wet) = record_gated(&input, false);
+        assert_eq!(heard, input);
+        assert_ne!(&wet.dense_samples()[..], &input[..]);
+        assert!(wet.dense_samples()[16800..]
+            .iter()
+            .all(|sample| sample.abs() < 0.001));
+    }
+
+    // plays `seconds` of buffers of 256 frames while the input arrives as fast, returning the
+    // fill level of the input buffer at the start of every one
+    fn play(
+        processor: &mut OutputProcessor,
+        producer: &mut HeapProducer<f32>,
+        seconds: f32,
+    ) -> Vec<usize> {
+        let mut data = [0.0; 256];
+
+        (0..(seconds * RATE as f32) as usize / data.len())
+            .map(|_| {
+                producer.push_slice(&data);
+                let fill = processor.input.len();
+                processor.process(&mut data, None);
+                fill
+            })
+            .collect()
+    }
+
+    #[test]
+    fn queued_actions_fire_at_the_wrap() {
+        // two seconds a loop
+        let engine = Arc::new(AudioEngine::default());
+        engine.set_beats(4);
+        let (mut processor, mut producer) = processor(&engine, Tracks::default(), &[]);
+
+        let mut resized = Tracks::default();
+        resized[0].volume = 30;
+        let mut macro_tracks = Tracks::default();
+        macro_tracks[0].volume = 40;
+
+        engine.queue.push(
+            1,
+            QueuedAction::Resize {
+                beats: 8,
+                tracks: resized,
+            },
+        );
+        engine.queue.push(
+            2,
+            QueuedAction::Macro {
+                slot: 0,
+                tracks: macro_tracks,
+            },
+        );
+
+        // nothing changes before the loop wraps
+        play(&mut processor, &mut producer, 1.5);
+        assert_eq!((engine.beats(), engine.queue.applied()), (4, 0));
+        assert_eq!(processor.tracks[0].volume, Tracks::default()[0].volume);
+
+        // both at the wrap, in the order they were queued
+        play(&mut processor, &mut producer, 1.0);
+        assert_eq!((engine.beats(), engine.queue.applied()), (8, 2));
+        assert_eq!(processor.tracks[0].volume, 40);
+
+        // and only that once
+        play(&mut processor, &mut producer, 4.0);
+        assert_eq!(engine.loop_count(), 2);
+        assert_eq!((engine.beats(), engine.queue.applied()), (8, 2));
+    }
+
+    #[test]
+    fn monitor_delay_converges_within_200_ms() {
+        let engine = Arc::new(AudioEngine::default());
+        engine.set_monitor_delay(480);
+        let (mut processor, mut producer) = processor(&engine, Tracks::default(), &[0.0; 480]);
+
+        let settled = |fills: &[usize], target: usize| {
+            let last = &fills[fills.len() / 2..];
+            last.iter().all(|&fill| fill.abs_diff(target) <= 256)
+        };
+
+        assert!(settled(&play(&mut processor, &mut producer, 0.2), 480));
+
+        // further behind, then closer than it started, each within 200 ms
+        for target in [RATE / 20, RATE / 200] {
+            engine.set_monitor_delay(target as u64);
+
+            let fills = play(&mut processor, &mut producer, 0.2);
+            assert!(fills[0].abs_diff(target) > 256);
+            assert!(settled(&fills, target), "{} frames", target);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+
+use crate::track::Tracks;
+
+// how many actions can wait for the loop boundary at once
+const QUEUE_CAPACITY: usize = 64;
+
+/// Something the output callback does the moment the loop wraps around.
+#[derive(Clone)]
+pub enum QueuedAction {
+    /// Changes the loop length, along with the tracks fitted to it.
+    Resize { beats: u64, tracks: Tracks },
+    /// Switches to the tracks of a scene, `None` for tracks that weren't saved as one.
+    Scene {
+        index: Option<usize>,
+        tracks: Tracks,
+    },
+    /// Takes the tracks as a macro left them.
+    Macro { slot: usize, tracks: Tracks },
+}
+
+impl QueuedAction {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Resize { beats, .. } => format!("{} beats", beats),
+            Self::Scene {
+                index: Some(index), ..
+            } => format!("scene {}", (index + 1) % 10),
+            Self::Scene { index: None, .. } => String::from("previous scene"),
+            Self::Macro { slot, .. } => format!("macro {}", slot + 1),
+        }
+    }
+}
+
+struct QueuedEntry {
+    id: u64,
+    generation: u64,
+    action: QueuedAction,
+}
+
+/// Actions waiting for the next loop boundary, applied in the order they were queued.
+///
+/// Only the ui pushes and only the output callback pops, so neither lock is ever contended,
+/// they only hand out the two ends of the ring buffer.
+pub struct ActionQueue {
+    producer: Mutex<HeapProducer<QueuedEntry>>,
+    consumer: Mutex<HeapConsumer<QueuedEntry>>,
+    // entries queued before the last cancel are skipped
+    generation: AtomicU64,
+    // the id of the last action applied
+    applied: AtomicU64,
+}
+
+impl Default for ActionQueue {
+    fn default() -> Self {
+        let (producer, consumer) = HeapRb::new(QUEUE_CAPACITY).split();
+
+        Self {
+            producer: Mutex::new(producer),
+            consumer: Mutex::new(consumer),
+            generation: AtomicU64::new(0),
+            applied: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ActionQueue {
+    /// Queues an action for the next loop boundary, returning false if the queue is full.
+    pub fn push(&self, id: u64, action: QueuedAction) -> bool {
+        let entry = QueuedEntry {
+            id,
+            generation: self.generation.load(Ordering::Acquire),
+            action,
+        };
+
+        let mut producer = self.producer.lock().unwrap();
+        producer.push(entry).is_ok()
+    }
+
+    /// Drops everything queued so far.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Returns the id of the last action applied.
+    pub fn applied(&self) -> u64 {
+        self.applied.load(Ordering::Acquire)
+    }
+
+    /// Frees the room taken by cancelled actions, called by the callback every buffer.
+    pub fn drop_cancelled(&self) {
+        let Ok(mut consumer) = self.consumer.try_lock() else {
+            return;
+        };
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let cancelled = consumer
+            .iter()
+            .take_while(|entry| entry.generation != generation)
+            .count();
+
+        consumer.skip(cancelled);
+    }
+
+    /// Applies every queued action in order, called by the callback when the loop wraps.
+    pub fn drain(&self, mut apply: impl FnMut(QueuedAction)) {
+        let Ok(mut consumer) = self.consumer.try_lock() else {
+            return;
+        };
+
+        let generation = self.generation.load(Ordering::Acquire);
+
+        while let Some(entry) = consumer.pop() {
+            if entry.generation != generation {
+                continue;
+            }
+
+            apply(entry.action);
+            self.applied.store(entry.id, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn macro_action(slot: usize) -> QueuedAction {
+        QueuedAction::Macro {
+            slot,
+            tracks: Tracks::default(),
+        }
+    }
+
+    fn drained(queue: &ActionQueue) -> Vec<String> {
+        let mut labels = Vec::new();
+        queue.drain(|action| labels.push(action.label()));
+        labels
+    }
+
+    #[test]
+    fn queued_actions_apply_once_in_order() {
+        let queue = ActionQueue::default();
+        assert!(queue.push(1, macro_action(0)));
+        assert!(queue.push(2, macro_action(1)));
+        assert!(queue.push(3, macro_action(2)));
+
+        assert_eq!(drained(&queue), ["macro 1", "macro 2", "macro 3"]);
+        assert_eq!(queue.applied(), 3);
+
+        // the next wrap has nothing left to apply
+        assert!(drained(&queue).is_empty());
+        assert_eq!(queue.applied(), 3);
+    }
+
+    #[test]
+    fn cancelled_actions_never_apply() {
+        let queue = ActionQueue::default();
+        queue.push(1, macro_action(0));
+        queue.push(2, macro_action(1));
+        queue.cancel();
+        queue.push(3, macro_action(2));
+
+        assert_eq!(drained(&queue), ["macro 3"]);
+        assert_eq!(queue.applied(), 3);
+    }
+
+    #[test]
+    fn cancelled_actions_free_their_room() {
+        let queue = ActionQueue::default();
+        (0..QUEUE_CAPACITY as u64).for_each(|id| assert!(queue.push(id, macro_action(0))));
+        assert!(!queue.push(QUEUE_CAPACITY as u64, macro_action(0)));
+
+        queue.cancel();
+        queue.drop_cancelled();
+        assert!(queue.push(QUEUE_CAPACITY as u64, macro_action(0)));
+        assert_eq!(drained(&queue).len(), 1);
+    }
+}
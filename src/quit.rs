@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::Alignment,
+    text::Spans,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::app::{centered_rect, App};
+
+// set by the signal handler, checked every iteration of the main loop
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Called from the signal handler when the process is interrupted or terminated.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Release);
+}
+
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::AcqRel)
+}
+
+impl App {
+    // quits right away if there's nothing to lose, otherwise asks first
+    pub fn quit(&mut self) {
+        let unsaved = self.audio.tracks.iter().any(|track| track.clip.is_some());
+
+        // a second interrupt while asking quits without saving
+        if !unsaved || self.confirm_quit {
+            self.running = false;
+        } else {
+            self.confirm_quit = true;
+        }
+    }
+
+    // handles a key while the quit confirmation is open
+    pub fn quit_key(&mut self, key: KeyEvent) {
+        match key.code {
+            // stay open if saving failed, so nothing is lost
+            KeyCode::Char('s') if self.save_session() => self.running = false,
+            KeyCode::Char('d') => self.running = false,
+            KeyCode::Char('c') | KeyCode::Esc => self.confirm_quit = false,
+            _ => {}
+        }
+    }
+
+    pub fn render_quit_confirm<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        if !self.confirm_quit {
+            return;
+        }
+
+        let area = centered_rect(frame.size(), 44, 6);
+
+        let text = vec![
+            Spans::from("some tracks have clips that aren't saved"),
+            Spans::from(""),
+            Spans::from("'s' save, 'd' discard, 'c' cancel"),
+        ];
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Quit")
+            .border_style(self.theme.warning);
+
+        let paragraph = Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(block);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
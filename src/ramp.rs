@@ -0,0 +1,78 @@
+use crate::{app::App, track::VolumeRamp};
+
+impl App {
+    // fades a track to `target` over a loop, in the output callback so it moves every frame
+    pub fn ramp_volume(&mut self, index: usize, target: u32) {
+        let engine = &self.audio.engine;
+        let beats = engine.beats();
+        let frames = engine.beats_frames(beats);
+
+        if frames == 0 {
+            self.warn("launch the streams and set a loop length to fade a track");
+            return;
+        }
+
+        let played = engine.played();
+        self.settle_ramp(index);
+
+        let track = &mut self.audio.tracks[index];
+        if track.volume == target {
+            return;
+        }
+
+        track.ramp = Some(VolumeRamp {
+            from: track.volume,
+            target,
+            start: played,
+            frames,
+        });
+
+        let from = track.volume;
+        self.audio.update_tracks();
+
+        self.info(format!(
+            "fading track {} from {}% to {}% over {} beats",
+            (index + 1) % 10,
+            from,
+            target,
+            beats
+        ));
+    }
+
+    // stops a ramp where it got to, so a change by hand carries on from what's heard
+    pub fn settle_ramp(&mut self, index: usize) {
+        let played = self.audio.engine.played();
+        let track = &mut self.audio.tracks[index];
+
+        if let Some(ramp) = track.ramp.take() {
+            track.volume = ramp.volume_at(played).round() as u32;
+        }
+    }
+
+    // writes the target of every finished ramp into its track, called every update
+    pub fn finish_ramps(&mut self) {
+        let played = self.audio.engine.played();
+        let mut finished = false;
+
+        for track in self.audio.tracks.iter_mut() {
+            if let Some(ramp) = track.ramp.filter(|ramp| ramp.is_finished(played)) {
+                track.volume = ramp.target;
+                track.ramp = None;
+                finished = true;
+            }
+        }
+
+        if finished {
+            self.audio.update_tracks();
+        }
+    }
+
+    // how many beats are left of a track's ramp, if it has one
+    pub fn ramp_beats_left(&self, index: usize) -> Option<f32> {
+        let ramp = self.audio.tracks.get(index)?.ramp?;
+        let frames = ramp.frames_left(self.audio.engine.played());
+
+        let sample_rate = self.audio.engine.sample_rate().max(1) as f32;
+        Some(frames as f32 / sample_rate * self.audio.engine.bpm() as f32 / 60.0)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{app::App, track::Track};
+
+impl App {
+    // the length of a beat in frames of a track's clip, which may be at another sample rate
+    fn region_beat(&self, index: usize) -> Option<u64> {
+        let clip = self.audio.tracks[index].clip.as_ref()?;
+
+        match self.audio.engine.bpm() {
+            0 => None,
+            bpm => Some((clip.sample_rate.0 as u64 * 60 / bpm).max(1)),
+        }
+    }
+
+    // moves the end of a track's region `beats` beats later, or earlier when negative
+    pub fn move_region_end(&mut self, index: usize, beats: i32) {
+        self.move_region(index, beats, false);
+    }
+
+    // moves the start of a track's region `beats` beats later, or earlier when negative
+    pub fn move_region_start(&mut self, index: usize, beats: i32) {
+        self.move_region(index, beats, true);
+    }
+
+    // keeps at least a beat of the clip heard, and forgets the region once it's the whole clip
+    fn move_region(&mut self, index: usize, beats: i32, start: bool) {
+        let Some(beat) = self.region_beat(index) else {
+            self.warn(format!(
+                "track {} has no clip to play a region of",
+                (index + 1) % 10
+            ));
+            return;
+        };
+
+        let track = &mut self.audio.tracks[index];
+        let frames = track.clip.as_ref().map_or(0, |clip| clip.frame_count());
+        let region = track.region.clone().unwrap_or(0..frames);
+        let shift = |frame: u64| (frame as i64 + beats as i64 * beat as i64).max(0) as u64;
+
+        let region = if start {
+            shift(region.start).min(region.end.saturating_sub(beat))..region.end
+        } else {
+            region.start..shift(region.end).clamp(region.start + beat, frames.max(beat))
+        };
+
+        let region = region.start..region.end.min(frames);
+        track.region = (region.start > 0 || region.end < frames).then_some(region);
+        self.audio.update_tracks();
+    }
+
+    // the region of a track in beats, for the info panel
+    pub fn region_text(&self, track: &Track) -> String {
+        let (Some(region), Some(clip)) = (&track.region, &track.clip) else {
+            return String::from("whole clip");
+        };
+
+        let beat = clip.sample_rate.0 as f32 * 60.0 / self.snapshot.bpm.max(1) as f32;
+        format!(
+            "beats {:.1} to {:.1}",
+            region.start as f32 / beat,
+            region.end as f32 / beat
+        )
+    }
+}
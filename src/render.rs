@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    cli::RenderOptions,
+    clip::Clip,
+    meter::level_db,
+    session::Session,
+    wav::{self, WavInfo},
+};
+
+// writes a file as the options ask for, telling how much it was normalized by
+fn write(options: &RenderOptions, path: &Path, clip: &Clip, info: &WavInfo) -> anyhow::Result<()> {
+    let gain = wav::save_clip_export(path, clip, info, options.export)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    if gain != 1.0 {
+        println!("normalized {} by {:+.1} dB", path.display(), level_db(gain));
+    }
+
+    Ok(())
+}
+
+/// Renders a session to wav files without touching any audio devices.
+pub fn render(options: &RenderOptions) -> anyhow::Result<()> {
+    let mut session = Session::load(&options.session)?;
+
+    let (sample_rate, channels) = session
+        .format()
+        .ok_or(anyhow!("the session has no clips to render"))?;
+
+    // clips recorded at other sample rates are matched to the first one
+    session.resample(sample_rate);
+
+    let frames = session.loop_frames(sample_rate) * options.loops;
+    if frames == 0 {
+        return Err(anyhow!("the loop is empty, check bpm and beats"));
+    }
+
+    let mix = session.tracks.render_mix(frames, channels, sample_rate);
+    write(options, &options.output, &mix, &session.wav_info())?;
+
+    if options.stems {
+        let stem = options.output.with_extension("");
+
+        for (index, track) in session.tracks.iter().enumerate() {
+            if track.clip.is_none() {
+                continue;
+            }
+
+            let path = format!("{}-track-{}.wav", stem.display(), index + 1);
+            let clip = track.render(frames, channels, sample_rate);
+
+            write(
+                options,
+                path.as_ref(),
+                &clip,
+                &session.track_wav_info(index),
+            )?;
+        }
+    }
+
+    Ok(())
+}
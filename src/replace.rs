@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use cpal::{ChannelCount, SampleRate};
+
+use crate::clip::Clip;
+
+// how long the input fades in and out when replacing engages and releases, in seconds
+pub const REPLACE_FADE: f32 = 0.01;
+
+/// A working copy of the clip of a track being replaced, shared between the ui and the output
+/// callback so neither allocates nor locks while the callback writes into it.
+pub struct ReplaceBuffer {
+    pub track: usize,
+    pub channels: ChannelCount,
+    pub sample_rate: SampleRate,
+    samples: Box<[AtomicU32]>,
+}
+
+impl ReplaceBuffer {
+    pub fn new(track: usize, clip: &Clip) -> Self {
+        Self {
+            track,
+            channels: clip.channels,
+            sample_rate: clip.sample_rate,
+            samples: clip
+                .dense_samples()
+                .iter()
+                .map(|sample| AtomicU32::new(sample.to_bits()))
+                .collect(),
+        }
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.samples.len() as u64 / self.channels.max(1) as u64
+    }
+
+    fn position(&self, index: u64, channel: u16) -> Option<usize> {
+        let frames = self.frame_count();
+        if frames == 0 {
+            return None;
+        }
+
+        Some((index % frames) as usize * self.channels as usize + channel as usize)
+    }
+
+    fn get(&self, position: usize) -> f32 {
+        f32::from_bits(self.samples[position].load(Ordering::Relaxed))
+    }
+
+    /// Returns the sample at a frame, looping like a clip, for an output with `channels`
+    /// channels.
+    pub fn sample(&self, index: u64, channel: u16, channels: ChannelCount) -> f32 {
+        if self.channels == channels {
+            return self.position(index, channel).map_or(0.0, |p| self.get(p));
+        }
+
+        let sum = (0..self.channels)
+            .filter_map(|channel| self.position(index, channel))
+            .map(|position| self.get(position))
+            .sum::<f32>();
+
+        sum / self.channels.max(1) as f32
+    }
+
+    /// Blends an input frame into a frame of the buffer, `amount` 1 replacing it completely.
+    pub fn replace(&self, index: u64, input: &[f32], amount: f32) {
+        if input.is_empty() {
+            return;
+        }
+
+        let average = input.iter().sum::<f32>() / input.len() as f32;
+
+        for channel in 0..self.channels {
+            let Some(position) = self.position(index, channel) else {
+                return;
+            };
+
+            // line channels up when they match, otherwise record the mix of the input
+            let new = if input.len() == self.channels as usize {
+                input[channel as usize]
+            } else {
+                average
+            };
+
+            let old = self.get(position);
+            let sample = old + (new - old) * amount;
+            self.samples[position].store(sample.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn to_clip(&self) -> Clip {
+        let samples = self
+            .samples
+            .iter()
+            .map(|sample| f32::from_bits(sample.load(Ordering::Relaxed)))
+            .collect::<Vec<_>>();
+
+        Clip::new(self.channels, self.sample_rate, samples.into())
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use cpal::SampleRate;
+use crossbeam::channel::Sender;
+use tui::text::Span;
+
+use crate::{app::App, clip::Clip, event::AppEvent};
+
+/// A batch of clips being resampled on a worker thread.
+pub struct ResampleJob {
+    pub id: u64,
+    pub total: usize,
+    progress: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ResampleJob {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn progress(&self) -> usize {
+        self.progress.load(Ordering::Acquire)
+    }
+}
+
+/// The clips of a finished job, each next to the clip it was made from.
+#[derive(Clone, Debug)]
+pub struct Resampled {
+    pub id: u64,
+    pub sample_rate: SampleRate,
+    pub tracks: Vec<(usize, Clip, Clip)>,
+    pub last_recording: Option<(Clip, Clip)>,
+}
+
+// resamples the clips one by one, giving up as soon as the job is superseded
+fn resample_worker(
+    mut job: Resampled,
+    progress: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+    sender: Sender<AppEvent>,
+) {
+    for (_, original, resampled) in job.tracks.iter_mut() {
+        if cancelled.load(Ordering::Acquire) {
+            return;
+        }
+
+        *resampled = original.resample(job.sample_rate);
+        progress.fetch_add(1, Ordering::AcqRel);
+    }
+
+    if let Some((ref original, ref mut resampled)) = job.last_recording {
+        if cancelled.load(Ordering::Acquire) {
+            return;
+        }
+
+        *resampled = original.resample(job.sample_rate);
+        progress.fetch_add(1, Ordering::AcqRel);
+    }
+
+    let _ = sender.send(AppEvent::Resampled(job));
+}
+
+impl App {
+    // resamples every clip that doesn't match `sample_rate` in the background, replacing any
+    // job that is still running
+    pub fn start_resample(&mut self, sample_rate: SampleRate) {
+        if let Some(job) = self.resample_job.take() {
+            job.cancel();
+        }
+
+        let tracks = self
+            .audio
+            .tracks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, track)| {
+                let clip = track.clip.as_ref()?;
+                (clip.sample_rate != sample_rate).then(|| (index, clip.clone(), clip.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        // the last recording is kept for committing later, so it has to match too
+        let last_recording = self
+            .audio
+            .get_clip()
+            .filter(|clip| clip.sample_rate != sample_rate)
+            .map(|clip| (clip.clone(), clip));
+
+        let total = tracks.len() + last_recording.is_some() as usize;
+        if total == 0 {
+            return;
+        }
+
+        self.resample_jobs += 1;
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let job = Resampled {
+            id: self.resample_jobs,
+            sample_rate,
+            tracks,
+            last_recording,
+        };
+
+        let worker_progress = progress.clone();
+        let worker_cancelled = cancelled.clone();
+        let sender = self.events.sender.clone();
+        thread::spawn(move || resample_worker(job, worker_progress, worker_cancelled, sender));
+
+        self.resample_job = Some(ResampleJob {
+            id: self.resample_jobs,
+            total,
+            progress,
+            cancelled,
+        });
+    }
+
+    // swaps in the resampled clips, skipping any that changed while the job ran
+    pub fn resampled(&mut self, job: Resampled) {
+        match self.resample_job {
+            Some(ref current) if current.id == job.id => self.resample_job = None,
+            _ => return,
+        }
+
+        for (index, original, resampled) in job.tracks {
+            let track = &mut self.audio.tracks[index];
+
+            let unchanged = matches!(
+                track.clip,
+                Some(ref clip) if clip.ptr_eq(&original)
+            );
+
+            if unchanged {
+                track.rescale_offset(original.sample_rate, resampled.sample_rate);
+                track.rescale_region(original.sample_rate, resampled.sample_rate);
+                track.clip = Some(resampled);
+            }
+        }
+
+        if let Some((original, resampled)) = job.last_recording {
+            let unchanged = matches!(
+                self.audio.clip,
+                Some(ref clip) if clip.ptr_eq(&original)
+            );
+
+            if unchanged {
+                self.audio.clip = Some(resampled);
+            }
+        }
+
+        self.audio.update_tracks();
+        self.info(format!("resampled tracks to {} Hz", job.sample_rate.0));
+    }
+
+    // the progress of the running job, shown in the tracks title
+    pub fn resample_status(&self) -> Option<Span<'static>> {
+        let job = self.resample_job.as_ref()?;
+        let text = format!(" resampling… {}/{} ", job.progress(), job.total);
+
+        Some(Span::styled(text, self.theme.warning))
+    }
+}
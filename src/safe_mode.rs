@@ -0,0 +1,48 @@
+use std::fs;
+
+use crate::{
+    app::{App, Tab},
+    config,
+};
+
+// written before the streams are launched and removed once they are, so a start that crashes
+// or hangs on a broken device leaves it behind
+const STARTUP_MARKER: &str = "starting";
+
+/// Marks the start as under way, returning true if the last one never finished.
+pub fn begin_startup() -> bool {
+    let Some(dir) = config::config_dir() else {
+        return false;
+    };
+
+    let path = dir.join(STARTUP_MARKER);
+    let crashed = path.exists();
+
+    let _ = fs::create_dir_all(&dir).and_then(|_| fs::write(path, ""));
+
+    crashed
+}
+
+pub fn finish_startup() {
+    if let Some(path) = config::config_file(STARTUP_MARKER) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+impl App {
+    /// Creates an app that neither loads the saved audio settings nor launches the streams,
+    /// waiting in the settings for them to be applied.
+    pub fn safe_mode(crashed: bool) -> Self {
+        let mut app = Self::offline();
+        app.load_config();
+        app.audio.settings.query_devices();
+        app.tab = Tab::Settings;
+
+        if crashed {
+            app.warn("the last start didn't finish, starting in safe mode");
+        }
+
+        app.warn("safe mode: the streams launch once the settings are applied");
+        app
+    }
+}
@@ -0,0 +1,201 @@
+use std::mem;
+
+use tui::text::Span;
+
+use crate::{
+    app::{App, EditMode},
+    queue::QueuedAction,
+    track::Tracks,
+};
+
+// how many scene switches can be undone
+const MAX_SCENE_HISTORY: usize = 32;
+
+// how many cells the progress of a crossfade between scenes takes up
+const FADE_CELLS: usize = 6;
+
+/// A saved set of tracks to switch between, the clips shared with the tracks it was saved
+/// from.
+#[derive(Clone)]
+pub struct Scene {
+    pub tracks: Tracks,
+}
+
+impl App {
+    // asks for the scene to save into or switch to, or switches between the two when pressed
+    // again
+    pub fn scene_key(&mut self, save: bool) {
+        self.edit_mode = match self.edit_mode {
+            EditMode::Scene(current) if current == save => EditMode::None,
+            _ => EditMode::Scene(save),
+        };
+    }
+
+    // called when a digit is pressed after one of the scene keys
+    pub fn scene_digit(&mut self, index: usize) {
+        let EditMode::Scene(save) = self.edit_mode else {
+            return;
+        };
+
+        self.edit_mode = EditMode::None;
+
+        if save {
+            self.save_scene(index);
+        } else {
+            self.switch_scene(index);
+        }
+    }
+
+    pub fn save_scene(&mut self, index: usize) {
+        self.scenes[index] = Some(Scene {
+            tracks: self.audio.tracks.clone(),
+        });
+
+        self.active_scene = Some(index);
+        self.info(format!("saved scene {}", (index + 1) % 10));
+    }
+
+    // switches to a saved scene at the next loop boundary
+    pub fn switch_scene(&mut self, index: usize) {
+        let Some(Some(scene)) = self.scenes.get(index) else {
+            self.warn(format!("scene {} is empty", (index + 1) % 10));
+            return;
+        };
+
+        let tracks = scene.tracks.clone();
+
+        if self.scene_history.len() == MAX_SCENE_HISTORY {
+            self.scene_history.remove(0);
+        }
+
+        let current = Scene {
+            tracks: self.audio.tracks.clone(),
+        };
+        self.scene_history.push((self.active_scene, current));
+
+        self.queue_scene(Some(index), tracks);
+    }
+
+    // goes back to the tracks played before the last scene switch
+    pub fn undo_scene(&mut self) {
+        let Some((index, scene)) = self.scene_history.pop() else {
+            self.warn("no scene switch to undo");
+            return;
+        };
+
+        self.queue_scene(index, scene.tracks);
+    }
+
+    fn queue_scene(&mut self, index: Option<usize>, tracks: Tracks) {
+        let engine = &self.audio.engine;
+
+        // without a running loop there's no boundary to wait for
+        if !engine.is_recording() {
+            self.queued_scene = Some((index, tracks));
+            self.finish_scene_switch();
+            return;
+        }
+
+        let action = QueuedAction::Scene {
+            index,
+            tracks: tracks.clone(),
+        };
+
+        if !self.audio.queue_action(action) {
+            self.warn("too much is queued for the next loop");
+            return;
+        }
+
+        self.queued_scene = Some((index, tracks));
+
+        match index {
+            Some(index) => self.info(format!(
+                "switching to scene {} with the next loop",
+                (index + 1) % 10
+            )),
+            None => self.info("switching back with the next loop"),
+        }
+    }
+
+    // takes the tracks of a queued scene once the callback plays them, called every update
+    pub fn update_scenes(&mut self) {
+        let queued = |action: &QueuedAction| matches!(action, QueuedAction::Scene { .. });
+        if self.queued_scene.is_none() || self.audio.is_queued(queued) {
+            return;
+        }
+
+        self.finish_scene_switch();
+    }
+
+    fn finish_scene_switch(&mut self) {
+        let Some((index, tracks)) = self.queued_scene.take() else {
+            return;
+        };
+
+        self.audio.tracks = tracks;
+        self.audio.update_tracks();
+
+        // the layers belong to the clips of the tracks switched away from
+        self.layers.clear();
+        self.faded_scene = mem::replace(&mut self.active_scene, index);
+
+        match index {
+            Some(index) => self.info(format!("switched to scene {}", (index + 1) % 10)),
+            None => self.info("switched back"),
+        }
+    }
+
+    // the saved scenes for the tracks title, the active one highlighted and the queued one
+    // marked, along with how far a crossfade between two of them is
+    pub fn scene_strip(&self) -> Vec<Span<'static>> {
+        let queued = self.queued_scene.as_ref().and_then(|(index, _)| *index);
+        let fade = self.snapshot.scene_fade;
+        let faded = self.faded_scene.filter(|_| fade.is_some());
+
+        let mut spans = Vec::new();
+        for (index, scene) in self.scenes.iter().enumerate() {
+            if scene.is_none() {
+                continue;
+            }
+
+            let number = format!("{}", (index + 1) % 10);
+            let span = if queued == Some(index) {
+                Span::styled(format!(">{}", number), self.theme.warning)
+            } else if self.active_scene == Some(index) {
+                Span::styled(format!("[{}]", number), self.theme.highlight)
+            } else if faded == Some(index) {
+                Span::styled(format!("<{}", number), self.theme.empty)
+            } else {
+                Span::raw(number)
+            };
+
+            spans.push(Span::raw(" "));
+            spans.push(span);
+        }
+
+        if !spans.is_empty() {
+            spans.insert(0, Span::raw(" scenes"));
+        }
+
+        // the crossfade fills up as the new scene takes over
+        if let Some(progress) = fade {
+            let filled = ((progress * FADE_CELLS as f32) as usize).min(FADE_CELLS);
+
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled("█".repeat(filled), self.theme.highlight));
+            spans.push(Span::styled(
+                "░".repeat(FADE_CELLS - filled),
+                self.theme.empty,
+            ));
+        }
+
+        let beats = self.audio.engine.scene_fade_beats();
+        if self.edit_mode == EditMode::SceneFade {
+            spans.push(Span::styled(format!(" fade {}b", beats), self.theme.active));
+        } else if beats > 0 && !spans.is_empty() {
+            spans.push(Span::styled(format!(" fade {}b", beats), self.theme.empty));
+        }
+
+        spans
+    }
+}
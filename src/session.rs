@@ -0,0 +1,755 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context};
+use cpal::{ChannelCount, SampleRate};
+
+use crate::{
+    app::{App, MAX_BEATS_PER_BAR, MAX_SCENE_FADE_BEATS},
+    archive::{ArchivedClip, ARCHIVE_LEN},
+    clip::Clip,
+    config::{parse_value, strip_comment},
+    delay::DelayLine,
+    engine::{MetronomePattern, DEFAULT_BEATS_PER_BAR},
+    pedal::LooperPedalState,
+    track::{Track, Tracks, PATTERN_STEPS},
+    wav::{self, WavInfo},
+};
+
+pub const SESSION_EXTENSION: &str = "wroom";
+
+/// What a session is called and what it's about, written into exported mixes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionNotes {
+    pub title: String,
+    pub artist: String,
+    pub note: String,
+}
+
+impl SessionNotes {
+    /// Returns the title and the note in one line, empty if there's neither.
+    pub fn summary(&self) -> String {
+        [self.title.as_str(), self.note.as_str()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" — ")
+    }
+}
+
+/// Everything needed to restore a looping session, saved as a text file with the clips in
+/// wav files next to it.
+pub struct Session {
+    pub bpm: u64,
+    pub beats: u64,
+    /// The meter the loop is counted in bars of, sessions from before it was saved counting
+    /// their beats in bars of the default.
+    pub beats_per_bar: u64,
+    pub metronome: MetronomePattern,
+    pub master_volume: u64,
+    pub delay_beats: u64,
+    pub delay_feedback: u64,
+    pub delay_mix: u64,
+    /// How many beats a scene switch crossfades over.
+    pub scene_fade_beats: u64,
+    /// Whether the session comes up locked, so a live set can't be changed by accident.
+    pub locked: bool,
+    pub notes: SessionNotes,
+    pub tracks: Tracks,
+    pub archive: Vec<ArchivedClip>,
+}
+
+// what's been read of an archived clip, which needs its clip to be of any use
+#[derive(Default)]
+struct ArchiveFields {
+    name: String,
+    time: Option<SystemTime>,
+    clip: Option<Clip>,
+}
+
+// the part of a session the lines belong to
+#[derive(Clone, Copy)]
+enum Section {
+    Session,
+    Track(usize),
+    Archive(usize),
+}
+
+// parses either a quoted string or a bare number or boolean
+fn parse_scalar(value: &str) -> Option<String> {
+    if value.starts_with('"') {
+        let mut values = parse_value(value)?;
+        return (values.len() == 1).then(|| values.remove(0));
+    }
+
+    (!value.is_empty() && !value.contains(char::is_whitespace)).then(|| value.to_string())
+}
+
+// strings are saved quoted without escapes, so quotes inside become apostrophes
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "'"))
+}
+
+fn parse_field<T: std::str::FromStr>(name: &str, value: &str) -> anyhow::Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid value `{}` for `{}`", value, name))
+}
+
+// the directory clips are saved in, `live.wroom` keeps its clips in `live-clips`
+fn clip_dir(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    PathBuf::from(format!("{}-clips", stem))
+}
+
+impl Session {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        let mut session = Self {
+            bpm: 120,
+            beats: 16,
+            beats_per_bar: DEFAULT_BEATS_PER_BAR,
+            metronome: MetronomePattern::Off,
+            master_volume: 100,
+            delay_beats: 2,
+            delay_feedback: 40,
+            delay_mix: 50,
+            scene_fade_beats: 0,
+            locked: false,
+            notes: SessionNotes::default(),
+            tracks: Tracks::new(),
+            archive: Vec::new(),
+        };
+
+        let mut section = Section::Session;
+        let mut archive: Vec<ArchiveFields> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line).trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let numbered = |prefix: &str, count: usize| {
+                    name.strip_prefix(prefix)
+                        .and_then(|number| number.parse::<usize>().ok())
+                        .filter(|number| (1..=count).contains(number))
+                };
+
+                section = if let Some(index) = numbered("track.", session.tracks.len()) {
+                    Section::Track(index - 1)
+                } else if let Some(index) = numbered("archive.", ARCHIVE_LEN) {
+                    if archive.len() < index {
+                        archive.resize_with(index, ArchiveFields::default);
+                    }
+
+                    Section::Archive(index - 1)
+                } else {
+                    return Err(anyhow!("line {}: unknown section `{}`", number, name));
+                };
+
+                continue;
+            }
+
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected `name = value`", number))?;
+
+            let name = name.trim();
+            let value = parse_scalar(value.trim())
+                .ok_or_else(|| anyhow!("line {}: invalid value for `{}`", number, name))?;
+
+            let result = match section {
+                Section::Session => session.set_field(name, &value),
+                Section::Track(index) => {
+                    Self::set_track_field(&mut session.tracks[index], base, name, &value)
+                }
+                Section::Archive(index) => {
+                    Self::set_archive_field(&mut archive[index], base, name, &value)
+                }
+            };
+
+            result.with_context(|| format!("line {}", number))?;
+        }
+
+        // the numbers of archived clips only keep them in order
+        for (index, fields) in archive.into_iter().enumerate() {
+            let Some(clip) = fields.clip else {
+                continue;
+            };
+
+            session.archive.push(ArchivedClip {
+                name: match fields.name.as_str() {
+                    "" => format!("archived clip {}", index + 1),
+                    _ => fields.name,
+                },
+                clip,
+                time: fields.time.unwrap_or(UNIX_EPOCH),
+            });
+        }
+
+        Ok(session)
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        match name {
+            "bpm" => self.bpm = parse_field(name, value)?,
+            "beats" => self.beats = parse_field(name, value)?,
+            "beats_per_bar" => self.beats_per_bar = parse_field(name, value)?,
+            "metronome" => {
+                // older sessions only had the metronome on or off
+                self.metronome = match value {
+                    "true" => MetronomePattern::Always,
+                    "false" => MetronomePattern::Off,
+                    _ => MetronomePattern::from_key(value)
+                        .ok_or_else(|| anyhow!("invalid value `{}` for `{}`", value, name))?,
+                }
+            }
+            "master_volume" => self.master_volume = parse_field(name, value)?,
+            "delay_beats" => self.delay_beats = parse_field(name, value)?,
+            "delay_feedback" => self.delay_feedback = parse_field(name, value)?,
+            "delay_mix" => self.delay_mix = parse_field(name, value)?,
+            "scene_fade_beats" => self.scene_fade_beats = parse_field(name, value)?,
+            "locked" => self.locked = parse_field(name, value)?,
+            "title" => self.notes.title = value.to_string(),
+            "artist" => self.notes.artist = value.to_string(),
+            "note" => self.notes.note = value.to_string(),
+            _ => return Err(anyhow!("unknown setting `{}`", name)),
+        }
+
+        Ok(())
+    }
+
+    fn set_track_field(
+        track: &mut Track,
+        base: &Path,
+        name: &str,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        match name {
+            "volume" => track.volume = parse_field(name, value)?,
+            "muted" => track.muted = parse_field(name, value)?,
+            "send" => track.send = parse_field(name, value)?,
+            "input" => {
+                // channels are counted from 1 like in the ui
+                let channel: u16 = parse_field(name, value)?;
+                if channel == 0 {
+                    return Err(anyhow!("input channels start at 1"));
+                }
+
+                track.input_channel = Some(channel - 1);
+            }
+            "offset" => track.offset_frames = parse_field(name, value)?,
+            "synced" => track.synced = parse_field(name, value)?,
+            "reversed" => track.reversed = parse_field(name, value)?,
+            "color" => {
+                // colors are counted from 1 like the tracks
+                let color: usize = parse_field(name, value)?;
+                if color == 0 {
+                    return Err(anyhow!("colors start at 1"));
+                }
+
+                track.color = Some(color - 1);
+            }
+            "group" => {
+                // groups are counted from 1 like in the ui
+                let group: u8 = parse_field(name, value)?;
+                if group == 0 {
+                    return Err(anyhow!("groups start at 1"));
+                }
+
+                track.exclusive_group = Some(group - 1);
+            }
+            "note" => track.note = value.to_string(),
+            "damping" => {
+                let damping: f32 = parse_field(name, value)?;
+                if !(0.0..1.0).contains(&damping) {
+                    return Err(anyhow!("damping goes from 0 up to 1"));
+                }
+
+                track.generation_damping = (damping > 0.0).then_some(damping);
+            }
+            "drive" => {
+                let drive: f32 = parse_field(name, value)?;
+                if !(0.0..=1.0).contains(&drive) {
+                    return Err(anyhow!("drive goes from 0 to 1"));
+                }
+
+                track.drive = drive;
+            }
+            "pattern" => {
+                // a step that's heard is an `x`, a silent one a `.`
+                let pattern = value
+                    .chars()
+                    .map(|step| match step {
+                        'x' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err(anyhow!("pattern steps are `x` or `.`")),
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let pattern = pattern
+                    .try_into()
+                    .map_err(|_| anyhow!("a pattern has {} steps", PATTERN_STEPS))?;
+                track.pattern = Some(pattern);
+            }
+            "region" => {
+                // the frames of the clip that are heard, like `44100..176400`
+                let (start, end) = value
+                    .split_once("..")
+                    .ok_or_else(|| anyhow!("a region is written as `start..end` in frames"))?;
+                let start: u64 = parse_field(name, start.trim())?;
+                let end: u64 = parse_field(name, end.trim())?;
+                if start >= end {
+                    return Err(anyhow!("a region has to end after it starts"));
+                }
+
+                track.region = Some(start..end);
+            }
+            "clip" => {
+                let path = base.join(value);
+                let clip = wav::load_clip(&path)
+                    .with_context(|| format!("failed to load {}", path.display()))?;
+
+                track.clip = Some(clip);
+            }
+            _ => return Err(anyhow!("unknown track setting `{}`", name)),
+        }
+
+        Ok(())
+    }
+
+    fn set_archive_field(
+        fields: &mut ArchiveFields,
+        base: &Path,
+        name: &str,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        match name {
+            "name" => fields.name = value.to_string(),
+            // seconds since the unix epoch
+            "time" => {
+                let seconds: u64 = parse_field(name, value)?;
+                fields.time = Some(UNIX_EPOCH + Duration::from_secs(seconds));
+            }
+            "clip" => {
+                let path = base.join(value);
+                let clip = wav::load_clip(&path)
+                    .with_context(|| format!("failed to load {}", path.display()))?;
+
+                fields.clip = Some(clip);
+            }
+            _ => return Err(anyhow!("unknown archive setting `{}`", name)),
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let base = path.parent().unwrap_or(Path::new(""));
+        let clip_dir = clip_dir(path);
+
+        let mut source = String::new();
+        writeln!(source, "bpm = {}", self.bpm)?;
+        writeln!(source, "beats = {}", self.beats)?;
+        writeln!(source, "beats_per_bar = {}", self.beats_per_bar)?;
+        writeln!(source, "metronome = {}", self.metronome.key())?;
+        writeln!(source, "master_volume = {}", self.master_volume)?;
+        writeln!(source, "delay_beats = {}", self.delay_beats)?;
+        writeln!(source, "delay_feedback = {}", self.delay_feedback)?;
+        writeln!(source, "delay_mix = {}", self.delay_mix)?;
+
+        if self.scene_fade_beats > 0 {
+            writeln!(source, "scene_fade_beats = {}", self.scene_fade_beats)?;
+        }
+
+        if self.locked {
+            writeln!(source, "locked = true")?;
+        }
+
+        for (name, text) in [
+            ("title", &self.notes.title),
+            ("artist", &self.notes.artist),
+            ("note", &self.notes.note),
+        ] {
+            if !text.is_empty() {
+                writeln!(source, "{} = {}", name, quoted(text))?;
+            }
+        }
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            let default = Track::default();
+            let untouched = track.volume == default.volume
+                && track.send == default.send
+                && track.input_channel == default.input_channel
+                && track.offset_frames == default.offset_frames
+                && track.synced == default.synced
+                && track.reversed == default.reversed
+                && track.color == default.color
+                && track.exclusive_group == default.exclusive_group
+                && track.generation_damping == default.generation_damping
+                && track.drive == default.drive
+                && track.pattern == default.pattern
+                && track.region == default.region
+                && track.note.is_empty();
+            if track.clip.is_none() && untouched && !track.muted {
+                continue;
+            }
+
+            writeln!(source, "\n[track.{}]", index + 1)?;
+            writeln!(source, "volume = {}", track.volume)?;
+            writeln!(source, "muted = {}", track.muted)?;
+            writeln!(source, "send = {}", track.send)?;
+
+            if let Some(channel) = track.input_channel {
+                writeln!(source, "input = {}", channel + 1)?;
+            }
+
+            if track.offset_frames != 0 {
+                writeln!(source, "offset = {}", track.offset_frames)?;
+            }
+
+            if !track.synced {
+                writeln!(source, "synced = false")?;
+            }
+
+            if track.reversed {
+                writeln!(source, "reversed = true")?;
+            }
+
+            if let Some(color) = track.color {
+                writeln!(source, "color = {}", color + 1)?;
+            }
+
+            if let Some(group) = track.exclusive_group {
+                writeln!(source, "group = {}", group + 1)?;
+            }
+
+            if let Some(damping) = track.generation_damping {
+                writeln!(source, "damping = {}", damping)?;
+            }
+
+            if track.drive > 0.0 {
+                writeln!(source, "drive = {}", track.drive)?;
+            }
+
+            if let Some(pattern) = track.pattern {
+                let steps = pattern
+                    .iter()
+                    .map(|&on| if on { 'x' } else { '.' })
+                    .collect::<String>();
+                writeln!(source, "pattern = {}", steps)?;
+            }
+
+            if let Some(ref region) = track.region {
+                writeln!(source, "region = {}..{}", region.start, region.end)?;
+            }
+
+            if !track.note.is_empty() {
+                writeln!(source, "note = {}", quoted(&track.note))?;
+            }
+
+            if let Some(ref clip) = track.clip {
+                let clip_path = clip_dir.join(format!("track-{}.wav", index + 1));
+
+                fs::create_dir_all(base.join(&clip_dir))?;
+                wav::save_clip(&base.join(&clip_path), clip)
+                    .with_context(|| format!("failed to save track {}", index + 1))?;
+
+                // always use forward slashes so sessions can move between systems
+                let clip_path = clip_path.to_string_lossy().replace('\\', "/");
+                writeln!(source, "clip = \"{}\"", clip_path)?;
+            }
+        }
+
+        for (index, archived) in self.archive.iter().enumerate() {
+            let seconds = archived
+                .time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+
+            writeln!(source, "\n[archive.{}]", index + 1)?;
+            writeln!(source, "name = {}", quoted(&archived.name))?;
+            writeln!(source, "time = {}", seconds)?;
+
+            let clip_path = clip_dir.join(format!("archive-{}.wav", index + 1));
+
+            fs::create_dir_all(base.join(&clip_dir))?;
+            wav::save_clip(&base.join(&clip_path), &archived.clip)
+                .with_context(|| format!("failed to save archived clip {}", index + 1))?;
+
+            let clip_path = clip_path.to_string_lossy().replace('\\', "/");
+            writeln!(source, "clip = \"{}\"", clip_path)?;
+        }
+
+        fs::write(path, source).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Returns the sample rate and channel count of the first clip, used for rendering.
+    pub fn format(&self) -> Option<(SampleRate, ChannelCount)> {
+        let clip = self.tracks.iter().find_map(|track| track.clip.as_ref())?;
+        Some((clip.sample_rate, clip.channels))
+    }
+
+    /// Returns what goes into an exported mix, the note followed by those of the tracks.
+    pub fn wav_info(&self) -> WavInfo {
+        let mut comment = self.notes.note.clone();
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            if track.note.is_empty() {
+                continue;
+            }
+
+            if !comment.is_empty() {
+                comment.push('\n');
+            }
+
+            write!(comment, "track {}: {}", index + 1, track.note).unwrap();
+        }
+
+        WavInfo {
+            title: self.notes.title.clone(),
+            artist: self.notes.artist.clone(),
+            comment,
+        }
+    }
+
+    /// Returns what goes into the exported stem of a track.
+    pub fn track_wav_info(&self, index: usize) -> WavInfo {
+        let title = match self.notes.title.as_str() {
+            "" => format!("track {}", index + 1),
+            title => format!("{} - track {}", title, index + 1),
+        };
+
+        WavInfo {
+            title,
+            artist: self.notes.artist.clone(),
+            comment: self.tracks[index].note.clone(),
+        }
+    }
+
+    /// Returns the length of one loop in frames.
+    pub fn loop_frames(&self, sample_rate: SampleRate) -> u64 {
+        if self.bpm == 0 {
+            return 0;
+        }
+
+        self.beats * 60 * sample_rate.0 as u64 / self.bpm
+    }
+
+    /// Renders the mix of the loop repeated `repeats` times, followed by `tail_frames` frames
+    /// of the echoes dying out.
+    pub fn render_mix_repeats(&self, repeats: u32, tail_frames: u64) -> anyhow::Result<Clip> {
+        let (sample_rate, channels) = self
+            .format()
+            .ok_or(anyhow!("the session has no clips to render"))?;
+
+        let loop_frames = self.loop_frames(sample_rate);
+        if loop_frames == 0 {
+            return Err(anyhow!("the loop is empty, check bpm and beats"));
+        }
+
+        let mut delay_line = DelayLine::new(sample_rate.0, channels);
+        let delay = delay_line.delay_samples(self.delay_beats, self.bpm, sample_rate.0, channels);
+        let feedback = self.delay_feedback as f32 / 100.0;
+        let mix = self.delay_mix as f32 / 100.0;
+
+        let frames = loop_frames * repeats as u64;
+        let mut samples = Vec::with_capacity((frames + tail_frames) as usize * channels as usize);
+
+        for frame in 0..frames + tail_frames {
+            for channel in 0..channels {
+                // the tracks stop with the last repeat, the delay rings on into the tail
+                let (dry, send) = if frame < frames {
+                    self.tracks
+                        .repeat_sample(frame, loop_frames, channel, channels)
+                } else {
+                    (0.0, 0.0)
+                };
+
+                let wet = delay_line.process(send, delay, feedback) * mix;
+                samples.push(dry + wet);
+            }
+        }
+
+        Ok(Clip::new(channels, sample_rate, samples.into()))
+    }
+
+    /// Resamples every clip that doesn't match `sample_rate`.
+    pub fn resample(&mut self, sample_rate: SampleRate) {
+        for track in self.tracks.iter_mut() {
+            let mismatched =
+                matches!(track.clip, Some(ref clip) if clip.sample_rate != sample_rate);
+
+            if mismatched {
+                track.resample(sample_rate);
+            }
+        }
+
+        for archived in self.archive.iter_mut() {
+            if archived.clip.sample_rate != sample_rate {
+                archived.clip = archived.clip.resample(sample_rate);
+            }
+        }
+    }
+}
+
+impl App {
+    pub fn session(&self) -> Session {
+        let engine = &self.audio.engine;
+
+        Session {
+            bpm: engine.bpm(),
+            beats: engine.beats(),
+            beats_per_bar: engine.beats_per_bar(),
+            metronome: engine.metronome(),
+            master_volume: engine.master_volume(),
+            delay_beats: engine.delay_beats(),
+            delay_feedback: engine.delay_feedback(),
+            delay_mix: engine.delay_mix(),
+            scene_fade_beats: engine.scene_fade_beats(),
+            locked: self.locked,
+            notes: self.notes.clone(),
+            tracks: self.audio.tracks.clone(),
+            archive: self.archive.clone(),
+        }
+    }
+
+    pub fn load_session(&mut self, path: &Path) {
+        let mut session = match Session::load(path) {
+            Ok(session) => session,
+            Err(err) => {
+                self.error(format!("failed to load session: {:#}", err));
+                return;
+            }
+        };
+
+        if let Some(sample_rate) = self.audio.settings.get_sample_rate() {
+            session.resample(sample_rate);
+        }
+
+        self.apply_session_settings(&session);
+
+        self.notes = session.notes;
+        self.audio.tracks = session.tracks;
+        self.audio.update_tracks();
+        self.archive = session.archive;
+        self.locked = session.locked;
+        self.layers.clear();
+        self.pedal = LooperPedalState::Idle;
+
+        self.session_path = Some(path.to_path_buf());
+        self.info(format!("loaded session {}", path.display()));
+    }
+
+    // takes everything of a session but its tracks
+    pub fn apply_session_settings(&self, session: &Session) {
+        let engine = &self.audio.engine;
+        engine.set_bpm(session.bpm);
+
+        // the beats are kept as they were saved, the bars follow from them
+        engine.set_beats_per_bar(session.beats_per_bar.clamp(1, MAX_BEATS_PER_BAR));
+        engine.set_beats(session.beats);
+        engine.set_metronome(session.metronome);
+        engine.set_master_volume(session.master_volume);
+        engine.set_delay_beats(session.delay_beats);
+        engine.set_delay_feedback(session.delay_feedback);
+        engine.set_delay_mix(session.delay_mix);
+        engine.set_scene_fade_beats(session.scene_fade_beats.min(MAX_SCENE_FADE_BEATS));
+    }
+
+    // saves to the session that was loaded, or a new file in the working directory
+    pub fn save_session(&mut self) -> bool {
+        let path = match self.session_path {
+            Some(ref path) => path.clone(),
+            None => wav::unique_path("session", SESSION_EXTENSION),
+        };
+
+        match self.session().save(&path) {
+            Ok(()) => {
+                self.info(format!("saved session to {}", path.display()));
+                self.session_path = Some(path);
+                true
+            }
+            Err(err) => {
+                self.error(format!("failed to save session: {:#}", err));
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+
+    // a session of four beats at 120 bpm, two seconds, with a sine of whole cycles in track 1
+    fn session(send: f32) -> Session {
+        let mut session = App::offline().session();
+        session.bpm = 120;
+        session.beats = 4;
+        session.delay_mix = 50;
+
+        let samples = (0..2000).map(|frame| (frame as f32 / 100.0 * TAU).sin() * 0.5);
+        let track = &mut session.tracks[0];
+        track.clip = Some(Clip::new(1, SampleRate(1000), samples.collect()));
+        track.send = send;
+
+        session
+    }
+
+    #[test]
+    fn repeats_and_tail_make_up_the_length() {
+        let session = session(0.0);
+        let loop_frames = session.loop_frames(SampleRate(1000));
+        assert_eq!(loop_frames, 2000);
+
+        for (repeats, tail) in [(1, 0), (3, 0), (4, 500)] {
+            let mix = session.render_mix_repeats(repeats, tail).unwrap();
+            assert_eq!(mix.frame_count(), loop_frames * repeats as u64 + tail);
+        }
+
+        let mut empty = session;
+        empty.bpm = 0;
+        assert!(empty.render_mix_repeats(2, 0).is_err());
+    }
+
+    #[test]
+    fn repeats_join_without_a_jump() {
+        let mix = session(0.0).render_mix_repeats(3, 1000).unwrap();
+        let samples = mix.dense_samples();
+
+        // no step between frames, the seams included, is bigger than one of the sine
+        let largest = 0.5 * (TAU / 100.0) * 1.01;
+        for frame in 1..6000 {
+            let step = samples[frame] - samples[frame - 1];
+            assert!(step.abs() <= largest, "frame {}", frame);
+        }
+
+        // every repeat is the same, and without a send the tail is silent
+        assert_eq!(samples[..2000], samples[2000..4000]);
+        assert_eq!(samples[2000..4000], samples[4000..6000]);
+        assert!(samples[6000..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn echoes_ring_on_into_the_tail() {
+        let mix = session(1.0).render_mix_repeats(2, 2000).unwrap();
+        let samples = mix.dense_samples();
+
+        assert!(samples[4000..].iter().any(|sample| sample.abs() > 0.01));
+    }
+}
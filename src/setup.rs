@@ -0,0 +1,449 @@
+use crossterm::event::KeyEvent;
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App, EditMode, Tab},
+    keymap::Action,
+    meter::level_db,
+    processor::TEST_TONE_FREQUENCY,
+};
+
+// the size of the setup window
+const SETUP_WIDTH: u16 = 64;
+const SETUP_HEIGHT: u16 = 20;
+
+// an input peak louder than this counts as a signal, about -34 dB
+const SIGNAL_THRESHOLD: f32 = 0.02;
+
+// how much of the input level is kept every update, so the meter falls off smoothly
+const LEVEL_DECAY: f32 = 0.9;
+
+/// A screen of the guided setup shown on the first launch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupStep {
+    Host,
+    InputDevice,
+    OutputDevice,
+    SampleRate,
+    BufferSize,
+    TestTone,
+}
+
+impl SetupStep {
+    pub const COUNT: usize = 6;
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::Host => 0,
+            Self::InputDevice => 1,
+            Self::OutputDevice => 2,
+            Self::SampleRate => 3,
+            Self::BufferSize => 4,
+            Self::TestTone => 5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Host => "Host",
+            Self::InputDevice => "Input Device",
+            Self::OutputDevice => "Output Device",
+            Self::SampleRate => "Sample Rate",
+            Self::BufferSize => "Buffer Size",
+            Self::TestTone => "Test",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Host => "Choose the audio system to use.",
+            Self::InputDevice => "Choose the device to record from.",
+            Self::OutputDevice => "Choose the device to listen on.",
+            Self::SampleRate => "Choose a sample rate, both devices have to support it.",
+            Self::BufferSize => "Smaller buffers lower the latency, but need a faster machine.",
+            Self::TestTone => "",
+        }
+    }
+
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Self::Host => Some(Self::InputDevice),
+            Self::InputDevice => Some(Self::OutputDevice),
+            Self::OutputDevice => Some(Self::SampleRate),
+            Self::SampleRate => Some(Self::BufferSize),
+            Self::BufferSize => Some(Self::TestTone),
+            Self::TestTone => None,
+        }
+    }
+
+    pub fn previous(self) -> Option<Self> {
+        match self {
+            Self::Host => None,
+            Self::InputDevice => Some(Self::Host),
+            Self::OutputDevice => Some(Self::InputDevice),
+            Self::SampleRate => Some(Self::OutputDevice),
+            Self::BufferSize => Some(Self::SampleRate),
+            Self::TestTone => Some(Self::BufferSize),
+        }
+    }
+
+    // the edit mode rotating the setting chosen on this screen
+    fn edit_mode(self) -> EditMode {
+        match self {
+            Self::Host => EditMode::Host,
+            Self::InputDevice => EditMode::InputDevice,
+            Self::OutputDevice => EditMode::OutputDevice,
+            Self::SampleRate => EditMode::SampleRate,
+            Self::BufferSize => EditMode::BufferSize,
+            Self::TestTone => EditMode::None,
+        }
+    }
+}
+
+/// The state of the guided setup.
+pub struct Setup {
+    pub step: SetupStep,
+    /// The input level shown on the meter, from 0 to 1.
+    pub level: f32,
+    /// Whether the input has picked up anything during the test.
+    pub signal: bool,
+}
+
+impl Setup {
+    pub fn new() -> Self {
+        Self {
+            step: SetupStep::Host,
+            level: 0.0,
+            signal: false,
+        }
+    }
+}
+
+impl App {
+    // starts the guided setup, the settings are staged until the test
+    pub fn start_setup(&mut self) {
+        self.setup = Some(Setup::new());
+        self.edit_mode = SetupStep::Host.edit_mode();
+    }
+
+    // called instead of the usual key handling while the setup is shown
+    pub fn setup_key(&mut self, key: KeyEvent) {
+        let Some(action) = self.keymap.action(Tab::Settings, key) else {
+            return;
+        };
+
+        match action {
+            Action::ApplySettings => self.next_setup_step(),
+            Action::Cancel => self.previous_setup_step(),
+            Action::RotateUp => self.rotate(1),
+            Action::RotateDown => self.rotate(-1),
+            Action::Quit => self.quit(),
+            _ => {}
+        }
+    }
+
+    fn next_setup_step(&mut self) {
+        let Some(current) = self.setup.as_ref().map(|setup| setup.step) else {
+            return;
+        };
+
+        // both devices have to support what was chosen before moving on
+        let error = match current {
+            SetupStep::SampleRate => self.shown_settings().sample_rate_error(),
+            SetupStep::BufferSize => self.shown_settings().buffer_size_error(),
+            _ => None,
+        };
+
+        if let Some(err) = error {
+            self.warn(format!("{}, pick another", err));
+            return;
+        }
+
+        let Some(ref mut setup) = self.setup else {
+            return;
+        };
+
+        let Some(step) = current.next() else {
+            self.finish_setup();
+            return;
+        };
+
+        setup.step = step;
+        setup.level = 0.0;
+        setup.signal = false;
+
+        if step == SetupStep::TestTone {
+            self.start_test();
+        } else {
+            self.edit_mode = step.edit_mode();
+        }
+    }
+
+    fn previous_setup_step(&mut self) {
+        let Some(ref mut setup) = self.setup else {
+            return;
+        };
+
+        let Some(step) = setup.step.previous() else {
+            return;
+        };
+
+        if setup.step == SetupStep::TestTone {
+            self.audio.engine.set_test_tone(false);
+        }
+
+        setup.step = step;
+        self.edit_mode = step.edit_mode();
+    }
+
+    // launches the streams with the chosen settings and plays the test tone
+    fn start_test(&mut self) {
+        if self.staged.is_some() {
+            self.apply_settings();
+        } else if self.audio.output_stream.is_none() {
+            self.launch_streams();
+        }
+
+        self.edit_mode = EditMode::None;
+        self.audio.engine.take_input_peak();
+        self.audio.engine.set_test_tone(true);
+    }
+
+    fn finish_setup(&mut self) {
+        self.audio.engine.set_test_tone(false);
+        self.setup = None;
+        self.tab = Tab::Play;
+
+        self.save_audio_settings();
+        self.info("setup complete, the settings tab changes these later");
+    }
+
+    // follows the input level during the test, called every update
+    pub fn update_setup(&mut self) {
+        let Some(ref mut setup) = self.setup else {
+            return;
+        };
+
+        if setup.step != SetupStep::TestTone {
+            return;
+        }
+
+        let peak = self.audio.engine.take_input_peak();
+        setup.level = peak.max(setup.level * LEVEL_DECAY);
+        setup.signal |= peak >= SIGNAL_THRESHOLD;
+    }
+
+    pub fn render_setup<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let Some(ref setup) = self.setup else {
+            return;
+        };
+
+        let step = setup.step;
+        let area = centered_rect(frame.size(), SETUP_WIDTH, SETUP_HEIGHT);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                "Setup {}/{} · {}",
+                step.index() + 1,
+                SetupStep::COUNT,
+                step.name()
+            ))
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let key = |action| self.keymap.key_name(action);
+        let footer = match step {
+            SetupStep::Host => format!(
+                "{}/{} to choose, {} for the next step",
+                key(Action::RotateUp),
+                key(Action::RotateDown),
+                key(Action::ApplySettings)
+            ),
+            SetupStep::TestTone => format!(
+                "{} to finish, {} to go back",
+                key(Action::ApplySettings),
+                key(Action::Cancel)
+            ),
+            _ => format!(
+                "{}/{} to choose, {} for the next step, {} to go back",
+                key(Action::RotateUp),
+                key(Action::RotateDown),
+                key(Action::ApplySettings),
+                key(Action::Cancel)
+            ),
+        };
+
+        let footer = Paragraph::new(footer)
+            .alignment(Alignment::Center)
+            .style(self.theme.empty);
+        frame.render_widget(footer, rows[1]);
+
+        if step == SetupStep::TestTone {
+            self.render_setup_test(frame, rows[0]);
+        } else {
+            self.render_setup_choice(frame, rows[0], step);
+        }
+    }
+
+    // the description of a step above the list of values to choose from
+    fn render_setup_choice<B: Backend>(
+        &mut self,
+        frame: &mut Frame<B>,
+        area: Rect,
+        step: SetupStep,
+    ) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .split(area);
+
+        let settings = self.shown_settings();
+        let (items, selected, error) = match step {
+            SetupStep::Host => {
+                let names = settings.host_names().into_iter().map(ListItem::new);
+                (names.collect(), Some(settings.host_index()), None)
+            }
+            SetupStep::InputDevice => {
+                let names = settings.input_device_names().into_iter().map(ListItem::new);
+                (names.collect(), settings.input_device_index(), None)
+            }
+            SetupStep::OutputDevice => {
+                let names = settings
+                    .output_device_names()
+                    .into_iter()
+                    .map(ListItem::new);
+                (names.collect(), settings.output_device_index(), None)
+            }
+            SetupStep::SampleRate => {
+                let rates = settings
+                    .sample_rates()
+                    .iter()
+                    .map(|&(rate, side)| self.support_item(rate.0, side));
+                let error = settings.sample_rate_error();
+                (
+                    rates.collect::<Vec<_>>(),
+                    settings.sample_rate_index(),
+                    error,
+                )
+            }
+            SetupStep::BufferSize => {
+                let sizes = settings
+                    .buffer_sizes()
+                    .iter()
+                    .map(|&(size, side)| self.support_item(size, side));
+                let error = settings.buffer_size_error();
+                (
+                    sizes.collect::<Vec<_>>(),
+                    settings.buffer_size_index(),
+                    error,
+                )
+            }
+            SetupStep::TestTone => return,
+        };
+
+        let description = Paragraph::new(step.description()).wrap(Wrap { trim: true });
+        frame.render_widget(description, rows[0]);
+
+        if items.is_empty() {
+            let paragraph = Paragraph::new("nothing available, try another host")
+                .alignment(Alignment::Center)
+                .style(self.theme.warning);
+
+            frame.render_widget(paragraph, rows[1]);
+            return;
+        }
+
+        let block = Block::default().borders(Borders::ALL);
+        let area = self.render_support_block(frame, rows[1], block, error);
+
+        let list = List::new(items).highlight_style(self.theme.highlight);
+
+        let mut state = ListState::default();
+        state.select(selected);
+
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    // plays the test tone and shows whether the input picks anything up
+    fn render_setup_test<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let Some(ref setup) = self.setup else {
+            return;
+        };
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(2),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        if let Some(ref error) = self.audio.error {
+            let paragraph = Paragraph::new(format!(
+                "the streams failed to launch, go back and pick other settings: {}",
+                error
+            ))
+            .style(self.theme.error)
+            .wrap(Wrap { trim: true });
+
+            frame.render_widget(paragraph, area);
+            return;
+        }
+
+        let settings = &self.audio.settings;
+        let output = settings.output_device_name().unwrap_or_default();
+        let input = settings.input_device_name().unwrap_or_default();
+
+        let tone = Paragraph::new(format!(
+            "Playing a {} Hz tone on {}. If you can't hear it, go back and pick another output.",
+            TEST_TONE_FREQUENCY, output
+        ))
+        .wrap(Wrap { trim: true });
+
+        let prompt =
+            Paragraph::new(format!("Play or speak into {}.", input)).wrap(Wrap { trim: true });
+
+        let label = format!(" {:.0} dB", level_db(setup.level));
+        let color = self.theme.track_color(0);
+        let meter = self.theme.db_meter(setup.level, color, false);
+
+        let status = if setup.signal {
+            Span::styled("signal detected", self.theme.accent)
+        } else {
+            Span::styled("waiting for a signal…", self.theme.warning)
+        };
+
+        let status = Paragraph::new(Spans::from(status)).alignment(Alignment::Center);
+
+        frame.render_widget(tone, rows[0]);
+        frame.render_widget(prompt, rows[1]);
+        let meter_row = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(label.len() as u16)])
+            .split(rows[2]);
+
+        frame.render_widget(meter, meter_row[0]);
+        frame.render_widget(Paragraph::new(label), meter_row[1]);
+        frame.render_widget(status, rows[4]);
+    }
+}
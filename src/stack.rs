@@ -0,0 +1,87 @@
+use crate::app::App;
+
+/// Whether every loop an armed track records lands on a track of its own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StackMode {
+    #[default]
+    Off,
+    /// Each loop lands on the next empty track, capturing stops once every track has a clip.
+    Stop,
+    /// Each loop lands on the next empty track, then on the last track once every track has
+    /// a clip.
+    Overdub,
+}
+
+impl StackMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Stop => "Stack",
+            Self::Overdub => "Stack + overdub",
+        }
+    }
+
+    pub fn cycled(self) -> Self {
+        match self {
+            Self::Off => Self::Stop,
+            Self::Stop => Self::Overdub,
+            Self::Overdub => Self::Off,
+        }
+    }
+
+    pub fn is_on(self) -> bool {
+        self != Self::Off
+    }
+}
+
+impl App {
+    pub fn cycle_stack_mode(&mut self) {
+        self.stack_mode = self.stack_mode.cycled();
+
+        // the engine keeps the armed track armed from loop to loop while stacking
+        self.audio.engine.set_stacking(self.stack_mode.is_on());
+
+        match self.stack_mode {
+            // a loop being recorded still lands, the track is disarmed after it
+            StackMode::Off => self.info("stacking off"),
+            StackMode::Stop => self.info("stacking every loop onto an empty track"),
+            StackMode::Overdub => self.info("stacking every loop, then overdubbing the last track"),
+        }
+    }
+
+    // picks the track a loop recorded for the armed track lands on, None if it's dropped
+    pub fn stack_target(&mut self, index: usize) -> Option<usize> {
+        if !self.stack_mode.is_on() {
+            return Some(index);
+        }
+
+        let tracks = &self.audio.tracks;
+        let empty = if tracks[index].clip.is_none() {
+            Some(index)
+        } else {
+            tracks.first_empty()
+        };
+
+        match (empty, self.stack_mode) {
+            (Some(target), _) => Some(target),
+            (None, StackMode::Overdub) => Some(tracks.len() - 1),
+            (None, _) => {
+                self.audio.engine.set_armed_track(None);
+                self.warn("every track has a clip, dropped the loop and stopped stacking");
+                None
+            }
+        }
+    }
+
+    // stops capturing once the last empty track was filled, instead of recording a loop that
+    // has nowhere to go
+    pub fn finish_full_stack(&mut self) {
+        let engine = &self.audio.engine;
+        let full = self.audio.tracks.iter().all(|track| track.clip.is_some());
+
+        if self.stack_mode == StackMode::Stop && full && engine.armed_track().is_some() {
+            engine.set_armed_track(None);
+            self.info("every track has a clip now, stopped stacking");
+        }
+    }
+}
@@ -0,0 +1,83 @@
+/// How long the slice a stuttering track repeats is, as a fraction of a beat.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StutterLength {
+    Quarter,
+    #[default]
+    Eighth,
+    Sixteenth,
+}
+
+impl StutterLength {
+    pub fn division(self) -> u64 {
+        match self {
+            Self::Quarter => 4,
+            Self::Eighth => 8,
+            Self::Sixteenth => 16,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quarter => "1/4",
+            Self::Eighth => "1/8",
+            Self::Sixteenth => "1/16",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Quarter => Self::Eighth,
+            Self::Eighth => Self::Sixteenth,
+            Self::Sixteenth => Self::Quarter,
+        }
+    }
+
+    /// Returns the length of the slice in frames, or 0 when the tempo is 0.
+    pub fn frames(self, bpm: u64, sample_rate: u64) -> u64 {
+        if bpm == 0 {
+            return 0;
+        }
+
+        sample_rate * 60 / (bpm * self.division())
+    }
+}
+
+/// A track repeating a short slice instead of playing along with the loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stutter {
+    pub track: usize,
+    pub length: StutterLength,
+}
+
+/// The slice the output callback is repeating, starting where the playhead was when the
+/// stutter began.
+pub struct ActiveStutter {
+    pub stutter: Stutter,
+    start: u64,
+    frames: u64,
+    elapsed: u64,
+}
+
+impl ActiveStutter {
+    pub fn new(stutter: Stutter, start: u64, bpm: u64, sample_rate: u64) -> Self {
+        Self {
+            stutter,
+            start,
+            frames: stutter.length.frames(bpm, sample_rate),
+            elapsed: 0,
+        }
+    }
+
+    /// Returns the track and the frame it plays instead of the playhead.
+    pub fn index(&self) -> Option<(usize, u64)> {
+        if self.frames == 0 {
+            return None;
+        }
+
+        Some((self.stutter.track, self.start + self.elapsed % self.frames))
+    }
+
+    pub fn advance(&mut self) {
+        self.elapsed += 1;
+    }
+}
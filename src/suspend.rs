@@ -0,0 +1,80 @@
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use tui::{backend::Backend, Terminal};
+
+use crate::{
+    app::App,
+    terminal::{restore_terminal, setup_terminal},
+};
+
+// set by the signal handler or the suspend key, checked every iteration of the main loop
+static SUSPEND: AtomicBool = AtomicBool::new(false);
+
+/// Asks the main loop to suspend the process, like the shell does on `SIGTSTP`.
+pub fn request_suspend() {
+    SUSPEND.store(true, Ordering::Release);
+}
+
+pub fn take_suspend() -> bool {
+    SUSPEND.swap(false, Ordering::AcqRel)
+}
+
+/// Routes `SIGTSTP` through the main loop, so the terminal is restored before stopping.
+#[cfg(unix)]
+pub fn register() -> io::Result<()> {
+    // only stores to an atomic, which is safe to do from a signal handler
+    unsafe { signal_hook::low_level::register(signal_hook::consts::SIGTSTP, request_suspend) }?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn register() -> io::Result<()> {
+    Ok(())
+}
+
+// stops the process until it's continued
+#[cfg(unix)]
+fn stop_process() -> io::Result<()> {
+    signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)
+}
+
+#[cfg(not(unix))]
+fn stop_process() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "suspending isn't supported on this platform",
+    ))
+}
+
+impl App {
+    // hands the terminal back to the shell until the process is continued, then takes it over
+    // again and redraws everything
+    pub fn suspend<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        let engine = &self.audio.engine;
+        let killed = engine.is_killed();
+
+        if self.mute_on_suspend {
+            engine.set_killed(true);
+        }
+
+        restore_terminal();
+        terminal.show_cursor()?;
+
+        let stopped = stop_process();
+
+        setup_terminal()?;
+        terminal.clear()?;
+        self.redraw = true;
+
+        self.audio.engine.set_killed(killed);
+
+        if let Err(err) = stopped {
+            self.warn(format!("failed to suspend: {}", err));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::{centered_rect, App},
+    config,
+    notes::edit_text,
+    session::{Session, SessionNotes, SESSION_EXTENSION},
+};
+
+// the directory in the config directory templates are saved in
+const TEMPLATE_DIR: &str = "templates";
+
+// the size of the template window
+const TEMPLATE_WIDTH: u16 = 44;
+const TEMPLATE_HEIGHT: u16 = 14;
+
+fn template_path(name: &str) -> Option<PathBuf> {
+    let file = format!("{}.{}", name, SESSION_EXTENSION);
+    Some(config::config_file(TEMPLATE_DIR)?.join(file))
+}
+
+/// Returns the names of the saved templates, sorted.
+pub fn template_names() -> Vec<String> {
+    let Some(dir) = config::config_file(TEMPLATE_DIR) else {
+        return Vec::new();
+    };
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut names = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == SESSION_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect::<Vec<_>>();
+
+    names.sort();
+    names
+}
+
+/// Loads a template by name, a session without any clips.
+pub fn load_template(name: &str) -> anyhow::Result<Session> {
+    let path = template_path(name).ok_or_else(|| anyhow!("no config directory"))?;
+    if !path.exists() {
+        return Err(anyhow!("no template named `{}`", name));
+    }
+
+    Session::load(&path)
+}
+
+// names can't leave the template directory or hide the extension
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(|c: char| matches!(c, '/' | '\\' | ':') || c.is_control())
+}
+
+/// The window asking which template to load, or what to name a new one.
+pub enum TemplateDialog {
+    Pick {
+        names: Vec<String>,
+        state: ListState,
+    },
+    Name(String),
+}
+
+impl Session {
+    /// Returns a copy with every clip left out, the settings a template keeps.
+    pub fn template(&self) -> Self {
+        let mut tracks = self.tracks.clone();
+
+        for track in tracks.iter_mut() {
+            track.clip = None;
+            track.offset_frames = 0;
+            track.note.clear();
+        }
+
+        Self {
+            notes: SessionNotes::default(),
+            tracks,
+            archive: Vec::new(),
+            ..*self
+        }
+    }
+}
+
+impl App {
+    pub fn open_template_picker(&mut self) {
+        let names = template_names();
+        if names.is_empty() {
+            self.warn("no templates saved yet");
+            return;
+        }
+
+        let mut state = ListState::default();
+        state.select(Some(0));
+
+        self.template = Some(TemplateDialog::Pick { names, state });
+    }
+
+    pub fn open_template_prompt(&mut self) {
+        self.template = Some(TemplateDialog::Name(String::new()));
+    }
+
+    // handles a key while the template window is open
+    pub fn template_key(&mut self, key: KeyEvent) {
+        let Some(ref mut dialog) = self.template else {
+            return;
+        };
+
+        match (dialog, key.code) {
+            (_, KeyCode::Esc) => self.template = None,
+            (TemplateDialog::Pick { names, state }, KeyCode::Up | KeyCode::Down) => {
+                let offset = if key.code == KeyCode::Up { -1 } else { 1 };
+                let index = state.selected().unwrap_or(0) as i32 + offset;
+                state.select(Some(index.rem_euclid(names.len() as i32) as usize));
+            }
+            (TemplateDialog::Pick { names, state }, KeyCode::Enter) => {
+                let name = names[state.selected().unwrap_or(0)].clone();
+                self.template = None;
+                self.load_template(&name);
+            }
+            (TemplateDialog::Name(name), KeyCode::Enter) => {
+                let name = name.trim().to_string();
+                if !valid_name(&name) {
+                    self.warn("a template needs a name without slashes");
+                    return;
+                }
+
+                self.template = None;
+                self.save_template(&name);
+            }
+            (TemplateDialog::Name(name), _) => {
+                edit_text(name, key);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn save_template(&mut self, name: &str) {
+        let result = template_path(name)
+            .ok_or_else(|| anyhow!("no config directory"))
+            .and_then(|path| {
+                let dir = path.parent().unwrap_or(&path);
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("failed to create {}", dir.display()))?;
+
+                self.session().template().save(&path)
+            });
+
+        match result {
+            Ok(()) => self.info(format!("saved template {}", name)),
+            Err(err) => self.error(format!("failed to save template: {:#}", err)),
+        }
+    }
+
+    // takes the settings of a template, keeping the clips of the tracks
+    pub fn load_template(&mut self, name: &str) {
+        let mut session = match load_template(name) {
+            Ok(session) => session,
+            Err(err) => {
+                self.error(format!("failed to load template: {:#}", err));
+                return;
+            }
+        };
+
+        let has_clips = self.audio.tracks.iter().any(|track| track.clip.is_some());
+
+        for (track, current) in session.tracks.iter_mut().zip(self.audio.tracks.iter_mut()) {
+            track.clip = current.clip.take();
+            track.offset_frames = current.offset_frames;
+            track.note = std::mem::take(&mut current.note);
+        }
+
+        self.apply_session_settings(&session);
+        self.audio.tracks = session.tracks;
+        self.audio.update_tracks();
+
+        if has_clips {
+            self.warn(format!(
+                "loaded template {}, the track settings were overwritten but the clips kept",
+                name
+            ));
+        } else {
+            self.info(format!("loaded template {}", name));
+        }
+    }
+
+    // starts a new session from a template, every track empty
+    pub fn new_from_template(&mut self, name: &str) {
+        for track in self.audio.tracks.iter_mut() {
+            track.clip = None;
+            track.note.clear();
+        }
+
+        self.notes = SessionNotes::default();
+        self.layers.clear();
+        self.session_path = None;
+        self.load_template(name);
+    }
+
+    pub fn render_template<B: Backend>(&mut self, frame: &mut Frame<B>) {
+        let Some(ref mut dialog) = self.template else {
+            return;
+        };
+
+        let area = centered_rect(frame.size(), TEMPLATE_WIDTH, TEMPLATE_HEIGHT);
+
+        let title = match dialog {
+            TemplateDialog::Pick { .. } => "Load Template",
+            TemplateDialog::Name(_) => "Save Template",
+        };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(self.theme.accent);
+
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+
+        let footer = match dialog {
+            TemplateDialog::Pick { names, state } => {
+                let items = names
+                    .iter()
+                    .map(|name| ListItem::new(name.as_str()))
+                    .collect::<Vec<_>>();
+                let list = List::new(items).highlight_style(self.theme.highlight);
+
+                frame.render_stateful_widget(list, rows[0], state);
+                "up/down to choose, enter to load, esc to cancel"
+            }
+            TemplateDialog::Name(name) => {
+                let text = vec![
+                    Spans::from("name:"),
+                    Spans::from(vec![
+                        Span::styled(name.as_str(), self.theme.active),
+                        Span::styled("_", self.theme.empty),
+                    ]),
+                ];
+
+                frame.render_widget(Paragraph::new(text), rows[0]);
+                "enter to save, esc to cancel"
+            }
+        };
+
+        let footer = Paragraph::new(footer).style(self.theme.empty);
+        frame.render_widget(footer, rows[1]);
+    }
+}
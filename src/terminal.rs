@@ -0,0 +1,40 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+/// Puts the terminal in raw mode and the alternate screen, capturing the mouse.
+pub fn setup_terminal() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+/// Restores the terminal to the state it was in before [`setup_terminal`].
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = leave_screen(&mut io::stdout());
+}
+
+// leaves the alternate screen and stops capturing the mouse
+fn leave_screen(out: &mut impl Write) -> io::Result<()> {
+    execute!(out, LeaveAlternateScreen, DisableMouseCapture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restoring_leaves_the_screen_and_the_mouse() {
+        let mut out = Vec::new();
+        leave_screen(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // back to the main screen, no more mouse reports
+        assert!(out.contains("\x1b[?1049l"), "{:?}", out);
+        assert!(out.contains("\x1b[?1000l"), "{:?}", out);
+    }
+}
@@ -0,0 +1,149 @@
+use tui::style::{Color, Modifier, Style};
+
+use crate::{app::meter::DbMeter, track::Track};
+
+const RAINBOW: [Color; 6] = [
+    Color::Red,
+    Color::Yellow,
+    Color::Green,
+    Color::Cyan,
+    Color::Blue,
+    Color::Magenta,
+];
+
+const BRIGHT_RAINBOW: [Color; 6] = [
+    Color::LightRed,
+    Color::LightYellow,
+    Color::LightGreen,
+    Color::LightCyan,
+    Color::LightBlue,
+    Color::LightMagenta,
+];
+
+/// The styles used to render the ui.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    /// The selected tab and track.
+    pub accent: Style,
+    /// The value currently being edited.
+    pub active: Style,
+    pub error: Style,
+    pub warning: Style,
+    /// The selected entry of a list.
+    pub highlight: Style,
+    /// Colors of the tracks and the beat bar.
+    pub palette: [Color; 6],
+    /// Color of the numbers drawn on meters, `None` reverses the meter instead.
+    pub meter_text: Option<Color>,
+    pub volume: Style,
+    pub muted: Style,
+    pub empty: Style,
+    /// The border of the play tab flashing on a beat.
+    pub beat: Style,
+    /// The border of the play tab flashing on the first beat of the loop.
+    pub downbeat: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "Default",
+            accent: Style::default().fg(Color::Yellow),
+            active: Style::default().fg(Color::Red),
+            error: Style::default().fg(Color::Red),
+            warning: Style::default().fg(Color::Yellow),
+            highlight: Style::default().add_modifier(Modifier::BOLD),
+            palette: RAINBOW,
+            meter_text: Some(Color::White),
+            volume: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::Gray),
+            empty: Style::default().fg(Color::DarkGray),
+            beat: Style::default().fg(Color::White),
+            downbeat: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+}
+
+impl Theme {
+    pub const COUNT: usize = 3;
+
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+
+        Self {
+            name: "High Contrast",
+            accent: bold.fg(Color::LightYellow),
+            active: bold.fg(Color::LightRed),
+            error: bold.fg(Color::LightRed),
+            warning: bold.fg(Color::LightYellow),
+            highlight: bold.add_modifier(Modifier::REVERSED),
+            palette: BRIGHT_RAINBOW,
+            meter_text: Some(Color::Black),
+            volume: bold.fg(Color::White),
+            muted: Style::default().fg(Color::Gray),
+            empty: Style::default().fg(Color::DarkGray),
+            beat: bold.fg(Color::LightCyan),
+            downbeat: bold.fg(Color::LightYellow).add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    pub fn monochrome() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let dim = Style::default().add_modifier(Modifier::DIM);
+
+        Self {
+            name: "Monochrome",
+            accent: bold.add_modifier(Modifier::UNDERLINED),
+            active: bold.add_modifier(Modifier::REVERSED),
+            error: bold,
+            warning: bold,
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            palette: [Color::Reset; 6],
+            meter_text: None,
+            volume: Style::default(),
+            muted: dim,
+            empty: dim.add_modifier(Modifier::CROSSED_OUT),
+            beat: bold,
+            downbeat: bold.add_modifier(Modifier::REVERSED),
+        }
+    }
+
+    pub fn from_index(index: usize) -> Self {
+        match index % Self::COUNT {
+            0 => Self::default(),
+            1 => Self::high_contrast(),
+            _ => Self::monochrome(),
+        }
+    }
+
+    pub fn track_color(&self, index: usize) -> Color {
+        self.palette[index % self.palette.len()]
+    }
+
+    /// Returns the color of the track at `index`, its own if it has one.
+    pub fn color_of(&self, track: &Track, index: usize) -> Color {
+        self.track_color(track.color.unwrap_or(index))
+    }
+
+    pub fn meter(&self, color: Color) -> Style {
+        Style::default().fg(color)
+    }
+
+    /// Returns a dB meter in the track color while safe, turning to the warning and error
+    /// colors as it gets hot and goes over.
+    pub fn db_meter(&self, level: f32, color: Color, vertical: bool) -> DbMeter {
+        DbMeter::new(level, vertical)
+            .zones(self.meter(color), self.warning, self.error)
+            .tick_style(self.empty)
+    }
+
+    pub fn meter_value(&self, color: Color) -> Style {
+        match self.meter_text {
+            Some(text) => Style::default().fg(text).bg(color),
+            None => Style::default().add_modifier(Modifier::REVERSED),
+        }
+    }
+}
@@ -1,13 +1,168 @@
-use cpal::SampleRate;
+use std::ops::Range;
+
+use cpal::{ChannelCount, SampleRate};
 use deref_derive::{Deref, DerefMut};
 
-use crate::clip::Clip;
+use crate::{clip::Clip, drive, meter::LevelAccumulator};
+
+/// How many steps a pattern divides the clip of a track into.
+pub const PATTERN_STEPS: usize = 16;
+
+// how long a step of a pattern fades in and out next to a silent one, in seconds
+const PATTERN_FADE: f32 = 0.002;
+
+// how long a track fades in and out at the edges of its region, in seconds
+const REGION_FADE: f32 = 0.002;
+
+/// How much of the armed track's settings the monitored input goes through, so it's heard
+/// the way the layer will sit in the mix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFollow {
+    #[default]
+    Off,
+    /// The input feeds the delay like the track does.
+    Send,
+    /// The input is also turned up or down to the track's volume.
+    SendAndVolume,
+}
+
+impl InputFollow {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Send => "Send",
+            Self::SendAndVolume => "Send + volume",
+        }
+    }
+
+    pub fn cycled(self) -> Self {
+        match self {
+            Self::Off => Self::Send,
+            Self::Send => Self::SendAndVolume,
+            Self::SendAndVolume => Self::Off,
+        }
+    }
+}
+
+/// A change of a track's volume spread over a number of frames, played out by the output
+/// callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VolumeRamp {
+    pub from: u32,
+    pub target: u32,
+    /// The frame of [`AudioEngine::played`](crate::engine::AudioEngine::played) the ramp
+    /// starts at.
+    pub start: u64,
+    pub frames: u64,
+}
+
+impl VolumeRamp {
+    /// Returns the volume at a played frame, moving in a straight line from `from` to
+    /// `target` and landing exactly on `target` once the ramp is over.
+    pub fn volume_at(&self, frame: u64) -> f32 {
+        let elapsed = frame.saturating_sub(self.start);
+        if elapsed >= self.frames {
+            return self.target as f32;
+        }
+
+        let t = elapsed as f32 / self.frames as f32;
+        self.from as f32 + (self.target as f32 - self.from as f32) * t
+    }
+
+    pub fn frames_left(&self, frame: u64) -> u64 {
+        (self.start + self.frames).saturating_sub(frame)
+    }
+
+    pub fn is_finished(&self, frame: u64) -> bool {
+        self.frames_left(frame) == 0
+    }
+}
+
+/// A crossfade from the clip a track played before to the one it plays now, played out by the
+/// output callback.
+#[derive(Clone)]
+pub struct ClipFade {
+    /// The clip faded out, silence if the track had none.
+    pub from: Option<Clip>,
+    /// The frame of [`AudioEngine::played`](crate::engine::AudioEngine::played) the fade
+    /// starts at, set by the output callback once it picks up the track.
+    pub start: Option<u64>,
+    pub frames: u64,
+}
+
+impl ClipFade {
+    /// Returns how much of the new clip is heard at a played frame, from 0 to 1.
+    pub fn mix_at(&self, frame: u64) -> f32 {
+        let Some(start) = self.start else {
+            return 0.0;
+        };
+
+        let elapsed = frame.saturating_sub(start);
+        if elapsed >= self.frames {
+            return 1.0;
+        }
+
+        elapsed as f32 / self.frames as f32
+    }
+}
+
+// returns the sample at a frame of a clip, for an output with `channels` channels
+fn clip_frame_sample(clip: &Clip, index: u64, channel: u16, channels: ChannelCount) -> f32 {
+    if clip.channels == channels {
+        clip.sample(index, channel)
+    } else {
+        clip.average_sample(index)
+    }
+}
 
 #[derive(Clone)]
 pub struct Track {
     pub clip: Option<Clip>,
     pub volume: u32,
+    /// A change of the volume under way, `volume` being where it started until it's over.
+    pub ramp: Option<VolumeRamp>,
+    /// The volume the ramp has reached, kept by the output callback.
+    pub ramp_volume: f32,
     pub muted: bool,
+    /// The entry of the theme palette the track is drawn in, picked by its position when
+    /// unset.
+    pub color: Option<usize>,
+    /// Whether the track is pre-listened on the cue channels, at full volume even while
+    /// muted.
+    pub cue: bool,
+    /// How much of the track feeds the delay, from 0 to 1.
+    pub send: f32,
+    /// The input channel the track records from, or the whole input when unset.
+    pub input_channel: Option<u16>,
+    /// How many frames later the clip plays, wrapping around its length.
+    pub offset_frames: i64,
+    /// Whether the clip restarts with the loop, or keeps looping on its own length.
+    pub synced: bool,
+    /// Whether the clip plays backwards, the offset and the phase counting forward as usual.
+    pub reversed: bool,
+    /// The group of tracks only one of which plays at a time, if the track is in one.
+    pub exclusive_group: Option<u8>,
+    /// The frame of the clip a free running track plays, kept by the output callback.
+    pub phase: u64,
+    /// A line about the take, like "verse riff, too loud".
+    pub note: String,
+    /// How much the layers already on the track are low-passed each time another one is
+    /// overdubbed, the coefficient of [`Clip::damped`], so older material recedes.
+    pub generation_damping: Option<f32>,
+    /// How hard the track is driven into saturation, from 0 for none up to 1, see
+    /// [`drive::saturate`].
+    pub drive: f32,
+    /// The steps of the clip that are heard, the rest silenced, so the track gates in a
+    /// rhythm. Every step is heard without one.
+    pub pattern: Option<[bool; PATTERN_STEPS]>,
+    /// The frames of the clip that are heard, the track being silent outside them, or the
+    /// whole clip if unset. Nothing is cut off the clip, and an overdub extends the region
+    /// over wherever the take has sound rather than being cut to it.
+    pub region: Option<Range<u64>>,
+    /// A change of clip being crossfaded.
+    pub fade: Option<ClipFade>,
+    /// How far the fade has gone, kept by the output callback.
+    pub fade_mix: f32,
 }
 
 impl Default for Track {
@@ -15,7 +170,25 @@ impl Default for Track {
         Self {
             clip: None,
             volume: 100,
+            ramp: None,
+            ramp_volume: 100.0,
             muted: false,
+            cue: false,
+            color: None,
+            send: 0.0,
+            input_channel: None,
+            offset_frames: 0,
+            synced: true,
+            reversed: false,
+            exclusive_group: None,
+            phase: 0,
+            note: String::new(),
+            generation_damping: None,
+            drive: 0.0,
+            pattern: None,
+            region: None,
+            fade: None,
+            fade_mix: 1.0,
         }
     }
 }
@@ -26,11 +199,208 @@ impl Track {
     }
 
     pub fn volume_factor(&self) -> f32 {
-        if !self.muted {
+        if self.muted {
+            0.0
+        } else if self.ramp.is_some() {
+            self.ramp_volume / 100.0
+        } else {
             self.volume as f32 / 100.0
+        }
+    }
+
+    /// Returns the frame of the clip that plays at a frame, shifted by the offset and counted
+    /// from the end when the track is reversed.
+    pub fn clip_index(&self, index: u64) -> u64 {
+        let index = match self.clip {
+            Some(ref clip) if self.offset_frames != 0 && clip.frame_count() > 0 => {
+                let frame_count = clip.frame_count() as i64;
+                (index as i64 - self.offset_frames).rem_euclid(frame_count) as u64
+            }
+            _ => index,
+        };
+
+        self.mirrored(index)
+    }
+
+    /// Returns the frame of the clip playing at a frame of the loop, which is the phase of
+    /// the track when it runs free.
+    pub fn playing_index(&self, index: u64) -> u64 {
+        if self.synced {
+            self.clip_index(index)
         } else {
-            0.0
+            self.mirrored(self.phase)
+        }
+    }
+
+    // counts a frame of the clip from the end if the track is reversed
+    fn mirrored(&self, index: u64) -> u64 {
+        match self.clip {
+            Some(ref clip) if self.reversed && clip.frame_count() > 0 => {
+                let frame_count = clip.frame_count();
+                frame_count - 1 - index % frame_count
+            }
+            _ => index,
+        }
+    }
+
+    /// Moves a free running track to the next frame of its clip.
+    pub fn advance_phase(&mut self) {
+        if self.synced {
+            return;
+        }
+
+        if let Some(ref clip) = self.clip {
+            self.phase = (self.phase + 1) % clip.frame_count().max(1);
+        }
+    }
+
+    /// Returns the sample this track plays at a frame, for an output with `channels` channels.
+    pub fn sample(&self, index: u64, channel: u16, channels: ChannelCount) -> f32 {
+        let clip_index = self.clip_index(index);
+        let sample = self.clip_sample(clip_index, channel, channels);
+        let gain = self.pattern_gain(index) * self.region_gain(clip_index);
+
+        self.driven(sample) * gain * self.volume_factor()
+    }
+
+    /// Applies the drive of the track to a sample of its clip, before the volume.
+    pub fn driven(&self, sample: f32) -> f32 {
+        drive::saturate(sample, self.drive)
+    }
+
+    /// Returns how much of the track its pattern lets through at a frame of the clip, the
+    /// offset aside, fading in and out next to silent steps so they don't click.
+    pub fn pattern_gain(&self, frame: u64) -> f32 {
+        let (Some(pattern), Some(clip)) = (self.pattern, &self.clip) else {
+            return 1.0;
+        };
+
+        let frames = clip.frame_count();
+        if frames == 0 {
+            return 1.0;
+        }
+
+        let frame = frame % frames;
+        let step = (frame * PATTERN_STEPS as u64 / frames) as usize;
+        if !pattern[step] {
+            return 0.0;
+        }
+
+        let start = step as u64 * frames / PATTERN_STEPS as u64;
+        let end = (step as u64 + 1) * frames / PATTERN_STEPS as u64;
+        let fade = (clip.sample_rate.0 as f32 * PATTERN_FADE).max(1.0);
+
+        let mut gain = 1.0f32;
+        if !pattern[(step + PATTERN_STEPS - 1) % PATTERN_STEPS] {
+            gain = gain.min((frame - start) as f32 / fade);
+        }
+
+        if !pattern[(step + 1) % PATTERN_STEPS] {
+            gain = gain.min((end - frame) as f32 / fade);
         }
+
+        gain
+    }
+
+    /// Returns how much of the track its region lets through at a frame of the clip, fading
+    /// in and out at the edges so they don't click.
+    pub fn region_gain(&self, index: u64) -> f32 {
+        let (Some(region), Some(clip)) = (&self.region, &self.clip) else {
+            return 1.0;
+        };
+
+        if !region.contains(&index) {
+            return 0.0;
+        }
+
+        let fade = (clip.sample_rate.0 as f32 * REGION_FADE).max(1.0);
+        let gain = ((index - region.start) as f32 + 1.0).min((region.end - index) as f32) / fade;
+
+        gain.min(1.0)
+    }
+
+    /// Like [`Track::pattern_gain`], at a frame of the loop, or at the phase of a track
+    /// running free.
+    pub fn playing_pattern_gain(&self, index: u64) -> f32 {
+        self.pattern_gain(if self.synced { index } else { self.phase })
+    }
+
+    // returns the sample at a frame of the clip before the volume, for an output with
+    // `channels` channels
+    fn clip_sample(&self, index: u64, channel: u16, channels: ChannelCount) -> f32 {
+        let Some(ref clip) = self.clip else {
+            return 0.0;
+        };
+
+        clip_frame_sample(clip, index, channel, channels)
+    }
+
+    // like `clip_sample`, but blending in the clip faded out while a fade is under way
+    fn fading_sample(&self, index: u64, channel: u16, channels: ChannelCount) -> f32 {
+        let sample = self.clip_sample(index, channel, channels);
+
+        let Some(ref fade) = self.fade else {
+            return sample;
+        };
+
+        if self.fade_mix >= 1.0 {
+            return sample;
+        }
+
+        let from = match fade.from {
+            Some(ref clip) if clip.frame_count() > 0 => {
+                clip_frame_sample(clip, index % clip.frame_count(), channel, channels)
+            }
+            _ => 0.0,
+        };
+
+        from + (sample - from) * self.fade_mix
+    }
+
+    /// Renders `frames` frames of this track alone, looping the clip if it's shorter.
+    pub fn render(&self, frames: u64, channels: ChannelCount, sample_rate: SampleRate) -> Clip {
+        let mut samples = Vec::with_capacity(frames as usize * channels as usize);
+
+        for frame in 0..frames {
+            let index = self.loop_index(frame);
+
+            for channel in 0..channels {
+                samples.push(self.sample(index, channel, channels));
+            }
+        }
+
+        Clip::new(channels, sample_rate, samples.into())
+    }
+
+    // wraps a frame index around the length of the clip
+    fn loop_index(&self, frame: u64) -> u64 {
+        match self.clip {
+            Some(ref clip) if clip.frame_count() > 0 => frame % clip.frame_count(),
+            _ => frame,
+        }
+    }
+
+    /// Returns true if the track sounds the same as another, the color and phase aside.
+    pub fn sounds_like(&self, other: &Self) -> bool {
+        let same_clip = match (&self.clip, &other.clip) {
+            (Some(clip), Some(other)) => clip.ptr_eq(other),
+            (None, None) => true,
+            _ => false,
+        };
+
+        same_clip
+            && self.volume == other.volume
+            && self.ramp == other.ramp
+            && self.muted == other.muted
+            && self.cue == other.cue
+            && self.send == other.send
+            && self.input_channel == other.input_channel
+            && self.offset_frames == other.offset_frames
+            && self.synced == other.synced
+            && self.reversed == other.reversed
+            && self.drive == other.drive
+            && self.pattern == other.pattern
+            && self.region == other.region
     }
 
     pub fn toggle_mute(&mut self) {
@@ -39,11 +409,58 @@ impl Track {
 
     pub fn resample(&mut self, sample_rate: SampleRate) {
         if let Some(ref mut clip) = self.clip {
-            *clip = clip.resample(sample_rate);
+            if clip.sample_rate != sample_rate {
+                let from = clip.sample_rate;
+                *clip = clip.resample(sample_rate);
+                self.rescale_offset(from, sample_rate);
+                self.rescale_region(from, sample_rate);
+            }
+        }
+    }
+
+    /// Keeps the offset at the same time after the clip is resampled.
+    pub fn rescale_offset(&mut self, from: SampleRate, to: SampleRate) {
+        if from.0 > 0 {
+            self.offset_frames = self.offset_frames * to.0 as i64 / from.0 as i64;
+        }
+    }
+
+    /// Grows the region to take in `frames`, dropping it once it covers the whole clip.
+    pub fn extend_region(&mut self, frames: Range<u64>) {
+        let Some(ref region) = self.region else {
+            return;
+        };
+
+        let region = region.start.min(frames.start)..region.end.max(frames.end);
+        let frame_count = self.clip.as_ref().map_or(0, Clip::frame_count);
+
+        self.region = (region.start > 0 || region.end < frame_count).then_some(region);
+    }
+
+    /// Keeps the region over the same time after the clip is resampled.
+    pub fn rescale_region(&mut self, from: SampleRate, to: SampleRate) {
+        if let Some(ref mut region) = self.region {
+            if from.0 > 0 {
+                let scale = |frame: u64| frame * to.0 as u64 / from.0 as u64;
+                *region = scale(region.start)..scale(region.end);
+            }
+        }
+    }
+
+    /// Returns true if the clip was recorded at another sample rate, which plays it at the
+    /// wrong speed.
+    pub fn rate_mismatch(&self, sample_rate: u64) -> bool {
+        match self.clip {
+            Some(ref clip) => sample_rate != 0 && clip.sample_rate.0 as u64 != sample_rate,
+            None => false,
         }
     }
 }
 
+// how many tracks there are, one for every digit key
+pub const TRACK_COUNT: usize = 10;
+
+/// Every track of the loop, one for each digit key.
 #[derive(Clone, Deref, DerefMut)]
 pub struct Tracks {
     #[deref]
@@ -53,7 +470,7 @@ pub struct Tracks {
 impl Default for Tracks {
     fn default() -> Self {
         Self {
-            tracks: vec![Track::default(); 10],
+            tracks: vec![Track::default(); TRACK_COUNT],
         }
     }
 }
@@ -63,9 +480,317 @@ impl Tracks {
         Self::default()
     }
 
-    pub fn resample(&mut self, sample_rate: SampleRate) {
-        for track in self.tracks.iter_mut() {
-            track.resample(sample_rate);
+    // returns the sample of a track before its volume, played from `stutter` instead of
+    // `index` if it's the one stuttering, or silent if it's the one being replaced
+    fn track_sample(
+        &self,
+        track: usize,
+        index: u64,
+        channel: u16,
+        channels: ChannelCount,
+        stutter: Option<(usize, u64)>,
+        replacing: Option<usize>,
+    ) -> f32 {
+        if replacing == Some(track) {
+            return 0.0;
         }
+
+        match stutter {
+            Some((stuttering, stutter_index)) if stuttering == track => {
+                let track = &self[track];
+                let frame = track.loop_index(stutter_index);
+                let clip_index = track.clip_index(frame);
+                let sample = track.fading_sample(clip_index, channel, channels);
+                track.driven(sample) * track.pattern_gain(frame) * track.region_gain(clip_index)
+            }
+            _ => {
+                let track = &self[track];
+                let clip_index = track.playing_index(index);
+                let sample = track.fading_sample(clip_index, channel, channels);
+                let gain = track.playing_pattern_gain(index) * track.region_gain(clip_index);
+                track.driven(sample) * gain
+            }
+        }
+    }
+
+    /// Mutes or unmutes a track, muting the rest of its exclusive group when it comes in.
+    pub fn toggle_mute(&mut self, index: usize) {
+        self[index].toggle_mute();
+
+        let track = &self[index];
+        let Some(group) = track.exclusive_group.filter(|_| !track.muted) else {
+            return;
+        };
+
+        for (other, track) in self.iter_mut().enumerate() {
+            if other != index && track.exclusive_group == Some(group) {
+                track.muted = true;
+            }
+        }
+    }
+
+    /// Moves every free running track to the next frame of its clip.
+    pub fn advance_phases(&mut self) {
+        for track in self.iter_mut() {
+            track.advance_phase();
+        }
+    }
+
+    /// Moves every volume ramp to a played frame.
+    pub fn advance_ramps(&mut self, frame: u64) {
+        for track in self.iter_mut() {
+            if let Some(ramp) = track.ramp {
+                track.ramp_volume = ramp.volume_at(frame);
+            }
+        }
+    }
+
+    /// Starts the fades the output callback hasn't started yet at a played frame, and moves
+    /// every fade to it.
+    pub fn advance_fades(&mut self, frame: u64) {
+        for track in self.iter_mut() {
+            if let Some(ref mut fade) = track.fade {
+                fade.start.get_or_insert(frame);
+                track.fade_mix = fade.mix_at(frame);
+            }
+        }
+    }
+
+    /// Picks up where the tracks played before left off at a frame of the loop, so a track
+    /// that starts running free continues from where it was.
+    pub fn carry_phases(&mut self, previous: &Self, index: u64) {
+        for (track, previous) in self.iter_mut().zip(previous.iter()) {
+            let frame_count = track.clip.as_ref().map_or(0, Clip::frame_count).max(1);
+
+            // the phase counts forward whichever way the track plays
+            track.phase = previous.mirrored(previous.playing_index(index)) % frame_count;
+        }
+    }
+
+    /// Returns a copy with every clip starting `frames` frames earlier, or later when negative.
+    pub fn shifted(&self, frames: i64) -> Self {
+        let mut tracks = self.clone();
+
+        for track in tracks.iter_mut() {
+            if let Some(ref mut clip) = track.clip {
+                *clip = clip.shifted(frames);
+            }
+        }
+
+        tracks
+    }
+
+    /// Returns a copy with every clip tiled or truncated to `frames`.
+    pub fn fitted(&self, frames: u64) -> Self {
+        let mut tracks = self.clone();
+
+        for track in tracks.iter_mut() {
+            if let Some(ref mut clip) = track.clip {
+                *clip = clip.fit(frames);
+            }
+        }
+
+        tracks
+    }
+
+    /// Returns the lowest track without a clip, if any.
+    pub fn first_empty(&self) -> Option<usize> {
+        self.iter().position(|track| track.clip.is_none())
+    }
+
+    /// Returns true if any track is pre-listened on the cue channels.
+    pub fn has_cues(&self) -> bool {
+        self.iter().any(|track| track.cue)
+    }
+
+    /// Returns the sum of all tracks at a frame, for an output with `channels` channels.
+    ///
+    /// `stutter` is a track and the frame it plays instead of `index`, `replacing` a track the
+    /// caller plays itself. What each track adds is measured into `levels`.
+    pub fn mix_sample(
+        &self,
+        index: u64,
+        channel: u16,
+        channels: ChannelCount,
+        stutter: Option<(usize, u64)>,
+        replacing: Option<usize>,
+        levels: &mut [LevelAccumulator],
+    ) -> f32 {
+        (0..self.len())
+            .map(|track| {
+                let sample = self.track_sample(track, index, channel, channels, stutter, replacing);
+                let sample = sample * self[track].volume_factor();
+
+                // the caller measures the track it plays itself
+                match levels.get_mut(track) {
+                    Some(level) if replacing != Some(track) => level.add(sample),
+                    _ => {}
+                }
+
+                sample
+            })
+            .sum()
+    }
+
+    /// Returns true if any track feeds the delay.
+    pub fn has_sends(&self) -> bool {
+        self.iter()
+            .any(|track| track.send > 0.0 && track.clip.is_some())
+    }
+
+    /// Returns the sum of all tracks scaled by their sends, the input of the delay.
+    pub fn send_sample(
+        &self,
+        index: u64,
+        channel: u16,
+        channels: ChannelCount,
+        stutter: Option<(usize, u64)>,
+        replacing: Option<usize>,
+    ) -> f32 {
+        (0..self.len())
+            .filter(|&track| self[track].send > 0.0)
+            .map(|track| {
+                let sample = self.track_sample(track, index, channel, channels, stutter, replacing);
+                sample * self[track].volume_factor() * self[track].send
+            })
+            .sum()
+    }
+
+    /// Returns the sum of the cued tracks at a frame at full volume, what the cue channels
+    /// play, measured into `levels` when they take over the mix.
+    pub fn cue_sample(
+        &self,
+        index: u64,
+        channel: u16,
+        channels: ChannelCount,
+        stutter: Option<(usize, u64)>,
+        replacing: Option<usize>,
+        mut levels: Option<&mut [LevelAccumulator]>,
+    ) -> f32 {
+        (0..self.len())
+            .filter(|&track| self[track].cue)
+            .map(|track| {
+                let sample = self.track_sample(track, index, channel, channels, stutter, replacing);
+
+                match levels.as_mut().and_then(|levels| levels.get_mut(track)) {
+                    Some(level) if replacing != Some(track) => level.add(sample),
+                    _ => {}
+                }
+
+                sample
+            })
+            .sum()
+    }
+
+    /// Returns the sum of all tracks at a frame of a render repeating a loop of `loop_frames`,
+    /// along with what they send to the delay.
+    ///
+    /// Synced tracks restart with every repeat, free running ones keep looping on their own
+    /// length like they do live.
+    pub fn repeat_sample(
+        &self,
+        frame: u64,
+        loop_frames: u64,
+        channel: u16,
+        channels: ChannelCount,
+    ) -> (f32, f32) {
+        let mut mix = 0.0;
+        let mut send = 0.0;
+
+        for track in self.iter() {
+            let index = if track.synced {
+                track.clip_index(frame % loop_frames.max(1))
+            } else {
+                track.clip_index(track.loop_index(frame))
+            };
+
+            let sample = track.sample(index, channel, channels);
+            mix += sample;
+            send += sample * track.send;
+        }
+
+        (mix, send)
+    }
+
+    /// Renders `frames` frames of the mix of all tracks offline.
+    pub fn render_mix(&self, frames: u64, channels: ChannelCount, sample_rate: SampleRate) -> Clip {
+        let mut samples = Vec::with_capacity(frames as usize * channels as usize);
+
+        for frame in 0..frames {
+            for channel in 0..channels {
+                let sample = self
+                    .iter()
+                    .map(|track| track.sample(track.loop_index(frame), channel, channels))
+                    .sum::<f32>();
+
+                samples.push(sample);
+            }
+        }
+
+        Clip::new(channels, sample_rate, samples.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_ramp_is_a_straight_line_to_the_target() {
+        let ramp = VolumeRamp {
+            from: 20,
+            target: 100,
+            start: 1000,
+            frames: 400,
+        };
+
+        // nothing moves before the start
+        assert_eq!(ramp.volume_at(0), 20.0);
+        assert_eq!(ramp.volume_at(1000), 20.0);
+
+        // evenly spaced frames are evenly spaced volumes
+        assert_eq!(ramp.volume_at(1100), 40.0);
+        assert_eq!(ramp.volume_at(1200), 60.0);
+        assert_eq!(ramp.volume_at(1300), 80.0);
+
+        // it lands exactly on the target and stays there
+        assert_eq!(ramp.volume_at(1399), 99.8);
+        assert_eq!(ramp.volume_at(1400), 100.0);
+        assert_eq!(ramp.volume_at(u64::MAX), 100.0);
+
+        assert_eq!(ramp.frames_left(1300), 100);
+        assert!(!ramp.is_finished(1399));
+        assert!(ramp.is_finished(1400));
+    }
+
+    #[test]
+    fn volume_ramp_down_and_of_no_length() {
+        let ramp = VolumeRamp {
+            from: 100,
+            target: 0,
+            start: 0,
+            frames: 4,
+        };
+        let volumes = (0..6).map(|frame| ramp.volume_at(frame));
+        assert_eq!(
+            volumes.collect::<Vec<_>>(),
+            [100.0, 75.0, 50.0, 25.0, 0.0, 0.0]
+        );
+
+        let ramp = VolumeRamp { frames: 0, ..ramp };
+        assert_eq!(ramp.volume_at(0), 0.0);
+        assert!(ramp.is_finished(0));
+    }
+
+    #[test]
+    fn offline_mix_loops_the_clips_at_their_volume() {
+        let mut tracks = Tracks::new();
+        tracks[0].clip = Some(Clip::new(1, SampleRate(48000), vec![0.5; 48000].into()));
+        tracks[0].volume = 50;
+
+        // two seconds of the one second clip at half volume
+        let mix = tracks.render_mix(96000, 1, SampleRate(48000));
+        assert_eq!(mix.frame_count(), 96000);
+        assert_eq!(mix.sample(72000, 0), 0.25);
     }
 }
@@ -0,0 +1,565 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use cpal::SampleRate;
+
+use crate::clip::Clip;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// The peak a normalized export is brought to, in dBFS, leaving room for the overshoot of
+/// converters and encoders.
+pub const NORMALIZE_PEAK_DB: f32 = -1.0;
+
+/// How the samples of an exported WAV file are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SampleFormat {
+    #[default]
+    Float32,
+    Int16,
+}
+
+impl SampleFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            SampleFormat::Float32 => "32-bit float",
+            SampleFormat::Int16 => "16-bit",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            SampleFormat::Float32 => SampleFormat::Int16,
+            SampleFormat::Int16 => SampleFormat::Float32,
+        }
+    }
+}
+
+/// How a clip is written on export. Normalizing and dithering only apply to 16-bit files,
+/// a float file keeps the samples exactly as they were mixed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub format: SampleFormat,
+    /// Brings the peak of the file to [`NORMALIZE_PEAK_DB`].
+    pub normalize: bool,
+    /// Adds triangular noise of a bit before rounding, so quiet parts fade into noise instead
+    /// of distorting.
+    pub dither: bool,
+}
+
+/// Triangular (TPDF) dither noise, the sum of two uniform values, spanning one step of the
+/// output either side. Seeded, so an export comes out the same every time.
+pub struct Dither {
+    state: u32,
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self::new(0x2545_f491)
+    }
+}
+
+impl Dither {
+    pub fn new(seed: u32) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    // a uniform value from 0 up to 1, from a xorshift generator
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns the next noise value, from -1 to 1 steps.
+    pub fn noise(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+}
+
+/// Returns the gain that brings the peak of `samples` to [`NORMALIZE_PEAK_DB`], or 1 for
+/// silence, which no gain can bring up.
+pub fn normalize_gain(samples: &[f32]) -> f32 {
+    let peak = samples
+        .iter()
+        .filter(|sample| sample.is_finite())
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+
+    if peak > 0.0 {
+        10f32.powf(NORMALIZE_PEAK_DB / 20.0) / peak
+    } else {
+        1.0
+    }
+}
+
+/// Converts a sample to 16 bits, rounding to the nearest step or dithering first when given
+/// the noise to.
+pub fn quantize_16(sample: f32, dither: Option<&mut Dither>) -> i16 {
+    let max = i16::MAX as f32;
+    let noise = dither.map_or(0.0, |dither| dither.noise());
+
+    let sample = if sample.is_finite() { sample } else { 0.0 };
+    (sample * max + noise).round().clamp(-max, max) as i16
+}
+
+/// The text written to the INFO list of a WAV file, empty fields left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WavInfo {
+    pub title: String,
+    pub artist: String,
+    pub comment: String,
+}
+
+impl WavInfo {
+    fn fields(&self) -> [(&'static [u8; 4], &str); 3] {
+        [
+            (b"INAM", &self.title),
+            (b"IART", &self.artist),
+            (b"ICMT", &self.comment),
+        ]
+    }
+
+    // the LIST chunk holding the fields, or nothing if they're all empty
+    fn chunk(&self) -> Vec<u8> {
+        let mut list = Vec::new();
+
+        for (id, text) in self.fields() {
+            if text.is_empty() {
+                continue;
+            }
+
+            // the text is null terminated and padded to an even size
+            let size = text.len() as u32 + 1;
+            list.extend_from_slice(id);
+            list.extend_from_slice(&size.to_le_bytes());
+            list.extend_from_slice(text.as_bytes());
+            list.push(0);
+
+            if size % 2 == 1 {
+                list.push(0);
+            }
+        }
+
+        if list.is_empty() {
+            return list;
+        }
+
+        let mut chunk = Vec::with_capacity(list.len() + 12);
+        chunk.extend_from_slice(b"LIST");
+        chunk.extend_from_slice(&(list.len() as u32 + 4).to_le_bytes());
+        chunk.extend_from_slice(b"INFO");
+        chunk.extend_from_slice(&list);
+        chunk
+    }
+
+    fn read_list(&mut self, list: &[u8]) -> io::Result<()> {
+        if list.get(0..4) != Some(b"INFO") {
+            return Ok(());
+        }
+
+        let mut offset = 4;
+        while offset + 8 <= list.len() {
+            let id = &list[offset..offset + 4];
+            let size = read_u32(list, offset + 4)? as usize;
+            let body = offset + 8;
+            let text = list
+                .get(body..body + size)
+                .ok_or_else(|| invalid("truncated info"))?;
+
+            // anything after the terminator is padding
+            let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+            let text = String::from_utf8_lossy(&text[..end]).into_owned();
+
+            match id {
+                b"INAM" => self.title = text,
+                b"IART" => self.artist = text,
+                b"ICMT" => self.comment = text,
+                _ => {}
+            }
+
+            offset = body + size + size % 2;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a clip as a 32-bit float WAV file.
+pub fn write_clip<W: Write>(writer: W, clip: &Clip) -> io::Result<()> {
+    write_clip_info(writer, clip, &WavInfo::default())
+}
+
+// writes everything up to the samples, `data_len` bytes of which follow
+fn write_header<W: Write>(
+    writer: &mut W,
+    clip: &Clip,
+    info: &WavInfo,
+    (tag, bits): (u16, u16),
+    data_len: u32,
+) -> io::Result<()> {
+    let channels = clip.channels;
+    let sample_rate = clip.sample_rate.0;
+    let block_align = channels * bits / 8;
+    let info = info.chunk();
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + info.len() as u32 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&tag.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits.to_le_bytes())?;
+
+    writer.write_all(&info)?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())
+}
+
+/// Writes a clip as a 32-bit float WAV file, with `info` in a LIST chunk before the samples.
+pub fn write_clip_info<W: Write>(mut writer: W, clip: &Clip, info: &WavInfo) -> io::Result<()> {
+    let samples = clip.dense_samples();
+    let data_len = samples.len() as u32 * 4;
+
+    write_header(&mut writer, clip, info, (FORMAT_FLOAT, 32), data_len)?;
+
+    for sample in samples.iter() {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    writer.flush()
+}
+
+/// Writes a clip the way `options` ask for, returning the gain it was normalized by, 1 when
+/// it wasn't.
+pub fn write_clip_export<W: Write>(
+    mut writer: W,
+    clip: &Clip,
+    info: &WavInfo,
+    options: ExportOptions,
+) -> io::Result<f32> {
+    if options.format == SampleFormat::Float32 {
+        write_clip_info(writer, clip, info)?;
+        return Ok(1.0);
+    }
+
+    let samples = clip.dense_samples();
+    let gain = if options.normalize {
+        normalize_gain(&samples)
+    } else {
+        1.0
+    };
+
+    write_header(
+        &mut writer,
+        clip,
+        info,
+        (FORMAT_PCM, 16),
+        samples.len() as u32 * 2,
+    )?;
+
+    let mut dither = options.dither.then(Dither::default);
+    for sample in samples.iter() {
+        let sample = quantize_16(sample * gain, dither.as_mut());
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    writer.flush()?;
+    Ok(gain)
+}
+
+pub fn save_clip(path: &Path, clip: &Clip) -> io::Result<()> {
+    let file = File::create(path)?;
+    write_clip(BufWriter::new(file), clip)
+}
+
+pub fn save_clip_info(path: &Path, clip: &Clip, info: &WavInfo) -> io::Result<()> {
+    let file = File::create(path)?;
+    write_clip_info(BufWriter::new(file), clip, info)
+}
+
+pub fn save_clip_export(
+    path: &Path,
+    clip: &Clip,
+    info: &WavInfo,
+    options: ExportOptions,
+) -> io::Result<f32> {
+    let file = File::create(path)?;
+    write_clip_export(BufWriter::new(file), clip, info, options)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> io::Result<u16> {
+    let bytes = bytes
+        .get(offset..offset + 2)
+        .ok_or_else(|| invalid("truncated"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| invalid("truncated"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a clip from 16, 24 or 32-bit integer or 32-bit float WAV data.
+pub fn read_clip(bytes: &[u8]) -> io::Result<Clip> {
+    if bytes.get(0..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+        return Err(invalid("not a wav file"));
+    }
+
+    // format, channels, sample rate and bits per sample
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(bytes, offset + 4)? as usize;
+        let body = offset + 8;
+        let chunk = bytes
+            .get(body..body + size)
+            .ok_or_else(|| invalid("truncated chunk"))?;
+
+        match id {
+            b"fmt " => {
+                let mut tag = read_u16(chunk, 0)?;
+
+                // the actual format is the start of the sub format guid
+                if tag == FORMAT_EXTENSIBLE {
+                    tag = read_u16(chunk, 24)?;
+                }
+
+                let channels = read_u16(chunk, 2)?;
+                let sample_rate = read_u32(chunk, 4)?;
+                let bits = read_u16(chunk, 14)?;
+                format = Some((tag, channels, sample_rate, bits));
+            }
+            b"data" => data = Some(chunk),
+            _ => {}
+        }
+
+        // chunks are padded to an even size
+        offset = body + size + size % 2;
+    }
+
+    let (tag, channels, sample_rate, bits) = format.ok_or_else(|| invalid("missing fmt chunk"))?;
+    let data = data.ok_or_else(|| invalid("missing data chunk"))?;
+
+    if channels == 0 {
+        return Err(invalid("no channels"));
+    }
+
+    let samples: Vec<f32> = match (tag, bits) {
+        (FORMAT_FLOAT, 32) => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        (FORMAT_PCM, 16) => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        (FORMAT_PCM, 24) => data
+            .chunks_exact(3)
+            .map(|b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0)
+            .collect(),
+        (FORMAT_PCM, 32) => data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0)
+            .collect(),
+        _ => return Err(invalid("unsupported sample format")),
+    };
+
+    Ok(Clip::new(channels, SampleRate(sample_rate), samples.into()))
+}
+
+/// Reads the title, artist and comment of WAV data, empty where the file has none.
+pub fn read_info(bytes: &[u8]) -> io::Result<WavInfo> {
+    if bytes.get(0..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+        return Err(invalid("not a wav file"));
+    }
+
+    let mut info = WavInfo::default();
+    let mut offset = 12;
+
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(bytes, offset + 4)? as usize;
+        let body = offset + 8;
+        let chunk = bytes
+            .get(body..body + size)
+            .ok_or_else(|| invalid("truncated chunk"))?;
+
+        if id == b"LIST" {
+            info.read_list(chunk)?;
+        }
+
+        offset = body + size + size % 2;
+    }
+
+    Ok(info)
+}
+
+pub fn load_clip(path: &Path) -> io::Result<Clip> {
+    read_clip(&fs::read(path)?)
+}
+
+/// Returns `<stem>.<extension>`, or `<stem>-N.<extension>` if that already exists.
+pub fn unique_path(stem: &str, extension: &str) -> PathBuf {
+    let mut path = PathBuf::from(format!("{}.{}", stem, extension));
+    let mut number = 2;
+
+    while path.exists() {
+        path = PathBuf::from(format!("{}-{}.{}", stem, number, extension));
+        number += 1;
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip() -> Clip {
+        let samples = (0..64).map(|i| (i as f32 * 0.1).sin() * 0.5).collect();
+        Clip::new(2, SampleRate(44100), samples)
+    }
+
+    fn write(clip: &Clip, info: &WavInfo, format: SampleFormat) -> Vec<u8> {
+        let options = ExportOptions {
+            format,
+            ..ExportOptions::default()
+        };
+
+        let mut bytes = Vec::new();
+        write_clip_export(&mut bytes, clip, info, options).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn info_round_trip() {
+        // one field of an odd length and one of an even, which get padded differently
+        let info = WavInfo {
+            title: String::from("loop"),
+            artist: String::from("wroom"),
+            comment: String::from("120 bpm, 16 beats — ünïcode"),
+        };
+
+        let clip = clip();
+        for format in [SampleFormat::Float32, SampleFormat::Int16] {
+            let bytes = write(&clip, &info, format);
+            assert_eq!(read_info(&bytes).unwrap(), info);
+
+            // the RIFF size covers the whole file, and the samples still read past the list
+            assert_eq!(read_u32(&bytes, 4).unwrap() as usize, bytes.len() - 8);
+            assert_eq!(read_clip(&bytes).unwrap().frame_count(), clip.frame_count());
+        }
+
+        let bytes = write(&clip, &info, SampleFormat::Float32);
+        assert_eq!(
+            read_clip(&bytes).unwrap().dense_samples(),
+            clip.dense_samples()
+        );
+    }
+
+    #[test]
+    fn empty_info_writes_no_list() {
+        let clip = clip();
+        let info = WavInfo {
+            artist: String::from("wroom"),
+            ..WavInfo::default()
+        };
+
+        // empty fields are left out, and without any the list goes too
+        let bytes = write(&clip, &info, SampleFormat::Float32);
+        assert_eq!(read_info(&bytes).unwrap(), info);
+        assert!(!bytes.windows(4).any(|id| id == b"INAM"));
+
+        let mut bytes = Vec::new();
+        write_clip(&mut bytes, &clip).unwrap();
+        assert_eq!(read_info(&bytes).unwrap(), WavInfo::default());
+        assert!(!bytes.windows(4).any(|id| id == b"LIST"));
+    }
+
+    #[test]
+    fn normalizing_brings_the_peak_to_the_target() {
+        let samples = [0.1, -0.25, 0.2];
+        let gain = normalize_gain(&samples);
+
+        let peak = samples.iter().map(|s| (s * gain).abs()).fold(0.0, f32::max);
+        assert!((20.0 * peak.log10() - NORMALIZE_PEAK_DB).abs() < 1e-4);
+
+        // silence can't be brought up
+        assert_eq!(normalize_gain(&[0.0; 4]), 1.0);
+    }
+
+    #[test]
+    fn dither_only_touches_the_lowest_bit() {
+        let mut dither = Dither::default();
+        let mut differ = 0;
+
+        for i in 0..1000 {
+            let sample = (i as f32 * 0.01).sin() * 0.5;
+            let rounded = quantize_16(sample, None);
+            let dithered = quantize_16(sample, Some(&mut dither));
+
+            assert!((dithered as i32 - rounded as i32).abs() <= 1);
+            differ += (dithered != rounded) as usize;
+        }
+
+        assert!(differ > 0);
+        assert_eq!(quantize_16(2.0, None), i16::MAX);
+        assert_eq!(quantize_16(-2.0, None), -i16::MAX);
+    }
+
+    #[test]
+    fn normalized_export_peaks_at_the_target() {
+        let clip = Clip::new(2, SampleRate(48000), vec![0.0, 0.25, -0.5, 0.125].into());
+        let target = 10f32.powf(NORMALIZE_PEAK_DB / 20.0);
+
+        let options = ExportOptions {
+            format: SampleFormat::Int16,
+            normalize: true,
+            dither: false,
+        };
+
+        let mut bytes = Vec::new();
+        let gain = write_clip_export(&mut bytes, &clip, &WavInfo::default(), options).unwrap();
+
+        let peak = read_clip(&bytes)
+            .unwrap()
+            .dense_samples()
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - target).abs() < 1.0 / 32768.0);
+        assert!((gain - target / 0.5).abs() < 1e-6);
+
+        // a float file is written as it is, whatever the options
+        let options = ExportOptions {
+            format: SampleFormat::Float32,
+            ..options
+        };
+
+        let mut bytes = Vec::new();
+        let gain = write_clip_export(&mut bytes, &clip, &WavInfo::default(), options).unwrap();
+        assert_eq!(gain, 1.0);
+        assert_eq!(read_clip(&bytes).unwrap().dense_samples()[2], -0.5);
+    }
+}
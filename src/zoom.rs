@@ -0,0 +1,135 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::app::App;
+
+// how long into a beat the beat digit stays inverted
+const FLASH_LENGTH: f32 = 0.15;
+
+const DIGITS: [[&str; 5]; 10] = [
+    ["███", "█ █", "█ █", "█ █", "███"],
+    [" █ ", "██ ", " █ ", " █ ", "███"],
+    ["███", "  █", "███", "█  ", "███"],
+    ["███", "  █", "███", "  █", "███"],
+    ["█ █", "█ █", "███", "  █", "  █"],
+    ["███", "█  ", "███", "  █", "███"],
+    ["███", "█  ", "███", "█ █", "███"],
+    ["███", "  █", "  █", "  █", "  █"],
+    ["███", "█ █", "███", "█ █", "███"],
+    ["███", "█ █", "███", "  █", "███"],
+];
+
+const COLON: [&str; 5] = [" ", "█", " ", "█", " "];
+
+// renders a number as rows of block characters, doubled horizontally
+fn big_text(text: &str) -> Vec<String> {
+    let mut rows = vec![String::new(); 5];
+
+    for c in text.chars() {
+        let glyph = match c.to_digit(10) {
+            Some(digit) => DIGITS[digit as usize],
+            None => COLON,
+        };
+
+        for (row, line) in rows.iter_mut().zip(glyph) {
+            for cell in line.chars() {
+                row.push(cell);
+                row.push(cell);
+            }
+
+            row.push_str("  ");
+        }
+    }
+
+    rows
+}
+
+impl App {
+    pub fn toggle_zoom(&mut self) {
+        self.zoomed = !self.zoomed;
+    }
+
+    pub fn render_zoomed<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let chunks = Layout::default()
+            .margin(1)
+            .horizontal_margin(2)
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(2),
+            ])
+            .split(area);
+
+        self.render_clock(frame, chunks[0]);
+        self.render_loop_progress(frame, chunks[1]);
+        self.render_track_states(frame, chunks[3]);
+    }
+
+    pub fn render_clock<B: Backend>(&mut self, frame: &mut Frame<B>, mut area: Rect) {
+        let beat = self.current_beat();
+        let whole_beat = beat as u64;
+        let beats_per_bar = self.snapshot.beats_per_bar.max(1);
+
+        let bar = whole_beat / beats_per_bar + 1;
+        let beat_in_bar = whole_beat % beats_per_bar + 1;
+
+        let mut beat_style = Style::default().add_modifier(Modifier::BOLD);
+        if beat.fract() < FLASH_LENGTH {
+            beat_style = beat_style.add_modifier(Modifier::REVERSED);
+        }
+
+        let bar_rows = big_text(&format!("{}:", bar));
+        let beat_rows = big_text(&beat_in_bar.to_string());
+
+        let lines = bar_rows
+            .into_iter()
+            .zip(beat_rows)
+            .map(|(bar, beat)| {
+                Spans::from(vec![
+                    Span::styled(bar, self.theme.accent),
+                    Span::styled(beat, beat_style),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        // center the clock vertically
+        let height = lines.len() as u16;
+        if area.height > height {
+            area.y += (area.height - height) / 2;
+            area.height = height;
+        }
+
+        let paragraph = Paragraph::new(lines).alignment(Alignment::Center);
+        frame.render_widget(paragraph, area);
+    }
+
+    // one character per track showing whether it's playing, muted or empty
+    pub fn render_track_states<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
+        let mut states = Vec::new();
+        let mut numbers = Vec::new();
+
+        for (i, track) in self.audio.tracks.iter().enumerate() {
+            let (state, style) = match track.clip {
+                None => ("·", self.theme.empty),
+                Some(_) if track.muted => ("□", self.theme.muted),
+                Some(_) => ("■", self.theme.meter(self.theme.color_of(track, i))),
+            };
+
+            states.push(Span::styled(format!("{} ", state), style));
+            numbers.push(Span::raw(format!("{} ", (i + 1) % 10)));
+        }
+
+        let paragraph = Paragraph::new(vec![Spans::from(states), Spans::from(numbers)])
+            .alignment(Alignment::Center);
+
+        frame.render_widget(paragraph, area);
+    }
+}